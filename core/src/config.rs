@@ -359,6 +359,13 @@ pub struct PortConfig {
     #[serde(default = "default_port_txd")]
     pub txd: usize,
 
+    /// The maximum transmission unit. Defaults to `1500`. Frames that don't
+    /// fit in a single mbuf segment, for example 9000 octet jumbo frames,
+    /// are received as multiple chained segments. The device must support
+    /// scattered receive.
+    #[serde(default = "default_port_mtu")]
+    pub mtu: usize,
+
     /// Whether promiscuous mode is enabled for this port. Defaults to `false`.
     #[serde(default)]
     pub promiscuous: bool,
@@ -383,6 +390,10 @@ fn default_port_txd() -> usize {
     128
 }
 
+fn default_port_mtu() -> usize {
+    1500
+}
+
 fn default_multicast_mode() -> bool {
     true
 }
@@ -398,6 +409,7 @@ impl fmt::Debug for PortConfig {
         d.field("cores", &self.cores)
             .field("rxd", &self.rxd)
             .field("txd", &self.txd)
+            .field("mtu", &self.mtu)
             .field("promiscuous", &self.promiscuous)
             .field("multicast", &self.multicast)
             .field("kni", &self.kni)
@@ -451,6 +463,7 @@ mod tests {
         assert_eq!(None, config.ports[0].args);
         assert_eq!(default_port_rxd(), config.ports[0].rxd);
         assert_eq!(default_port_txd(), config.ports[0].txd);
+        assert_eq!(default_port_mtu(), config.ports[0].mtu);
         assert_eq!(false, config.ports[0].promiscuous);
         assert_eq!(default_multicast_mode(), config.ports[0].multicast);
         assert_eq!(false, config.ports[0].kni);
@@ -194,7 +194,7 @@ impl KniTx {
 
                     let bytes: u64 = ptrs[..sent as usize]
                         .iter()
-                        .map(|&ptr| unsafe { (*ptr).data_len as u64 })
+                        .map(|&ptr| unsafe { (*ptr).pkt_len as u64 })
                         .sum();
                     self.octets.record(bytes);
                 }
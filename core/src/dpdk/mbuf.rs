@@ -23,6 +23,7 @@ use crate::packets::{Internal, Packet};
 use crate::{ensure, trace};
 use anyhow::Result;
 use std::fmt;
+use std::marker::PhantomData;
 use std::mem;
 use std::os::raw;
 use std::ptr::{self, NonNull};
@@ -95,15 +96,51 @@ pub(crate) enum BufferError {
     /// The struct size exceeds the remaining buffer length.
     #[error("Struct size {0} exceeds the remaining buffer length {1}.")]
     OutOfBuffer(usize, usize),
+
+    /// The data spans more than one segment.
+    #[error("Data at offset {0} of length {1} is not contiguous.")]
+    NotContiguous(usize, usize),
+}
+
+/// The maximum length of the data in a buffer.
+const MAX_PKT_LEN: usize = u16::MAX as usize;
+
+/// Returns the address of the data at offset in the segment.
+#[inline]
+unsafe fn seg_address(seg: *mut ffi::rte_mbuf, offset: usize) -> *mut u8 {
+    ((*seg).buf_addr as *mut u8).add((*seg).data_off as usize + offset)
+}
+
+/// Returns the amount of bytes left in the segment.
+#[inline]
+unsafe fn seg_tailroom(seg: *mut ffi::rte_mbuf) -> usize {
+    ((*seg).buf_len - (*seg).data_off - (*seg).data_len) as usize
+}
+
+/// Releases a segment unlinked from its chain.
+#[inline]
+unsafe fn free_segment(seg: *mut ffi::rte_mbuf) {
+    (*seg).next = ptr::null_mut();
+    (*seg).nb_segs = 1;
+    (*seg).pkt_len = (*seg).data_len as u32;
+    ffi::_rte_pktmbuf_free(seg);
 }
 
 /// A DPDK message buffer that carries the network packet.
 ///
 /// # Remarks
 ///
-/// Multi-segment Mbuf is not supported. It's the application's responsibilty
-/// to ensure that the ethernet device's MTU is less than the default size
-/// of a single Mbuf segment (`RTE_MBUF_DEFAULT_DATAROOM` = 2048).
+/// Packets larger than the default size of a single Mbuf segment
+/// (`RTE_MBUF_DEFAULT_DATAROOM` = 2048), for example jumbo frames received
+/// by a port with a larger MTU, are stored in multiple chained segments.
+/// Offsets are always relative to the start of the packet.
+///
+/// Reads return raw pointers into a single segment, and fail with
+/// `BufferError::NotContiguous` if the data spans more than one segment.
+/// Reads never move the data. The data can be made contiguous with
+/// `Mbuf::linearize`, or copied across segment boundaries with
+/// `copy_to_slice` and `copy_from_slice`. Packet headers are normally
+/// within the first segment.
 pub struct Mbuf {
     inner: MbufInner,
 }
@@ -158,16 +195,19 @@ impl Mbuf {
 
     /// Creates a new message buffer from a byte array.
     ///
+    /// A byte array larger than a single segment is stored in chained
+    /// segments.
+    ///
     /// # Errors
     ///
     /// Returns `MempoolError::Exhausted` if the allocation of mbuf fails.
     /// Returns `BufferError::NotResized` if the byte array is larger than
-    /// the maximum mbuf size.
+    /// the maximum packet length.
     #[inline]
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
         let mut mbuf = Mbuf::new()?;
         mbuf.extend(0, data.len())?;
-        mbuf.write_data_slice(0, data)?;
+        mbuf.copy_from_slice(0, data)?;
         Ok(mbuf)
     }
 
@@ -191,62 +231,146 @@ impl Mbuf {
         unsafe { self.inner.ptr_mut().as_mut() }
     }
 
-    /// Returns amount of data stored in the buffer.
+    /// Returns amount of data stored in the buffer, across all the
+    /// segments.
     #[inline]
     pub fn data_len(&self) -> usize {
-        self.raw().data_len as usize
+        self.raw().pkt_len as usize
     }
 
-    /// Returns the raw pointer from the offset
+    /// Returns the number of segments chained together to hold the data.
     #[inline]
-    pub(crate) unsafe fn data_address(&self, offset: usize) -> *mut u8 {
-        let raw = self.raw();
-        (raw.buf_addr as *mut u8).offset(raw.data_off as isize + offset as isize)
+    pub fn num_segments(&self) -> usize {
+        self.raw().nb_segs as usize
     }
 
-    /// Returns the amount of bytes left in the buffer.
+    /// Returns the segment holding the data at offset and the offset
+    /// relative to the start of the segment's data. The end of the data is
+    /// in the last segment.
     #[inline]
-    fn tailroom(&self) -> usize {
-        let raw = self.raw();
-        (raw.buf_len - raw.data_off - raw.data_len) as usize
+    fn segment_at(&self, offset: usize) -> (*mut ffi::rte_mbuf, usize) {
+        let mut seg = self.inner.ptr().as_ptr();
+        let mut offset = offset;
+        unsafe {
+            while offset >= (*seg).data_len as usize && !(*seg).next.is_null() {
+                offset -= (*seg).data_len as usize;
+                seg = (*seg).next;
+            }
+        }
+        (seg, offset)
     }
 
     /// Extends the data buffer at offset by `len` bytes.
     ///
     /// If the offset is not at the end of the data. The data after the
-    /// offset is shifted down to make room.
+    /// offset is shifted down to make room. If the segment at offset does
+    /// not have enough free space, new segments are chained to the buffer.
+    /// The new room is contiguous as long as it fits in one segment.
     ///
     /// # Errors
     ///
     /// Returns `BufferError::NotResized` if the offset is out of bound,
-    /// or the length to extend is either 0 or exceeds the available free
-    /// buffer capacity.
+    /// or the length to extend is either 0 or exceeds the maximum packet
+    /// length. Returns `MempoolError::Exhausted` if the allocation of the
+    /// new segments fails.
     #[inline]
     pub fn extend(&mut self, offset: usize, len: usize) -> Result<()> {
         ensure!(len > 0, BufferError::NotResized);
         ensure!(offset <= self.data_len(), BufferError::NotResized);
-        ensure!(len < self.tailroom(), BufferError::NotResized);
+        ensure!(
+            self.data_len() + len <= MAX_PKT_LEN,
+            BufferError::NotResized
+        );
 
-        // shifts down data to make room
-        let to_copy = self.data_len() - offset;
-        if to_copy > 0 {
-            unsafe {
-                let src = self.data_address(offset);
-                let dst = self.data_address(offset + len);
+        let (seg, seg_offset) = self.segment_at(offset);
+        unsafe {
+            if len >= seg_tailroom(seg) {
+                return self.extend_segments(seg, offset, seg_offset, len);
+            }
+
+            // shifts down data to make room
+            let to_copy = (*seg).data_len as usize - seg_offset;
+            if to_copy > 0 {
+                let src = seg_address(seg, seg_offset);
+                let dst = seg_address(seg, seg_offset + len);
                 ptr::copy(src, dst, to_copy);
             }
+
+            // do some record keeping
+            (*seg).data_len += len as u16;
+        }
+        self.raw_mut().pkt_len += len as u32;
+
+        Ok(())
+    }
+
+    /// Extends the data buffer at offset by chaining new segments after
+    /// `seg`.
+    unsafe fn extend_segments(
+        &mut self,
+        seg: *mut ffi::rte_mbuf,
+        offset: usize,
+        seg_offset: usize,
+        len: usize,
+    ) -> Result<()> {
+        // saves the data after the offset, it's copied back after the room.
+        let tail_len = (*seg).data_len as usize - seg_offset;
+        let tail = slice::from_raw_parts(seg_address(seg, seg_offset), tail_len).to_vec();
+        let tailroom = seg_tailroom(seg) + tail_len;
+        let capacity = (*seg).buf_len as usize - ffi::RTE_PKTMBUF_HEADROOM as usize;
+
+        // keeps the room in this segment if it fits, otherwise starts it in
+        // a new segment if it fits there.
+        let filled = if len <= tailroom {
+            (len + tail_len).min(tailroom)
+        } else if len <= capacity {
+            0
+        } else {
+            tailroom
+        };
+        let mut rest = len + tail_len - filled;
+
+        let mut num_segs = rest / capacity;
+        if rest % capacity > 0 {
+            num_segs += 1;
+        }
+        let mut segs = Vec::with_capacity(num_segs);
+        for _ in 0..num_segs {
+            match ffi::_rte_pktmbuf_alloc(self.raw().pool).into_result(|_| MempoolError::Exhausted)
+            {
+                Ok(new_seg) => segs.push(new_seg.as_ptr()),
+                Err(err) => {
+                    segs.into_iter().for_each(|s| ffi::_rte_pktmbuf_free(s));
+                    return Err(err);
+                }
+            }
         }
 
-        // do some record keeping
-        self.raw_mut().data_len += len as u16;
+        (*seg).data_len = (seg_offset + filled) as u16;
+        let next = (*seg).next;
+        let mut prev = seg;
+        for &new_seg in segs.iter() {
+            let count = rest.min(capacity);
+            (*new_seg).data_len = count as u16;
+            (*prev).next = new_seg;
+            prev = new_seg;
+            rest -= count;
+        }
+        (*prev).next = next;
+
+        self.raw_mut().nb_segs += segs.len() as u16;
         self.raw_mut().pkt_len += len as u32;
 
+        if tail_len > 0 {
+            self.copy_from_slice(offset + len, &tail)?;
+        }
+
         Ok(())
     }
 
     /// Shrinks the data buffer at offset by `len` bytes.
     ///
-    /// The data at offset is shifted up.
+    /// The data at offset is shifted up. Segments left empty are released.
     ///
     /// # Errors
     ///
@@ -257,18 +381,46 @@ impl Mbuf {
         ensure!(len > 0, BufferError::NotResized);
         ensure!(offset + len <= self.data_len(), BufferError::NotResized);
 
-        // shifts up data to fill the room
-        let to_copy = self.data_len() - offset - len;
-        if to_copy > 0 {
-            unsafe {
-                let src = self.data_address(offset + len);
-                let dst = self.data_address(offset);
-                ptr::copy(src, dst, to_copy);
+        let head = self.inner.ptr().as_ptr();
+        let mut prev = ptr::null_mut::<ffi::rte_mbuf>();
+        let mut seg = head;
+        let mut seg_offset = offset;
+        let mut rest = len;
+
+        unsafe {
+            while rest > 0 {
+                let data_len = (*seg).data_len as usize;
+                if seg_offset >= data_len {
+                    seg_offset -= data_len;
+                } else {
+                    let count = rest.min(data_len - seg_offset);
+
+                    // shifts up data to fill the room
+                    let to_copy = data_len - seg_offset - count;
+                    if to_copy > 0 {
+                        let src = seg_address(seg, seg_offset + count);
+                        let dst = seg_address(seg, seg_offset);
+                        ptr::copy(src, dst, to_copy);
+                    }
+
+                    (*seg).data_len -= count as u16;
+                    seg_offset = 0;
+                    rest -= count;
+                }
+
+                let next = (*seg).next;
+                if (*seg).data_len == 0 && seg != head {
+                    (*prev).next = next;
+                    (*head).nb_segs -= 1;
+                    free_segment(seg);
+                } else {
+                    prev = seg;
+                }
+                seg = next;
             }
         }
 
         // do some record keeping
-        self.raw_mut().data_len -= len as u16;
         self.raw_mut().pkt_len -= len as u32;
 
         Ok(())
@@ -288,6 +440,8 @@ impl Mbuf {
 
     /// Truncates the data buffer to len.
     ///
+    /// Segments past the new length are released.
+    ///
     /// # Errors
     ///
     /// Returns `BufferError::NotResized` if the target length exceeds the
@@ -296,19 +450,125 @@ impl Mbuf {
     pub fn truncate(&mut self, to_len: usize) -> Result<()> {
         ensure!(to_len < self.data_len(), BufferError::NotResized);
 
-        self.raw_mut().data_len = to_len as u16;
+        let head = self.inner.ptr().as_ptr();
+        let mut seg = head;
+        let mut seg_len = to_len;
+
+        unsafe {
+            while seg_len > (*seg).data_len as usize {
+                seg_len -= (*seg).data_len as usize;
+                seg = (*seg).next;
+            }
+            (*seg).data_len = seg_len as u16;
+
+            // releases the remaining segments.
+            let mut next = (*seg).next;
+            (*seg).next = ptr::null_mut();
+            while !next.is_null() {
+                let seg = next;
+                next = (*seg).next;
+                (*head).nb_segs -= 1;
+                free_segment(seg);
+            }
+        }
+
         self.raw_mut().pkt_len = to_len as u32;
 
         Ok(())
     }
 
+    /// Returns the address of `len` bytes of data at offset.
+    ///
+    /// The data is not moved. Fails if the data spans more than one
+    /// segment.
+    #[inline]
+    fn contiguous(&self, offset: usize, len: usize) -> Result<*mut u8> {
+        let (seg, seg_offset) = self.segment_at(offset);
+
+        unsafe {
+            ensure!(
+                seg_offset + len <= (*seg).data_len as usize,
+                BufferError::NotContiguous(offset, len)
+            );
+            Ok(seg_address(seg, seg_offset))
+        }
+    }
+
+    /// Makes `len` bytes of data at offset contiguous.
+    ///
+    /// If the data spans more than one segment, it's moved from the
+    /// following segments to the end of the segment at offset. Segments
+    /// left empty are released. The data up to the end of the segment at
+    /// offset does not move, but raw pointers previously returned for data
+    /// in the following segments are no longer valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferError::OutOfBuffer` if the length exceeds the size
+    /// of the data stored at offset. Returns `BufferError::NotContiguous`
+    /// if the data doesn't fit in the segment at offset.
+    #[inline]
+    pub fn linearize(&mut self, offset: usize, len: usize) -> Result<()> {
+        ensure!(
+            offset + len <= self.data_len(),
+            BufferError::OutOfBuffer(len, self.data_len().saturating_sub(offset))
+        );
+
+        let (seg, seg_offset) = self.segment_at(offset);
+
+        unsafe {
+            let data_len = (*seg).data_len as usize;
+            if seg_offset + len > data_len {
+                let capacity = ((*seg).buf_len - (*seg).data_off) as usize;
+                ensure!(
+                    seg_offset + len <= capacity,
+                    BufferError::NotContiguous(offset, len)
+                );
+                self.pull_up(seg, seg_offset + len - data_len);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves `count` bytes of data from the following segments to the end
+    /// of `seg`. Segments left empty are released.
+    unsafe fn pull_up(&mut self, seg: *mut ffi::rte_mbuf, count: usize) {
+        let head = self.inner.ptr().as_ptr();
+        let mut rest = count;
+
+        while rest > 0 {
+            let next = (*seg).next;
+            let count = rest.min((*next).data_len as usize);
+            ptr::copy_nonoverlapping(
+                seg_address(next, 0),
+                seg_address(seg, (*seg).data_len as usize),
+                count,
+            );
+            (*seg).data_len += count as u16;
+            (*next).data_off += count as u16;
+            (*next).data_len -= count as u16;
+            rest -= count;
+
+            if (*next).data_len == 0 {
+                (*seg).next = (*next).next;
+                (*head).nb_segs -= 1;
+                free_segment(next);
+            }
+        }
+    }
+
     /// Reads the data at offset as `T` and returns it as a raw pointer.
     ///
+    /// The data is not moved. Call `Mbuf::linearize` first if it may span
+    /// more than one segment.
+    ///
     /// # Errors
     ///
     /// Returns `BufferError::BadOffset` if the offset is out of bound.
     /// Returns `BufferError::OutOfBuffer` if the size of `T` exceeds the
-    /// size of the data stored at offset.
+    /// size of the data stored at offset. Returns
+    /// `BufferError::NotContiguous` if the data spans more than one segment.
     #[inline]
     pub fn read_data<T: SizeOf>(&self, offset: usize) -> Result<NonNull<T>> {
         ensure!(
@@ -321,7 +581,7 @@ impl Mbuf {
         );
 
         unsafe {
-            let item = self.data_address(offset) as *mut T;
+            let item = self.contiguous(offset, T::size_of())? as *mut T;
             Ok(NonNull::new_unchecked(item))
         }
     }
@@ -331,12 +591,14 @@ impl Mbuf {
     ///
     /// Before writing to the data buffer, should call `Mbuf::extend` first
     /// to make sure enough space is allocated for the write and data is not
-    /// being overridden.
+    /// being overridden. If the data spans more than one segment, it's
+    /// linearized first.
     ///
     /// # Errors
     ///
     /// Returns `BufferError::OutOfBuffer` if the size of `T` exceeds the
-    /// available buffer capacity starting at offset.
+    /// available buffer capacity starting at offset. Returns
+    /// `BufferError::NotContiguous` if the data can't be linearized.
     #[inline]
     pub fn write_data<T: SizeOf>(&mut self, offset: usize, item: &T) -> Result<NonNull<T>> {
         ensure!(
@@ -344,9 +606,11 @@ impl Mbuf {
             BufferError::OutOfBuffer(T::size_of(), self.data_len() - offset)
         );
 
+        self.linearize(offset, T::size_of())?;
+
         unsafe {
             let src = item as *const T;
-            let dst = self.data_address(offset) as *mut T;
+            let dst = self.contiguous(offset, T::size_of())? as *mut T;
             ptr::copy_nonoverlapping(src, dst, 1);
         }
 
@@ -356,11 +620,16 @@ impl Mbuf {
     /// Reads the data at offset as a slice of `T` and returns the slice as
     /// a raw pointer.
     ///
+    /// The data is not moved. Call `Mbuf::linearize` first if it may span
+    /// more than one segment, or use `Mbuf::copy_to_slice` or
+    /// `Mbuf::data_segments` instead to read data larger than a segment.
+    ///
     /// # Errors
     ///
     /// Returns `BufferError::BadOffset` if the offset is out of bound.
     /// Returns `BufferError::OutOfBuffer` if the size of `T` slice exceeds
-    /// the size of the data stored at offset.
+    /// the size of the data stored at offset. Returns
+    /// `BufferError::NotContiguous` if the data spans more than one segment.
     #[inline]
     pub fn read_data_slice<T: SizeOf>(&self, offset: usize, count: usize) -> Result<NonNull<[T]>> {
         ensure!(
@@ -373,7 +642,7 @@ impl Mbuf {
         );
 
         unsafe {
-            let item0 = self.data_address(offset) as *mut T;
+            let item0 = self.contiguous(offset, T::size_of() * count)? as *mut T;
             let slice = slice::from_raw_parts_mut(item0, count) as *mut [T];
             Ok(NonNull::new_unchecked(slice))
         }
@@ -384,12 +653,14 @@ impl Mbuf {
     ///
     /// Before writing to the data buffer, should call `Mbuf::extend` first
     /// to make sure enough space is allocated for the write and data is not
-    /// being overridden.
+    /// being overridden. If the data spans more than one segment, it's
+    /// linearized first.
     ///
     /// # Errors
     ///
     /// Returns `BufferError::OutOfBuffer` if the size of `T` slice exceeds
-    /// the available buffer capacity starting at offset.
+    /// the available buffer capacity starting at offset. Returns
+    /// `BufferError::NotContiguous` if the data can't be linearized.
     #[inline]
     pub fn write_data_slice<T: SizeOf>(
         &mut self,
@@ -403,15 +674,99 @@ impl Mbuf {
             BufferError::OutOfBuffer(T::size_of() * count, self.data_len() - offset)
         );
 
+        self.linearize(offset, T::size_of() * count)?;
+
         unsafe {
             let src = slice.as_ptr();
-            let dst = self.data_address(offset) as *mut T;
+            let dst = self.contiguous(offset, T::size_of() * count)? as *mut T;
             ptr::copy_nonoverlapping(src, dst, count);
         }
 
         self.read_data_slice(offset, count)
     }
 
+    /// Copies the data at offset into `dst`, across segment boundaries.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferError::OutOfBuffer` if the length of `dst` exceeds
+    /// the size of the data stored at offset.
+    #[inline]
+    pub fn copy_to_slice(&self, offset: usize, dst: &mut [u8]) -> Result<()> {
+        ensure!(
+            offset + dst.len() <= self.data_len(),
+            BufferError::OutOfBuffer(dst.len(), self.data_len().saturating_sub(offset))
+        );
+
+        let (mut seg, mut seg_offset) = self.segment_at(offset);
+        let mut pos = 0;
+        unsafe {
+            while pos < dst.len() {
+                let count = ((*seg).data_len as usize - seg_offset).min(dst.len() - pos);
+                ptr::copy_nonoverlapping(
+                    seg_address(seg, seg_offset),
+                    dst[pos..].as_mut_ptr(),
+                    count,
+                );
+                pos += count;
+                seg = (*seg).next;
+                seg_offset = 0;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies `src` to the data buffer at offset, across segment
+    /// boundaries.
+    ///
+    /// Before writing to the data buffer, should call `Mbuf::extend` first
+    /// to make sure enough space is allocated for the write and data is not
+    /// being overridden.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferError::OutOfBuffer` if the length of `src` exceeds
+    /// the available buffer capacity starting at offset.
+    #[inline]
+    pub fn copy_from_slice(&mut self, offset: usize, src: &[u8]) -> Result<()> {
+        ensure!(
+            offset + src.len() <= self.data_len(),
+            BufferError::OutOfBuffer(src.len(), self.data_len().saturating_sub(offset))
+        );
+
+        let (mut seg, mut seg_offset) = self.segment_at(offset);
+        let mut pos = 0;
+        unsafe {
+            while pos < src.len() {
+                let count = ((*seg).data_len as usize - seg_offset).min(src.len() - pos);
+                ptr::copy_nonoverlapping(src[pos..].as_ptr(), seg_address(seg, seg_offset), count);
+                pos += count;
+                seg = (*seg).next;
+                seg_offset = 0;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns an iterator over `len` bytes of data at offset, as one slice
+    /// per segment.
+    ///
+    /// The iterator stops at the end of the data if `len` exceeds the size
+    /// of the data stored at offset.
+    #[inline]
+    pub fn data_segments(&self, offset: usize, len: usize) -> DataSegments<'_> {
+        let (seg, seg_offset) = self.segment_at(offset);
+        let len = len.min(self.data_len().saturating_sub(offset));
+        DataSegments {
+            seg,
+            seg_offset,
+            rest: len,
+            _phantom: PhantomData,
+        }
+    }
+
     /// Acquires the underlying raw struct pointer.
     ///
     /// The `Mbuf` is consumed. It is the caller's the responsibility to
@@ -452,6 +807,47 @@ impl Mbuf {
     }
 }
 
+/// An iterator over the data of a `Mbuf`, one slice per segment.
+///
+/// Created by `Mbuf::data_segments`.
+pub struct DataSegments<'a> {
+    seg: *mut ffi::rte_mbuf,
+    seg_offset: usize,
+    rest: usize,
+    _phantom: PhantomData<&'a Mbuf>,
+}
+
+impl<'a> Iterator for DataSegments<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.rest > 0 && !self.seg.is_null() {
+            unsafe {
+                let seg = self.seg;
+                let count = ((*seg).data_len as usize - self.seg_offset).min(self.rest);
+                let data = slice::from_raw_parts(seg_address(seg, self.seg_offset), count);
+                self.seg = (*seg).next;
+                self.seg_offset = 0;
+                self.rest -= count;
+
+                if count > 0 {
+                    return Some(data);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl fmt::Debug for DataSegments<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataSegments")
+            .field("rest", &self.rest)
+            .finish()
+    }
+}
+
 impl fmt::Debug for Mbuf {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let raw = self.raw();
@@ -460,6 +856,7 @@ impl fmt::Debug for Mbuf {
             .field("pkt_len", &raw.pkt_len)
             .field("data_len", &raw.data_len)
             .field("data_off", &raw.data_off)
+            .field("nb_segs", &raw.nb_segs)
             .finish()
     }
 }
@@ -734,4 +1131,137 @@ mod tests {
             assert_eq!(0, mbuf.data_len());
         }
     }
+
+    const JUMBO_LEN: usize = 9000;
+
+    fn jumbo_bytes() -> Vec<u8> {
+        (0..JUMBO_LEN).map(|i| i as u8).collect()
+    }
+
+    fn to_vec(mbuf: &Mbuf) -> Vec<u8> {
+        let mut data = vec![0; mbuf.data_len()];
+        mbuf.copy_to_slice(0, &mut data).unwrap();
+        data
+    }
+
+    #[capsule::test]
+    fn new_from_jumbo_bytes() {
+        let data = jumbo_bytes();
+        let mbuf = Mbuf::from_bytes(&data).unwrap();
+
+        assert_eq!(JUMBO_LEN, mbuf.data_len());
+        assert_eq!(5, mbuf.num_segments());
+        assert_eq!(data, to_vec(&mbuf));
+    }
+
+    #[capsule::test]
+    fn read_data_across_segments() {
+        let data = jumbo_bytes();
+        let mbuf = Mbuf::from_bytes(&data).unwrap();
+
+        // reads don't move the data
+        assert!(mbuf.read_data::<[u8; 16]>(2040).is_err());
+        assert_eq!(5, mbuf.num_segments());
+
+        let mut item = [0; 16];
+        assert!(mbuf.copy_to_slice(2040, &mut item).is_ok());
+        assert_eq!(data[2040..2056], item[..]);
+
+        let segments = mbuf.data_segments(2040, 16).collect::<Vec<_>>();
+        assert_eq!(2, segments.len());
+        assert_eq!(data[2040..2048], segments[0][..]);
+        assert_eq!(data[2048..2056], segments[1][..]);
+    }
+
+    #[capsule::test]
+    fn linearize_data_across_segments() {
+        let data = jumbo_bytes();
+        let mut mbuf = Mbuf::from_bytes(&data).unwrap();
+
+        // the first segment is full, the data can't be pulled up.
+        assert!(mbuf.linearize(2040, 16).is_err());
+
+        // makes some room at the end of the first segment.
+        assert!(mbuf.shrink(0, 100).is_ok());
+        assert!(mbuf.read_data::<[u8; 16]>(1940).is_err());
+        assert!(mbuf.linearize(1940, 16).is_ok());
+
+        let item = mbuf.read_data::<[u8; 16]>(1940).unwrap();
+        let item = unsafe { item.as_ref() };
+        assert_eq!(data[2040..2056], item[..]);
+
+        // make sure data is untouched
+        assert_eq!(JUMBO_LEN - 100, mbuf.data_len());
+        assert_eq!(data[100..], to_vec(&mbuf)[..]);
+
+        // can't be contiguous in one segment
+        assert!(mbuf.linearize(0, 4096).is_err());
+    }
+
+    #[capsule::test]
+    fn write_data_across_segments() {
+        let data = jumbo_bytes();
+        let mut mbuf = Mbuf::from_bytes(&data).unwrap();
+
+        assert!(mbuf.write_data(2040, &BUFFER).is_err());
+        assert!(mbuf.copy_from_slice(2040, &BUFFER).is_ok());
+
+        let mut expected = data;
+        expected[2040..2056].copy_from_slice(&BUFFER);
+        assert_eq!(expected, to_vec(&mbuf));
+
+        // linearizes the data before writing
+        assert!(mbuf.shrink(0, 100).is_ok());
+        assert!(mbuf.write_data(1940, &[0u8; 16]).is_ok());
+        expected[2040..2056].copy_from_slice(&[0; 16]);
+        assert_eq!(expected[100..], to_vec(&mbuf)[..]);
+    }
+
+    #[capsule::test]
+    fn extend_chained_data_buffer_middle() {
+        let data = jumbo_bytes();
+        let mut mbuf = Mbuf::from_bytes(&data).unwrap();
+
+        // the first segment is full, a new segment is chained.
+        assert!(mbuf.extend(100, 16).is_ok());
+        assert_eq!(JUMBO_LEN + 16, mbuf.data_len());
+        assert_eq!(6, mbuf.num_segments());
+
+        // the new room is contiguous
+        assert!(mbuf.write_data(100, &BUFFER).is_ok());
+
+        let mut expected = data[..100].to_vec();
+        expected.extend_from_slice(&BUFFER);
+        expected.extend_from_slice(&data[100..]);
+        assert_eq!(expected, to_vec(&mbuf));
+    }
+
+    #[capsule::test]
+    fn shrink_chained_data_buffer() {
+        let data = jumbo_bytes();
+        let mut mbuf = Mbuf::from_bytes(&data).unwrap();
+
+        // shrinks across the first and the second segments
+        assert!(mbuf.shrink(2000, 100).is_ok());
+        assert_eq!(JUMBO_LEN - 100, mbuf.data_len());
+
+        let mut expected = data[..2000].to_vec();
+        expected.extend_from_slice(&data[2100..]);
+        assert_eq!(expected, to_vec(&mbuf));
+
+        // releases the empty segment
+        assert!(mbuf.shrink(JUMBO_LEN - 908, 808).is_ok());
+        assert_eq!(4, mbuf.num_segments());
+    }
+
+    #[capsule::test]
+    fn truncate_chained_data_buffer() {
+        let data = jumbo_bytes();
+        let mut mbuf = Mbuf::from_bytes(&data).unwrap();
+
+        assert!(mbuf.truncate(100).is_ok());
+        assert_eq!(100, mbuf.data_len());
+        assert_eq!(1, mbuf.num_segments());
+        assert_eq!(data[..100], to_vec(&mbuf)[..]);
+    }
 }
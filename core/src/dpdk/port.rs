@@ -276,6 +276,14 @@ pub(crate) enum PortError {
     /// assigned to the port.
     #[error("Insufficient number of TX queues '{0}'.")]
     InsufficientTxQueues(usize),
+
+    /// The MTU exceeds the maximum receive packet length.
+    #[error("MTU '{0}' exceeds the maximum receive packet length '{1}'.")]
+    MtuTooLarge(usize, usize),
+
+    /// The MTU needs scattered receive, which is not supported.
+    #[error("MTU '{0}' needs scattered receive, which is not supported.")]
+    ScatterNotSupported(usize),
 }
 
 /// An Ethernet device port.
@@ -385,6 +393,7 @@ pub(crate) struct PortBuilder<'a> {
     mempools: MempoolMap<'a>,
     rxd: u16,
     txd: u16,
+    mtu: usize,
 }
 
 impl<'a> PortBuilder<'a> {
@@ -421,6 +430,7 @@ impl<'a> PortBuilder<'a> {
             mempools: Default::default(),
             rxd: 0,
             txd: 0,
+            mtu: ffi::RTE_ETHER_MTU as usize,
         })
     }
 
@@ -490,6 +500,32 @@ impl<'a> PortBuilder<'a> {
         Ok(self)
     }
 
+    /// Sets the maximum transmission unit.
+    ///
+    /// If the Ethernet frame does not fit in a single mbuf segment, the
+    /// port receives it as multiple chained segments.
+    ///
+    /// # Errors
+    ///
+    /// If the frame length exceeds the maximum receive packet length of
+    /// the Ethernet device, or the frame needs multiple segments but the
+    /// device does not support scattered receive, `PortError` is returned.
+    pub(crate) fn mtu(&mut self, mtu: usize) -> Result<&mut Self> {
+        let frame_len = mtu + (ffi::RTE_ETHER_HDR_LEN + ffi::RTE_ETHER_CRC_LEN) as usize;
+        ensure!(
+            frame_len <= self.dev_info.max_rx_pktlen as usize,
+            PortError::MtuTooLarge(mtu, self.dev_info.max_rx_pktlen as usize)
+        );
+        ensure!(
+            frame_len <= ffi::RTE_MBUF_DEFAULT_DATAROOM as usize
+                || self.dev_info.rx_offload_capa & ffi::DEV_RX_OFFLOAD_SCATTER as u64 > 0,
+            PortError::ScatterNotSupported(mtu)
+        );
+
+        self.mtu = mtu;
+        Ok(self)
+    }
+
     /// Sets the available mempools.
     pub(crate) fn mempools(&'a mut self, mempools: &'a mut [Mempool]) -> &'a mut Self {
         self.mempools = MempoolMap::new(mempools);
@@ -514,6 +550,25 @@ impl<'a> PortBuilder<'a> {
                 DEFAULT_RSS_HF & self.dev_info.flow_type_rss_offloads;
         }
 
+        // turns on jumbo frames if the MTU is larger than the standard
+        // Ethernet MTU, and scattered receive and multi-segment transmit if
+        // the frame does not fit in a single mbuf segment.
+        if self.mtu > ffi::RTE_ETHER_MTU as usize {
+            let frame_len = self.mtu + (ffi::RTE_ETHER_HDR_LEN + ffi::RTE_ETHER_CRC_LEN) as usize;
+            conf.rxmode.max_rx_pkt_len = frame_len as u32;
+            if self.dev_info.rx_offload_capa & ffi::DEV_RX_OFFLOAD_JUMBO_FRAME as u64 > 0 {
+                conf.rxmode.offloads |= ffi::DEV_RX_OFFLOAD_JUMBO_FRAME as u64;
+            }
+
+            if frame_len > ffi::RTE_MBUF_DEFAULT_DATAROOM as usize {
+                conf.rxmode.offloads |= ffi::DEV_RX_OFFLOAD_SCATTER as u64;
+                if self.dev_info.tx_offload_capa & ffi::DEV_TX_OFFLOAD_MULTI_SEGS as u64 > 0 {
+                    conf.txmode.offloads |= ffi::DEV_TX_OFFLOAD_MULTI_SEGS as u64;
+                }
+                debug!("turned on scattered receive.");
+            }
+        }
+
         // turns on optimization for fast release of mbufs.
         if self.dev_info.tx_offload_capa & ffi::DEV_TX_OFFLOAD_MBUF_FAST_FREE as u64 > 0 {
             conf.txmode.offloads |= ffi::DEV_TX_OFFLOAD_MBUF_FAST_FREE as u64;
@@ -526,6 +581,13 @@ impl<'a> PortBuilder<'a> {
                 .into_result(DpdkError::from_errno)?;
         }
 
+        if self.mtu != ffi::RTE_ETHER_MTU as usize {
+            unsafe {
+                ffi::rte_eth_dev_set_mtu(self.port_id.0, self.mtu as u16)
+                    .into_result(DpdkError::from_errno)?;
+            }
+        }
+
         // if the port is virtual, we will allocate it to the socket of
         // the first assigned core.
         let socket_id = self
@@ -70,6 +70,16 @@
 //! Each metric is tracked per core and labeled with the core id and the
 //! pipeline name. If the pipeline doesn't have a name, it will be labeled
 //! as "default".
+//!
+//!
+//! # Reassembly Metrics
+//!
+//! * `reassembly.timeouts`, total number of incomplete datagrams discarded
+//! because the reassembly timeout expired.
+//! * `reassembly.evictions`, total number of incomplete datagrams discarded
+//! to stay within the reassembly table limits.
+//!
+//! Each metric is tracked per reassembly table and labeled with the core id.
 
 // re-export some metrics types to make feature gated imports easier.
 pub(crate) use metrics_core::{labels, Key};
@@ -98,6 +108,11 @@ pub(crate) fn init() -> Result<()> {
     Ok(())
 }
 
+/// Returns whether the metrics store is initialized.
+pub(crate) fn is_initialized() -> bool {
+    RECEIVER.get().is_some()
+}
+
 /// Registers DPDK collected port stats with the metrics store.
 pub(crate) fn register_port_stats(ports: &[Port]) {
     let stats = ports.iter().map(Port::stats).collect::<Vec<_>>();
@@ -21,6 +21,7 @@
 
 use crate::packets::ip::ProtocolNumber;
 use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::slice;
 
@@ -167,6 +168,61 @@ pub fn compute(pseudo_header_sum: u16, payload: &[u8]) -> u16 {
     !(checksum as u16)
 }
 
+/// Computes the Internet checksum over data split into multiple slices,
+/// such as the segments of a chained `Mbuf`.
+///
+/// The slices are summed as if they were one contiguous payload. A slice
+/// of odd length is paired with the first byte of the next slice.
+pub fn compute_segments<'a, I>(pseudo_header_sum: u16, segments: I) -> u16
+where
+    I: IntoIterator<Item = &'a [u8]>,
+{
+    let mut checksum = u32::from(pseudo_header_sum);
+    let mut odd_byte = None;
+
+    for segment in segments {
+        let mut data = segment;
+
+        // pairs up the odd byte left over from the previous slice
+        if let Some(byte) = odd_byte.take() {
+            match data.split_first() {
+                Some((&next, rest)) => {
+                    checksum += u32::from(u16::from_be_bytes([byte, next]));
+                    data = rest;
+                }
+                None => {
+                    odd_byte = Some(byte);
+                    continue;
+                }
+            }
+        }
+
+        if data.len() % 2 > 0 {
+            odd_byte = Some(data[data.len() - 1]);
+            data = &data[..(data.len() - 1)];
+        }
+
+        checksum = data.chunks_exact(2).fold(checksum, |acc, x| {
+            acc + u32::from(u16::from_be_bytes([x[0], x[1]]))
+        });
+
+        while checksum >> 16 != 0 {
+            checksum = (checksum >> 16) + (checksum & 0xFFFF);
+        }
+    }
+
+    // odd # of bytes, we add the last byte with padding separately
+    if let Some(byte) = odd_byte {
+        checksum += u32::from(byte) << 8;
+    }
+
+    while checksum >> 16 != 0 {
+        checksum = (checksum >> 16) + (checksum & 0xFFFF);
+    }
+
+    !(checksum as u16)
+}
+
 /// Computes the Internet checksum via incremental update as defined in
 /// [IETF RFC 1624].
 ///
@@ -219,6 +275,47 @@ pub fn compute_with_ipaddr(
     }
 }
 
+/// Lookup table for the reflected CRC32c (Castagnoli) polynomial.
+static CRC32C_TABLE: Lazy<[u32; 256]> = Lazy::new(|| {
+    let mut table = [0u32; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut crc = i as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+        }
+        *entry = crc;
+    }
+    table
+});
+
+/// Computes the CRC32c checksum as defined in [IETF RFC 3309].
+///
+/// The checksum is used by SCTP instead of the Internet checksum.
+///
+/// [IETF RFC 3309]: https://tools.ietf.org/html/rfc3309
+pub fn crc32c(data: &[u8]) -> u32 {
+    crc32c_segments(Some(data))
+}
+
+/// Computes the CRC32c checksum over data split into multiple slices,
+/// such as the segments of a chained `Mbuf`.
+pub fn crc32c_segments<'a, I>(segments: I) -> u32
+where
+    I: IntoIterator<Item = &'a [u8]>,
+{
+    let crc = segments.into_iter().fold(0xffff_ffff, |crc, data| {
+        data.iter().fold(crc, |crc, &b| {
+            CRC32C_TABLE[((crc ^ u32::from(b)) & 0xff) as usize] ^ (crc >> 8)
+        })
+    });
+
+    !crc
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -227,4 +324,34 @@ mod tests {
     fn compute_checksum_incrementally() {
         assert_eq!(0x0000, compute_inc(0xdd2f, &[0x5555], &[0x3285]));
     }
+
+    #[test]
+    fn compute_crc32c() {
+        assert_eq!(0xe306_9283, crc32c(b"123456789"));
+        assert_eq!(0, crc32c(&[]));
+    }
+
+    #[test]
+    fn compute_checksum_over_segments() {
+        let data = [0x45u8, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40];
+        let expected = compute(0x1234, &data);
+
+        assert_eq!(expected, compute_segments(0x1234, vec![&data[..]]));
+        assert_eq!(
+            expected,
+            compute_segments(
+                0x1234,
+                vec![&data[..3], &data[3..3], &data[3..8], &data[8..]]
+            )
+        );
+    }
+
+    #[test]
+    fn compute_crc32c_over_segments() {
+        let data = b"123456789";
+        assert_eq!(
+            0xe306_9283,
+            crc32c_segments(vec![&data[..2], &data[2..7], &data[7..]])
+        );
+    }
 }
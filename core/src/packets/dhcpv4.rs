@@ -0,0 +1,1215 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::net::MacAddr;
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{Internal, Packet, Udp};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::ptr::NonNull;
+
+/// The well-known UDP port for DHCPv4 servers and relay agents.
+pub const DHCPV4_SERVER_PORT: u16 = 67;
+
+/// The well-known UDP port for DHCPv4 clients.
+pub const DHCPV4_CLIENT_PORT: u16 = 68;
+
+/// The magic cookie that marks the start of the options.
+pub const DHCPV4_MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+
+/// BOOTP op code of a message sent by a client.
+pub const BOOTREQUEST: u8 = 1;
+
+/// BOOTP op code of a message sent by a server.
+pub const BOOTREPLY: u8 = 2;
+
+const BROADCAST: u16 = 0x8000;
+
+/// Hardware type of 10Mb Ethernet, used by all Ethernet links.
+const HTYPE_ETHERNET: u8 = 1;
+
+// Option codes.
+const PAD: u8 = 0;
+const SUBNET_MASK: u8 = 1;
+const ROUTER: u8 = 3;
+const DOMAIN_NAME_SERVER: u8 = 6;
+const REQUESTED_IP_ADDR: u8 = 50;
+const LEASE_TIME: u8 = 51;
+const MESSAGE_TYPE: u8 = 53;
+const SERVER_IDENTIFIER: u8 = 54;
+const RELAY_AGENT_INFO: u8 = 82;
+const END: u8 = 255;
+
+/// DHCP message type carried in option 53.
+///
+/// See [`Dhcpv4MessageTypes`] for which are current supported.
+///
+/// [`Dhcpv4MessageTypes`]: crate::packets::Dhcpv4MessageTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Dhcpv4MessageType(pub u8);
+
+impl Dhcpv4MessageType {
+    /// Creates a new message type.
+    pub fn new(value: u8) -> Self {
+        Dhcpv4MessageType(value)
+    }
+}
+
+/// Supported DHCP message types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod Dhcpv4MessageTypes {
+    use super::Dhcpv4MessageType;
+
+    /// Client broadcast to locate available servers.
+    pub const Discover: Dhcpv4MessageType = Dhcpv4MessageType(1);
+
+    /// Server to client in response to a discover with an offer of
+    /// configuration parameters.
+    pub const Offer: Dhcpv4MessageType = Dhcpv4MessageType(2);
+
+    /// Client message to servers requesting offered parameters.
+    pub const Request: Dhcpv4MessageType = Dhcpv4MessageType(3);
+
+    /// Client to server indicating the address is already in use.
+    pub const Decline: Dhcpv4MessageType = Dhcpv4MessageType(4);
+
+    /// Server to client with configuration parameters.
+    pub const Ack: Dhcpv4MessageType = Dhcpv4MessageType(5);
+
+    /// Server to client refusing the request.
+    pub const Nak: Dhcpv4MessageType = Dhcpv4MessageType(6);
+
+    /// Client to server relinquishing the address.
+    pub const Release: Dhcpv4MessageType = Dhcpv4MessageType(7);
+
+    /// Client to server asking only for local configuration parameters.
+    pub const Inform: Dhcpv4MessageType = Dhcpv4MessageType(8);
+}
+
+impl fmt::Display for Dhcpv4MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Dhcpv4MessageTypes::Discover => "DHCPDISCOVER".to_string(),
+                Dhcpv4MessageTypes::Offer => "DHCPOFFER".to_string(),
+                Dhcpv4MessageTypes::Request => "DHCPREQUEST".to_string(),
+                Dhcpv4MessageTypes::Decline => "DHCPDECLINE".to_string(),
+                Dhcpv4MessageTypes::Ack => "DHCPACK".to_string(),
+                Dhcpv4MessageTypes::Nak => "DHCPNAK".to_string(),
+                Dhcpv4MessageTypes::Release => "DHCPRELEASE".to_string(),
+                Dhcpv4MessageTypes::Inform => "DHCPINFORM".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// A sub-option of the relay agent information option based on
+/// [IETF RFC 3046].
+///
+/// [IETF RFC 3046]: https://tools.ietf.org/html/rfc3046#section-2.0
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RelayAgentSubOption {
+    /// The sub-option code, for example 1 for the agent circuit ID, and 2
+    /// for the agent remote ID.
+    pub code: u8,
+    /// The sub-option data.
+    pub data: Vec<u8>,
+}
+
+/// A DHCP option based on [IETF RFC 2132].
+///
+/// Options are read out of and written into the buffer by value. The pad
+/// and end options are handled by the options iterator and builder, and are
+/// not exposed. Options not modeled here are returned as `Other`.
+///
+/// [IETF RFC 2132]: https://tools.ietf.org/html/rfc2132
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Dhcpv4Option {
+    /// Option 1, the client's subnet mask.
+    SubnetMask(Ipv4Addr),
+    /// Option 3, the routers on the client's subnet in order of preference.
+    Router(Vec<Ipv4Addr>),
+    /// Option 6, the domain name servers available to the client.
+    DomainNameServer(Vec<Ipv4Addr>),
+    /// Option 50, the address requested by the client.
+    RequestedIpAddr(Ipv4Addr),
+    /// Option 51, the lease time in seconds.
+    LeaseTime(u32),
+    /// Option 53, the type of the DHCP message.
+    MessageType(Dhcpv4MessageType),
+    /// Option 54, the address of the selected server.
+    ServerIdentifier(Ipv4Addr),
+    /// Option 82, the relay agent information.
+    RelayAgentInfo(Vec<RelayAgentSubOption>),
+    /// Any other option.
+    Other {
+        /// The option code.
+        code: u8,
+        /// The option data.
+        data: Vec<u8>,
+    },
+}
+
+impl Dhcpv4Option {
+    /// Returns the option code.
+    pub fn code(&self) -> u8 {
+        match self {
+            Dhcpv4Option::SubnetMask(_) => SUBNET_MASK,
+            Dhcpv4Option::Router(_) => ROUTER,
+            Dhcpv4Option::DomainNameServer(_) => DOMAIN_NAME_SERVER,
+            Dhcpv4Option::RequestedIpAddr(_) => REQUESTED_IP_ADDR,
+            Dhcpv4Option::LeaseTime(_) => LEASE_TIME,
+            Dhcpv4Option::MessageType(_) => MESSAGE_TYPE,
+            Dhcpv4Option::ServerIdentifier(_) => SERVER_IDENTIFIER,
+            Dhcpv4Option::RelayAgentInfo(_) => RELAY_AGENT_INFO,
+            Dhcpv4Option::Other { code, .. } => *code,
+        }
+    }
+
+    /// Decodes the data of the option with `code`.
+    fn decode(code: u8, data: &[u8]) -> Result<Self> {
+        fn addr(data: &[u8]) -> Result<Ipv4Addr> {
+            ensure!(data.len() == 4, anyhow!("invalid DHCP address option."));
+            Ok(Ipv4Addr::new(data[0], data[1], data[2], data[3]))
+        }
+
+        fn addrs(data: &[u8]) -> Result<Vec<Ipv4Addr>> {
+            ensure!(
+                !data.is_empty() && data.len() % 4 == 0,
+                anyhow!("invalid DHCP address list option.")
+            );
+            data.chunks(4).map(addr).collect()
+        }
+
+        let option = match code {
+            SUBNET_MASK => Dhcpv4Option::SubnetMask(addr(data)?),
+            ROUTER => Dhcpv4Option::Router(addrs(data)?),
+            DOMAIN_NAME_SERVER => Dhcpv4Option::DomainNameServer(addrs(data)?),
+            REQUESTED_IP_ADDR => Dhcpv4Option::RequestedIpAddr(addr(data)?),
+            LEASE_TIME => {
+                ensure!(data.len() == 4, anyhow!("invalid DHCP lease time option."));
+                Dhcpv4Option::LeaseTime(u32::from_be_bytes([data[0], data[1], data[2], data[3]]))
+            }
+            MESSAGE_TYPE => {
+                ensure!(
+                    data.len() == 1,
+                    anyhow!("invalid DHCP message type option.")
+                );
+                Dhcpv4Option::MessageType(Dhcpv4MessageType(data[0]))
+            }
+            SERVER_IDENTIFIER => Dhcpv4Option::ServerIdentifier(addr(data)?),
+            RELAY_AGENT_INFO => {
+                let mut sub_options = Vec::new();
+                let mut rest = data;
+                while !rest.is_empty() {
+                    ensure!(
+                        rest.len() >= 2 && rest.len() >= 2 + rest[1] as usize,
+                        anyhow!("invalid DHCP relay agent sub-option.")
+                    );
+                    let len = 2 + rest[1] as usize;
+                    sub_options.push(RelayAgentSubOption {
+                        code: rest[0],
+                        data: rest[2..len].to_vec(),
+                    });
+                    rest = &rest[len..];
+                }
+                Dhcpv4Option::RelayAgentInfo(sub_options)
+            }
+            _ => Dhcpv4Option::Other {
+                code,
+                data: data.to_vec(),
+            },
+        };
+
+        Ok(option)
+    }
+
+    /// Encodes the option, including the code and length octets.
+    fn encode(&self) -> Result<Vec<u8>> {
+        let data = match self {
+            Dhcpv4Option::SubnetMask(addr)
+            | Dhcpv4Option::RequestedIpAddr(addr)
+            | Dhcpv4Option::ServerIdentifier(addr) => addr.octets().to_vec(),
+            Dhcpv4Option::Router(addrs) | Dhcpv4Option::DomainNameServer(addrs) => addrs
+                .iter()
+                .flat_map(|addr| addr.octets().to_vec())
+                .collect(),
+            Dhcpv4Option::LeaseTime(secs) => secs.to_be_bytes().to_vec(),
+            Dhcpv4Option::MessageType(message_type) => vec![message_type.0],
+            Dhcpv4Option::RelayAgentInfo(sub_options) => {
+                let mut data = Vec::new();
+                for sub_option in sub_options {
+                    ensure!(
+                        sub_option.data.len() <= u8::MAX as usize,
+                        anyhow!("DHCP relay agent sub-option is too long.")
+                    );
+                    data.push(sub_option.code);
+                    data.push(sub_option.data.len() as u8);
+                    data.extend_from_slice(&sub_option.data);
+                }
+                data
+            }
+            Dhcpv4Option::Other { code, data } => {
+                ensure!(
+                    *code != PAD && *code != END,
+                    anyhow!("invalid DHCP option code {}.", code)
+                );
+                data.clone()
+            }
+        };
+
+        ensure!(
+            data.len() <= u8::MAX as usize,
+            anyhow!("DHCP option {} is too long.", self.code())
+        );
+
+        let mut bytes = Vec::with_capacity(data.len() + 2);
+        bytes.push(self.code());
+        bytes.push(data.len() as u8);
+        bytes.extend_from_slice(&data);
+        Ok(bytes)
+    }
+}
+
+/// Dynamic Host Configuration Protocol message based on [IETF RFC 2131].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +---------------+---------------+---------------+---------------+
+/// |     op (1)    |   htype (1)   |   hlen (1)    |   hops (1)    |
+/// +---------------+---------------+---------------+---------------+
+/// |                            xid (4)                            |
+/// +-------------------------------+-------------------------------+
+/// |           secs (2)            |           flags (2)           |
+/// +-------------------------------+-------------------------------+
+/// |                          ciaddr  (4)                          |
+/// +---------------------------------------------------------------+
+/// |                          yiaddr  (4)                          |
+/// +---------------------------------------------------------------+
+/// |                          siaddr  (4)                          |
+/// +---------------------------------------------------------------+
+/// |                          giaddr  (4)                          |
+/// +---------------------------------------------------------------+
+/// |                          chaddr  (16)                         |
+/// +---------------------------------------------------------------+
+/// |                          sname   (64)                         |
+/// +---------------------------------------------------------------+
+/// |                          file    (128)                        |
+/// +---------------------------------------------------------------+
+/// |                          options (variable)                   |
+/// +---------------------------------------------------------------+
+/// ```
+///
+/// - *op*:         Message op code, 1 = BOOTREQUEST, 2 = BOOTREPLY.
+///
+/// - *htype*:      Hardware address type, 1 for Ethernet.
+///
+/// - *hlen*:       Hardware address length, 6 for Ethernet.
+///
+/// - *hops*:       Incremented by relay agents forwarding the message.
+///
+/// - *xid*:        Transaction ID, a random number chosen by the client.
+///
+/// - *secs*:       Seconds elapsed since the client began the exchange.
+///
+/// - *flags*:      The leftmost bit is the broadcast flag.
+///
+/// - *ciaddr*:     Client IP address, if the client is already bound.
+///
+/// - *yiaddr*:     'your' (client) IP address assigned by the server.
+///
+/// - *siaddr*:     IP address of next server to use in bootstrap.
+///
+/// - *giaddr*:     Relay agent IP address.
+///
+/// - *chaddr*:     Client hardware address.
+///
+/// - *sname*:      Optional server host name.
+///
+/// - *file*:       Boot file name.
+///
+/// - *options*:    Optional parameters field, starting with the magic
+///                 cookie 99.130.83.99.
+///
+/// The options are part of the header, up to and including the end option.
+/// Options overloaded into the *sname* and *file* fields are not parsed.
+///
+/// A relay agent can insert the relay agent information option before
+/// forwarding a client's message to the server.
+///
+/// ```
+/// let mut dhcp = udp.parse::<Dhcpv4>()?;
+/// dhcp.set_giaddr(relay_addr);
+/// dhcp.set_hops(dhcp.hops() + 1);
+/// dhcp.options_mut().append(&Dhcpv4Option::RelayAgentInfo(vec![
+///     RelayAgentSubOption { code: 1, data: circuit_id },
+/// ]))?;
+/// dhcp.reconcile_all();
+/// ```
+///
+/// [IETF RFC 2131]: https://tools.ietf.org/html/rfc2131#section-2
+pub struct Dhcpv4 {
+    envelope: Udp<Ipv4>,
+    header: NonNull<Dhcpv4Header>,
+    offset: usize,
+    options_len: usize,
+    /// Whether the options end with an end option.
+    has_end: bool,
+}
+
+impl Dhcpv4 {
+    #[inline]
+    fn header(&self) -> &Dhcpv4Header {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut Dhcpv4Header {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the message op code.
+    #[inline]
+    pub fn op(&self) -> u8 {
+        self.header().op
+    }
+
+    /// Sets the message op code.
+    #[inline]
+    pub fn set_op(&mut self, op: u8) {
+        self.header_mut().op = op;
+    }
+
+    /// Returns the hardware address type.
+    #[inline]
+    pub fn htype(&self) -> u8 {
+        self.header().htype
+    }
+
+    /// Returns the hardware address length.
+    #[inline]
+    pub fn hlen(&self) -> u8 {
+        self.header().hlen
+    }
+
+    /// Returns the number of relay agent hops.
+    #[inline]
+    pub fn hops(&self) -> u8 {
+        self.header().hops
+    }
+
+    /// Sets the number of relay agent hops.
+    #[inline]
+    pub fn set_hops(&mut self, hops: u8) {
+        self.header_mut().hops = hops;
+    }
+
+    /// Returns the transaction ID.
+    #[inline]
+    pub fn xid(&self) -> u32 {
+        self.header().xid.into()
+    }
+
+    /// Sets the transaction ID.
+    #[inline]
+    pub fn set_xid(&mut self, xid: u32) {
+        self.header_mut().xid = xid.into();
+    }
+
+    /// Returns the seconds elapsed since the client began the exchange.
+    #[inline]
+    pub fn secs(&self) -> u16 {
+        self.header().secs.into()
+    }
+
+    /// Sets the seconds elapsed since the client began the exchange.
+    #[inline]
+    pub fn set_secs(&mut self, secs: u16) {
+        self.header_mut().secs = secs.into();
+    }
+
+    /// Returns a flag indicating whether the client requires the replies
+    /// to be broadcast.
+    #[inline]
+    pub fn broadcast(&self) -> bool {
+        u16::from(self.header().flags) & BROADCAST != 0
+    }
+
+    /// Sets the broadcast flag.
+    #[inline]
+    pub fn set_broadcast(&mut self, broadcast: bool) {
+        let flags = u16::from(self.header().flags);
+        let flags = if broadcast {
+            flags | BROADCAST
+        } else {
+            flags & !BROADCAST
+        };
+        self.header_mut().flags = flags.into();
+    }
+
+    /// Returns the client IP address.
+    #[inline]
+    pub fn ciaddr(&self) -> Ipv4Addr {
+        self.header().ciaddr
+    }
+
+    /// Sets the client IP address.
+    #[inline]
+    pub fn set_ciaddr(&mut self, ciaddr: Ipv4Addr) {
+        self.header_mut().ciaddr = ciaddr;
+    }
+
+    /// Returns the IP address assigned to the client.
+    #[inline]
+    pub fn yiaddr(&self) -> Ipv4Addr {
+        self.header().yiaddr
+    }
+
+    /// Sets the IP address assigned to the client.
+    #[inline]
+    pub fn set_yiaddr(&mut self, yiaddr: Ipv4Addr) {
+        self.header_mut().yiaddr = yiaddr;
+    }
+
+    /// Returns the IP address of the next server to use in bootstrap.
+    #[inline]
+    pub fn siaddr(&self) -> Ipv4Addr {
+        self.header().siaddr
+    }
+
+    /// Sets the IP address of the next server to use in bootstrap.
+    #[inline]
+    pub fn set_siaddr(&mut self, siaddr: Ipv4Addr) {
+        self.header_mut().siaddr = siaddr;
+    }
+
+    /// Returns the relay agent IP address.
+    #[inline]
+    pub fn giaddr(&self) -> Ipv4Addr {
+        self.header().giaddr
+    }
+
+    /// Sets the relay agent IP address.
+    #[inline]
+    pub fn set_giaddr(&mut self, giaddr: Ipv4Addr) {
+        self.header_mut().giaddr = giaddr;
+    }
+
+    /// Returns the client hardware address, up to *hlen* octets.
+    #[inline]
+    pub fn chaddr(&self) -> &[u8] {
+        let len = (self.hlen() as usize).min(16);
+        &self.header().chaddr[..len]
+    }
+
+    /// Returns the client MAC address if the hardware type is Ethernet.
+    #[inline]
+    pub fn client_mac_addr(&self) -> Option<MacAddr> {
+        if self.htype() == HTYPE_ETHERNET && self.hlen() == 6 {
+            let mut octets = [0; 6];
+            octets.copy_from_slice(self.chaddr());
+            Some(MacAddr::from(octets))
+        } else {
+            None
+        }
+    }
+
+    /// Sets the client MAC address, and the hardware type and length to
+    /// Ethernet.
+    #[inline]
+    pub fn set_client_mac_addr(&mut self, mac: MacAddr) {
+        let header = self.header_mut();
+        header.htype = HTYPE_ETHERNET;
+        header.hlen = 6;
+        header.chaddr = [0; 16];
+        header.chaddr[..6].copy_from_slice(&mac.octets());
+    }
+
+    /// Returns the length of the options in octets, including the magic
+    /// cookie and the end option.
+    #[inline]
+    pub fn options_len(&self) -> usize {
+        self.options_len
+    }
+
+    /// Returns the DHCP message type, which is carried in an option.
+    ///
+    /// Returns `None` for a BOOTP message without the message type option.
+    pub fn message_type(&self) -> Option<Dhcpv4MessageType> {
+        let mut iter = self.options_iter();
+        while let Ok(Some(option)) = iter.next() {
+            if let Dhcpv4Option::MessageType(message_type) = option {
+                return Some(message_type);
+            }
+        }
+        None
+    }
+
+    /// Returns an iterator that iterates through the options.
+    #[inline]
+    pub fn options_iter(&self) -> Dhcpv4OptionsIterator<'_> {
+        let start = self.offset + Dhcpv4Header::size_of();
+        Dhcpv4OptionsIterator {
+            mbuf: self.mbuf(),
+            offset: start + DHCPV4_MAGIC_COOKIE.len(),
+            end: start + self.options_len,
+        }
+    }
+
+    /// Returns the options for modification.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut dhcp = udp.push::<Dhcpv4>()?;
+    /// dhcp.options_mut()
+    ///     .append(&Dhcpv4Option::MessageType(Dhcpv4MessageTypes::Discover))?;
+    /// dhcp.reconcile_all();
+    /// ```
+    #[inline]
+    pub fn options_mut(&mut self) -> Dhcpv4Options<'_> {
+        let offset = self.offset + Dhcpv4Header::size_of() + DHCPV4_MAGIC_COOKIE.len();
+        Dhcpv4Options {
+            mbuf: self.envelope.mbuf_mut(),
+            offset,
+            len: &mut self.options_len,
+            has_end: &mut self.has_end,
+        }
+    }
+}
+
+impl fmt::Debug for Dhcpv4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("dhcpv4")
+            .field("op", &self.op())
+            .field("htype", &self.htype())
+            .field("hlen", &self.hlen())
+            .field("hops", &self.hops())
+            .field("xid", &format!("0x{:08x}", self.xid()))
+            .field("secs", &self.secs())
+            .field("broadcast", &self.broadcast())
+            .field("ciaddr", &self.ciaddr())
+            .field("yiaddr", &self.yiaddr())
+            .field("siaddr", &self.siaddr())
+            .field("giaddr", &self.giaddr())
+            .field("chaddr", &self.chaddr())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Dhcpv4 {
+    /// The preceding type for a DHCPv4 message must be UDP over IPv4.
+    type Envelope = Udp<Ipv4>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the fixed fields and the options.
+    #[inline]
+    fn header_len(&self) -> usize {
+        Dhcpv4Header::size_of() + self.options_len
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Dhcpv4 {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+            options_len: self.options_len,
+            has_end: self.has_end,
+        }
+    }
+
+    /// Parses the UDP packet's payload as a DHCPv4 message.
+    ///
+    /// The UDP ports are not checked.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload does not have sufficient data for
+    /// the fixed fields, or if the magic cookie is missing. Returns an
+    /// error if any option is truncated.
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let mut packet = Dhcpv4 {
+            envelope,
+            header,
+            offset,
+            options_len: 0,
+            has_end: false,
+        };
+
+        let start = offset + Dhcpv4Header::size_of();
+        let cookie = packet
+            .mbuf()
+            .read_data_slice::<u8>(start, DHCPV4_MAGIC_COOKIE.len())?;
+        ensure!(
+            unsafe { cookie.as_ref() } == DHCPV4_MAGIC_COOKIE,
+            anyhow!("missing DHCP magic cookie.")
+        );
+
+        // walks the options to find the end option. if there isn't one,
+        // the options extend to the end of the packet.
+        let end = packet.mbuf().data_len();
+        let mut offset = start + DHCPV4_MAGIC_COOKIE.len();
+        while offset < end {
+            let (code, len) = read_option(packet.mbuf(), offset, end)?;
+            offset += len;
+            if code == END {
+                packet.has_end = true;
+                break;
+            }
+        }
+        packet.options_len = offset - start;
+
+        Ok(packet)
+    }
+
+    /// Prepends a DHCPv4 message to the beginning of the UDP's payload.
+    ///
+    /// The op code is set to `BOOTREQUEST`, the hardware type to Ethernet,
+    /// and the options contain only the magic cookie and the end option.
+    /// The UDP ports are not changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        let options_len = DHCPV4_MAGIC_COOKIE.len() + 1;
+        mbuf.extend(offset, Dhcpv4Header::size_of() + options_len)?;
+        let header = mbuf.write_data(offset, &Dhcpv4Header::default())?;
+
+        let start = offset + Dhcpv4Header::size_of();
+        mbuf.write_data_slice(start, &DHCPV4_MAGIC_COOKIE)?;
+        mbuf.write_data_slice(start + DHCPV4_MAGIC_COOKIE.len(), &[END])?;
+
+        Ok(Dhcpv4 {
+            envelope,
+            header,
+            offset,
+            options_len,
+            has_end: true,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// Reads the code and the total length of the option at `offset`.
+fn read_option(mbuf: &Mbuf, offset: usize, end: usize) -> Result<(u8, usize)> {
+    let code = mbuf.read_data_slice::<u8>(offset, 1)?;
+    let code = unsafe { code.as_ref()[0] };
+    if code == PAD || code == END {
+        return Ok((code, 1));
+    }
+
+    ensure!(
+        offset + 2 <= end,
+        anyhow!("DHCP option {} is truncated.", code)
+    );
+    let len = mbuf.read_data_slice::<u8>(offset + 1, 1)?;
+    let len = unsafe { len.as_ref()[0] } as usize + 2;
+    ensure!(
+        offset + len <= end,
+        anyhow!("DHCP option {} is truncated.", code)
+    );
+
+    Ok((code, len))
+}
+
+/// Reads and decodes the option at `offset`, returning `None` for the pad
+/// and end options.
+fn decode_option(mbuf: &Mbuf, offset: usize, end: usize) -> Result<(Option<Dhcpv4Option>, usize)> {
+    let (code, len) = read_option(mbuf, offset, end)?;
+    if code == PAD || code == END {
+        return Ok((None, len));
+    }
+
+    let data = mbuf.read_data_slice::<u8>(offset + 2, len - 2);
+    let data = match data {
+        Ok(data) => unsafe { data.as_ref() },
+        // an option with no data at the end of the buffer.
+        Err(_) if len == 2 => &[],
+        Err(err) => return Err(err),
+    };
+
+    Ok((Some(Dhcpv4Option::decode(code, data)?), len))
+}
+
+/// An iterator that iterates through the DHCPv4 options.
+pub struct Dhcpv4OptionsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl Dhcpv4OptionsIterator<'_> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<Dhcpv4Option>> {
+        while self.offset < self.end {
+            let (option, len) = decode_option(self.mbuf, self.offset, self.end)?;
+            self.offset += len;
+            if option.is_some() {
+                return Ok(option);
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl fmt::Debug for Dhcpv4OptionsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dhcpv4OptionsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// Options in a DHCPv4 message, following the magic cookie.
+///
+/// Adding or removing options resizes the message. The envelope's length
+/// is not updated until the packet is reconciled.
+pub struct Dhcpv4Options<'a> {
+    mbuf: &'a mut Mbuf,
+    offset: usize,
+    /// The length of the options, including the magic cookie.
+    len: &'a mut usize,
+    /// Whether the options end with an end option.
+    has_end: &'a mut bool,
+}
+
+impl Dhcpv4Options<'_> {
+    #[inline]
+    fn end(&self) -> usize {
+        self.offset + *self.len - DHCPV4_MAGIC_COOKIE.len()
+    }
+
+    /// Returns an iterator to read the options.
+    #[inline]
+    pub fn iter(&self) -> Dhcpv4OptionsIterator<'_> {
+        Dhcpv4OptionsIterator {
+            mbuf: self.mbuf,
+            offset: self.offset,
+            end: self.end(),
+        }
+    }
+
+    /// Appends a new option before the end option.
+    ///
+    /// The end option is added if the options don't already have one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the option data is longer than 255 octets, or the
+    /// buffer does not have enough free space.
+    pub fn append(&mut self, option: &Dhcpv4Option) -> Result<()> {
+        let mut bytes = option.encode()?;
+
+        let end = self.end();
+        let offset = if *self.has_end {
+            end - 1
+        } else {
+            bytes.push(END);
+            end
+        };
+
+        self.mbuf.extend(offset, bytes.len())?;
+        self.mbuf.write_data_slice(offset, &bytes)?;
+        *self.len += bytes.len();
+        *self.has_end = true;
+
+        Ok(())
+    }
+
+    /// Retains only the options specified by the predicate.
+    ///
+    /// In other words, remove all options `o` such that `f(o)` returns false.
+    /// The pad and end options are kept. If an error occurs, all removals
+    /// done prior to the error cannot be undone.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut dhcp = udp.parse::<Dhcpv4>()?;
+    /// dhcp.options_mut()
+    ///     .retain(|option| !matches!(option, Dhcpv4Option::RelayAgentInfo(_)))?;
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&Dhcpv4Option) -> bool,
+    {
+        let mut offset = self.offset;
+        while offset < self.end() {
+            let (option, len) = decode_option(self.mbuf, offset, self.end())?;
+            match option {
+                Some(option) if !f(&option) => {
+                    self.mbuf.shrink(offset, len)?;
+                    *self.len -= len;
+                }
+                _ => offset += len,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Dhcpv4Options<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dhcpv4Options")
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .field("has_end", &self.has_end)
+            .finish()
+    }
+}
+
+/// DHCPv4 fixed fields, not including the magic cookie.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct Dhcpv4Header {
+    op: u8,
+    htype: u8,
+    hlen: u8,
+    hops: u8,
+    xid: u32be,
+    secs: u16be,
+    flags: u16be,
+    ciaddr: Ipv4Addr,
+    yiaddr: Ipv4Addr,
+    siaddr: Ipv4Addr,
+    giaddr: Ipv4Addr,
+    chaddr: [u8; 16],
+    sname: [u8; 64],
+    file: [u8; 128],
+}
+
+impl Default for Dhcpv4Header {
+    fn default() -> Self {
+        Dhcpv4Header {
+            op: BOOTREQUEST,
+            htype: HTYPE_ETHERNET,
+            hlen: 6,
+            hops: 0,
+            xid: u32be::default(),
+            secs: u16be::default(),
+            flags: u16be::default(),
+            ciaddr: Ipv4Addr::UNSPECIFIED,
+            yiaddr: Ipv4Addr::UNSPECIFIED,
+            siaddr: Ipv4Addr::UNSPECIFIED,
+            giaddr: Ipv4Addr::UNSPECIFIED,
+            chaddr: [0; 16],
+            sname: [0; 64],
+            file: [0; 128],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::Ethernet;
+
+    #[test]
+    fn size_of_dhcpv4_header() {
+        assert_eq!(236, Dhcpv4Header::size_of());
+    }
+
+    #[test]
+    fn encode_and_decode_dhcpv4_options() {
+        let option = Dhcpv4Option::RelayAgentInfo(vec![
+            RelayAgentSubOption {
+                code: 1,
+                data: b"eth0".to_vec(),
+            },
+            RelayAgentSubOption {
+                code: 2,
+                data: vec![0xaa, 0xbb],
+            },
+        ]);
+        let bytes = option.encode().unwrap();
+        assert_eq!(
+            vec![82, 10, 1, 4, b'e', b't', b'h', b'0', 2, 2, 0xaa, 0xbb],
+            bytes
+        );
+        assert_eq!(option, Dhcpv4Option::decode(bytes[0], &bytes[2..]).unwrap());
+
+        assert!(Dhcpv4Option::decode(ROUTER, &[10, 0, 0]).is_err());
+        assert!(Dhcpv4Option::decode(RELAY_AGENT_INFO, &[1, 4, 0]).is_err());
+        assert!(Dhcpv4Option::Other {
+            code: END,
+            data: vec![]
+        }
+        .encode()
+        .is_err());
+    }
+
+    fn discover_packet() -> Vec<u8> {
+        let mut bytes = DHCPV4_DISCOVER_HEADERS.to_vec();
+        let mut dhcp = [0; 236];
+        // op = BOOTREQUEST, htype = Ethernet, hlen = 6, hops = 0
+        dhcp[..4].copy_from_slice(&[1, 1, 6, 0]);
+        // xid
+        dhcp[4..8].copy_from_slice(&[0x39, 0x03, 0xf3, 0x26]);
+        // flags = broadcast
+        dhcp[10] = 0x80;
+        // chaddr
+        dhcp[28..34].copy_from_slice(&[0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42]);
+        bytes.extend_from_slice(&dhcp);
+        bytes.extend_from_slice(&DHCPV4_MAGIC_COOKIE);
+        bytes.extend_from_slice(&DHCPV4_DISCOVER_OPTIONS);
+        bytes
+    }
+
+    #[capsule::test]
+    fn parse_dhcpv4_packet() {
+        let packet = Mbuf::from_bytes(&discover_packet()).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        assert_eq!(DHCPV4_SERVER_PORT, udp.dst_port());
+
+        let dhcp = udp.parse::<Dhcpv4>().unwrap();
+        assert_eq!(BOOTREQUEST, dhcp.op());
+        assert_eq!(0x3903_f326, dhcp.xid());
+        assert!(dhcp.broadcast());
+        assert_eq!(Ipv4Addr::UNSPECIFIED, dhcp.giaddr());
+        assert_eq!(
+            "00:0b:82:01:fc:42",
+            dhcp.client_mac_addr().unwrap().to_string()
+        );
+        assert_eq!(Some(Dhcpv4MessageTypes::Discover), dhcp.message_type());
+        assert_eq!(4 + DHCPV4_DISCOVER_OPTIONS.len(), dhcp.options_len());
+
+        let mut iter = dhcp.options_iter();
+        assert_eq!(
+            Dhcpv4Option::MessageType(Dhcpv4MessageTypes::Discover),
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(
+            Dhcpv4Option::RequestedIpAddr(Ipv4Addr::new(192, 168, 1, 100)),
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(
+            Dhcpv4Option::Other {
+                code: 55,
+                data: vec![1, 3, 6]
+            },
+            iter.next().unwrap().unwrap()
+        );
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn parse_dhcpv4_without_magic_cookie() {
+        let mut bytes = discover_packet();
+        bytes[42 + 236] = 0;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Dhcpv4>().is_err());
+    }
+
+    #[capsule::test]
+    fn insert_relay_agent_info() {
+        let packet = Mbuf::from_bytes(&discover_packet()).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let mut dhcp = udp.parse::<Dhcpv4>().unwrap();
+
+        let relay_info = Dhcpv4Option::RelayAgentInfo(vec![RelayAgentSubOption {
+            code: 1,
+            data: b"eth0".to_vec(),
+        }]);
+        dhcp.set_giaddr(Ipv4Addr::new(10, 0, 0, 1));
+        dhcp.options_mut().append(&relay_info).unwrap();
+        assert_eq!(4 + DHCPV4_DISCOVER_OPTIONS.len() + 8, dhcp.options_len());
+
+        let mut iter = dhcp.options_iter();
+        let mut last = None;
+        while let Some(option) = iter.next().unwrap() {
+            last = Some(option);
+        }
+        assert_eq!(Some(relay_info), last);
+
+        // the end option is still the last option.
+        let end = dhcp.offset() + dhcp.header_len() - 1;
+        let code = dhcp.mbuf().read_data_slice::<u8>(end, 1).unwrap();
+        assert_eq!(END, unsafe { code.as_ref()[0] });
+
+        dhcp.options_mut()
+            .retain(|option| !matches!(option, Dhcpv4Option::RelayAgentInfo(_)))
+            .unwrap();
+        assert_eq!(4 + DHCPV4_DISCOVER_OPTIONS.len(), dhcp.options_len());
+
+        dhcp.reconcile_all();
+        let udp = dhcp.deparse();
+        assert_eq!(
+            8 + 236 + 4 + DHCPV4_DISCOVER_OPTIONS.len(),
+            udp.length() as usize
+        );
+    }
+
+    #[capsule::test]
+    fn append_dhcpv4_option_without_end() {
+        // the last option's data ends in 0xff, but there's no end option.
+        let mut bytes = discover_packet();
+        bytes.truncate(bytes.len() - DHCPV4_DISCOVER_OPTIONS.len());
+        bytes.extend_from_slice(&[0x35, 0x01, 0x01, 0x3d, 0x02, 0x01, 0xff]);
+
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let mut dhcp = udp.parse::<Dhcpv4>().unwrap();
+        assert_eq!(4 + 7, dhcp.options_len());
+
+        let dns = Dhcpv4Option::DomainNameServer(vec![Ipv4Addr::new(8, 8, 8, 8)]);
+        dhcp.options_mut().append(&dns).unwrap();
+        assert_eq!(4 + 7 + 6 + 1, dhcp.options_len());
+
+        let mut iter = dhcp.options_iter();
+        assert_eq!(
+            Dhcpv4Option::MessageType(Dhcpv4MessageTypes::Discover),
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(
+            Dhcpv4Option::Other {
+                code: 0x3d,
+                data: vec![0x01, 0xff]
+            },
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(dns, iter.next().unwrap().unwrap());
+        assert!(iter.next().unwrap().is_none());
+
+        // the end option is added once.
+        dhcp.options_mut().append(&dns).unwrap();
+        assert_eq!(4 + 7 + 6 + 6 + 1, dhcp.options_len());
+    }
+
+    #[capsule::test]
+    fn push_dhcpv4_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        let mut dhcp = udp.push::<Dhcpv4>().unwrap();
+
+        assert_eq!(BOOTREQUEST, dhcp.op());
+        assert_eq!(5, dhcp.options_len());
+        assert_eq!(None, dhcp.message_type());
+
+        dhcp.set_client_mac_addr(MacAddr::new(0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42));
+        dhcp.options_mut()
+            .append(&Dhcpv4Option::MessageType(Dhcpv4MessageTypes::Request))
+            .unwrap();
+        dhcp.options_mut()
+            .append(&Dhcpv4Option::DomainNameServer(vec![
+                Ipv4Addr::new(8, 8, 8, 8),
+                Ipv4Addr::new(8, 8, 4, 4),
+            ]))
+            .unwrap();
+
+        assert_eq!(5 + 3 + 10, dhcp.options_len());
+        assert_eq!(Some(Dhcpv4MessageTypes::Request), dhcp.message_type());
+        assert_eq!(236 + 18, dhcp.len());
+    }
+
+    /// Ethernet, IPv4 and UDP headers of a DHCPDISCOVER message with 16
+    /// octets of options.
+    #[rustfmt::skip]
+    const DHCPV4_DISCOVER_HEADERS: [u8; 42] = [
+    // Ethernet header
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42,
+        0x08, 0x00,
+    // IPv4 header
+        0x45, 0x00,
+        // total length = 284
+        0x01, 0x1c,
+        0x00, 0x00, 0x00, 0x00,
+        // ttl = 64, protocol = UDP
+        0x40, 0x11, 0x00, 0x00,
+        // src = 0.0.0.0
+        0x00, 0x00, 0x00, 0x00,
+        // dst = 255.255.255.255
+        0xff, 0xff, 0xff, 0xff,
+    // UDP header
+        // src_port = 68, dst_port = 67
+        0x00, 0x44, 0x00, 0x43,
+        // length = 264, checksum = 0
+        0x01, 0x08, 0x00, 0x00,
+    ];
+
+    /// Options of a DHCPDISCOVER message, following the magic cookie.
+    #[rustfmt::skip]
+    const DHCPV4_DISCOVER_OPTIONS: [u8; 16] = [
+        // message type = DHCPDISCOVER
+        0x35, 0x01, 0x01,
+        // requested ip = 192.168.1.100
+        0x32, 0x04, 0xc0, 0xa8, 0x01, 0x64,
+        // parameter request list
+        0x37, 0x03, 0x01, 0x03, 0x06,
+        // pad, end
+        0x00, 0xff,
+    ];
+}
@@ -0,0 +1,1333 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::ip::IpPacket;
+use crate::packets::types::u16be;
+use crate::packets::{Internal, Packet, Udp};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ptr::NonNull;
+
+/// The well-known UDP port for DNS.
+pub const DNS_PORT: u16 = 53;
+
+/// The maximum length of an encoded domain name in octets.
+pub const DNS_MAX_NAME_LEN: usize = 255;
+
+/// The maximum length of a label in octets.
+pub const DNS_MAX_LABEL_LEN: usize = 63;
+
+const QR: u16 = 0x8000;
+const OPCODE: u16 = 0x7800;
+const AA: u16 = 0x0400;
+const TC: u16 = 0x0200;
+const RD: u16 = 0x0100;
+const RA: u16 = 0x0080;
+const RCODE: u16 = 0x000f;
+
+/// Compression pointers have the two high bits set.
+const POINTER: u8 = 0xc0;
+
+// Indices of the sections in the message.
+const QUESTION: usize = 0;
+const ANSWER: usize = 1;
+const AUTHORITY: usize = 2;
+const ADDITIONAL: usize = 3;
+
+/// The length of the type, class, TTL and data length fields of a
+/// resource record.
+const RECORD_FIXED_LEN: usize = 10;
+
+/// The length of the type and class fields of a question.
+const QUESTION_FIXED_LEN: usize = 4;
+
+/// [IANA] assigned DNS resource record type.
+///
+/// See [`DnsTypes`] for which are current supported.
+///
+/// [IANA]: https://www.iana.org/assignments/dns-parameters/dns-parameters.xhtml#dns-parameters-4
+/// [`DnsTypes`]: crate::packets::DnsTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct DnsType(pub u16);
+
+impl DnsType {
+    /// Creates a new resource record type.
+    pub fn new(value: u16) -> Self {
+        DnsType(value)
+    }
+}
+
+/// Supported DNS resource record types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod DnsTypes {
+    use super::DnsType;
+
+    /// IPv4 host address.
+    pub const A: DnsType = DnsType(1);
+
+    /// Authoritative name server.
+    pub const Ns: DnsType = DnsType(2);
+
+    /// Canonical name for an alias.
+    pub const Cname: DnsType = DnsType(5);
+
+    /// Start of a zone of authority.
+    pub const Soa: DnsType = DnsType(6);
+
+    /// Domain name pointer.
+    pub const Ptr: DnsType = DnsType(12);
+
+    /// Mail exchange.
+    pub const Mx: DnsType = DnsType(15);
+
+    /// Text strings.
+    pub const Txt: DnsType = DnsType(16);
+
+    /// IPv6 host address.
+    pub const Aaaa: DnsType = DnsType(28);
+
+    /// Server selection.
+    pub const Srv: DnsType = DnsType(33);
+
+    /// EDNS option pseudo-record.
+    pub const Opt: DnsType = DnsType(41);
+
+    /// Request for all records, only valid in a question.
+    pub const Any: DnsType = DnsType(255);
+}
+
+impl fmt::Display for DnsType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                DnsTypes::A => "A".to_string(),
+                DnsTypes::Ns => "NS".to_string(),
+                DnsTypes::Cname => "CNAME".to_string(),
+                DnsTypes::Soa => "SOA".to_string(),
+                DnsTypes::Ptr => "PTR".to_string(),
+                DnsTypes::Mx => "MX".to_string(),
+                DnsTypes::Txt => "TXT".to_string(),
+                DnsTypes::Aaaa => "AAAA".to_string(),
+                DnsTypes::Srv => "SRV".to_string(),
+                DnsTypes::Opt => "OPT".to_string(),
+                DnsTypes::Any => "ANY".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("0x{:04x}", t)
+                }
+            }
+        )
+    }
+}
+
+/// The Internet DNS class.
+pub const DNS_CLASS_IN: u16 = 1;
+
+/// DNS response code.
+///
+/// See [`DnsResponseCodes`] for which are current supported.
+///
+/// [`DnsResponseCodes`]: crate::packets::DnsResponseCodes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct DnsResponseCode(pub u8);
+
+impl DnsResponseCode {
+    /// Creates a new response code.
+    pub fn new(value: u8) -> Self {
+        DnsResponseCode(value)
+    }
+}
+
+/// Supported DNS response codes.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod DnsResponseCodes {
+    use super::DnsResponseCode;
+
+    /// No error condition.
+    pub const NoError: DnsResponseCode = DnsResponseCode(0);
+
+    /// The server was unable to interpret the query.
+    pub const FormErr: DnsResponseCode = DnsResponseCode(1);
+
+    /// The server was unable to process the query.
+    pub const ServFail: DnsResponseCode = DnsResponseCode(2);
+
+    /// The domain name referenced in the query does not exist.
+    pub const NxDomain: DnsResponseCode = DnsResponseCode(3);
+
+    /// The server does not support the requested kind of query.
+    pub const NotImp: DnsResponseCode = DnsResponseCode(4);
+
+    /// The server refuses to perform the specified operation.
+    pub const Refused: DnsResponseCode = DnsResponseCode(5);
+}
+
+impl fmt::Display for DnsResponseCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                DnsResponseCodes::NoError => "NoError".to_string(),
+                DnsResponseCodes::FormErr => "FormErr".to_string(),
+                DnsResponseCodes::ServFail => "ServFail".to_string(),
+                DnsResponseCodes::NxDomain => "NXDomain".to_string(),
+                DnsResponseCodes::NotImp => "NotImp".to_string(),
+                DnsResponseCodes::Refused => "Refused".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// Domain Name System message based on [IETF RFC 1035].
+///
+/// ```
+///                                 1  1  1  1  1  1
+///   0  1  2  3  4  5  6  7  8  9  0  1  2  3  4  5
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                      ID                       |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |QR|   Opcode  |AA|TC|RD|RA|   Z    |   RCODE   |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                    QDCOUNT                    |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                    ANCOUNT                    |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                    NSCOUNT                    |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                    ARCOUNT                    |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                   Question                    |
+/// ~                                               ~
+/// |                    Answer                     |
+/// ~                                               ~
+/// |                   Authority                   |
+/// ~                                               ~
+/// |                  Additional                   |
+/// ~                                               ~
+/// ```
+///
+/// - *ID*:                 16 bits. An identifier assigned by the program
+///                         that generates the query, copied into the reply.
+///
+/// - *QR*:                 1 bit. Specifies whether the message is a query
+///                         (0), or a response (1).
+///
+/// - *Opcode*:             4 bits. Specifies the kind of query.
+///
+/// - *AA*:                 1 bit. Authoritative Answer.
+///
+/// - *TC*:                 1 bit. Specifies that the message was truncated.
+///
+/// - *RD*:                 1 bit. Recursion Desired.
+///
+/// - *RA*:                 1 bit. Recursion Available.
+///
+/// - *RCODE*:              4 bits. Response code.
+///
+/// - *QDCOUNT*, *ANCOUNT*, *NSCOUNT* and *ARCOUNT*: 16 bits each. The
+///                         number of entries in the question, answer,
+///                         authority and additional sections.
+///
+/// The sections are read in place. Domain names, including compressed
+/// names, are decoded lazily from the buffer without copying. The whole
+/// message is validated when parsed, so a malformed message is an error.
+///
+/// ```
+/// let mut batch = batch.group_by(
+///     |packet| {
+///         packet
+///             .questions_iter()
+///             .next()
+///             .ok()
+///             .flatten()
+///             .map_or(false, |q| q.name().matches("example.com"))
+///     },
+///     |groups| {
+///         compose!( groups {
+///             true => |group| {
+///                 group.map(answer_locally)
+///             }
+///             false => |group| {
+///                 group.map(forward)
+///             }
+///         })
+///     },
+/// );
+/// ```
+///
+/// A response is built by pushing a message onto a UDP packet, and then
+/// appending the sections in order. Names of resource records matching a
+/// question are compressed into a pointer to the question.
+///
+/// ```
+/// let mut dns = udp.push::<Dns<Ipv4>>()?;
+/// dns.set_id(query_id);
+/// dns.set_response(true);
+/// dns.append_question("example.com", DnsTypes::A, DNS_CLASS_IN)?;
+/// dns.append_answer("example.com", DnsTypes::A, DNS_CLASS_IN, 3600, &addr.octets())?;
+/// dns.reconcile_all();
+/// ```
+///
+/// [IETF RFC 1035]: https://tools.ietf.org/html/rfc1035#section-4.1
+pub struct Dns<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<DnsHeader>,
+    offset: usize,
+    /// The offsets of the answer, authority and additional sections, and
+    /// the end of the message.
+    sections: [usize; 4],
+}
+
+impl<E: IpPacket> Dns<E> {
+    #[inline]
+    fn header(&self) -> &DnsHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut DnsHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    #[inline]
+    fn flags(&self) -> u16 {
+        self.header().flags.into()
+    }
+
+    #[inline]
+    fn set_flags(&mut self, mask: u16, value: u16) {
+        let flags = self.flags() & !mask;
+        self.header_mut().flags = (flags | (value & mask)).into();
+    }
+
+    #[inline]
+    fn count(&self, section: usize) -> u16 {
+        self.header().counts[section].into()
+    }
+
+    /// Returns the message identifier.
+    #[inline]
+    pub fn id(&self) -> u16 {
+        self.header().id.into()
+    }
+
+    /// Sets the message identifier.
+    #[inline]
+    pub fn set_id(&mut self, id: u16) {
+        self.header_mut().id = id.into();
+    }
+
+    /// Returns a flag indicating whether the message is a response.
+    #[inline]
+    pub fn is_response(&self) -> bool {
+        self.flags() & QR != 0
+    }
+
+    /// Sets the flag indicating whether the message is a response.
+    #[inline]
+    pub fn set_response(&mut self, response: bool) {
+        self.set_flags(QR, if response { QR } else { 0 });
+    }
+
+    /// Returns the kind of query.
+    #[inline]
+    pub fn opcode(&self) -> u8 {
+        ((self.flags() & OPCODE) >> 11) as u8
+    }
+
+    /// Sets the kind of query.
+    #[inline]
+    pub fn set_opcode(&mut self, opcode: u8) {
+        self.set_flags(OPCODE, (opcode as u16) << 11);
+    }
+
+    /// Returns a flag indicating whether the answer is authoritative.
+    #[inline]
+    pub fn authoritative(&self) -> bool {
+        self.flags() & AA != 0
+    }
+
+    /// Sets the flag indicating whether the answer is authoritative.
+    #[inline]
+    pub fn set_authoritative(&mut self, authoritative: bool) {
+        self.set_flags(AA, if authoritative { AA } else { 0 });
+    }
+
+    /// Returns a flag indicating whether the message was truncated.
+    #[inline]
+    pub fn truncated(&self) -> bool {
+        self.flags() & TC != 0
+    }
+
+    /// Sets the flag indicating whether the message was truncated.
+    #[inline]
+    pub fn set_truncated(&mut self, truncated: bool) {
+        self.set_flags(TC, if truncated { TC } else { 0 });
+    }
+
+    /// Returns a flag indicating whether recursion is desired.
+    #[inline]
+    pub fn recursion_desired(&self) -> bool {
+        self.flags() & RD != 0
+    }
+
+    /// Sets the flag indicating whether recursion is desired.
+    #[inline]
+    pub fn set_recursion_desired(&mut self, desired: bool) {
+        self.set_flags(RD, if desired { RD } else { 0 });
+    }
+
+    /// Returns a flag indicating whether recursion is available.
+    #[inline]
+    pub fn recursion_available(&self) -> bool {
+        self.flags() & RA != 0
+    }
+
+    /// Sets the flag indicating whether recursion is available.
+    #[inline]
+    pub fn set_recursion_available(&mut self, available: bool) {
+        self.set_flags(RA, if available { RA } else { 0 });
+    }
+
+    /// Returns the response code.
+    #[inline]
+    pub fn response_code(&self) -> DnsResponseCode {
+        DnsResponseCode((self.flags() & RCODE) as u8)
+    }
+
+    /// Sets the response code.
+    #[inline]
+    pub fn set_response_code(&mut self, rcode: DnsResponseCode) {
+        self.set_flags(RCODE, rcode.0 as u16);
+    }
+
+    /// Returns the number of entries in the question section.
+    #[inline]
+    pub fn question_count(&self) -> u16 {
+        self.count(QUESTION)
+    }
+
+    /// Returns the number of resource records in the answer section.
+    #[inline]
+    pub fn answer_count(&self) -> u16 {
+        self.count(ANSWER)
+    }
+
+    /// Returns the number of resource records in the authority section.
+    #[inline]
+    pub fn authority_count(&self) -> u16 {
+        self.count(AUTHORITY)
+    }
+
+    /// Returns the number of resource records in the additional section.
+    #[inline]
+    pub fn additional_count(&self) -> u16 {
+        self.count(ADDITIONAL)
+    }
+
+    /// Returns an iterator that iterates through the questions.
+    #[inline]
+    pub fn questions_iter(&self) -> DnsQuestionsIterator<'_> {
+        DnsQuestionsIterator {
+            mbuf: self.mbuf(),
+            base: self.offset,
+            offset: self.offset + DnsHeader::size_of(),
+            remaining: self.question_count(),
+        }
+    }
+
+    #[inline]
+    fn records_iter(&self, section: usize) -> DnsRecordsIterator<'_> {
+        DnsRecordsIterator {
+            mbuf: self.mbuf(),
+            base: self.offset,
+            offset: self.sections[section - 1],
+            remaining: self.count(section),
+        }
+    }
+
+    /// Returns an iterator that iterates through the answer records.
+    #[inline]
+    pub fn answers_iter(&self) -> DnsRecordsIterator<'_> {
+        self.records_iter(ANSWER)
+    }
+
+    /// Returns an iterator that iterates through the authority records.
+    #[inline]
+    pub fn authorities_iter(&self) -> DnsRecordsIterator<'_> {
+        self.records_iter(AUTHORITY)
+    }
+
+    /// Returns an iterator that iterates through the additional records.
+    #[inline]
+    pub fn additionals_iter(&self) -> DnsRecordsIterator<'_> {
+        self.records_iter(ADDITIONAL)
+    }
+
+    /// Walks through all the sections, and records where each section
+    /// starts and where the message ends.
+    fn index_sections(&mut self) -> Result<()> {
+        let mut questions = self.questions_iter();
+        while questions.next()?.is_some() {}
+        let mut offset = questions.offset;
+
+        for section in ANSWER..=ADDITIONAL {
+            self.sections[section - 1] = offset;
+            let mut records = self.records_iter(section);
+            while records.next()?.is_some() {}
+            offset = records.offset;
+        }
+        self.sections[ADDITIONAL] = offset;
+
+        Ok(())
+    }
+
+    /// Encodes the name, or a pointer to a question with the same name.
+    fn encode_record_name(&self, name: &str) -> Result<Vec<u8>> {
+        let mut questions = self.questions_iter();
+        while let Some(question) = questions.next()? {
+            let pointer = question.name().offset - self.offset;
+            if pointer <= 0x3fff && question.name().matches(name) {
+                return Ok((0xc000 | pointer as u16).to_be_bytes().to_vec());
+            }
+        }
+
+        encode_name(name)
+    }
+
+    /// Appends the encoded entry to the end of the section.
+    ///
+    /// Because entries in later sections may point at names in earlier
+    /// ones, the entry can only be appended if all later sections are empty.
+    fn append_entry(&mut self, section: usize, entry: &[u8]) -> Result<()> {
+        ensure!(
+            (section + 1..=ADDITIONAL).all(|s| self.count(s) == 0),
+            anyhow!("DNS sections must be appended in order.")
+        );
+        let count = self.count(section);
+        ensure!(count < u16::MAX, anyhow!("too many DNS section entries."));
+
+        let end = self.sections[ADDITIONAL];
+        self.mbuf_mut().extend(end, entry.len())?;
+        self.mbuf_mut().write_data_slice(end, entry)?;
+
+        self.header_mut().counts[section] = (count + 1).into();
+        for start in self.sections.iter_mut().skip(section) {
+            *start = end + entry.len();
+        }
+
+        Ok(())
+    }
+
+    /// Appends a question to the question section.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is not a valid domain name, or if any
+    /// resource record is already appended. Returns an error if the
+    /// buffer does not have enough free space.
+    pub fn append_question(&mut self, name: &str, qtype: DnsType, qclass: u16) -> Result<()> {
+        let mut entry = encode_name(name)?;
+        entry.extend_from_slice(&qtype.0.to_be_bytes());
+        entry.extend_from_slice(&qclass.to_be_bytes());
+        self.append_entry(QUESTION, &entry)
+    }
+
+    fn append_record(
+        &mut self,
+        section: usize,
+        name: &str,
+        rtype: DnsType,
+        class: u16,
+        ttl: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        ensure!(
+            data.len() <= u16::MAX as usize,
+            anyhow!("DNS record data is too long.")
+        );
+
+        let mut entry = self.encode_record_name(name)?;
+        entry.extend_from_slice(&rtype.0.to_be_bytes());
+        entry.extend_from_slice(&class.to_be_bytes());
+        entry.extend_from_slice(&ttl.to_be_bytes());
+        entry.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        entry.extend_from_slice(data);
+        self.append_entry(section, &entry)
+    }
+
+    /// Appends a resource record to the answer section.
+    ///
+    /// The record data is written as is. Names inside the data, for
+    /// example the target of a CNAME record, are not compressed and can
+    /// be encoded with [`encode_name`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is not a valid domain name, or if any
+    /// authority or additional record is already appended. Returns an error
+    /// if the buffer does not have enough free space.
+    ///
+    /// [`encode_name`]: encode_name
+    pub fn append_answer(
+        &mut self,
+        name: &str,
+        rtype: DnsType,
+        class: u16,
+        ttl: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        self.append_record(ANSWER, name, rtype, class, ttl, data)
+    }
+
+    /// Appends a resource record to the authority section.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is not a valid domain name, or if any
+    /// additional record is already appended. Returns an error if the
+    /// buffer does not have enough free space.
+    pub fn append_authority(
+        &mut self,
+        name: &str,
+        rtype: DnsType,
+        class: u16,
+        ttl: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        self.append_record(AUTHORITY, name, rtype, class, ttl, data)
+    }
+
+    /// Appends a resource record to the additional section.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is not a valid domain name. Returns an
+    /// error if the buffer does not have enough free space.
+    pub fn append_additional(
+        &mut self,
+        name: &str,
+        rtype: DnsType,
+        class: u16,
+        ttl: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        self.append_record(ADDITIONAL, name, rtype, class, ttl, data)
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Dns<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("dns")
+            .field("id", &self.id())
+            .field("response", &self.is_response())
+            .field("opcode", &self.opcode())
+            .field("authoritative", &self.authoritative())
+            .field("truncated", &self.truncated())
+            .field("recursion_desired", &self.recursion_desired())
+            .field("recursion_available", &self.recursion_available())
+            .field("response_code", &format!("{}", self.response_code()))
+            .field("question_count", &self.question_count())
+            .field("answer_count", &self.answer_count())
+            .field("authority_count", &self.authority_count())
+            .field("additional_count", &self.additional_count())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Dns<E> {
+    /// The preceding type for a DNS message must be UDP.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        DnsHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Dns::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+            sections: self.sections,
+        }
+    }
+
+    /// Parses the UDP packet's payload as a DNS message.
+    ///
+    /// The UDP ports are not checked. All the sections are validated,
+    /// including the compressed names.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload does not have sufficient data for
+    /// the header and the sections, or if a name in any section is
+    /// malformed.
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let mut packet = Dns {
+            envelope,
+            header,
+            offset,
+            sections: [0; 4],
+        };
+        packet.index_sections()?;
+
+        Ok(packet)
+    }
+
+    /// Prepends an empty DNS message to the beginning of the UDP's payload.
+    ///
+    /// The UDP ports are not changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, DnsHeader::size_of())?;
+        let header = mbuf.write_data(offset, &DnsHeader::default())?;
+        let end = offset + DnsHeader::size_of();
+
+        Ok(Dns {
+            envelope,
+            header,
+            offset,
+            sections: [end; 4],
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// Reads `len` octets at `offset`.
+#[inline]
+fn read_bytes(mbuf: &Mbuf, offset: usize, len: usize) -> Result<&[u8]> {
+    if len == 0 {
+        Ok(&[])
+    } else {
+        let bytes = mbuf.read_data_slice::<u8>(offset, len)?;
+        Ok(unsafe { &*bytes.as_ptr() })
+    }
+}
+
+/// Encodes a domain name in the uncompressed wire format.
+///
+/// The name is a sequence of labels separated by dots, with an optional
+/// trailing dot. An empty name or a single dot is the root.
+///
+/// # Errors
+///
+/// Returns an error if a label is empty or longer than 63 octets, or if
+/// the encoded name is longer than 255 octets.
+pub fn encode_name(name: &str) -> Result<Vec<u8>> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let mut bytes = Vec::with_capacity(name.len() + 2);
+
+    if !name.is_empty() {
+        for label in name.split('.') {
+            ensure!(
+                !label.is_empty() && label.len() <= DNS_MAX_LABEL_LEN,
+                anyhow!("invalid DNS label '{}'.", label)
+            );
+            bytes.push(label.len() as u8);
+            bytes.extend_from_slice(label.as_bytes());
+        }
+    }
+    bytes.push(0);
+
+    ensure!(
+        bytes.len() <= DNS_MAX_NAME_LEN,
+        anyhow!("DNS name '{}' is too long.", name)
+    );
+
+    Ok(bytes)
+}
+
+/// A domain name read in place, possibly compressed.
+#[derive(Clone, Copy)]
+pub struct DnsName<'a> {
+    mbuf: &'a Mbuf,
+    /// The offset of the message, which compression pointers are relative to.
+    base: usize,
+    offset: usize,
+    /// The number of octets the name occupies in place, up to and including
+    /// the terminating zero octet or the first compression pointer.
+    len: usize,
+}
+
+impl<'a> DnsName<'a> {
+    /// Reads and validates the name at `offset`.
+    ///
+    /// Compression pointers must point backwards, and the decoded name
+    /// must not exceed 255 octets, so a malicious name can not loop.
+    fn parse(mbuf: &'a Mbuf, base: usize, offset: usize) -> Result<Self> {
+        let mut pos = offset;
+        let mut len = None;
+        let mut decoded = 0;
+
+        loop {
+            let octet = read_bytes(mbuf, pos, 1)?[0];
+            match octet & POINTER {
+                0 if octet == 0 => {
+                    decoded += 1;
+                    ensure!(
+                        decoded <= DNS_MAX_NAME_LEN,
+                        anyhow!("DNS name is too long.")
+                    );
+                    break;
+                }
+                0 => {
+                    decoded += octet as usize + 1;
+                    ensure!(decoded < DNS_MAX_NAME_LEN, anyhow!("DNS name is too long."));
+                    read_bytes(mbuf, pos + 1, octet as usize)?;
+                    pos += octet as usize + 1;
+                }
+                POINTER => {
+                    let pointer = read_bytes(mbuf, pos, 2)?;
+                    let target =
+                        base + (u16::from_be_bytes([pointer[0], pointer[1]]) & 0x3fff) as usize;
+                    ensure!(
+                        target < pos,
+                        anyhow!("invalid DNS name compression pointer.")
+                    );
+                    len.get_or_insert(pos + 2 - offset);
+                    pos = target;
+                }
+                _ => return Err(anyhow!("invalid DNS label type 0x{:02x}.", octet)),
+            }
+        }
+
+        Ok(DnsName {
+            mbuf,
+            base,
+            offset,
+            len: len.unwrap_or(pos + 1 - offset),
+        })
+    }
+
+    /// Returns an iterator over the labels of the name, following the
+    /// compression pointers.
+    #[inline]
+    pub fn labels(&self) -> DnsLabels<'a> {
+        DnsLabels {
+            mbuf: self.mbuf,
+            base: self.base,
+            offset: self.offset,
+        }
+    }
+
+    /// Returns whether the name is equal to `name`, ignoring ASCII case and
+    /// a trailing dot.
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.strip_suffix('.').unwrap_or(name);
+        let mut labels = self.labels();
+
+        if !name.is_empty() {
+            for expected in name.split('.') {
+                match labels.next() {
+                    Some(label) if label.eq_ignore_ascii_case(expected.as_bytes()) => (),
+                    _ => return false,
+                }
+            }
+        }
+
+        labels.next().is_none()
+    }
+}
+
+impl fmt::Display for DnsName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut labels = self.labels().peekable();
+        if labels.peek().is_none() {
+            return write!(f, ".");
+        }
+
+        while let Some(label) = labels.next() {
+            write!(f, "{}", String::from_utf8_lossy(label))?;
+            if labels.peek().is_some() {
+                write!(f, ".")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for DnsName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+/// An iterator over the labels of a domain name.
+pub struct DnsLabels<'a> {
+    mbuf: &'a Mbuf,
+    base: usize,
+    offset: usize,
+}
+
+impl<'a> Iterator for DnsLabels<'a> {
+    type Item = &'a [u8];
+
+    /// The name is already validated, so the labels are read without error
+    /// checking beyond the buffer bounds.
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let octet = read_bytes(self.mbuf, self.offset, 1).ok()?[0];
+            if octet & POINTER == POINTER {
+                let pointer = read_bytes(self.mbuf, self.offset, 2).ok()?;
+                self.offset =
+                    self.base + (u16::from_be_bytes([pointer[0], pointer[1]]) & 0x3fff) as usize;
+            } else if octet == 0 {
+                return None;
+            } else {
+                let label = read_bytes(self.mbuf, self.offset + 1, octet as usize).ok()?;
+                self.offset += octet as usize + 1;
+                return Some(label);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for DnsLabels<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsLabels")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// An entry in the question section.
+#[derive(Clone, Copy, Debug)]
+pub struct DnsQuestion<'a> {
+    name: DnsName<'a>,
+    qtype: DnsType,
+    qclass: u16,
+}
+
+impl<'a> DnsQuestion<'a> {
+    /// Returns the name being queried.
+    #[inline]
+    pub fn name(&self) -> DnsName<'a> {
+        self.name
+    }
+
+    /// Returns the type of the query.
+    #[inline]
+    pub fn qtype(&self) -> DnsType {
+        self.qtype
+    }
+
+    /// Returns the class of the query.
+    #[inline]
+    pub fn qclass(&self) -> u16 {
+        self.qclass
+    }
+}
+
+/// An iterator that iterates through the questions.
+pub struct DnsQuestionsIterator<'a> {
+    mbuf: &'a Mbuf,
+    base: usize,
+    offset: usize,
+    remaining: u16,
+}
+
+impl<'a> DnsQuestionsIterator<'a> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<DnsQuestion<'a>>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        let name = DnsName::parse(self.mbuf, self.base, self.offset)?;
+        let fixed = read_bytes(self.mbuf, self.offset + name.len, QUESTION_FIXED_LEN)?;
+        let question = DnsQuestion {
+            name,
+            qtype: DnsType(u16::from_be_bytes([fixed[0], fixed[1]])),
+            qclass: u16::from_be_bytes([fixed[2], fixed[3]]),
+        };
+
+        self.offset += name.len + QUESTION_FIXED_LEN;
+        self.remaining -= 1;
+
+        Ok(Some(question))
+    }
+}
+
+impl fmt::Debug for DnsQuestionsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsQuestionsIterator")
+            .field("offset", &self.offset)
+            .field("remaining", &self.remaining)
+            .finish()
+    }
+}
+
+/// A resource record in the answer, authority or additional section.
+#[derive(Clone, Copy, Debug)]
+pub struct DnsRecord<'a> {
+    name: DnsName<'a>,
+    rtype: DnsType,
+    class: u16,
+    ttl: u32,
+    data: &'a [u8],
+    data_offset: usize,
+}
+
+impl<'a> DnsRecord<'a> {
+    /// Returns the name of the node to which the record pertains.
+    #[inline]
+    pub fn name(&self) -> DnsName<'a> {
+        self.name
+    }
+
+    /// Returns the record type.
+    #[inline]
+    pub fn rtype(&self) -> DnsType {
+        self.rtype
+    }
+
+    /// Returns the class of the record data.
+    #[inline]
+    pub fn class(&self) -> u16 {
+        self.class
+    }
+
+    /// Returns the time interval, in seconds, that the record may be cached.
+    #[inline]
+    pub fn ttl(&self) -> u32 {
+        self.ttl
+    }
+
+    /// Returns the record data.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the address of an A or AAAA record.
+    pub fn addr(&self) -> Option<IpAddr> {
+        match (self.rtype, self.data.len()) {
+            (DnsTypes::A, 4) => {
+                let mut octets = [0; 4];
+                octets.copy_from_slice(self.data);
+                Some(IpAddr::V4(Ipv4Addr::from(octets)))
+            }
+            (DnsTypes::Aaaa, 16) => {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(self.data);
+                Some(IpAddr::V6(Ipv6Addr::from(octets)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Reads the record data as a domain name, for example the target of a
+    /// CNAME, NS or PTR record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not a valid name.
+    pub fn data_name(&self) -> Result<DnsName<'a>> {
+        let name = DnsName::parse(self.name.mbuf, self.name.base, self.data_offset)?;
+        ensure!(
+            name.len <= self.data.len(),
+            anyhow!("DNS name exceeds the record data.")
+        );
+        Ok(name)
+    }
+}
+
+/// An iterator that iterates through the resource records of a section.
+pub struct DnsRecordsIterator<'a> {
+    mbuf: &'a Mbuf,
+    base: usize,
+    offset: usize,
+    remaining: u16,
+}
+
+impl<'a> DnsRecordsIterator<'a> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<DnsRecord<'a>>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        let name = DnsName::parse(self.mbuf, self.base, self.offset)?;
+        let fixed = read_bytes(self.mbuf, self.offset + name.len, RECORD_FIXED_LEN)?;
+        let data_len = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+        let data_offset = self.offset + name.len + RECORD_FIXED_LEN;
+
+        let record = DnsRecord {
+            name,
+            rtype: DnsType(u16::from_be_bytes([fixed[0], fixed[1]])),
+            class: u16::from_be_bytes([fixed[2], fixed[3]]),
+            ttl: u32::from_be_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]),
+            data: read_bytes(self.mbuf, data_offset, data_len)?,
+            data_offset,
+        };
+
+        self.offset = data_offset + data_len;
+        self.remaining -= 1;
+
+        Ok(Some(record))
+    }
+}
+
+impl fmt::Debug for DnsRecordsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsRecordsIterator")
+            .field("offset", &self.offset)
+            .field("remaining", &self.remaining)
+            .finish()
+    }
+}
+
+/// DNS header.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct DnsHeader {
+    id: u16be,
+    flags: u16be,
+    /// The entry counts of the question, answer, authority and additional
+    /// sections.
+    counts: [u16be; 4],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::Ethernet;
+
+    #[test]
+    fn size_of_dns_header() {
+        assert_eq!(12, DnsHeader::size_of());
+    }
+
+    #[test]
+    fn encode_dns_name() {
+        assert_eq!(
+            b"\x07example\x03com\x00".to_vec(),
+            encode_name("example.com.").unwrap()
+        );
+        assert_eq!(vec![0], encode_name(".").unwrap());
+        assert!(encode_name("example..com").is_err());
+        assert!(encode_name(&"a".repeat(64)).is_err());
+        assert!(encode_name(&["a"; 128].join(".")).is_err());
+    }
+
+    #[capsule::test]
+    fn parse_dns_packet() {
+        let packet = Mbuf::from_bytes(&DNS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let dns = udp.parse::<Dns<Ipv4>>().unwrap();
+
+        assert_eq!(0x1234, dns.id());
+        assert!(dns.is_response());
+        assert_eq!(0, dns.opcode());
+        assert!(!dns.authoritative());
+        assert!(!dns.truncated());
+        assert!(dns.recursion_desired());
+        assert!(dns.recursion_available());
+        assert_eq!(DnsResponseCodes::NoError, dns.response_code());
+        assert_eq!(1, dns.question_count());
+        assert_eq!(1, dns.answer_count());
+        assert_eq!(0, dns.authority_count());
+        assert_eq!(0, dns.additional_count());
+
+        let mut questions = dns.questions_iter();
+        let question = questions.next().unwrap().unwrap();
+        assert_eq!("example.com", question.name().to_string());
+        assert_eq!(DnsTypes::A, question.qtype());
+        assert_eq!(DNS_CLASS_IN, question.qclass());
+        assert!(questions.next().unwrap().is_none());
+
+        let mut answers = dns.answers_iter();
+        let answer = answers.next().unwrap().unwrap();
+        assert!(answer.name().matches("Example.COM."));
+        assert_eq!(DnsTypes::A, answer.rtype());
+        assert_eq!(3600, answer.ttl());
+        assert_eq!(
+            Some("93.184.216.34".parse::<IpAddr>().unwrap()),
+            answer.addr()
+        );
+        assert!(answers.next().unwrap().is_none());
+        assert!(dns.additionals_iter().next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn parse_dns_packet_with_pointer_loop() {
+        let mut bytes = DNS_PACKET.to_vec();
+        // points the answer name at itself.
+        bytes[72] = 0x1d;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Dns<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn parse_truncated_dns_packet() {
+        let packet = Mbuf::from_bytes(&DNS_PACKET[..80]).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Dns<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_dns_response() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        let mut dns = udp.push::<Dns<Ipv4>>().unwrap();
+
+        dns.set_id(0x1234);
+        dns.set_response(true);
+        dns.set_recursion_desired(true);
+        dns.set_recursion_available(true);
+        dns.append_question("example.com", DnsTypes::A, DNS_CLASS_IN)
+            .unwrap();
+        dns.append_answer(
+            "example.com",
+            DnsTypes::A,
+            DNS_CLASS_IN,
+            3600,
+            &[93, 184, 216, 34],
+        )
+        .unwrap();
+
+        // questions can't follow the answers.
+        assert!(dns
+            .append_question("example.org", DnsTypes::A, DNS_CLASS_IN)
+            .is_err());
+
+        // the answer name is compressed into a pointer to the question.
+        assert_eq!(DNS_PACKET.len() - 42, dns.len());
+        assert_eq!(&DNS_PACKET[42..], unsafe {
+            dns.mbuf()
+                .read_data_slice::<u8>(42, dns.len())
+                .unwrap()
+                .as_ref()
+        });
+
+        dns.reconcile_all();
+        let udp = dns.deparse();
+        assert_eq!(8 + 45, udp.length());
+    }
+
+    /// DNS response for `example.com`, with the answer name compressed.
+    #[rustfmt::skip]
+    const DNS_PACKET: [u8; 87] = [
+    // Ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x08, 0x00,
+    // IPv4 header
+        0x45, 0x00,
+        // total length = 73
+        0x00, 0x49,
+        0x00, 0x00, 0x40, 0x00,
+        // ttl = 64, protocol = UDP
+        0x40, 0x11, 0x00, 0x00,
+        // src = 8.8.8.8
+        0x08, 0x08, 0x08, 0x08,
+        // dst = 10.0.0.1
+        0x0a, 0x00, 0x00, 0x01,
+    // UDP header
+        // src_port = 53, dst_port = 54321
+        0x00, 0x35, 0xd4, 0x31,
+        // length = 53, checksum = 0
+        0x00, 0x35, 0x00, 0x00,
+    // DNS header
+        // id = 0x1234, flags = response, rd, ra
+        0x12, 0x34, 0x81, 0x80,
+        // qdcount = 1, ancount = 1, nscount = 0, arcount = 0
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+    // question
+        // example.com
+        0x07, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65,
+        0x03, 0x63, 0x6f, 0x6d, 0x00,
+        // type = A, class = IN
+        0x00, 0x01, 0x00, 0x01,
+    // answer
+        // pointer to example.com
+        0xc0, 0x0c,
+        // type = A, class = IN
+        0x00, 0x01, 0x00, 0x01,
+        // ttl = 3600
+        0x00, 0x00, 0x0e, 0x10,
+        // length = 4, address = 93.184.216.34
+        0x00, 0x04, 0x5d, 0xb8, 0xd8, 0x22,
+    ];
+}
@@ -21,15 +21,20 @@ use crate::net::MacAddr;
 use crate::packets::types::u16be;
 use crate::packets::{Internal, Packet};
 use crate::{ensure, Mbuf, SizeOf};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::fmt;
 use std::ptr::NonNull;
 
 const ETH_HEADER_SIZE: usize = 14;
 
-// Tag protocol identifiers.
-const VLAN_802_1Q: u16 = 0x8100;
-const VLAN_802_1AD: u16 = 0x88a8;
+// Offset of the first VLAN tag, right after the source MAC.
+const ETH_TAG_OFFSET: usize = 12;
+
+/// Tag protocol identifier for 802.1Q (Dot1q) VLAN tags.
+pub const VLAN_802_1Q: u16 = 0x8100;
+
+/// Tag protocol identifier for 802.1ad (QinQ) service tags.
+pub const VLAN_802_1AD: u16 = 0x88a8;
 
 /// Ethernet II frame.
 ///
@@ -118,13 +123,13 @@ const VLAN_802_1AD: u16 = 0x88a8;
 ///
 /// [IEEE 802.1Q]: https://en.wikipedia.org/wiki/IEEE_802.1Q
 /// [IEEE 802.1ad]: https://en.wikipedia.org/wiki/IEEE_802.1ad
-pub struct Ethernet {
-    envelope: Mbuf,
+pub struct Ethernet<E: EthernetEnvelope = Mbuf> {
+    envelope: E,
     header: NonNull<EthernetHeader>,
     offset: usize,
 }
 
-impl Ethernet {
+impl<E: EthernetEnvelope> Ethernet<E> {
     #[inline]
     fn header(&self) -> &EthernetHeader {
         unsafe { self.header.as_ref() }
@@ -203,6 +208,178 @@ impl Ethernet {
         self.vlan_marker() == VLAN_802_1AD
     }
 
+    /// Returns the customer tag. For Dot1q frames, this is the only tag;
+    /// for QinQ frames, this is the inner C-TAG.
+    #[inline]
+    fn ctag(&self) -> Option<&VlanTag> {
+        let header = self.header();
+        unsafe {
+            match self.vlan_marker() {
+                VLAN_802_1Q => Some(&header.chunk.dot1q.tag),
+                VLAN_802_1AD => Some(&header.chunk.qinq.ctag),
+                _ => None,
+            }
+        }
+    }
+
+    #[inline]
+    fn ctag_mut(&mut self) -> Option<&mut VlanTag> {
+        let marker = self.vlan_marker();
+        let header = self.header_mut();
+        unsafe {
+            match marker {
+                VLAN_802_1Q => Some(&mut header.chunk.dot1q.tag),
+                VLAN_802_1AD => Some(&mut header.chunk.qinq.ctag),
+                _ => None,
+            }
+        }
+    }
+
+    /// Returns the VLAN identifier of the customer tag, if the frame is
+    /// tagged. For QinQ frames, this is the VID of the inner C-TAG.
+    #[inline]
+    pub fn vlan_id(&self) -> Option<u16> {
+        self.ctag().map(VlanTag::identifier)
+    }
+
+    /// Sets the VLAN identifier of the customer tag. For QinQ frames, this
+    /// is the VID of the inner C-TAG.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame is not VLAN tagged.
+    #[inline]
+    pub fn set_vlan_id(&mut self, vlan_id: u16) -> Result<()> {
+        let tag = self
+            .ctag_mut()
+            .ok_or_else(|| anyhow!("frame is not VLAN tagged."))?;
+        tag.set_identifier(vlan_id);
+        Ok(())
+    }
+
+    /// Returns the priority code point of the customer tag, if the frame
+    /// is tagged.
+    #[inline]
+    pub fn vlan_pcp(&self) -> Option<u8> {
+        self.ctag().map(VlanTag::priority)
+    }
+
+    /// Sets the priority code point of the customer tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame is not VLAN tagged.
+    #[inline]
+    pub fn set_vlan_pcp(&mut self, pcp: u8) -> Result<()> {
+        let tag = self
+            .ctag_mut()
+            .ok_or_else(|| anyhow!("frame is not VLAN tagged."))?;
+        tag.set_priority(pcp);
+        Ok(())
+    }
+
+    /// Returns the drop eligible indicator of the customer tag, if the
+    /// frame is tagged.
+    #[inline]
+    pub fn vlan_dei(&self) -> Option<bool> {
+        self.ctag().map(VlanTag::drop_eligible)
+    }
+
+    /// Sets the drop eligible indicator of the customer tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame is not VLAN tagged.
+    #[inline]
+    pub fn set_vlan_dei(&mut self, dei: bool) -> Result<()> {
+        let tag = self
+            .ctag_mut()
+            .ok_or_else(|| anyhow!("frame is not VLAN tagged."))?;
+        tag.set_drop_eligible(dei);
+        Ok(())
+    }
+
+    /// Returns the VLAN identifier of the outer S-TAG, if the frame is
+    /// QinQ tagged.
+    #[inline]
+    pub fn svlan_id(&self) -> Option<u16> {
+        if self.is_qinq() {
+            Some(unsafe { self.header().chunk.qinq.stag.identifier() })
+        } else {
+            None
+        }
+    }
+
+    /// Sets the VLAN identifier of the outer S-TAG.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame is not QinQ tagged.
+    #[inline]
+    pub fn set_svlan_id(&mut self, vlan_id: u16) -> Result<()> {
+        ensure!(self.is_qinq(), anyhow!("frame is not QinQ tagged."));
+        unsafe { self.header_mut().chunk.qinq.stag.set_identifier(vlan_id) };
+        Ok(())
+    }
+
+    /// Inserts a new outermost VLAN tag after the source MAC address.
+    ///
+    /// An untagged frame can be tagged with a [`VLAN_802_1Q`] tag. A Dot1q
+    /// tagged frame can be further tagged with a [`VLAN_802_1AD`] S-TAG,
+    /// turning it into a QinQ frame. The payload is moved to make room for
+    /// the tag and the `ether_type` of the payload is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tag combination is not supported, or if the
+    /// buffer does not have enough free space.
+    ///
+    /// [`VLAN_802_1Q`]: VLAN_802_1Q
+    /// [`VLAN_802_1AD`]: VLAN_802_1AD
+    pub fn push_vlan(&mut self, tpid: u16, tci: u16) -> Result<()> {
+        let marker = self.vlan_marker();
+        ensure!(
+            (marker != VLAN_802_1Q && marker != VLAN_802_1AD && tpid == VLAN_802_1Q)
+                || (marker == VLAN_802_1Q && tpid == VLAN_802_1AD),
+            anyhow!(
+                "can't push tag 0x{:04x} onto frame with marker 0x{:04x}.",
+                tpid,
+                marker
+            )
+        );
+
+        let offset = self.offset + ETH_TAG_OFFSET;
+        let tag = VlanTag {
+            tpid: tpid.into(),
+            tci: tci.into(),
+        };
+
+        let mbuf = self.mbuf_mut();
+        mbuf.extend(offset, VlanTag::size_of())?;
+        mbuf.write_data(offset, &tag)?;
+
+        Ok(())
+    }
+
+    /// Removes the outermost VLAN tag.
+    ///
+    /// A Dot1q frame becomes untagged and a QinQ frame loses its S-TAG,
+    /// leaving the C-TAG as the outermost tag. The payload is moved to
+    /// fill in the gap and the `ether_type` of the payload is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame is not VLAN tagged.
+    pub fn pop_vlan(&mut self) -> Result<()> {
+        ensure!(
+            self.is_dot1q() || self.is_qinq(),
+            anyhow!("frame is not VLAN tagged.")
+        );
+
+        let offset = self.offset + ETH_TAG_OFFSET;
+        self.mbuf_mut().shrink(offset, VlanTag::size_of())
+    }
+
     /// Swaps the source MAC address with the destination MAC address.
     #[inline]
     pub fn swap_addresses(&mut self) {
@@ -213,7 +390,7 @@ impl Ethernet {
     }
 }
 
-impl fmt::Debug for Ethernet {
+impl<E: EthernetEnvelope> fmt::Debug for Ethernet<E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ethernet")
             .field("src", &format!("{}", self.src()))
@@ -227,9 +404,10 @@ impl fmt::Debug for Ethernet {
     }
 }
 
-impl Packet for Ethernet {
-    /// The preceding type for Ethernet must be `Mbuf`.
-    type Envelope = Mbuf;
+impl<E: EthernetEnvelope> Packet for Ethernet<E> {
+    /// The preceding type for Ethernet is either `Mbuf`, or a tunneling
+    /// packet that carries an Ethernet frame as its payload.
+    type Envelope = E;
 
     #[inline]
     fn envelope(&self) -> &Self::Envelope {
@@ -269,14 +447,20 @@ impl Packet for Ethernet {
         }
     }
 
-    /// Parses the mbuf's payload as `Ethernet`.
+    /// Parses the envelope's payload as `Ethernet`.
     ///
     /// # Errors
     ///
+    /// Returns an error if the envelope's payload is not an Ethernet frame.
     /// Returns an error if the `Ethernet` header is larger than the data
     /// payload.
     #[inline]
     fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.is_ethernet_payload(),
+            anyhow!("not an Ethernet frame.")
+        );
+
         let mbuf = envelope.mbuf();
         let offset = envelope.payload_offset();
         let header = mbuf.read_data(offset)?;
@@ -301,6 +485,8 @@ impl Packet for Ethernet {
 
     /// Prepends a new packet to the beginning of the envelope's payload.
     ///
+    /// The envelope's payload is marked as an Ethernet frame.
+    ///
     /// # Errors
     ///
     /// Returns an error if the buffer does not have enough free space.
@@ -317,6 +503,8 @@ impl Packet for Ethernet {
         let _ = mbuf.write_data_slice(offset, &[0; ETH_HEADER_SIZE])?;
         let header = mbuf.read_data(offset)?;
 
+        envelope.set_ethernet_payload();
+
         Ok(Ethernet {
             envelope,
             header,
@@ -330,6 +518,64 @@ impl Packet for Ethernet {
     }
 }
 
+impl<E: EthernetEnvelope> Datalink for Ethernet<E> {
+    #[inline]
+    fn protocol_type(&self) -> EtherType {
+        self.ether_type()
+    }
+
+    #[inline]
+    fn set_protocol_type(&mut self, ether_type: EtherType) -> Result<()> {
+        self.set_ether_type(ether_type);
+        Ok(())
+    }
+}
+
+/// A trait implemented by packets that can carry an Ethernet frame.
+///
+/// The Ethernet frame is normally at the beginning of the message buffer.
+/// Tunneling protocols, such as GRE, can also carry Ethernet frames as
+/// their payload.
+pub trait EthernetEnvelope: Packet {
+    /// Returns whether the payload is an Ethernet frame.
+    fn is_ethernet_payload(&self) -> bool;
+
+    /// Marks the payload as an Ethernet frame.
+    fn set_ethernet_payload(&mut self);
+}
+
+impl EthernetEnvelope for Mbuf {
+    /// The message buffer always begins with an Ethernet frame.
+    #[inline]
+    fn is_ethernet_payload(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn set_ethernet_payload(&mut self) {}
+}
+
+/// A trait implemented by packets whose payload is identified by an
+/// [`EtherType`], such as Ethernet and GRE.
+///
+/// IPv4 and IPv6 packets also implement the trait, so they can carry
+/// another IP packet. The EtherType maps to and from the IP protocol
+/// number of the encapsulated packet.
+///
+/// [`EtherType`]: EtherType
+pub trait Datalink: Packet {
+    /// Returns the protocol identifier of the payload.
+    fn protocol_type(&self) -> EtherType;
+
+    /// Sets the protocol identifier of the payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the packet can't carry a payload of
+    /// `ether_type`.
+    fn set_protocol_type(&mut self, ether_type: EtherType) -> Result<()>;
+}
+
 /// The protocol identifier of the Ethernet frame payload.
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
 #[repr(C, packed)]
@@ -354,6 +600,14 @@ pub mod EtherTypes {
     pub const Ipv4: EtherType = EtherType(0x0800);
     /// Internet Protocol version 6.
     pub const Ipv6: EtherType = EtherType(0x86DD);
+    /// Transparent Ethernet bridging.
+    pub const TransEtherBridging: EtherType = EtherType(0x6558);
+    /// Multiprotocol label switching unicast.
+    pub const MplsUnicast: EtherType = EtherType(0x8847);
+    /// Multiprotocol label switching multicast.
+    pub const MplsMulticast: EtherType = EtherType(0x8848);
+    /// Link layer discovery protocol.
+    pub const Lldp: EtherType = EtherType(0x88CC);
 }
 
 impl fmt::Display for EtherType {
@@ -365,6 +619,10 @@ impl fmt::Display for EtherType {
                 EtherTypes::Arp => "ARP".to_string(),
                 EtherTypes::Ipv4 => "IPv4".to_string(),
                 EtherTypes::Ipv6 => "IPv6".to_string(),
+                EtherTypes::TransEtherBridging => "Transparent Ethernet Bridging".to_string(),
+                EtherTypes::MplsUnicast => "MPLS Unicast".to_string(),
+                EtherTypes::MplsMulticast => "MPLS Multicast".to_string(),
+                EtherTypes::Lldp => "LLDP".to_string(),
                 _ => {
                     let t = self.0;
                     format!("0x{:04x}", t)
@@ -392,27 +650,48 @@ impl VlanTag {
     }
 
     /// Returns the priority code point.
-    #[allow(dead_code)]
     #[inline]
     fn priority(&self) -> u8 {
         let tci: u16 = self.tci.into();
         (tci >> 13) as u8
     }
 
+    /// Sets the priority code point.
+    #[inline]
+    fn set_priority(&mut self, priority: u8) {
+        let tci: u16 = self.tci.into();
+        self.tci = ((tci & 0x1fff) | (u16::from(priority & 0x07) << 13)).into();
+    }
+
     /// Returns whether the frame is eligible to be dropped in the presence
     /// of congestion.
-    #[allow(dead_code)]
     #[inline]
     fn drop_eligible(&self) -> bool {
         self.tci & u16be::from(0x1000) > u16be::MIN
     }
 
+    /// Sets the drop eligible indicator.
+    #[inline]
+    fn set_drop_eligible(&mut self, drop_eligible: bool) {
+        let tci = self.tci;
+        self.tci = if drop_eligible {
+            tci | u16be::from(0x1000)
+        } else {
+            tci & !u16be::from(0x1000)
+        };
+    }
+
     /// Returns the VLAN identifier.
-    #[allow(dead_code)]
     #[inline]
     fn identifier(&self) -> u16 {
         (self.tci & u16be::from(0x0fff)).into()
     }
+
+    /// Sets the VLAN identifier.
+    #[inline]
+    fn set_identifier(&mut self, identifier: u16) {
+        self.tci = (self.tci & u16be::from(0xf000)) | u16be::from(identifier & 0x0fff);
+    }
 }
 
 /// Dot1q chunk for a VLAN header.
@@ -478,6 +757,7 @@ impl SizeOf for EthernetHeader {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::packets::ip::v4::Ipv4;
     use crate::testils::byte_arrays::{IPV4_UDP_PACKET, VLAN_DOT1Q_PACKET, VLAN_QINQ_PACKET};
 
     #[test]
@@ -527,6 +807,97 @@ mod tests {
         assert_eq!(22, ethernet.header_len());
     }
 
+    #[capsule::test]
+    fn get_and_set_vlan_tag() {
+        let packet = Mbuf::from_bytes(&VLAN_DOT1Q_PACKET).unwrap();
+        let mut ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert_eq!(Some(123), ethernet.vlan_id());
+        assert_eq!(Some(0), ethernet.vlan_pcp());
+        assert_eq!(Some(false), ethernet.vlan_dei());
+        assert_eq!(None, ethernet.svlan_id());
+
+        assert!(ethernet.set_vlan_id(456).is_ok());
+        assert!(ethernet.set_vlan_pcp(5).is_ok());
+        assert!(ethernet.set_vlan_dei(true).is_ok());
+        assert_eq!(Some(456), ethernet.vlan_id());
+        assert_eq!(Some(5), ethernet.vlan_pcp());
+        assert_eq!(Some(true), ethernet.vlan_dei());
+        assert_eq!(EtherTypes::Arp, ethernet.ether_type());
+
+        // can't set the S-TAG on a Dot1q frame.
+        assert!(ethernet.set_svlan_id(10).is_err());
+    }
+
+    #[capsule::test]
+    fn get_and_set_qinq_tags() {
+        let packet = Mbuf::from_bytes(&VLAN_QINQ_PACKET).unwrap();
+        let mut ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert_eq!(Some(30), ethernet.svlan_id());
+        assert_eq!(Some(101), ethernet.vlan_id());
+        assert_eq!(Some(1), ethernet.vlan_pcp());
+
+        assert!(ethernet.set_svlan_id(40).is_ok());
+        assert!(ethernet.set_vlan_id(200).is_ok());
+        assert_eq!(Some(40), ethernet.svlan_id());
+        assert_eq!(Some(200), ethernet.vlan_id());
+        assert_eq!(Some(1), ethernet.vlan_pcp());
+        assert_eq!(EtherTypes::Arp, ethernet.ether_type());
+    }
+
+    #[capsule::test]
+    fn untagged_vlan_accessors() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let mut ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert_eq!(None, ethernet.vlan_id());
+        assert!(ethernet.set_vlan_id(1).is_err());
+        assert!(ethernet.pop_vlan().is_err());
+    }
+
+    #[capsule::test]
+    fn push_and_pop_vlan_tags() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let mut ethernet = packet.parse::<Ethernet>().unwrap();
+        let len = ethernet.len();
+
+        // can't push a S-TAG onto an untagged frame.
+        assert!(ethernet.push_vlan(VLAN_802_1AD, 10).is_err());
+
+        assert!(ethernet.push_vlan(VLAN_802_1Q, 0x2064).is_ok());
+        assert!(ethernet.is_dot1q());
+        assert_eq!(Some(100), ethernet.vlan_id());
+        assert_eq!(Some(1), ethernet.vlan_pcp());
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        assert_eq!(18, ethernet.header_len());
+        assert_eq!(len + 4, ethernet.len());
+
+        assert!(ethernet.push_vlan(VLAN_802_1AD, 10).is_ok());
+        assert!(ethernet.is_qinq());
+        assert_eq!(Some(10), ethernet.svlan_id());
+        assert_eq!(Some(100), ethernet.vlan_id());
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        assert_eq!(22, ethernet.header_len());
+
+        // triple tagging is not supported.
+        assert!(ethernet.push_vlan(VLAN_802_1AD, 20).is_err());
+
+        assert!(ethernet.pop_vlan().is_ok());
+        assert!(ethernet.is_dot1q());
+        assert_eq!(Some(100), ethernet.vlan_id());
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+
+        assert!(ethernet.pop_vlan().is_ok());
+        assert!(!ethernet.is_dot1q());
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        assert_eq!(len, ethernet.len());
+
+        // the payload is intact.
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert_eq!("139.133.217.110", ipv4.src().to_string());
+    }
+
     #[capsule::test]
     fn swap_addresses() {
         let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
@@ -0,0 +1,858 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::ip::IpPacket;
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{Datalink, EtherType, EtherTypes, EthernetEnvelope, Internal, Packet, Udp};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// The IANA assigned UDP destination port for Geneve.
+pub const GENEVE_PORT: u16 = 6081;
+
+/// The largest Geneve virtual network identifier, which is 24 bits.
+pub const GENEVE_MAX_VNI: u32 = 0x00ff_ffff;
+
+/// The maximum length of the Geneve options in octets.
+///
+/// The Opt Len field is 6 bits and measured in 4-octet units.
+pub const GENEVE_MAX_OPTIONS_LEN: usize = 63 * 4;
+
+/// The maximum length of the data of a single Geneve option in octets.
+///
+/// The Length field is 5 bits and measured in 4-octet units.
+pub const GENEVE_MAX_OPTION_DATA_LEN: usize = 31 * 4;
+
+// Masks.
+const OPT_LEN: u8 = 0x3f;
+const OAM: u8 = 0x80;
+const CRITICAL: u8 = 0x40;
+const OPTION_CRITICAL: u8 = 0x80;
+const OPTION_LENGTH: u8 = 0x1f;
+
+/// Generic Network Virtualization Encapsulation packet based on
+/// [IETF RFC 8926].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |Ver|  Opt Len  |O|C|    Rsvd.  |          Protocol Type        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |        Virtual Network Identifier (VNI)       |    Reserved   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// ~                    Variable-Length Options                    ~
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Version*:          2 bits. The current version number is 0.
+///
+/// - *Opt Len*:          6 bits. The length of the option fields,
+///                       expressed in 4-byte multiples, not including the
+///                       8-byte fixed tunnel header.
+///
+/// - *O*:                1 bit. OAM packet. This packet contains a control
+///                       message instead of a data payload.
+///
+/// - *C*:                1 bit. Critical options present. One or more
+///                       options has the critical bit set.
+///
+/// - *Protocol Type*:    16 bits. The type of the protocol data unit
+///                       appearing after the Geneve header, using the
+///                       Ethernet type. Ethernet frames are identified by
+///                       [`EtherTypes::TransEtherBridging`].
+///
+/// - *VNI*:              24 bits. An identifier for a unique element of a
+///                       virtual network.
+///
+/// - *Variable-Length Options*: The options in TLV format.
+///
+/// The payload packet follows the protocol type field. For example, an
+/// Ethernet frame or an IPv4 packet may be parsed from the Geneve packet.
+///
+/// ```
+/// let udp = ipv4.parse::<Udp<Ipv4>>()?;
+/// if udp.dst_port() == GENEVE_PORT {
+///     let geneve = udp.parse::<Geneve<Ipv4>>()?;
+///     if geneve.protocol_type() == EtherTypes::TransEtherBridging {
+///         let inner = geneve.parse::<Ethernet<Geneve<Ipv4>>>()?;
+///     }
+/// }
+/// ```
+///
+/// [IETF RFC 8926]: https://tools.ietf.org/html/rfc8926#section-3.4
+/// [`EtherTypes::TransEtherBridging`]: EtherTypes::TransEtherBridging
+pub struct Geneve<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<GeneveHeader>,
+    offset: usize,
+    options_len: usize,
+}
+
+impl<E: IpPacket> Geneve<E> {
+    #[inline]
+    fn header(&self) -> &GeneveHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut GeneveHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the protocol version.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        self.header().ver_opt_len >> 6
+    }
+
+    /// Returns the length of the options in 4-octet units.
+    ///
+    /// The value is read from the header and may be stale until the packet
+    /// is reconciled after options are added or removed.
+    #[inline]
+    pub fn opt_len(&self) -> u8 {
+        self.header().ver_opt_len & OPT_LEN
+    }
+
+    /// Returns the length of the options in octets.
+    #[inline]
+    pub fn options_len(&self) -> usize {
+        self.options_len
+    }
+
+    /// Returns a flag indicating whether the packet is an OAM packet.
+    #[inline]
+    pub fn oam(&self) -> bool {
+        self.header().flags & OAM != 0
+    }
+
+    /// Sets the OAM flag.
+    #[inline]
+    pub fn set_oam(&mut self, oam: bool) {
+        let flags = self.header().flags;
+        self.header_mut().flags = if oam { flags | OAM } else { flags & !OAM };
+    }
+
+    /// Returns a flag indicating whether one or more options are critical.
+    ///
+    /// The value is read from the header and may be stale until the packet
+    /// is reconciled after options are added or removed.
+    #[inline]
+    pub fn critical(&self) -> bool {
+        self.header().flags & CRITICAL != 0
+    }
+
+    #[inline]
+    fn set_critical(&mut self, critical: bool) {
+        let flags = self.header().flags;
+        self.header_mut().flags = if critical {
+            flags | CRITICAL
+        } else {
+            flags & !CRITICAL
+        };
+    }
+
+    /// Returns the virtual network identifier.
+    #[inline]
+    pub fn vni(&self) -> u32 {
+        let vni: u32 = self.header().vni_reserved.into();
+        vni >> 8
+    }
+
+    /// Sets the virtual network identifier.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the identifier is larger than [`GENEVE_MAX_VNI`].
+    ///
+    /// [`GENEVE_MAX_VNI`]: GENEVE_MAX_VNI
+    #[inline]
+    pub fn set_vni(&mut self, vni: u32) -> Result<()> {
+        ensure!(
+            vni <= GENEVE_MAX_VNI,
+            anyhow!("VNI {} is larger than 24 bits.", vni)
+        );
+
+        self.header_mut().vni_reserved = (vni << 8).into();
+        Ok(())
+    }
+
+    /// Returns an iterator that iterates through the options.
+    #[inline]
+    pub fn options_iter(&self) -> GeneveOptionsIterator<'_> {
+        let start = self.offset() + GeneveHeader::size_of();
+        GeneveOptionsIterator {
+            mbuf: self.mbuf(),
+            offset: start,
+            end: start + self.options_len,
+        }
+    }
+
+    /// Returns the options for modification.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut geneve = udp.parse::<Geneve<Ipv4>>()?;
+    /// let option = GeneveOption::new(0x0102, 0x80, vec![0; 4])?;
+    /// geneve.options_mut().append(&option)?;
+    /// geneve.reconcile();
+    /// ```
+    #[inline]
+    pub fn options_mut(&mut self) -> GeneveOptions<'_> {
+        let offset = self.offset + GeneveHeader::size_of();
+        GeneveOptions {
+            mbuf: self.envelope.mbuf_mut(),
+            offset,
+            len: &mut self.options_len,
+        }
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Geneve<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("geneve")
+            .field("version", &self.version())
+            .field("opt_len", &self.opt_len())
+            .field("oam", &self.oam())
+            .field("critical", &self.critical())
+            .field("protocol_type", &format!("{}", self.protocol_type()))
+            .field("vni", &self.vni())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Geneve<E> {
+    /// The preceding type for a Geneve packet must be UDP.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the fixed header and the options.
+    #[inline]
+    fn header_len(&self) -> usize {
+        GeneveHeader::size_of() + self.options_len
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Geneve::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+            options_len: self.options_len,
+        }
+    }
+
+    /// Parses the UDP payload as a Geneve packet.
+    ///
+    /// The UDP destination port is not checked because deployments may use
+    /// a port other than [`GENEVE_PORT`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the version is not 0. Returns an error if the
+    /// payload does not have sufficient data for the Geneve header and the
+    /// options.
+    ///
+    /// [`GENEVE_PORT`]: GENEVE_PORT
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let mut packet = Geneve {
+            envelope,
+            header,
+            offset,
+            options_len: 0,
+        };
+        packet.options_len = packet.opt_len() as usize * 4;
+
+        ensure!(
+            packet.version() == 0,
+            anyhow!("unsupported Geneve version {}.", packet.version())
+        );
+        ensure!(
+            packet.len() >= packet.header_len(),
+            anyhow!("packet has incomplete Geneve header.")
+        );
+
+        Ok(packet)
+    }
+
+    /// Prepends a Geneve packet with no options to the beginning of the
+    /// UDP's payload.
+    ///
+    /// The UDP destination port is set to [`GENEVE_PORT`]. The protocol type
+    /// is not set, it should be set to the type of the payload packet,
+    /// unless one is pushed after the Geneve packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`GENEVE_PORT`]: GENEVE_PORT
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, GeneveHeader::size_of())?;
+        let header = mbuf.write_data(offset, &GeneveHeader::default())?;
+
+        envelope.set_dst_port(GENEVE_PORT);
+
+        Ok(Geneve {
+            envelope,
+            header,
+            offset,
+            options_len: 0,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`opt_len`] is set to the length of the options.
+    /// * [`critical`] is set if any of the options is critical.
+    ///
+    /// [`opt_len`]: Geneve::opt_len
+    /// [`critical`]: Geneve::critical
+    #[inline]
+    fn reconcile(&mut self) {
+        let opt_len = (self.options_len / 4) as u8;
+        let ver_opt_len = self.header().ver_opt_len;
+        self.header_mut().ver_opt_len = (ver_opt_len & !OPT_LEN) | (opt_len & OPT_LEN);
+
+        let mut critical = false;
+        let mut iter = self.options_iter();
+        while let Ok(Some(option)) = iter.next() {
+            critical |= option.critical();
+        }
+        self.set_critical(critical);
+    }
+}
+
+impl<E: IpPacket> Datalink for Geneve<E> {
+    #[inline]
+    fn protocol_type(&self) -> EtherType {
+        EtherType::new(self.header().protocol_type.into())
+    }
+
+    #[inline]
+    fn set_protocol_type(&mut self, ether_type: EtherType) -> Result<()> {
+        self.header_mut().protocol_type = ether_type.0.into();
+        Ok(())
+    }
+}
+
+impl<E: IpPacket> EthernetEnvelope for Geneve<E> {
+    /// Returns whether the protocol type is set to
+    /// [`EtherTypes::TransEtherBridging`].
+    ///
+    /// [`EtherTypes::TransEtherBridging`]: EtherTypes::TransEtherBridging
+    #[inline]
+    fn is_ethernet_payload(&self) -> bool {
+        self.protocol_type() == EtherTypes::TransEtherBridging
+    }
+
+    #[inline]
+    fn set_ethernet_payload(&mut self) {
+        self.header_mut().protocol_type = EtherTypes::TransEtherBridging.0.into();
+    }
+}
+
+/// A Geneve option in TLV format.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          Option Class         |      Type     |R|R|R| Length  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 Variable-Length Option Data                   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// Options are read out of and written into the buffer by value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GeneveOption {
+    option_class: u16,
+    option_type: u8,
+    data: Vec<u8>,
+}
+
+impl GeneveOption {
+    /// Creates a new option.
+    ///
+    /// The high-order bit of `option_type` marks the option as critical.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the length of `data` is not a multiple of 4, or
+    /// is larger than [`GENEVE_MAX_OPTION_DATA_LEN`].
+    ///
+    /// [`GENEVE_MAX_OPTION_DATA_LEN`]: GENEVE_MAX_OPTION_DATA_LEN
+    pub fn new(option_class: u16, option_type: u8, data: Vec<u8>) -> Result<Self> {
+        ensure!(
+            data.len() % 4 == 0,
+            anyhow!("Geneve option data must be a multiple of 4 octets.")
+        );
+        ensure!(
+            data.len() <= GENEVE_MAX_OPTION_DATA_LEN,
+            anyhow!(
+                "Geneve option data exceeds {} octets.",
+                GENEVE_MAX_OPTION_DATA_LEN
+            )
+        );
+
+        Ok(GeneveOption {
+            option_class,
+            option_type,
+            data,
+        })
+    }
+
+    /// Returns the namespace of the option type.
+    #[inline]
+    pub fn option_class(&self) -> u16 {
+        self.option_class
+    }
+
+    /// Returns the option type, including the critical bit.
+    #[inline]
+    pub fn option_type(&self) -> u8 {
+        self.option_type
+    }
+
+    /// Returns whether the option is critical. A tunnel endpoint that does
+    /// not recognize a critical option must drop the packet.
+    #[inline]
+    pub fn critical(&self) -> bool {
+        self.option_type & OPTION_CRITICAL != 0
+    }
+
+    /// Returns the option data.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the length of the option in octets, including the 4-octet
+    /// option header.
+    #[inline]
+    pub fn length(&self) -> usize {
+        4 + self.data.len()
+    }
+
+    /// Encodes the option including the option header.
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.length());
+        bytes.extend_from_slice(&self.option_class.to_be_bytes());
+        bytes.push(self.option_type);
+        bytes.push((self.data.len() / 4) as u8);
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+}
+
+/// Reads the option at offset.
+///
+/// # Errors
+///
+/// Returns an error if the option runs past the end offset.
+fn read_option(mbuf: &Mbuf, offset: usize, end: usize) -> Result<GeneveOption> {
+    ensure!(offset + 4 <= end, anyhow!("truncated Geneve option."));
+
+    let header = mbuf.read_data_slice::<u8>(offset, 4)?;
+    let header = unsafe { &*header.as_ptr() };
+    let option_class = u16::from_be_bytes([header[0], header[1]]);
+    let option_type = header[2];
+    let data_len = (header[3] & OPTION_LENGTH) as usize * 4;
+
+    ensure!(
+        offset + 4 + data_len <= end,
+        anyhow!("invalid Geneve option length {}.", data_len)
+    );
+
+    let data = if data_len > 0 {
+        let data = mbuf.read_data_slice::<u8>(offset + 4, data_len)?;
+        unsafe { &*data.as_ptr() }.to_vec()
+    } else {
+        vec![]
+    };
+
+    Ok(GeneveOption {
+        option_class,
+        option_type,
+        data,
+    })
+}
+
+/// An iterator that iterates through the options in a Geneve header.
+pub struct GeneveOptionsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl GeneveOptionsIterator<'_> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<GeneveOption>> {
+        if self.offset < self.end {
+            let option = read_option(self.mbuf, self.offset, self.end)?;
+            // advances the offset to the next option
+            self.offset += option.length();
+            Ok(Some(option))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl fmt::Debug for GeneveOptionsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeneveOptionsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// Options in a Geneve header.
+///
+/// Adding or removing options resizes the header. The Opt Len and the
+/// critical flag in the header, as well as the envelope's length, are not
+/// updated until the packet is reconciled.
+pub struct GeneveOptions<'a> {
+    mbuf: &'a mut Mbuf,
+    offset: usize,
+    len: &'a mut usize,
+}
+
+impl GeneveOptions<'_> {
+    /// Returns an iterator to read the options.
+    #[inline]
+    pub fn iter(&self) -> GeneveOptionsIterator<'_> {
+        GeneveOptionsIterator {
+            mbuf: self.mbuf,
+            offset: self.offset,
+            end: self.offset + *self.len,
+        }
+    }
+
+    /// Appends a new option at the end of the options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options would exceed
+    /// [`GENEVE_MAX_OPTIONS_LEN`] or the buffer does not have enough free
+    /// space.
+    ///
+    /// [`GENEVE_MAX_OPTIONS_LEN`]: GENEVE_MAX_OPTIONS_LEN
+    pub fn append(&mut self, option: &GeneveOption) -> Result<()> {
+        let len = *self.len + option.length();
+        ensure!(
+            len <= GENEVE_MAX_OPTIONS_LEN,
+            anyhow!("Geneve options exceed {} octets.", GENEVE_MAX_OPTIONS_LEN)
+        );
+
+        let offset = self.offset + *self.len;
+        self.mbuf.extend(offset, option.length())?;
+        self.mbuf.write_data_slice(offset, &option.encode())?;
+        *self.len = len;
+
+        Ok(())
+    }
+
+    /// Retains only the options specified by the predicate.
+    ///
+    /// In other words, remove all options `o` such that `f(o)` returns false.
+    /// If an error occurs, all removals done prior to the error cannot be
+    /// undone.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut geneve = udp.parse::<Geneve<Ipv4>>()?;
+    /// geneve.options_mut().retain(|option| !option.critical())?;
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&GeneveOption) -> bool,
+    {
+        let mut offset = self.offset;
+        while offset < self.offset + *self.len {
+            let option = read_option(self.mbuf, offset, self.offset + *self.len)?;
+            let length = option.length();
+            if !f(&option) {
+                self.mbuf.shrink(offset, length)?;
+                *self.len -= length;
+            } else {
+                offset += length;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for GeneveOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeneveOptions")
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+/// Geneve header.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct GeneveHeader {
+    ver_opt_len: u8,
+    flags: u8,
+    protocol_type: u16be,
+    vni_reserved: u32be,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::Ethernet;
+
+    #[test]
+    fn size_of_geneve_header() {
+        assert_eq!(8, GeneveHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_geneve_packet() {
+        let packet = Mbuf::from_bytes(&GENEVE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        assert_eq!(GENEVE_PORT, udp.dst_port());
+
+        let geneve = udp.parse::<Geneve<Ipv4>>().unwrap();
+        assert_eq!(0, geneve.version());
+        assert_eq!(2, geneve.opt_len());
+        assert_eq!(8, geneve.options_len());
+        assert_eq!(16, geneve.header_len());
+        assert!(!geneve.oam());
+        assert!(geneve.critical());
+        assert_eq!(EtherTypes::TransEtherBridging, geneve.protocol_type());
+        assert_eq!(100, geneve.vni());
+
+        let mut iter = geneve.options_iter();
+        let option = iter.next().unwrap().unwrap();
+        assert_eq!(0x0102, option.option_class());
+        assert_eq!(0x80, option.option_type());
+        assert!(option.critical());
+        assert_eq!(&[0xde, 0xad, 0xbe, 0xef], option.data());
+        assert!(iter.next().unwrap().is_none());
+
+        let inner = geneve.parse::<Ethernet<Geneve<Ipv4>>>().unwrap();
+        assert_eq!("00:00:00:00:00:01", inner.dst().to_string());
+
+        let ipv4 = inner.parse::<Ipv4<Ethernet<Geneve<Ipv4>>>>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4<Ethernet<Geneve<Ipv4>>>>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+        assert_eq!(1087, udp.dst_port());
+    }
+
+    #[capsule::test]
+    fn parse_geneve_with_truncated_options() {
+        // sets opt len to 63 words.
+        let mut bytes = GENEVE_PACKET;
+        bytes[42] = 0x3f;
+
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Geneve<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_and_set_geneve_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let udp = ipv6.push::<Udp<Ipv6>>().unwrap();
+        let mut geneve = udp.push::<Geneve<Ipv6>>().unwrap();
+
+        assert_eq!(GeneveHeader::size_of(), geneve.len());
+        assert_eq!(GENEVE_PORT, geneve.envelope().dst_port());
+        assert_eq!(0, geneve.version());
+        assert_eq!(0, geneve.options_len());
+
+        geneve.set_vni(GENEVE_MAX_VNI).unwrap();
+        assert_eq!(GENEVE_MAX_VNI, geneve.vni());
+        assert!(geneve.set_vni(GENEVE_MAX_VNI + 1).is_err());
+
+        geneve.set_oam(true);
+        assert!(geneve.oam());
+        geneve.set_oam(false);
+        assert!(!geneve.oam());
+
+        let inner = geneve.push::<Ipv4<Geneve<Ipv6>>>().unwrap();
+        assert_eq!(EtherTypes::Ipv4, inner.envelope().protocol_type());
+
+        let geneve = inner.remove().unwrap();
+        assert!(geneve.peek::<Ethernet<Geneve<Ipv6>>>().is_err());
+
+        let inner = geneve.push::<Ethernet<Geneve<Ipv6>>>().unwrap();
+        assert_eq!(
+            EtherTypes::TransEtherBridging,
+            inner.envelope().protocol_type()
+        );
+    }
+
+    #[capsule::test]
+    fn append_and_retain_geneve_options() {
+        let packet = Mbuf::from_bytes(&GENEVE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let mut geneve = udp.parse::<Geneve<Ipv4>>().unwrap();
+
+        let option = GeneveOption::new(0x0103, 0x01, vec![1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        geneve.options_mut().append(&option).unwrap();
+        assert_eq!(20, geneve.options_len());
+        assert_eq!(28, geneve.header_len());
+
+        geneve.reconcile_all();
+        assert_eq!(5, geneve.opt_len());
+        assert!(geneve.critical());
+        assert_eq!(88, geneve.envelope().length());
+
+        let mut iter = geneve.options_iter();
+        assert_eq!(0x0102, iter.next().unwrap().unwrap().option_class());
+        assert_eq!(option, iter.next().unwrap().unwrap());
+        assert!(iter.next().unwrap().is_none());
+
+        geneve
+            .options_mut()
+            .retain(|option| !option.critical())
+            .unwrap();
+        geneve.reconcile_all();
+        assert_eq!(3, geneve.opt_len());
+        assert!(!geneve.critical());
+        assert_eq!(80, geneve.envelope().length());
+
+        let mut iter = geneve.options_iter();
+        assert_eq!(option, iter.next().unwrap().unwrap());
+        assert!(iter.next().unwrap().is_none());
+
+        // the payload is intact after the options are modified.
+        let inner = geneve.parse::<Ethernet<Geneve<Ipv4>>>().unwrap();
+        assert_eq!("00:00:00:00:00:01", inner.dst().to_string());
+    }
+
+    #[test]
+    fn invalid_geneve_option() {
+        assert!(GeneveOption::new(0x0102, 0x01, vec![0; 3]).is_err());
+        assert!(GeneveOption::new(0x0102, 0x01, vec![0; 128]).is_err());
+        assert!(GeneveOption::new(0x0102, 0x01, vec![0; 124]).is_ok());
+    }
+
+    /// IPv4 UDP packet encapsulated in Geneve with VNI 100 and one critical
+    /// option.
+    #[rustfmt::skip]
+    const GENEVE_PACKET: [u8; 110] = [
+    // Ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x0a,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x0b,
+        0x08, 0x00,
+    // IPv4 header
+        0x45, 0x00,
+        // total length = 96
+        0x00, 0x60,
+        0x00, 0x00, 0x40, 0x00,
+        // ttl = 64, protocol = UDP
+        0x40, 0x11, 0x00, 0x00,
+        // src = 10.0.0.1
+        0x0a, 0x00, 0x00, 0x01,
+        // dst = 10.0.0.2
+        0x0a, 0x00, 0x00, 0x02,
+    // UDP header
+        // src_port = 50000, dst_port = 6081
+        0xc3, 0x50, 0x17, 0xc1,
+        // length = 76, checksum = 0
+        0x00, 0x4c, 0x00, 0x00,
+    // Geneve header
+        // version = 0, opt len = 2, C flag, protocol type = 0x6558
+        0x02, 0x40, 0x65, 0x58,
+        // VNI = 100
+        0x00, 0x00, 0x64, 0x00,
+    // Geneve option
+        // class = 0x0102, type = 0x80, length = 1
+        0x01, 0x02, 0x80, 0x01,
+        0xde, 0xad, 0xbe, 0xef,
+    // inner Ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x08, 0x00,
+    // inner IPv4 header
+        0x45, 0x00, 0x00, 0x26,
+        0xab, 0x49, 0x40, 0x00,
+        0xff, 0x11, 0xf7, 0x00,
+        0x8b, 0x85, 0xd9, 0x6e,
+        0x8b, 0x85, 0xe9, 0x02,
+    // inner UDP header
+        0x99, 0xd0, 0x04, 0x3f,
+        0x00, 0x12, 0x72, 0x28,
+    // inner UDP payload
+        0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+    ];
+}
@@ -0,0 +1,681 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::ip::{IpPacket, ProtocolNumbers};
+use crate::packets::types::u16be;
+use crate::packets::{
+    checksum, Datalink, EtherType, EtherTypes, EthernetEnvelope, Internal, Packet,
+};
+use crate::{ensure, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+// Flags and masks.
+const CHECKSUM_PRESENT: u16be = u16be(u16::to_be(0x8000));
+const ROUTING_PRESENT: u16be = u16be(u16::to_be(0x4000));
+const KEY_PRESENT: u16be = u16be(u16::to_be(0x2000));
+const SEQ_PRESENT: u16be = u16be(u16::to_be(0x1000));
+const VERSION: u16be = u16be(u16::to_be(0x0007));
+
+/// The length of each optional field in octets.
+const FIELD_LEN: usize = 4;
+
+/// Generic Routing Encapsulation packet based on [IETF RFC 2784] with the
+/// key and sequence number extensions defined in [IETF RFC 2890].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |C| |K|S| Reserved0       | Ver |         Protocol Type         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |      Checksum (optional)      |       Reserved1 (Optional)    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         Key (optional)                        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 Sequence Number (Optional)                    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Checksum Present (C)*:   1 bit. If set, the Checksum and Reserved1
+///                             fields are present.
+///
+/// - *Key Present (K)*:        1 bit. If set, the Key field is present.
+///
+/// - *Seq Present (S)*:        1 bit. If set, the Sequence Number field is
+///                             present.
+///
+/// - *Version Number (Ver)*:   3 bits. Must contain the value zero.
+///
+/// - *Protocol Type*:          16 bits. Contains the protocol type of the
+///                             payload packet, using the same values as the
+///                             Ethernet [`EtherType`].
+///
+/// - *Checksum*:               16 bits. The IP (one's complement) checksum
+///                             sum of all the 16 bit words in the GRE header
+///                             and the payload packet.
+///
+/// - *Key*:                    32 bits. Used to identify an individual
+///                             traffic flow within a tunnel.
+///
+/// - *Sequence Number*:        32 bits. Used to maintain the order of the
+///                             packets sent through the tunnel.
+///
+/// The payload packet can be an `Ethernet`, an `Ipv4` or an `Ipv6` packet.
+///
+/// ```
+/// let ipv4 = ethernet.parse::<Ipv4>()?;
+/// let gre = ipv4.parse::<Gre<Ipv4>>()?;
+/// if gre.protocol_type() == EtherTypes::Ipv6 {
+///     let inner = gre.parse::<Ipv6<Gre<Ipv4>>>()?;
+/// }
+/// ```
+///
+/// # Remarks
+///
+/// The routing field of the obsoleted [IETF RFC 1701] is not supported.
+///
+/// [IETF RFC 2784]: https://tools.ietf.org/html/rfc2784
+/// [IETF RFC 2890]: https://tools.ietf.org/html/rfc2890
+/// [IETF RFC 1701]: https://tools.ietf.org/html/rfc1701
+/// [`EtherType`]: EtherType
+pub struct Gre<E: IpPacket> {
+    envelope: E,
+    header: NonNull<GreHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Gre<E> {
+    #[inline]
+    fn header(&self) -> &GreHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut GreHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns whether the flag is set.
+    #[inline]
+    fn flag(&self, flag: u16be) -> bool {
+        self.header().flags_version & flag > u16be::MIN
+    }
+
+    /// Sets or unsets the flag.
+    #[inline]
+    fn set_flag(&mut self, flag: u16be, set: bool) {
+        let flags_version = self.header().flags_version;
+        self.header_mut().flags_version = if set {
+            flags_version | flag
+        } else {
+            flags_version & !flag
+        };
+    }
+
+    /// Returns the buffer offset of the optional field indicated by the
+    /// flag, whether the field is present or not.
+    #[inline]
+    fn field_offset(&self, flag: u16be) -> usize {
+        let mut offset = self.offset + GreHeader::size_of();
+
+        if flag != CHECKSUM_PRESENT && self.flag(CHECKSUM_PRESENT) {
+            offset += FIELD_LEN;
+        }
+
+        if flag == SEQ_PRESENT && self.flag(KEY_PRESENT) {
+            offset += FIELD_LEN;
+        }
+
+        offset
+    }
+
+    /// Reads the optional field indicated by the flag.
+    #[inline]
+    fn read_field(&self, flag: u16be) -> Option<u32> {
+        if self.flag(flag) {
+            let field = self
+                .mbuf()
+                .read_data_slice::<u8>(self.field_offset(flag), FIELD_LEN)
+                .ok()?;
+            let field = unsafe { field.as_ref() };
+            Some(u32::from_be_bytes([field[0], field[1], field[2], field[3]]))
+        } else {
+            None
+        }
+    }
+
+    /// Writes, inserts or removes the optional field indicated by the flag.
+    fn write_field(&mut self, flag: u16be, value: Option<u32>) -> Result<()> {
+        let offset = self.field_offset(flag);
+
+        match (self.flag(flag), value) {
+            (true, Some(value)) => {
+                self.mbuf_mut()
+                    .write_data_slice(offset, &value.to_be_bytes())?;
+            }
+            (false, Some(value)) => {
+                self.mbuf_mut().extend(offset, FIELD_LEN)?;
+                self.mbuf_mut()
+                    .write_data_slice(offset, &value.to_be_bytes())?;
+                self.set_flag(flag, true);
+            }
+            (true, None) => {
+                self.mbuf_mut().shrink(offset, FIELD_LEN)?;
+                self.set_flag(flag, false);
+            }
+            (false, None) => (),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the version number.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        let v: u16 = (self.header().flags_version & VERSION).into();
+        v as u8
+    }
+
+    /// Returns the checksum if the checksum field is present.
+    #[inline]
+    pub fn checksum(&self) -> Option<u16> {
+        self.read_field(CHECKSUM_PRESENT).map(|v| (v >> 16) as u16)
+    }
+
+    /// Adds the checksum field to the header.
+    ///
+    /// The checksum is computed when the packet is reconciled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    pub fn enable_checksum(&mut self) -> Result<()> {
+        if self.flag(CHECKSUM_PRESENT) {
+            Ok(())
+        } else {
+            self.write_field(CHECKSUM_PRESENT, Some(0))
+        }
+    }
+
+    /// Removes the checksum field from the header.
+    #[inline]
+    pub fn disable_checksum(&mut self) -> Result<()> {
+        self.write_field(CHECKSUM_PRESENT, None)
+    }
+
+    /// Returns the key if the key field is present.
+    #[inline]
+    pub fn key(&self) -> Option<u32> {
+        self.read_field(KEY_PRESENT)
+    }
+
+    /// Sets the key. The key field is added to the header if not already
+    /// present. Setting the key to `None` removes the field.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    pub fn set_key(&mut self, key: Option<u32>) -> Result<()> {
+        self.write_field(KEY_PRESENT, key)
+    }
+
+    /// Returns the sequence number if the sequence number field is present.
+    #[inline]
+    pub fn sequence_number(&self) -> Option<u32> {
+        self.read_field(SEQ_PRESENT)
+    }
+
+    /// Sets the sequence number. The sequence number field is added to the
+    /// header if not already present. Setting the sequence number to `None`
+    /// removes the field.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    pub fn set_sequence_number(&mut self, seq_no: Option<u32>) -> Result<()> {
+        self.write_field(SEQ_PRESENT, seq_no)
+    }
+
+    #[inline]
+    fn compute_checksum(&mut self) {
+        let offset = self.field_offset(CHECKSUM_PRESENT);
+        let _ = self.mbuf_mut().write_data_slice(offset, &[0u8; FIELD_LEN]);
+
+        let data = self.mbuf().data_segments(self.offset, self.len());
+        let checksum = checksum::compute_segments(0, data);
+        let _ = self
+            .mbuf_mut()
+            .write_data_slice(offset, &checksum.to_be_bytes());
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Gre<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("gre")
+            .field("version", &self.version())
+            .field("protocol_type", &format!("{}", self.protocol_type()))
+            .field("checksum", &self.checksum().map(|c| format!("0x{:04x}", c)))
+            .field("key", &self.key())
+            .field("sequence_number", &self.sequence_number())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Gre<E> {
+    /// The preceding packet type for a GRE packet can be either an [IPv4]
+    /// packet, an [IPv6] packet, or any IPv6 extension packets.
+    ///
+    /// [IPv4]: crate::packets::ip::v4::Ipv4
+    /// [IPv6]: crate::packets::ip::v6::Ipv6
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the packet header.
+    ///
+    /// The length of the GRE header depends on the optional fields present.
+    #[inline]
+    fn header_len(&self) -> usize {
+        let fields = [CHECKSUM_PRESENT, KEY_PRESENT, SEQ_PRESENT]
+            .iter()
+            .filter(|&&flag| self.flag(flag))
+            .count();
+        GreHeader::size_of() + fields * FIELD_LEN
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Gre::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as a GRE packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the [`next_protocol`] is not set to
+    /// [`ProtocolNumbers::Gre`]. Returns an error if the version is not 0,
+    /// or if the routing field is present. Returns an error if the payload
+    /// does not have sufficient data for the GRE header.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    /// [`ProtocolNumbers::Gre`]: ProtocolNumbers::Gre
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.next_protocol() == ProtocolNumbers::Gre,
+            anyhow!("not a GRE packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Gre {
+            envelope,
+            header,
+            offset,
+        };
+
+        ensure!(
+            packet.version() == 0,
+            anyhow!("unsupported GRE version {}.", packet.version())
+        );
+        ensure!(
+            !packet.flag(ROUTING_PRESENT),
+            anyhow!("GRE routing field is not supported.")
+        );
+        ensure!(
+            packet.len() >= packet.header_len(),
+            anyhow!("packet has incomplete GRE header.")
+        );
+
+        Ok(packet)
+    }
+
+    /// Prepends a GRE packet to the beginning of the envelope's payload.
+    ///
+    /// The header has no optional fields. [`next_protocol`] is set to
+    /// [`ProtocolNumbers::Gre`]. The protocol type is not set, it should be
+    /// set to the type of the payload packet, unless one is pushed after
+    /// the GRE packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    /// [`ProtocolNumbers::Gre`]: ProtocolNumbers::Gre
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, GreHeader::size_of())?;
+        let header = mbuf.write_data(offset, &GreHeader::default())?;
+
+        envelope.set_next_protocol(ProtocolNumbers::Gre);
+
+        Ok(Gre {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Removes the GRE packet from the message buffer.
+    ///
+    /// If the payload packet is either IPv4 or IPv6, the envelope's
+    /// [`next_protocol`] is set to the corresponding IP encapsulation
+    /// protocol number. To fully decapsulate the payload packet, the outer
+    /// IP packet should be removed as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have sufficient data to
+    /// remove.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    #[inline]
+    fn remove(mut self) -> Result<Self::Envelope> {
+        let offset = self.offset();
+        let len = self.header_len();
+        let protocol_type = self.protocol_type();
+        self.mbuf_mut().shrink(offset, len)?;
+
+        match protocol_type {
+            EtherTypes::Ipv4 => self.envelope_mut().set_next_protocol(ProtocolNumbers::Ipv4),
+            EtherTypes::Ipv6 => self.envelope_mut().set_next_protocol(ProtocolNumbers::Ipv6),
+            _ => (),
+        }
+
+        Ok(self.envelope)
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`checksum`] is computed based on the GRE header and the payload
+    /// packet, if the checksum field is present.
+    ///
+    /// [`checksum`]: Gre::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        if self.flag(CHECKSUM_PRESENT) {
+            self.compute_checksum();
+        }
+    }
+}
+
+impl<E: IpPacket> Datalink for Gre<E> {
+    #[inline]
+    fn protocol_type(&self) -> EtherType {
+        EtherType::new(self.header().protocol_type.into())
+    }
+
+    #[inline]
+    fn set_protocol_type(&mut self, ether_type: EtherType) -> Result<()> {
+        self.header_mut().protocol_type = ether_type.0.into();
+        Ok(())
+    }
+}
+
+impl<E: IpPacket> EthernetEnvelope for Gre<E> {
+    /// Returns whether the protocol type is set to
+    /// [`EtherTypes::TransEtherBridging`].
+    ///
+    /// [`EtherTypes::TransEtherBridging`]: EtherTypes::TransEtherBridging
+    #[inline]
+    fn is_ethernet_payload(&self) -> bool {
+        self.protocol_type() == EtherTypes::TransEtherBridging
+    }
+
+    #[inline]
+    fn set_ethernet_payload(&mut self) {
+        self.header_mut().protocol_type = EtherTypes::TransEtherBridging.0.into();
+    }
+}
+
+/// GRE header.
+///
+/// The header only include the fixed portion of the GRE header. Optional
+/// fields are read separately.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct GreHeader {
+    flags_version: u16be,
+    protocol_type: u16be,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::{Ethernet, Udp};
+    use crate::testils::byte_arrays::IPV4_UDP_PACKET;
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_gre_header() {
+        assert_eq!(4, GreHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_gre_packet() {
+        let packet = Mbuf::from_bytes(&GRE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let gre = ipv4.parse::<Gre<Ipv4>>().unwrap();
+
+        assert_eq!(0, gre.version());
+        assert_eq!(EtherTypes::Ipv4, gre.protocol_type());
+        assert_eq!(None, gre.checksum());
+        assert_eq!(Some(0x0102_0304), gre.key());
+        assert_eq!(None, gre.sequence_number());
+        assert_eq!(8, gre.header_len());
+
+        let inner = gre.parse::<Ipv4<Gre<Ipv4>>>().unwrap();
+        assert_eq!("139.133.217.110", inner.src().to_string());
+        assert_eq!("139.133.233.2", inner.dst().to_string());
+
+        let udp = inner.parse::<Udp<Ipv4<Gre<Ipv4>>>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+        assert_eq!(1087, udp.dst_port());
+    }
+
+    #[capsule::test]
+    fn parse_non_gre_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Gre<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn parse_gre_with_wrong_payload_type() {
+        let packet = Mbuf::from_bytes(&GRE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let gre = ipv4.parse::<Gre<Ipv4>>().unwrap();
+
+        assert!(gre.peek::<Ethernet<Gre<Ipv4>>>().is_err());
+        assert!(gre.peek::<Ipv6<Gre<Ipv4>>>().is_err());
+    }
+
+    #[capsule::test]
+    fn set_optional_fields() {
+        let packet = Mbuf::from_bytes(&GRE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut gre = ipv4.parse::<Gre<Ipv4>>().unwrap();
+        let payload_len = gre.payload_len();
+
+        gre.set_sequence_number(Some(42)).unwrap();
+        gre.enable_checksum().unwrap();
+        gre.set_key(Some(0xdead_beef)).unwrap();
+
+        assert_eq!(16, gre.header_len());
+        assert_eq!(payload_len, gre.payload_len());
+        assert_eq!(Some(0), gre.checksum());
+        assert_eq!(Some(0xdead_beef), gre.key());
+        assert_eq!(Some(42), gre.sequence_number());
+
+        gre.reconcile();
+        let data = gre.mbuf().read_data_slice(gre.offset(), gre.len()).unwrap();
+        assert_eq!(0, checksum::compute(0, unsafe { data.as_ref() }));
+
+        gre.set_key(None).unwrap();
+        assert_eq!(12, gre.header_len());
+        assert_eq!(None, gre.key());
+        assert_eq!(Some(42), gre.sequence_number());
+
+        gre.disable_checksum().unwrap();
+        assert_eq!(8, gre.header_len());
+        assert_eq!(None, gre.checksum());
+        assert_eq!(Some(42), gre.sequence_number());
+        assert_eq!(payload_len, gre.payload_len());
+
+        let inner = gre.parse::<Ipv4<Gre<Ipv4>>>().unwrap();
+        assert_eq!("139.133.217.110", inner.src().to_string());
+    }
+
+    #[capsule::test]
+    fn encap_ipv4_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let inner_len = ethernet.payload_len();
+
+        let outer = ethernet.push::<Ipv4>().unwrap();
+        let mut gre = outer.push::<Gre<Ipv4>>().unwrap();
+        gre.set_protocol_type(EtherTypes::Ipv4).unwrap();
+        gre.set_key(Some(7)).unwrap();
+        gre.reconcile_all();
+
+        assert_eq!(ProtocolNumbers::Gre, gre.envelope().next_protocol());
+        assert_eq!((20 + 8 + inner_len) as u16, gre.envelope().total_length());
+
+        let inner = gre.parse::<Ipv4<Gre<Ipv4>>>().unwrap();
+        let udp = inner.parse::<Udp<Ipv4<Gre<Ipv4>>>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+    }
+
+    #[capsule::test]
+    fn decap_ipv4_packet() {
+        let packet = Mbuf::from_bytes(&GRE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let gre = ipv4.parse::<Gre<Ipv4>>().unwrap();
+
+        let outer = gre.remove().unwrap();
+        assert_eq!(ProtocolNumbers::Ipv4, outer.next_protocol());
+
+        let ethernet = outer.remove().unwrap();
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+
+        let inner = ethernet.parse::<Ipv4>().unwrap();
+        assert_eq!("139.133.217.110", inner.src().to_string());
+        assert!(inner.parse::<Udp<Ipv4>>().is_ok());
+    }
+
+    #[capsule::test]
+    fn push_inner_ethernet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let gre = ipv6.push::<Gre<Ipv6>>().unwrap();
+        let inner = gre.push::<Ethernet<Gre<Ipv6>>>().unwrap();
+
+        assert_eq!(
+            EtherTypes::TransEtherBridging,
+            inner.envelope().protocol_type()
+        );
+
+        let inner = inner.push::<Ipv4<Ethernet<Gre<Ipv6>>>>().unwrap();
+        assert_eq!(EtherTypes::Ipv4, inner.envelope().ether_type());
+
+        let gre = inner.deparse().deparse();
+        assert!(gre.peek::<Ethernet<Gre<Ipv6>>>().is_ok());
+    }
+
+    /// IPv4 UDP packet encapsulated in GRE with a key.
+    #[rustfmt::skip]
+    const GRE_PACKET: [u8; 80] = [
+    // Ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x08, 0x00,
+    // IPv4 header
+        0x45, 0x00,
+        // total length = 66
+        0x00, 0x42,
+        0x00, 0x00, 0x40, 0x00,
+        // ttl = 64, protocol = GRE
+        0x40, 0x2f, 0x00, 0x00,
+        // src = 10.0.0.1
+        0x0a, 0x00, 0x00, 0x01,
+        // dst = 10.0.0.2
+        0x0a, 0x00, 0x00, 0x02,
+    // GRE header
+        // key present, version 0, protocol type = IPv4
+        0x20, 0x00, 0x08, 0x00,
+        // key
+        0x01, 0x02, 0x03, 0x04,
+    // inner IPv4 header
+        0x45, 0x00, 0x00, 0x26,
+        0xab, 0x49, 0x40, 0x00,
+        0xff, 0x11, 0xf7, 0x00,
+        0x8b, 0x85, 0xd9, 0x6e,
+        0x8b, 0x85, 0xe9, 0x02,
+    // UDP header
+        0x99, 0xd0, 0x04, 0x3f,
+        0x00, 0x12, 0x72, 0x28,
+    // UDP payload
+        0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+    ];
+}
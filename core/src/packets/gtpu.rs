@@ -0,0 +1,1011 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::ip::IpPacket;
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{Datalink, EtherType, EtherTypes, Internal, Packet, Udp};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// The IANA assigned UDP port for GTP-U.
+pub const GTPU_PORT: u16 = 2152;
+
+// Flags.
+const VERSION: u8 = 0xe0;
+const PROTOCOL_TYPE: u8 = 0x10;
+const EXTENSION_HEADER: u8 = 0x04;
+const SEQUENCE_NUMBER: u8 = 0x02;
+const N_PDU_NUMBER: u8 = 0x01;
+const OPTIONAL: u8 = EXTENSION_HEADER | SEQUENCE_NUMBER | N_PDU_NUMBER;
+
+// Length of the optional fields in octets.
+const OPTIONAL_LEN: usize = 4;
+
+/// The type of a GTP-U message.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct GtpUMessageType(pub u8);
+
+/// Supported GTP-U message types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod GtpUMessageTypes {
+    use super::GtpUMessageType;
+
+    /// Echo request.
+    pub const EchoRequest: GtpUMessageType = GtpUMessageType(1);
+
+    /// Echo response.
+    pub const EchoResponse: GtpUMessageType = GtpUMessageType(2);
+
+    /// Error indication.
+    pub const ErrorIndication: GtpUMessageType = GtpUMessageType(26);
+
+    /// Supported extension headers notification.
+    pub const SupportedExtensionHeadersNotification: GtpUMessageType = GtpUMessageType(31);
+
+    /// End marker.
+    pub const EndMarker: GtpUMessageType = GtpUMessageType(254);
+
+    /// G-PDU, which carries a user packet.
+    pub const GPdu: GtpUMessageType = GtpUMessageType(255);
+}
+
+impl fmt::Display for GtpUMessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                GtpUMessageTypes::EchoRequest => "Echo Request".to_string(),
+                GtpUMessageTypes::EchoResponse => "Echo Response".to_string(),
+                GtpUMessageTypes::ErrorIndication => "Error Indication".to_string(),
+                GtpUMessageTypes::SupportedExtensionHeadersNotification => {
+                    "Supported Extension Headers Notification".to_string()
+                }
+                GtpUMessageTypes::EndMarker => "End Marker".to_string(),
+                GtpUMessageTypes::GPdu => "G-PDU".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// GPRS Tunnelling Protocol User Plane packet based on [3GPP TS 29.281].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |Version|P|R|E|S|N| Message Type  |             Length            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 Tunnel Endpoint Identifier                    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |        Sequence Number        | N-PDU Number  |  Next Ext Hdr |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Version*:            3 bits. The GTP version, which is 1.
+///
+/// - *Protocol Type*:      1 bit. Set to 1 for GTP, and 0 for GTP'.
+///
+/// - *E*, *S* and *N*:     1 bit each. Indicate the presence of the next
+///                         extension header, the sequence number and the
+///                         N-PDU number. If any of the flags is set, all
+///                         three optional fields are present.
+///
+/// - *Message Type*:       8 bits. The type of GTP-U message.
+///
+/// - *Length*:             16 bits. The length of the payload in octets,
+///                         including the optional fields and the extension
+///                         headers, but not the first 8 octets.
+///
+/// - *TEID*:               32 bits. Tunnel endpoint identifier.
+///
+/// The extension headers follow the optional fields. The payload of a G-PDU
+/// message is a user IPv4 or IPv6 packet. Because GTP-U does not identify
+/// the type of the payload, unless it is set explicitly with
+/// [`set_protocol_type`], it is guessed from the first nibble of the
+/// payload.
+///
+/// ```
+/// let udp = ipv4.parse::<Udp<Ipv4>>()?;
+/// if udp.dst_port() == GTPU_PORT {
+///     let gtpu = udp.parse::<GtpU<Ipv4>>()?;
+///     if gtpu.protocol_type() == EtherTypes::Ipv4 {
+///         let user = gtpu.parse::<Ipv4<GtpU<Ipv4>>>()?;
+///     }
+/// }
+/// ```
+///
+/// [3GPP TS 29.281]: https://www.3gpp.org/ftp/Specs/archive/29_series/29.281/
+/// [`set_protocol_type`]: Datalink::set_protocol_type
+pub struct GtpU<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<GtpUHeader>,
+    offset: usize,
+    ext_len: usize,
+    payload_type: Option<EtherType>,
+}
+
+impl<E: IpPacket> GtpU<E> {
+    #[inline]
+    fn header(&self) -> &GtpUHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut GtpUHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    #[inline]
+    fn flag(&self, flag: u8) -> bool {
+        self.header().flags & flag != 0
+    }
+
+    #[inline]
+    fn set_flag(&mut self, flag: u8, value: bool) {
+        let flags = self.header().flags;
+        self.header_mut().flags = if value { flags | flag } else { flags & !flag };
+    }
+
+    #[inline]
+    fn read_u8(&self, offset: usize) -> Result<u8> {
+        let value = self.mbuf().read_data_slice::<u8>(offset, 1)?;
+        Ok(unsafe { value.as_ref()[0] })
+    }
+
+    /// Returns the protocol version.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        (self.header().flags & VERSION) >> 5
+    }
+
+    /// Returns the message type.
+    #[inline]
+    pub fn message_type(&self) -> GtpUMessageType {
+        GtpUMessageType(self.header().message_type)
+    }
+
+    /// Sets the message type.
+    #[inline]
+    pub fn set_message_type(&mut self, message_type: GtpUMessageType) {
+        self.header_mut().message_type = message_type.0;
+    }
+
+    /// Returns the length of the payload, including the optional fields
+    /// and the extension headers.
+    #[inline]
+    pub fn length(&self) -> u16 {
+        self.header().length.into()
+    }
+
+    #[inline]
+    fn set_length(&mut self, length: u16) {
+        self.header_mut().length = length.into();
+    }
+
+    /// Returns the tunnel endpoint identifier.
+    #[inline]
+    pub fn teid(&self) -> u32 {
+        self.header().teid.into()
+    }
+
+    /// Sets the tunnel endpoint identifier.
+    #[inline]
+    pub fn set_teid(&mut self, teid: u32) {
+        self.header_mut().teid = teid.into();
+    }
+
+    /// Inserts the optional fields, set to 0, if they are not present.
+    fn insert_optional(&mut self) -> Result<()> {
+        if !self.flag(OPTIONAL) {
+            let offset = self.offset + GtpUHeader::size_of();
+            self.mbuf_mut().extend(offset, OPTIONAL_LEN)?;
+            self.mbuf_mut().write_data_slice(offset, &[0u8; 4])?;
+        }
+        Ok(())
+    }
+
+    /// Clears the flag and removes the optional fields if they are no
+    /// longer used.
+    fn clear_optional(&mut self, flag: u8) -> Result<()> {
+        if self.flag(flag) {
+            self.set_flag(flag, false);
+            if !self.flag(OPTIONAL) {
+                let offset = self.offset + GtpUHeader::size_of();
+                self.mbuf_mut().shrink(offset, OPTIONAL_LEN)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the sequence number if the S flag is set.
+    #[inline]
+    pub fn sequence_number(&self) -> Option<u16> {
+        if self.flag(SEQUENCE_NUMBER) {
+            let offset = self.offset + GtpUHeader::size_of();
+            let seq = self.mbuf().read_data_slice::<u8>(offset, 2).ok()?;
+            let seq = unsafe { seq.as_ref() };
+            Some(u16::from_be_bytes([seq[0], seq[1]]))
+        } else {
+            None
+        }
+    }
+
+    /// Sets or removes the sequence number.
+    ///
+    /// The optional fields are inserted or removed as needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    pub fn set_sequence_number(&mut self, seq: Option<u16>) -> Result<()> {
+        match seq {
+            Some(seq) => {
+                self.insert_optional()?;
+                let offset = self.offset + GtpUHeader::size_of();
+                self.mbuf_mut()
+                    .write_data_slice(offset, &seq.to_be_bytes())?;
+                self.set_flag(SEQUENCE_NUMBER, true);
+                Ok(())
+            }
+            None => self.clear_optional(SEQUENCE_NUMBER),
+        }
+    }
+
+    /// Returns the N-PDU number if the N flag is set.
+    #[inline]
+    pub fn n_pdu_number(&self) -> Option<u8> {
+        if self.flag(N_PDU_NUMBER) {
+            self.read_u8(self.offset + GtpUHeader::size_of() + 2).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Sets or removes the N-PDU number.
+    ///
+    /// The optional fields are inserted or removed as needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    pub fn set_n_pdu_number(&mut self, n_pdu: Option<u8>) -> Result<()> {
+        match n_pdu {
+            Some(n_pdu) => {
+                self.insert_optional()?;
+                let offset = self.offset + GtpUHeader::size_of() + 2;
+                self.mbuf_mut().write_data_slice(offset, &[n_pdu])?;
+                self.set_flag(N_PDU_NUMBER, true);
+                Ok(())
+            }
+            None => self.clear_optional(N_PDU_NUMBER),
+        }
+    }
+
+    /// Returns the buffer offset of the next extension header type field
+    /// in the optional fields.
+    #[inline]
+    fn next_type_offset(&self) -> usize {
+        self.offset + GtpUHeader::size_of() + 3
+    }
+
+    /// Returns an iterator that iterates through the extension headers.
+    #[inline]
+    pub fn extension_headers_iter(&self) -> GtpUExtensionHeadersIterator<'_> {
+        let next_type = if self.flag(EXTENSION_HEADER) {
+            self.read_u8(self.next_type_offset()).unwrap_or(0)
+        } else {
+            0
+        };
+
+        GtpUExtensionHeadersIterator {
+            mbuf: self.mbuf(),
+            offset: self.offset + GtpUHeader::size_of() + OPTIONAL_LEN,
+            next_type,
+        }
+    }
+
+    /// Appends an extension header at the end of the extension header
+    /// chain.
+    ///
+    /// The optional fields are inserted if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    pub fn append_extension_header(&mut self, header: &GtpUExtensionHeader) -> Result<()> {
+        self.insert_optional()?;
+        if !self.flag(EXTENSION_HEADER) {
+            let offset = self.next_type_offset();
+            self.mbuf_mut().write_data_slice(offset, &[0u8])?;
+            self.set_flag(EXTENSION_HEADER, true);
+        }
+
+        // the next extension header type field of the last header.
+        let last = self.offset + self.header_len() - 1;
+        let end = last + 1;
+
+        self.mbuf_mut()
+            .write_data_slice(last, &[header.header_type])?;
+        self.mbuf_mut().extend(end, header.length())?;
+        self.mbuf_mut().write_data_slice(end, &header.encode())?;
+        self.ext_len += header.length();
+
+        Ok(())
+    }
+
+    /// Removes all the extension headers.
+    ///
+    /// The optional fields are removed if they are no longer used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have sufficient data to
+    /// remove.
+    pub fn clear_extension_headers(&mut self) -> Result<()> {
+        if self.flag(EXTENSION_HEADER) {
+            let offset = self.offset + GtpUHeader::size_of() + OPTIONAL_LEN;
+            let len = self.ext_len;
+            self.mbuf_mut().shrink(offset, len)?;
+            self.ext_len = 0;
+
+            let next_type = self.next_type_offset();
+            self.mbuf_mut().write_data_slice(next_type, &[0u8])?;
+            self.clear_optional(EXTENSION_HEADER)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the length of the extension headers, starting with the
+    /// next extension header type in the optional fields.
+    fn extension_headers_len(&self) -> Result<usize> {
+        let mut iter = self.extension_headers_iter();
+        let mut len = 0;
+        while let Some(header) = iter.next()? {
+            len += header.length();
+        }
+        Ok(len)
+    }
+
+    /// Encapsulates the envelope's payload, a user IP packet, in a G-PDU
+    /// message.
+    ///
+    /// A UDP packet and a GTP-U packet are pushed in front of the user
+    /// packet. Both UDP ports are set to [`GTPU_PORT`]. The packet is
+    /// reconciled afterwards, so the GTP-U length, and the outer UDP and IP
+    /// lengths are filled in.
+    ///
+    /// ```
+    /// let ethernet = ipv4.deparse();
+    /// let mut outer = ethernet.push::<Ipv4>()?;
+    /// outer.set_src(upf);
+    /// outer.set_dst(gnb);
+    /// let gtpu = GtpU::encap(outer, teid)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`GTPU_PORT`]: GTPU_PORT
+    pub fn encap(envelope: E, teid: u32) -> Result<Self> {
+        let mut udp = envelope.push::<Udp<E>>()?;
+        udp.set_src_port(GTPU_PORT);
+        let mut gtpu = udp.push::<GtpU<E>>()?;
+        gtpu.set_teid(teid);
+        gtpu.reconcile_all();
+
+        Ok(gtpu)
+    }
+
+    /// Decapsulates the user IP packet by removing the GTP-U, the UDP and
+    /// the outer IP packets.
+    ///
+    /// The protocol type of the outer IP packet's envelope is set to the
+    /// type of the user packet, so the user packet can be parsed from it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not a G-PDU, or if the payload is
+    /// neither an IPv4 nor an IPv6 packet. Returns an error if the buffer
+    /// does not have sufficient data to remove.
+    pub fn decap(self) -> Result<E::Envelope>
+    where
+        E::Envelope: Datalink,
+    {
+        ensure!(
+            self.message_type() == GtpUMessageTypes::GPdu,
+            anyhow!("not a G-PDU message.")
+        );
+
+        let protocol_type = self.protocol_type();
+        ensure!(
+            protocol_type == EtherTypes::Ipv4 || protocol_type == EtherTypes::Ipv6,
+            anyhow!("payload is not an IP packet.")
+        );
+
+        let udp = self.remove()?;
+        let ip = udp.remove()?;
+        let mut envelope = ip.remove()?;
+        envelope.set_protocol_type(protocol_type)?;
+
+        Ok(envelope)
+    }
+
+    /// Guesses the type of the payload from the first nibble.
+    fn guess_payload_type(&self) -> EtherType {
+        match self.read_u8(self.payload_offset()).map(|b| b >> 4) {
+            Ok(4) => EtherTypes::Ipv4,
+            Ok(6) => EtherTypes::Ipv6,
+            _ => EtherType::new(0),
+        }
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for GtpU<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("gtpu")
+            .field("version", &self.version())
+            .field("message_type", &format!("{}", self.message_type()))
+            .field("length", &self.length())
+            .field("teid", &format!("0x{:08x}", self.teid()))
+            .field("sequence_number", &self.sequence_number())
+            .field("n_pdu_number", &self.n_pdu_number())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for GtpU<E> {
+    /// The preceding type for a GTP-U packet must be UDP.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the header, including the optional fields and
+    /// the extension headers.
+    #[inline]
+    fn header_len(&self) -> usize {
+        if self.flag(OPTIONAL) {
+            GtpUHeader::size_of() + OPTIONAL_LEN + self.ext_len
+        } else {
+            GtpUHeader::size_of()
+        }
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        GtpU::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+            ext_len: self.ext_len,
+            payload_type: self.payload_type,
+        }
+    }
+
+    /// Parses the UDP payload as a GTP-U packet.
+    ///
+    /// The UDP port is not checked because deployments may use a port
+    /// other than [`GTPU_PORT`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the version is not 1, or if the protocol type is
+    /// not GTP. Returns an error if the payload does not have sufficient
+    /// data for the header, including the optional fields and the extension
+    /// headers.
+    ///
+    /// [`GTPU_PORT`]: GTPU_PORT
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let mut packet = GtpU {
+            envelope,
+            header,
+            offset,
+            ext_len: 0,
+            payload_type: None,
+        };
+
+        ensure!(
+            packet.version() == 1,
+            anyhow!("unsupported GTP version {}.", packet.version())
+        );
+        ensure!(
+            packet.flag(PROTOCOL_TYPE),
+            anyhow!("GTP' is not supported.")
+        );
+
+        packet.ext_len = packet.extension_headers_len()?;
+        ensure!(
+            packet.len() >= packet.header_len(),
+            anyhow!("packet has incomplete GTP-U header.")
+        );
+
+        Ok(packet)
+    }
+
+    /// Prepends a GTP-U packet to the beginning of the UDP's payload.
+    ///
+    /// The message type is set to G-PDU, and the header has no optional
+    /// fields. The UDP destination port is set to [`GTPU_PORT`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`GTPU_PORT`]: GTPU_PORT
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, GtpUHeader::size_of())?;
+        let header = mbuf.write_data(offset, &GtpUHeader::default())?;
+
+        envelope.set_dst_port(GTPU_PORT);
+
+        Ok(GtpU {
+            envelope,
+            header,
+            offset,
+            ext_len: 0,
+            payload_type: None,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`length`] is set to the length of the packet, not including the
+    /// first 8 octets of the header.
+    ///
+    /// [`length`]: GtpU::length
+    #[inline]
+    fn reconcile(&mut self) {
+        let len = (self.len() - GtpUHeader::size_of()) as u16;
+        self.set_length(len);
+    }
+}
+
+impl<E: IpPacket> Datalink for GtpU<E> {
+    /// Returns the type of the user packet.
+    ///
+    /// Returns the type set with [`set_protocol_type`] if there is one.
+    /// Otherwise returns [`EtherTypes::Ipv4`] or [`EtherTypes::Ipv6`] based
+    /// on the first nibble of the payload, or an unassigned EtherType of 0
+    /// if the nibble is neither 4 nor 6.
+    ///
+    /// [`set_protocol_type`]: Datalink::set_protocol_type
+    /// [`EtherTypes::Ipv4`]: EtherTypes::Ipv4
+    /// [`EtherTypes::Ipv6`]: EtherTypes::Ipv6
+    #[inline]
+    fn protocol_type(&self) -> EtherType {
+        self.payload_type
+            .unwrap_or_else(|| self.guess_payload_type())
+    }
+
+    /// Sets the type of the user packet.
+    ///
+    /// The type is not written to the buffer because GTP-U does not have a
+    /// payload type field.
+    #[inline]
+    fn set_protocol_type(&mut self, ether_type: EtherType) -> Result<()> {
+        self.payload_type = Some(ether_type);
+        Ok(())
+    }
+}
+
+/// A GTP-U extension header.
+///
+/// ```
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |    Length     |                   Contents                    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 ...                           |  Next Ext Hdr |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// The length is in 4-octet units, including the length and the next
+/// extension header type octets. The type of an extension header is in the
+/// next extension header type field of the preceding header.
+///
+/// Extension headers are read out of and written into the buffer by value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GtpUExtensionHeader {
+    header_type: u8,
+    contents: Vec<u8>,
+}
+
+impl GtpUExtensionHeader {
+    /// Creates a new extension header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `header_type` is 0, which marks the end of the
+    /// chain. Returns an error if the length of `contents` plus 2 is not a
+    /// multiple of 4, or the header exceeds 1020 octets.
+    pub fn new(header_type: u8, contents: Vec<u8>) -> Result<Self> {
+        ensure!(
+            header_type != 0,
+            anyhow!("invalid GTP-U extension header type 0.")
+        );
+        ensure!(
+            (contents.len() + 2) % 4 == 0 && contents.len() + 2 <= 255 * 4,
+            anyhow!(
+                "invalid GTP-U extension header contents length {}.",
+                contents.len()
+            )
+        );
+
+        Ok(GtpUExtensionHeader {
+            header_type,
+            contents,
+        })
+    }
+
+    /// Returns the extension header type.
+    #[inline]
+    pub fn header_type(&self) -> u8 {
+        self.header_type
+    }
+
+    /// Returns the contents of the extension header.
+    #[inline]
+    pub fn contents(&self) -> &[u8] {
+        &self.contents
+    }
+
+    /// Returns the length of the extension header in octets.
+    #[inline]
+    pub fn length(&self) -> usize {
+        self.contents.len() + 2
+    }
+
+    /// Encodes the extension header, ending the chain.
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.length());
+        bytes.push((self.length() / 4) as u8);
+        bytes.extend_from_slice(&self.contents);
+        bytes.push(0);
+        bytes
+    }
+}
+
+/// An iterator that iterates through the GTP-U extension headers.
+pub struct GtpUExtensionHeadersIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    next_type: u8,
+}
+
+impl GtpUExtensionHeadersIterator<'_> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<GtpUExtensionHeader>> {
+        if self.next_type == 0 {
+            return Ok(None);
+        }
+
+        let len = self.mbuf.read_data_slice::<u8>(self.offset, 1)?;
+        let len = unsafe { len.as_ref()[0] } as usize * 4;
+        ensure!(len > 0, anyhow!("invalid GTP-U extension header length 0."));
+
+        let bytes = self.mbuf.read_data_slice::<u8>(self.offset, len)?;
+        let bytes = unsafe { bytes.as_ref() };
+        let header = GtpUExtensionHeader {
+            header_type: self.next_type,
+            contents: bytes[1..len - 1].to_vec(),
+        };
+
+        // advances the offset to the next extension header
+        self.next_type = bytes[len - 1];
+        self.offset += len;
+
+        Ok(Some(header))
+    }
+}
+
+impl fmt::Debug for GtpUExtensionHeadersIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GtpUExtensionHeadersIterator")
+            .field("offset", &self.offset)
+            .field("next_type", &self.next_type)
+            .finish()
+    }
+}
+
+/// GTP-U header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct GtpUHeader {
+    flags: u8,
+    message_type: u8,
+    length: u16be,
+    teid: u32be,
+}
+
+impl Default for GtpUHeader {
+    fn default() -> Self {
+        GtpUHeader {
+            // version 1 and protocol type GTP.
+            flags: 0x30,
+            message_type: GtpUMessageTypes::GPdu.0,
+            length: u16be::default(),
+            teid: u32be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::IPV4_UDP_PACKET;
+
+    #[test]
+    fn size_of_gtpu_header() {
+        assert_eq!(8, GtpUHeader::size_of());
+    }
+
+    #[test]
+    fn message_type_to_string() {
+        assert_eq!("G-PDU", GtpUMessageTypes::GPdu.to_string());
+        assert_eq!("Echo Request", GtpUMessageTypes::EchoRequest.to_string());
+        assert_eq!("100", GtpUMessageType(100).to_string());
+    }
+
+    #[capsule::test]
+    fn parse_gtpu_packet() {
+        let packet = Mbuf::from_bytes(&GTPU_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        assert_eq!(GTPU_PORT, udp.dst_port());
+
+        let gtpu = udp.parse::<GtpU<Ipv4>>().unwrap();
+        assert_eq!(1, gtpu.version());
+        assert_eq!(GtpUMessageTypes::GPdu, gtpu.message_type());
+        assert_eq!(46, gtpu.length());
+        assert_eq!(0x1234_5678, gtpu.teid());
+        assert_eq!(Some(1), gtpu.sequence_number());
+        assert_eq!(None, gtpu.n_pdu_number());
+        assert_eq!(16, gtpu.header_len());
+
+        let mut iter = gtpu.extension_headers_iter();
+        let header = iter.next().unwrap().unwrap();
+        assert_eq!(0x85, header.header_type());
+        assert_eq!(&[0x00, 0x09], header.contents());
+        assert!(iter.next().unwrap().is_none());
+
+        assert_eq!(EtherTypes::Ipv4, gtpu.protocol_type());
+        let user = gtpu.parse::<Ipv4<GtpU<Ipv4>>>().unwrap();
+        assert_eq!("139.133.217.110", user.src().to_string());
+
+        let udp = user.parse::<Udp<Ipv4<GtpU<Ipv4>>>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+    }
+
+    #[capsule::test]
+    fn parse_gtp_prime_packet() {
+        // clears the protocol type flag.
+        let mut bytes = GTPU_PACKET;
+        bytes[42] = 0x26;
+
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<GtpU<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn set_optional_fields() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let udp = ipv6.push::<Udp<Ipv6>>().unwrap();
+        let mut gtpu = udp.push::<GtpU<Ipv6>>().unwrap();
+
+        assert_eq!(8, gtpu.header_len());
+        assert_eq!(GTPU_PORT, gtpu.envelope().dst_port());
+        assert_eq!(1, gtpu.version());
+        assert_eq!(GtpUMessageTypes::GPdu, gtpu.message_type());
+
+        gtpu.set_sequence_number(Some(10)).unwrap();
+        assert_eq!(Some(10), gtpu.sequence_number());
+        assert_eq!(None, gtpu.n_pdu_number());
+        assert_eq!(12, gtpu.header_len());
+
+        gtpu.set_n_pdu_number(Some(3)).unwrap();
+        assert_eq!(Some(3), gtpu.n_pdu_number());
+        assert_eq!(12, gtpu.header_len());
+
+        gtpu.set_sequence_number(None).unwrap();
+        assert_eq!(None, gtpu.sequence_number());
+        assert_eq!(12, gtpu.header_len());
+
+        gtpu.set_n_pdu_number(None).unwrap();
+        assert_eq!(8, gtpu.header_len());
+        assert_eq!(8, gtpu.len());
+    }
+
+    #[capsule::test]
+    fn append_and_clear_extension_headers() {
+        let packet = Mbuf::from_bytes(&GTPU_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let mut gtpu = udp.parse::<GtpU<Ipv4>>().unwrap();
+
+        let header = GtpUExtensionHeader::new(0x40, vec![0x08, 0x68]).unwrap();
+        gtpu.append_extension_header(&header).unwrap();
+        assert_eq!(20, gtpu.header_len());
+
+        gtpu.reconcile_all();
+        assert_eq!(50, gtpu.length());
+        assert_eq!(66, gtpu.envelope().length());
+
+        let mut iter = gtpu.extension_headers_iter();
+        assert_eq!(0x85, iter.next().unwrap().unwrap().header_type());
+        assert_eq!(header, iter.next().unwrap().unwrap());
+        assert!(iter.next().unwrap().is_none());
+
+        gtpu.clear_extension_headers().unwrap();
+        assert!(gtpu.extension_headers_iter().next().unwrap().is_none());
+        assert_eq!(Some(1), gtpu.sequence_number());
+        assert_eq!(12, gtpu.header_len());
+
+        gtpu.reconcile_all();
+        assert_eq!(42, gtpu.length());
+
+        // the user packet is intact.
+        let user = gtpu.parse::<Ipv4<GtpU<Ipv4>>>().unwrap();
+        assert_eq!("139.133.217.110", user.src().to_string());
+    }
+
+    #[test]
+    fn invalid_extension_header() {
+        assert!(GtpUExtensionHeader::new(0, vec![0; 2]).is_err());
+        assert!(GtpUExtensionHeader::new(0x85, vec![0; 3]).is_err());
+        assert!(GtpUExtensionHeader::new(0x85, vec![0; 6]).is_ok());
+    }
+
+    #[capsule::test]
+    fn encap_user_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let gtpu = GtpU::encap(ipv4, 0x1234_5678).unwrap();
+
+        let user_len = IPV4_UDP_PACKET.len() - 14;
+        assert_eq!(0x1234_5678, gtpu.teid());
+        assert_eq!(user_len as u16, gtpu.length());
+        assert_eq!(GTPU_PORT, gtpu.envelope().src_port());
+        assert_eq!((8 + 8 + user_len) as u16, gtpu.envelope().length());
+        assert_eq!(
+            (20 + 8 + 8 + user_len) as u16,
+            gtpu.envelope().envelope().total_length()
+        );
+
+        let user = gtpu.parse::<Ipv4<GtpU<Ipv4>>>().unwrap();
+        assert_eq!("139.133.217.110", user.src().to_string());
+    }
+
+    #[capsule::test]
+    fn decap_user_packet() {
+        let packet = Mbuf::from_bytes(&GTPU_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let gtpu = udp.parse::<GtpU<Ipv4>>().unwrap();
+
+        let ethernet = gtpu.decap().unwrap();
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        assert_eq!(IPV4_UDP_PACKET.len(), ethernet.len());
+
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+    }
+
+    /// IPv4 UDP user packet in a G-PDU with a sequence number and a PDU
+    /// session container extension header.
+    #[rustfmt::skip]
+    const GTPU_PACKET: [u8; 96] = [
+    // Ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x0a,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x0b,
+        0x08, 0x00,
+    // IPv4 header
+        0x45, 0x00,
+        // total length = 82
+        0x00, 0x52,
+        0x00, 0x00, 0x40, 0x00,
+        // ttl = 64, protocol = UDP
+        0x40, 0x11, 0x00, 0x00,
+        // src = 10.0.0.1
+        0x0a, 0x00, 0x00, 0x01,
+        // dst = 10.0.0.2
+        0x0a, 0x00, 0x00, 0x02,
+    // UDP header
+        // src_port = 2152, dst_port = 2152
+        0x08, 0x68, 0x08, 0x68,
+        // length = 62, checksum = 0
+        0x00, 0x3e, 0x00, 0x00,
+    // GTP-U header
+        // version = 1, PT, E and S flags, message type = G-PDU
+        0x36, 0xff,
+        // length = 46
+        0x00, 0x2e,
+        // TEID = 0x12345678
+        0x12, 0x34, 0x56, 0x78,
+        // sequence number = 1, N-PDU number = 0, next type = 0x85
+        0x00, 0x01, 0x00, 0x85,
+    // PDU session container
+        // length = 1, QFI = 9, next type = 0
+        0x01, 0x00, 0x09, 0x00,
+    // user IPv4 header
+        0x45, 0x00, 0x00, 0x26,
+        0xab, 0x49, 0x40, 0x00,
+        0xff, 0x11, 0xf7, 0x00,
+        0x8b, 0x85, 0xd9, 0x6e,
+        0x8b, 0x85, 0xe9, 0x02,
+    // user UDP header
+        0x99, 0xd0, 0x04, 0x3f,
+        0x00, 0x12, 0x72, 0x28,
+    // user UDP payload
+        0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+    ];
+}
@@ -99,18 +99,14 @@ impl EchoReply {
         self.payload_len() - EchoReplyBody::size_of()
     }
 
-    /// Returns the data as a `u8` slice.
+    /// Returns a copy of the data.
     #[inline]
-    pub fn data(&self) -> &[u8] {
-        if let Ok(data) = self
-            .icmp()
+    pub fn data(&self) -> Vec<u8> {
+        self.icmp()
             .mbuf()
-            .read_data_slice(self.data_offset(), self.data_len())
-        {
-            unsafe { &*data.as_ptr() }
-        } else {
-            unreachable!()
-        }
+            .data_segments(self.data_offset(), self.data_len())
+            .collect::<Vec<_>>()
+            .concat()
     }
 
     /// Sets the data.
@@ -123,7 +119,7 @@ impl EchoReply {
         let offset = self.data_offset();
         let len = data.len() as isize - self.data_len() as isize;
         self.icmp_mut().mbuf_mut().resize(offset, len)?;
-        self.icmp_mut().mbuf_mut().write_data_slice(offset, data)?;
+        self.icmp_mut().mbuf_mut().copy_from_slice(offset, data)?;
         Ok(())
     }
 }
@@ -247,7 +243,7 @@ mod tests {
 
         let data = [0; 10];
         assert!(echo.set_data(&data).is_ok());
-        assert_eq!(&data, echo.data());
+        assert_eq!(data.to_vec(), echo.data());
         assert_eq!(EchoReplyBody::size_of() + 10, echo.payload_len());
 
         echo.reconcile_all();
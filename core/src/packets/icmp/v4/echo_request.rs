@@ -100,18 +100,14 @@ impl EchoRequest {
         self.payload_len() - EchoRequestBody::size_of()
     }
 
-    /// Returns the data as a `u8` slice.
+    /// Returns a copy of the data.
     #[inline]
-    pub fn data(&self) -> &[u8] {
-        if let Ok(data) = self
-            .icmp()
+    pub fn data(&self) -> Vec<u8> {
+        self.icmp()
             .mbuf()
-            .read_data_slice(self.data_offset(), self.data_len())
-        {
-            unsafe { &*data.as_ptr() }
-        } else {
-            unreachable!()
-        }
+            .data_segments(self.data_offset(), self.data_len())
+            .collect::<Vec<_>>()
+            .concat()
     }
 
     /// Sets the data.
@@ -124,7 +120,7 @@ impl EchoRequest {
         let offset = self.data_offset();
         let len = data.len() as isize - self.data_len() as isize;
         self.icmp_mut().mbuf_mut().resize(offset, len)?;
-        self.icmp_mut().mbuf_mut().write_data_slice(offset, data)?;
+        self.icmp_mut().mbuf_mut().copy_from_slice(offset, data)?;
         Ok(())
     }
 }
@@ -248,10 +244,23 @@ mod tests {
 
         let data = [0; 10];
         assert!(echo.set_data(&data).is_ok());
-        assert_eq!(&data, echo.data());
+        assert_eq!(data.to_vec(), echo.data());
         assert_eq!(EchoRequestBody::size_of() + 10, echo.payload_len());
 
         echo.reconcile_all();
         assert!(echo.checksum() != 0);
     }
+
+    #[capsule::test]
+    fn set_and_read_chained_echo_request_data() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ip = ethernet.push::<Ipv4>().unwrap();
+        let mut echo = ip.push::<EchoRequest>().unwrap();
+
+        let data = (0..8000).map(|i| i as u8).collect::<Vec<_>>();
+        echo.set_data(&data).unwrap();
+        assert!(echo.mbuf().num_segments() > 1);
+        assert_eq!(data, echo.data());
+    }
 }
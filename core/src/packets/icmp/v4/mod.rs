@@ -18,13 +18,17 @@
 
 //! Internet Control Message Protocol for IPv4.
 
+mod destination_unreachable;
 mod echo_reply;
 mod echo_request;
+mod parameter_problem;
 mod redirect;
 mod time_exceeded;
 
+pub use self::destination_unreachable::*;
 pub use self::echo_reply::*;
 pub use self::echo_request::*;
+pub use self::parameter_problem::*;
 pub use self::redirect::*;
 pub use self::time_exceeded::*;
 pub use capsule_macros::Icmpv4Packet;
@@ -118,14 +122,9 @@ impl Icmpv4 {
     pub fn compute_checksum(&mut self) {
         self.header_mut().checksum = u16be::default();
 
-        if let Ok(data) = self.mbuf().read_data_slice(self.offset(), self.len()) {
-            let data = unsafe { data.as_ref() };
-            let checksum = checksum::compute(0, data);
-            self.header_mut().checksum = checksum.into();
-        } else {
-            // we are reading till the end of buffer, should never run out
-            unreachable!()
-        }
+        let data = self.mbuf().data_segments(self.offset(), self.len());
+        let checksum = checksum::compute_segments(0, data);
+        self.header_mut().checksum = checksum.into();
     }
 
     /// Casts the ICMPv4 packet to a message of type `T`.
@@ -290,6 +289,16 @@ pub mod Icmpv4Types {
     ///
     /// [Redirect]: crate::packets::icmp::v4::Redirect
     pub const Redirect: Icmpv4Type = Icmpv4Type(5);
+
+    /// Message type for [Destination Unreachable].
+    ///
+    /// [Destination Unreachable]: crate::packets::icmp::v4::DestinationUnreachable
+    pub const DestinationUnreachable: Icmpv4Type = Icmpv4Type(3);
+
+    /// Message type for [Parameter Problem].
+    ///
+    /// [Parameter Problem]: crate::packets::icmp::v4::ParameterProblem
+    pub const ParameterProblem: Icmpv4Type = Icmpv4Type(12);
 }
 
 impl fmt::Display for Icmpv4Type {
@@ -302,6 +311,8 @@ impl fmt::Display for Icmpv4Type {
                 Icmpv4Types::EchoReply => "Echo Reply".to_string(),
                 Icmpv4Types::TimeExceeded => "Time Exceeded".to_string(),
                 Icmpv4Types::Redirect => "Redirect".to_string(),
+                Icmpv4Types::DestinationUnreachable => "Destination Unreachable".to_string(),
+                Icmpv4Types::ParameterProblem => "Parameter Problem".to_string(),
                 _ => format!("{}", self.0),
             }
         )
@@ -82,18 +82,14 @@ impl Redirect {
         self.payload_len() - RedirectBody::size_of()
     }
 
-    /// Returns the data packet as a `u8` slice.
+    /// Returns a copy of the data packet.
     #[inline]
-    pub fn data(&self) -> &[u8] {
-        if let Ok(data) = self
-            .icmp()
+    pub fn data(&self) -> Vec<u8> {
+        self.icmp()
             .mbuf()
-            .read_data_slice(self.data_offset(), self.data_len())
-        {
-            unsafe { &*data.as_ptr() }
-        } else {
-            &[]
-        }
+            .data_segments(self.data_offset(), self.data_len())
+            .collect::<Vec<_>>()
+            .concat()
     }
 }
 
@@ -58,18 +58,14 @@ impl TimeExceeded {
         self.payload_len() - TimeExceededBody::size_of()
     }
 
-    /// Returns the invoking packet as a `u8` slice.
+    /// Returns a copy of the invoking packet.
     #[inline]
-    pub fn data(&self) -> &[u8] {
-        if let Ok(data) = self
-            .icmp()
+    pub fn data(&self) -> Vec<u8> {
+        self.icmp()
             .mbuf()
-            .read_data_slice(self.data_offset(), self.data_len())
-        {
-            unsafe { &*data.as_ptr() }
-        } else {
-            &[]
-        }
+            .data_segments(self.data_offset(), self.data_len())
+            .collect::<Vec<_>>()
+            .concat()
     }
 }
 
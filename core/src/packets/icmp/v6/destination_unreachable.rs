@@ -48,17 +48,17 @@ pub struct DestinationUnreachable<E: Ipv6Packet> {
 }
 
 impl<E: Ipv6Packet> DestinationUnreachable<E> {
-    /// Returns the invoking packet as a `u8` slice.
+    /// Returns a copy of the invoking packet.
     #[inline]
-    pub fn data(&self) -> &[u8] {
+    pub fn data(&self) -> Vec<u8> {
         let offset = self.payload_offset() + DestinationUnreachableBody::size_of();
         let len = self.payload_len() - DestinationUnreachableBody::size_of();
 
-        if let Ok(data) = self.icmp().mbuf().read_data_slice(offset, len) {
-            unsafe { &*data.as_ptr() }
-        } else {
-            &[]
-        }
+        self.icmp()
+            .mbuf()
+            .data_segments(offset, len)
+            .collect::<Vec<_>>()
+            .concat()
     }
 }
 
@@ -0,0 +1,184 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Multicast Listener Discovery
+//!
+//! MLD is used by IPv6 routers to discover the presence of multicast
+//! listeners on their directly attached links, and to discover which
+//! multicast addresses are of interest to those listeners. Version 1 is
+//! defined in [IETF RFC 2710], and version 2, which adds source filtering,
+//! in [IETF RFC 3810]. All MLD messages are ICMPv6 messages.
+//!
+//! [IETF RFC 2710]: https://tools.ietf.org/html/rfc2710
+//! [IETF RFC 3810]: https://tools.ietf.org/html/rfc3810
+
+mod done;
+mod query;
+mod report;
+mod report_v2;
+
+pub use self::done::*;
+pub use self::query::*;
+pub use self::report::*;
+pub use self::report_v2::*;
+
+use crate::packets::GroupRecordType;
+use crate::{ensure, Mbuf};
+use anyhow::{anyhow, Result};
+use std::convert::TryFrom;
+use std::fmt;
+use std::net::Ipv6Addr;
+
+/// The length of the fixed fields of a multicast address record.
+const ADDRESS_RECORD_FIXED_LEN: usize = 20;
+
+/// Reads `count` IPv6 addresses at `offset`.
+fn read_addrs(mbuf: &Mbuf, offset: usize, count: usize) -> Result<Vec<Ipv6Addr>> {
+    if count == 0 {
+        return Ok(vec![]);
+    }
+
+    let bytes = mbuf.read_data_slice::<u8>(offset, count * 16)?;
+    let bytes = unsafe { bytes.as_ref() };
+    Ok(bytes
+        .chunks(16)
+        .map(|octets| Ipv6Addr::from(<[u8; 16]>::try_from(octets).unwrap()))
+        .collect())
+}
+
+/// A multicast address record in an MLDv2 report.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Record Type  |  Aux Data Len |     Number of Sources (N)     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// *                       Multicast Address                       *
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// *                       Source Address [1..N]                   *
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// .                         Auxiliary Data                        .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// Address records are read out of and written into the buffer by value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MldAddressRecord {
+    /// The type of the record.
+    pub record_type: GroupRecordType,
+    /// The multicast address to which the record pertains.
+    pub multicast_addr: Ipv6Addr,
+    /// The source addresses.
+    pub sources: Vec<Ipv6Addr>,
+    /// The auxiliary data, in multiples of 4 octets.
+    pub aux_data: Vec<u8>,
+}
+
+impl MldAddressRecord {
+    /// Returns the length of the record in octets.
+    #[inline]
+    pub fn length(&self) -> usize {
+        ADDRESS_RECORD_FIXED_LEN + self.sources.len() * 16 + self.aux_data.len()
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        ensure!(
+            self.aux_data.len() % 4 == 0 && self.aux_data.len() <= 255 * 4,
+            anyhow!("invalid auxiliary data length {}.", self.aux_data.len())
+        );
+        ensure!(
+            self.sources.len() <= u16::MAX as usize,
+            anyhow!("too many address record sources.")
+        );
+
+        let mut bytes = Vec::with_capacity(self.length());
+        bytes.push(self.record_type.0);
+        bytes.push((self.aux_data.len() / 4) as u8);
+        bytes.extend_from_slice(&(self.sources.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&self.multicast_addr.octets());
+        for source in &self.sources {
+            bytes.extend_from_slice(&source.octets());
+        }
+        bytes.extend_from_slice(&self.aux_data);
+        Ok(bytes)
+    }
+}
+
+/// An iterator that iterates through the address records of an MLDv2
+/// report.
+pub struct MldAddressRecordsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    remaining: u16,
+}
+
+impl MldAddressRecordsIterator<'_> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<MldAddressRecord>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        let fixed = self
+            .mbuf
+            .read_data_slice::<u8>(self.offset, ADDRESS_RECORD_FIXED_LEN)?;
+        let fixed = unsafe { fixed.as_ref() };
+        let aux_len = fixed[1] as usize * 4;
+        let count = u16::from_be_bytes([fixed[2], fixed[3]]) as usize;
+        let multicast_addr = Ipv6Addr::from(<[u8; 16]>::try_from(&fixed[4..20]).unwrap());
+
+        let sources_offset = self.offset + ADDRESS_RECORD_FIXED_LEN;
+        let sources = read_addrs(self.mbuf, sources_offset, count)?;
+
+        let aux_offset = sources_offset + count * 16;
+        let aux_data = if aux_len > 0 {
+            let aux = self.mbuf.read_data_slice::<u8>(aux_offset, aux_len)?;
+            unsafe { aux.as_ref() }.to_vec()
+        } else {
+            vec![]
+        };
+
+        self.offset = aux_offset + aux_len;
+        self.remaining -= 1;
+
+        Ok(Some(MldAddressRecord {
+            record_type: GroupRecordType(fixed[0]),
+            multicast_addr,
+            sources,
+            aux_data,
+        }))
+    }
+}
+
+impl fmt::Debug for MldAddressRecordsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MldAddressRecordsIterator")
+            .field("offset", &self.offset)
+            .field("remaining", &self.remaining)
+            .finish()
+    }
+}
@@ -0,0 +1,423 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::read_addrs;
+use crate::packets::icmp::v6::{Icmpv6, Icmpv6Message, Icmpv6Packet, Icmpv6Type, Icmpv6Types};
+use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::types::u16be;
+use crate::packets::{Internal, Packet};
+use crate::{ensure, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::ptr::NonNull;
+use std::time::Duration;
+
+/// The length of the MLDv2 query fields following the version 1 body.
+const V2_QUERY_LEN: usize = 4;
+
+/// Multicast Listener Query Message defined in [IETF RFC 2710] and
+/// [IETF RFC 3810].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Type = 130   |      Code     |           Checksum            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |    Maximum Response Code      |           Reserved            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// *                       Multicast Address                       *
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | Resv  |S| QRV |     QQIC      |     Number of Sources (N)     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// *                       Source Address [1..N]                   *
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Maximum Response Code*:
+///                     The maximum time allowed before sending a report,
+///                     in milliseconds. Values of 32768 and above are a
+///                     floating point encoding in MLDv2.
+///
+/// - *Multicast Address*:
+///                     The multicast address being queried. Unspecified in
+///                     a general query.
+///
+/// The *S*, *QRV*, *QQIC* and source fields are only present in an MLDv2
+/// query. A version is told from the other by the length of the message.
+///
+/// [IETF RFC 2710]: https://tools.ietf.org/html/rfc2710#section-3
+/// [IETF RFC 3810]: https://tools.ietf.org/html/rfc3810#section-5.1
+#[derive(Icmpv6Packet)]
+pub struct MulticastListenerQuery<E: Ipv6Packet> {
+    icmp: Icmpv6<E>,
+    body: NonNull<MulticastListenerQueryBody>,
+}
+
+impl<E: Ipv6Packet> MulticastListenerQuery<E> {
+    #[inline]
+    fn body(&self) -> &MulticastListenerQueryBody {
+        unsafe { self.body.as_ref() }
+    }
+
+    #[inline]
+    fn body_mut(&mut self) -> &mut MulticastListenerQueryBody {
+        unsafe { self.body.as_mut() }
+    }
+
+    /// Returns the maximum response code.
+    #[inline]
+    pub fn max_resp_code(&self) -> u16 {
+        self.body().max_resp_code.into()
+    }
+
+    /// Sets the maximum response code.
+    #[inline]
+    pub fn set_max_resp_code(&mut self, code: u16) {
+        self.body_mut().max_resp_code = code.into();
+    }
+
+    /// Returns the maximum response delay decoded from the maximum response
+    /// code.
+    ///
+    /// Codes below 32768 are in milliseconds for both versions. Codes of
+    /// 32768 and above use the MLDv2 floating point encoding.
+    pub fn max_resp_delay(&self) -> Duration {
+        let code = self.max_resp_code() as u64;
+        let millis = if code < 32768 {
+            code
+        } else {
+            let exp = (code >> 12) & 0x07;
+            let mant = code & 0x0fff;
+            (mant | 0x1000) << (exp + 3)
+        };
+        Duration::from_millis(millis)
+    }
+
+    /// Returns the multicast address.
+    #[inline]
+    pub fn multicast_addr(&self) -> Ipv6Addr {
+        self.body().multicast_addr
+    }
+
+    /// Sets the multicast address.
+    #[inline]
+    pub fn set_multicast_addr(&mut self, multicast_addr: Ipv6Addr) {
+        self.body_mut().multicast_addr = multicast_addr;
+    }
+
+    /// Returns whether the message is an MLDv2 query.
+    #[inline]
+    pub fn is_v2(&self) -> bool {
+        self.payload_len() >= MulticastListenerQueryBody::size_of() + V2_QUERY_LEN
+    }
+
+    /// Returns the offset of the MLDv2 query fields.
+    #[inline]
+    fn v2_offset(&self) -> usize {
+        self.payload_offset() + MulticastListenerQueryBody::size_of()
+    }
+
+    /// Reads the MLDv2 query fields, or `None` if the message is not an
+    /// MLDv2 query.
+    #[inline]
+    fn v2_fields(&self) -> Option<&[u8]> {
+        if self.is_v2() {
+            self.mbuf()
+                .read_data_slice::<u8>(self.v2_offset(), V2_QUERY_LEN)
+                .ok()
+                .map(|fields| unsafe { &*fields.as_ptr() })
+        } else {
+            None
+        }
+    }
+
+    /// Writes the octet of an MLDv2 query field.
+    fn set_v2_field(&mut self, index: usize, mask: u8, value: u8) -> Result<()> {
+        let fields = self
+            .v2_fields()
+            .ok_or_else(|| anyhow!("not an MLDv2 query."))?;
+        let octet = (fields[index] & !mask) | (value & mask);
+        let offset = self.v2_offset() + index;
+        self.mbuf_mut().write_data_slice(offset, &[octet])?;
+        Ok(())
+    }
+
+    /// Extends the query with the MLDv2 fields, with no sources.
+    ///
+    /// Does nothing if the message is already an MLDv2 query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    pub fn make_v2(&mut self) -> Result<()> {
+        if !self.is_v2() {
+            let offset = self.v2_offset();
+            self.mbuf_mut().extend(offset, V2_QUERY_LEN)?;
+            self.mbuf_mut()
+                .write_data_slice(offset, &[0u8; V2_QUERY_LEN])?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the suppress router-side processing flag of an MLDv2 query.
+    #[inline]
+    pub fn suppress_router_processing(&self) -> bool {
+        matches!(self.v2_fields(), Some(fields) if fields[0] & 0x08 != 0)
+    }
+
+    /// Sets the suppress router-side processing flag of an MLDv2 query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not an MLDv2 query.
+    #[inline]
+    pub fn set_suppress_router_processing(&mut self, suppress: bool) -> Result<()> {
+        self.set_v2_field(0, 0x08, if suppress { 0x08 } else { 0 })
+    }
+
+    /// Returns the querier's robustness variable of an MLDv2 query, or 0
+    /// if the message is not an MLDv2 query.
+    #[inline]
+    pub fn qrv(&self) -> u8 {
+        self.v2_fields().map_or(0, |fields| fields[0] & 0x07)
+    }
+
+    /// Sets the querier's robustness variable of an MLDv2 query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not an MLDv2 query.
+    #[inline]
+    pub fn set_qrv(&mut self, qrv: u8) -> Result<()> {
+        self.set_v2_field(0, 0x07, qrv)
+    }
+
+    /// Returns the querier's query interval code of an MLDv2 query, or 0
+    /// if the message is not an MLDv2 query.
+    #[inline]
+    pub fn qqic(&self) -> u8 {
+        self.v2_fields().map_or(0, |fields| fields[1])
+    }
+
+    /// Sets the querier's query interval code of an MLDv2 query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not an MLDv2 query.
+    #[inline]
+    pub fn set_qqic(&mut self, qqic: u8) -> Result<()> {
+        self.set_v2_field(1, 0xff, qqic)
+    }
+
+    /// Returns the source addresses of an MLDv2 multicast address and
+    /// source specific query. Other queries have no sources.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is truncated.
+    pub fn sources(&self) -> Result<Vec<Ipv6Addr>> {
+        match self.v2_fields() {
+            Some(fields) => {
+                let count = u16::from_be_bytes([fields[2], fields[3]]) as usize;
+                read_addrs(self.mbuf(), self.v2_offset() + V2_QUERY_LEN, count)
+            }
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Appends a source address to an MLDv2 query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not an MLDv2 query, or the
+    /// buffer does not have enough free space.
+    pub fn append_source(&mut self, source: Ipv6Addr) -> Result<()> {
+        let fields = self
+            .v2_fields()
+            .ok_or_else(|| anyhow!("not an MLDv2 query."))?;
+        let count = u16::from_be_bytes([fields[2], fields[3]]);
+        ensure!(count < u16::MAX, anyhow!("too many MLD query sources."));
+
+        let offset = self.v2_offset() + V2_QUERY_LEN + count as usize * 16;
+        self.mbuf_mut().extend(offset, 16)?;
+        self.mbuf_mut().write_data_slice(offset, &source.octets())?;
+        let count_offset = self.v2_offset() + 2;
+        self.mbuf_mut()
+            .write_data_slice(count_offset, &(count + 1).to_be_bytes())?;
+
+        Ok(())
+    }
+}
+
+impl<E: Ipv6Packet> fmt::Debug for MulticastListenerQuery<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MulticastListenerQuery")
+            .field("type", &format!("{}", self.msg_type()))
+            .field("code", &self.code())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("max_resp_code", &self.max_resp_code())
+            .field("multicast_addr", &self.multicast_addr())
+            .field("v2", &self.is_v2())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: Ipv6Packet> Icmpv6Message for MulticastListenerQuery<E> {
+    type Envelope = E;
+
+    #[inline]
+    fn msg_type() -> Icmpv6Type {
+        Icmpv6Types::MulticastListenerQuery
+    }
+
+    #[inline]
+    fn icmp(&self) -> &Icmpv6<Self::Envelope> {
+        &self.icmp
+    }
+
+    #[inline]
+    fn icmp_mut(&mut self) -> &mut Icmpv6<Self::Envelope> {
+        &mut self.icmp
+    }
+
+    #[inline]
+    fn into_icmp(self) -> Icmpv6<Self::Envelope> {
+        self.icmp
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        MulticastListenerQuery {
+            icmp: self.icmp.clone(internal),
+            body: self.body,
+        }
+    }
+
+    /// Parses the ICMPv6 packet's payload as multicast listener query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload does not have sufficient data for
+    /// the query message body, or the sources of an MLDv2 query.
+    #[inline]
+    fn try_parse(icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Result<Self> {
+        let mbuf = icmp.mbuf();
+        let offset = icmp.payload_offset();
+        let body = mbuf.read_data(offset)?;
+
+        let query = MulticastListenerQuery { icmp, body };
+        query.sources()?;
+
+        Ok(query)
+    }
+
+    /// Prepends a new MLDv1 general query to the beginning of the ICMPv6's
+    /// payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Result<Self> {
+        let offset = icmp.payload_offset();
+        let mbuf = icmp.mbuf_mut();
+
+        mbuf.extend(offset, MulticastListenerQueryBody::size_of())?;
+        let body = mbuf.write_data(offset, &MulticastListenerQueryBody::default())?;
+
+        Ok(MulticastListenerQuery { icmp, body })
+    }
+}
+
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct MulticastListenerQueryBody {
+    max_resp_code: u16be,
+    reserved: u16be,
+    multicast_addr: Ipv6Addr,
+}
+
+impl Default for MulticastListenerQueryBody {
+    fn default() -> Self {
+        MulticastListenerQueryBody {
+            max_resp_code: u16be::default(),
+            reserved: u16be::default(),
+            multicast_addr: Ipv6Addr::UNSPECIFIED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::icmp::v6::Icmpv6;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::Ethernet;
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_multicast_listener_query_body() {
+        assert_eq!(20, MulticastListenerQueryBody::size_of());
+    }
+
+    #[capsule::test]
+    fn push_and_set_mldv2_query() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut query = ipv6.push::<MulticastListenerQuery<Ipv6>>().unwrap();
+
+        assert_eq!(Icmpv6Types::MulticastListenerQuery, query.msg_type());
+        assert!(!query.is_v2());
+        assert!(query.set_qrv(2).is_err());
+        assert!(query.sources().unwrap().is_empty());
+
+        query.set_max_resp_code(0x8400);
+        assert_eq!(Duration::from_millis(0x1400 << 3), query.max_resp_delay());
+
+        query.make_v2().unwrap();
+        assert!(query.is_v2());
+        query.set_qrv(2).unwrap();
+        query.set_qqic(125).unwrap();
+        query.set_suppress_router_processing(true).unwrap();
+        let source: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        query.append_source(source).unwrap();
+        query.reconcile_all();
+
+        // parses again from the generic ICMPv6 packet.
+        let ipv6 = query.deparse();
+        let icmpv6 = ipv6.parse::<Icmpv6<Ipv6>>().unwrap();
+        let query = icmpv6.downcast::<MulticastListenerQuery<Ipv6>>().unwrap();
+        assert!(query.is_v2());
+        assert_eq!(2, query.qrv());
+        assert_eq!(125, query.qqic());
+        assert!(query.suppress_router_processing());
+        assert_eq!(vec![source], query.sources().unwrap());
+        assert_eq!(4 + 20 + 4 + 16, query.len());
+    }
+}
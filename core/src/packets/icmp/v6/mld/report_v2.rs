@@ -0,0 +1,264 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::{MldAddressRecord, MldAddressRecordsIterator};
+use crate::packets::icmp::v6::{Icmpv6, Icmpv6Message, Icmpv6Packet, Icmpv6Type, Icmpv6Types};
+use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::types::u16be;
+use crate::packets::{Internal, Packet};
+use crate::{ensure, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// Version 2 Multicast Listener Report Message defined in [IETF RFC 3810].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Type = 143   |    Reserved   |           Checksum            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           Reserved            |Nr of Mcast Address Records (M)|
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// .                                                               .
+/// .                  Multicast Address Record [1..M]              .
+/// .                                                               .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Nr of Mcast Address Records*:
+///                     The number of multicast address records present in
+///                     this report.
+///
+/// - *Multicast Address Record*:
+///                     Each record contains information on the sender
+///                     listening to a single multicast address. See
+///                     [`MldAddressRecord`].
+///
+/// [IETF RFC 3810]: https://tools.ietf.org/html/rfc3810#section-5.2
+/// [`MldAddressRecord`]: MldAddressRecord
+#[derive(Icmpv6Packet)]
+pub struct MulticastListenerReportV2<E: Ipv6Packet> {
+    icmp: Icmpv6<E>,
+    body: NonNull<MulticastListenerReportV2Body>,
+}
+
+impl<E: Ipv6Packet> MulticastListenerReportV2<E> {
+    #[inline]
+    fn body(&self) -> &MulticastListenerReportV2Body {
+        unsafe { self.body.as_ref() }
+    }
+
+    #[inline]
+    fn body_mut(&mut self) -> &mut MulticastListenerReportV2Body {
+        unsafe { self.body.as_mut() }
+    }
+
+    /// Returns the number of multicast address records.
+    #[inline]
+    pub fn record_count(&self) -> u16 {
+        self.body().record_count.into()
+    }
+
+    /// Returns an iterator that iterates through the multicast address
+    /// records.
+    #[inline]
+    pub fn records_iter(&self) -> MldAddressRecordsIterator<'_> {
+        MldAddressRecordsIterator {
+            mbuf: self.mbuf(),
+            offset: self.payload_offset() + MulticastListenerReportV2Body::size_of(),
+            remaining: self.record_count(),
+        }
+    }
+
+    /// Appends a multicast address record.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut report = ipv6.push::<MulticastListenerReportV2<Ipv6>>()?;
+    /// report.append_record(&MldAddressRecord {
+    ///     record_type: GroupRecordTypes::ChangeToExcludeMode,
+    ///     multicast_addr: "ff05::1:3".parse()?,
+    ///     sources: vec![],
+    ///     aux_data: vec![],
+    /// })?;
+    /// report.reconcile_all();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record is invalid, or the buffer does not
+    /// have enough free space.
+    pub fn append_record(&mut self, record: &MldAddressRecord) -> Result<()> {
+        let count = self.record_count();
+        ensure!(count < u16::MAX, anyhow!("too many MLD address records."));
+
+        // skips the existing records to find the end.
+        let mut iter = self.records_iter();
+        while iter.next()?.is_some() {}
+        let offset = iter.offset;
+
+        let bytes = record.encode()?;
+        self.mbuf_mut().extend(offset, bytes.len())?;
+        self.mbuf_mut().write_data_slice(offset, &bytes)?;
+        self.body_mut().record_count = (count + 1).into();
+
+        Ok(())
+    }
+}
+
+impl<E: Ipv6Packet> fmt::Debug for MulticastListenerReportV2<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MulticastListenerReportV2")
+            .field("type", &format!("{}", self.msg_type()))
+            .field("code", &self.code())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("record_count", &self.record_count())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: Ipv6Packet> Icmpv6Message for MulticastListenerReportV2<E> {
+    type Envelope = E;
+
+    #[inline]
+    fn msg_type() -> Icmpv6Type {
+        Icmpv6Types::MulticastListenerReportV2
+    }
+
+    #[inline]
+    fn icmp(&self) -> &Icmpv6<Self::Envelope> {
+        &self.icmp
+    }
+
+    #[inline]
+    fn icmp_mut(&mut self) -> &mut Icmpv6<Self::Envelope> {
+        &mut self.icmp
+    }
+
+    #[inline]
+    fn into_icmp(self) -> Icmpv6<Self::Envelope> {
+        self.icmp
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        MulticastListenerReportV2 {
+            icmp: self.icmp.clone(internal),
+            body: self.body,
+        }
+    }
+
+    /// Parses the ICMPv6 packet's payload as version 2 multicast listener
+    /// report.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload does not have sufficient data for
+    /// the report message body and all the multicast address records.
+    #[inline]
+    fn try_parse(icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Result<Self> {
+        let mbuf = icmp.mbuf();
+        let offset = icmp.payload_offset();
+        let body = mbuf.read_data(offset)?;
+
+        let report = MulticastListenerReportV2 { icmp, body };
+        let mut iter = report.records_iter();
+        while iter.next()?.is_some() {}
+
+        Ok(report)
+    }
+
+    /// Prepends a new version 2 multicast listener report with no records
+    /// to the beginning of the ICMPv6's payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Result<Self> {
+        let offset = icmp.payload_offset();
+        let mbuf = icmp.mbuf_mut();
+
+        mbuf.extend(offset, MulticastListenerReportV2Body::size_of())?;
+        let body = mbuf.write_data(offset, &MulticastListenerReportV2Body::default())?;
+
+        Ok(MulticastListenerReportV2 { icmp, body })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct MulticastListenerReportV2Body {
+    reserved: u16be,
+    record_count: u16be,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::{Ethernet, GroupRecordTypes};
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_multicast_listener_report_v2_body() {
+        assert_eq!(4, MulticastListenerReportV2Body::size_of());
+    }
+
+    #[capsule::test]
+    fn push_and_parse_multicast_listener_report_v2() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut report = ipv6.push::<MulticastListenerReportV2<Ipv6>>().unwrap();
+
+        assert_eq!(Icmpv6Types::MulticastListenerReportV2, report.msg_type());
+        assert_eq!(0, report.record_count());
+
+        let exclude = MldAddressRecord {
+            record_type: GroupRecordTypes::ChangeToExcludeMode,
+            multicast_addr: "ff05::1:3".parse().unwrap(),
+            sources: vec![],
+            aux_data: vec![],
+        };
+        let include = MldAddressRecord {
+            record_type: GroupRecordTypes::ModeIsInclude,
+            multicast_addr: "ff3e::8000:1".parse().unwrap(),
+            sources: vec!["2001:db8::1".parse().unwrap()],
+            aux_data: vec![],
+        };
+        report.append_record(&exclude).unwrap();
+        report.append_record(&include).unwrap();
+        assert_eq!(2, report.record_count());
+        assert_eq!(4 + 4 + 20 + 36, report.len());
+        report.reconcile_all();
+
+        let ipv6 = report.deparse();
+        let report = ipv6.parse::<MulticastListenerReportV2<Ipv6>>().unwrap();
+        let mut iter = report.records_iter();
+        assert_eq!(exclude, iter.next().unwrap().unwrap());
+        assert_eq!(include, iter.next().unwrap().unwrap());
+        assert!(iter.next().unwrap().is_none());
+    }
+}
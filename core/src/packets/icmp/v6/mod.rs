@@ -21,13 +21,16 @@
 mod destination_unreachable;
 mod echo_reply;
 mod echo_request;
+pub mod mld;
 pub mod ndp;
+mod parameter_problem;
 mod time_exceeded;
 mod too_big;
 
 pub use self::destination_unreachable::*;
 pub use self::echo_reply::*;
 pub use self::echo_request::*;
+pub use self::parameter_problem::*;
 pub use self::time_exceeded::*;
 pub use self::too_big::*;
 pub use capsule_macros::Icmpv6Packet;
@@ -123,18 +126,13 @@ impl<E: Ipv6Packet> Icmpv6<E> {
     pub fn compute_checksum(&mut self) {
         self.header_mut().checksum = u16be::default();
 
-        if let Ok(data) = self.mbuf().read_data_slice(self.offset(), self.len()) {
-            let data = unsafe { data.as_ref() };
-            let pseudo_header_sum = self
-                .envelope()
-                .pseudo_header(data.len() as u16, ProtocolNumbers::Icmpv6)
-                .sum();
-            let checksum = checksum::compute(pseudo_header_sum, data);
-            self.header_mut().checksum = checksum.into();
-        } else {
-            // we are reading till the end of buffer, should never run out
-            unreachable!()
-        }
+        let pseudo_header_sum = self
+            .envelope()
+            .pseudo_header(self.len() as u16, ProtocolNumbers::Icmpv6)
+            .sum();
+        let data = self.mbuf().data_segments(self.offset(), self.len());
+        let checksum = checksum::compute_segments(pseudo_header_sum, data);
+        self.header_mut().checksum = checksum.into();
     }
 
     /// Casts the ICMPv6 packet to a message of type `T`.
@@ -300,6 +298,11 @@ pub mod Icmpv6Types {
     /// [Destination Unreachable]: crate::packets::icmp::v6::DestinationUnreachable
     pub const DestinationUnreachable: Icmpv6Type = Icmpv6Type(1);
 
+    /// Message type for [Parameter Problem].
+    ///
+    /// [Parameter Problem]: crate::packets::icmp::v6::ParameterProblem
+    pub const ParameterProblem: Icmpv6Type = Icmpv6Type(4);
+
     /// Message type for [Echo Request].
     ///
     /// [Echo Request]: crate::packets::icmp::v6::EchoRequest
@@ -310,6 +313,21 @@ pub mod Icmpv6Types {
     /// [Echo Reply]: crate::packets::icmp::v6::EchoReply
     pub const EchoReply: Icmpv6Type = Icmpv6Type(129);
 
+    /// Message type for [Multicast Listener Query].
+    ///
+    /// [Multicast Listener Query]: crate::packets::icmp::v6::mld::MulticastListenerQuery
+    pub const MulticastListenerQuery: Icmpv6Type = Icmpv6Type(130);
+
+    /// Message type for [Multicast Listener Report].
+    ///
+    /// [Multicast Listener Report]: crate::packets::icmp::v6::mld::MulticastListenerReport
+    pub const MulticastListenerReport: Icmpv6Type = Icmpv6Type(131);
+
+    /// Message type for [Multicast Listener Done].
+    ///
+    /// [Multicast Listener Done]: crate::packets::icmp::v6::mld::MulticastListenerDone
+    pub const MulticastListenerDone: Icmpv6Type = Icmpv6Type(132);
+
     /// Message type for [Router Solicitation].
     ///
     /// [Router Solicitation]: crate::packets::icmp::v6::ndp::RouterSolicitation
@@ -334,6 +352,11 @@ pub mod Icmpv6Types {
     ///
     /// [Redirect]: crate::packets::icmp::v6::ndp::Redirect
     pub const Redirect: Icmpv6Type = Icmpv6Type(137);
+
+    /// Message type for [Version 2 Multicast Listener Report].
+    ///
+    /// [Version 2 Multicast Listener Report]: crate::packets::icmp::v6::mld::MulticastListenerReportV2
+    pub const MulticastListenerReportV2: Icmpv6Type = Icmpv6Type(143);
 }
 
 impl fmt::Display for Icmpv6Type {
@@ -344,13 +367,20 @@ impl fmt::Display for Icmpv6Type {
             match *self {
                 Icmpv6Types::PacketTooBig => "Packet Too Big".to_string(),
                 Icmpv6Types::TimeExceeded => "Time Exceeded".to_string(),
+                Icmpv6Types::ParameterProblem => "Parameter Problem".to_string(),
                 Icmpv6Types::EchoRequest => "Echo Request".to_string(),
                 Icmpv6Types::EchoReply => "Echo Reply".to_string(),
+                Icmpv6Types::MulticastListenerQuery => "Multicast Listener Query".to_string(),
+                Icmpv6Types::MulticastListenerReport => "Multicast Listener Report".to_string(),
+                Icmpv6Types::MulticastListenerDone => "Multicast Listener Done".to_string(),
                 Icmpv6Types::RouterSolicitation => "Router Solicitation".to_string(),
                 Icmpv6Types::RouterAdvertisement => "Router Advertisement".to_string(),
                 Icmpv6Types::NeighborSolicitation => "Neighbor Solicitation".to_string(),
                 Icmpv6Types::NeighborAdvertisement => "Neighbor Advertisement".to_string(),
                 Icmpv6Types::Redirect => "Redirect".to_string(),
+                Icmpv6Types::MulticastListenerReportV2 => {
+                    "Version 2 Multicast Listener Report".to_string()
+                }
                 _ => format!("{}", self.0),
             }
         )
@@ -134,6 +134,26 @@ pub mod NdpOptionTypes {
     ///
     /// [MTU]: crate::packets::icmp::v6::ndp::Mtu
     pub const Mtu: NdpOptionType = NdpOptionType(5);
+
+    /// Option type for [Nonce].
+    ///
+    /// [Nonce]: crate::packets::icmp::v6::ndp::Nonce
+    pub const Nonce: NdpOptionType = NdpOptionType(14);
+
+    /// Option type for [Route Information].
+    ///
+    /// [Route Information]: crate::packets::icmp::v6::ndp::RouteInformation
+    pub const RouteInformation: NdpOptionType = NdpOptionType(24);
+
+    /// Option type for [Recursive DNS Server].
+    ///
+    /// [Recursive DNS Server]: crate::packets::icmp::v6::ndp::RecursiveDnsServer
+    pub const RecursiveDnsServer: NdpOptionType = NdpOptionType(25);
+
+    /// Option type for [DNS Search List].
+    ///
+    /// [DNS Search List]: crate::packets::icmp::v6::ndp::DnsSearchList
+    pub const DnsSearchList: NdpOptionType = NdpOptionType(31);
 }
 
 impl fmt::Display for NdpOptionType {
@@ -147,6 +167,10 @@ impl fmt::Display for NdpOptionType {
                 NdpOptionTypes::PrefixInformation => "Prefix Information".to_string(),
                 NdpOptionTypes::RedirectedHeader => "Redirected Header".to_string(),
                 NdpOptionTypes::Mtu => "MTU".to_string(),
+                NdpOptionTypes::Nonce => "Nonce".to_string(),
+                NdpOptionTypes::RouteInformation => "Route Information".to_string(),
+                NdpOptionTypes::RecursiveDnsServer => "Recursive DNS Server".to_string(),
+                NdpOptionTypes::DnsSearchList => "DNS Search List".to_string(),
                 _ => format!("{}", self.0),
             }
         )
@@ -625,7 +649,7 @@ mod tests {
         let mut prefix = false;
         let mut mtu = false;
         let mut source = false;
-        let mut unknown = false;
+        let mut rdnss = false;
 
         let mut options = advert.options_mut();
         let mut iter = options.iter();
@@ -635,14 +659,15 @@ mod tests {
                 NdpOptionTypes::PrefixInformation => prefix = true,
                 NdpOptionTypes::Mtu => mtu = true,
                 NdpOptionTypes::SourceLinkLayerAddress => source = true,
-                _ => unknown = true, // no support for recursive DNS server option yet
+                NdpOptionTypes::RecursiveDnsServer => rdnss = true,
+                _ => (),
             }
         }
 
         assert!(prefix);
         assert!(mtu);
         assert!(source);
-        assert!(unknown);
+        assert!(rdnss);
     }
 
     #[capsule::test]
@@ -0,0 +1,339 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::icmp::v6::ndp::{NdpOption, NdpOptionType, NdpOptionTypes};
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{encode_name, Internal};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// DNS Search List option defined in [IETF RFC 8106].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     Type      |     Length    |           Reserved            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                           Lifetime                            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// :                Domain Names of DNS Search List                :
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Type*:           31
+///
+/// - *Length*:         The length of the option in units of 8 octets. The
+///                     minimum value is 2 if at least one domain name is
+///                     contained in the option.
+///
+/// - *Reserved*:       This field is unused. It MUST be initialized to
+///                     zero by the sender and MUST be ignored by the
+///                     receiver.
+///
+/// - *Lifetime*:       32-bit unsigned integer. The maximum time in seconds
+///                     over which these DNSSL domain names MAY be used for
+///                     name resolution.
+///
+/// - *Domain Names of DNS Search List*:
+///                     One or more domain names of DNS search list, encoded
+///                     without compression. The field is padded with zeros
+///                     to a multiple of 8 octets.
+///
+/// # Remarks
+///
+/// The option is pushed without any domain names. Use [`set_domain_names`]
+/// to add the names before the packet is sent.
+///
+/// [IETF RFC 8106]: https://tools.ietf.org/html/rfc8106#section-5.2
+/// [`set_domain_names`]: DnsSearchList::set_domain_names
+pub struct DnsSearchList<'a> {
+    mbuf: &'a mut Mbuf,
+    fields: NonNull<DnsSearchListFields>,
+    offset: usize,
+}
+
+impl DnsSearchList<'_> {
+    #[inline]
+    fn fields(&self) -> &DnsSearchListFields {
+        unsafe { self.fields.as_ref() }
+    }
+
+    #[inline]
+    fn fields_mut(&mut self) -> &mut DnsSearchListFields {
+        unsafe { self.fields.as_mut() }
+    }
+
+    /// Returns the maximum time in seconds over which the domain names may
+    /// be used for name resolution.
+    #[inline]
+    pub fn lifetime(&self) -> u32 {
+        self.fields().lifetime.into()
+    }
+
+    /// Sets the lifetime.
+    #[inline]
+    pub fn set_lifetime(&mut self, lifetime: u32) {
+        self.fields_mut().lifetime = lifetime.into();
+    }
+
+    /// Returns the encoded domain names, including the padding.
+    #[inline]
+    fn data(&self) -> &[u8] {
+        let offset = self.offset + DnsSearchListFields::size_of();
+        let len = self.length() as usize * 8 - DnsSearchListFields::size_of();
+
+        if len == 0 {
+            return &[];
+        }
+
+        if let Ok(data) = self.mbuf.read_data_slice(offset, len) {
+            unsafe { &*data.as_ptr() }
+        } else {
+            &[]
+        }
+    }
+
+    /// Returns the domain names of the search list.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a domain name is malformed or truncated.
+    pub fn domain_names(&self) -> Result<Vec<String>> {
+        let data = self.data();
+        let mut names = Vec::new();
+        let mut pos = 0;
+
+        // a zero octet where a name would start is the padding.
+        while pos < data.len() && data[pos] != 0 {
+            let mut labels = Vec::new();
+            loop {
+                ensure!(pos < data.len(), anyhow!("domain name is truncated."));
+                let len = data[pos] as usize;
+                pos += 1;
+                if len == 0 {
+                    break;
+                }
+
+                // compression is not allowed in the search list.
+                ensure!(len <= 63, anyhow!("invalid domain name label."));
+                ensure!(
+                    pos + len <= data.len(),
+                    anyhow!("domain name is truncated.")
+                );
+                labels.push(String::from_utf8_lossy(&data[pos..pos + len]).into_owned());
+                pos += len;
+            }
+            names.push(labels.join("."));
+        }
+
+        Ok(names)
+    }
+
+    /// Sets the domain names of the search list, replacing the existing
+    /// ones. The option is resized and padded to fit the names.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `names` is empty, a name is invalid, or the
+    /// encoded names are too long for the option. Returns an error if the
+    /// buffer does not have enough free space.
+    pub fn set_domain_names(&mut self, names: &[&str]) -> Result<()> {
+        ensure!(!names.is_empty(), anyhow!("empty DNS search list."));
+
+        let mut bytes = Vec::new();
+        for name in names {
+            ensure!(
+                !name.is_empty() && *name != ".",
+                anyhow!("invalid domain name '{}'.", name)
+            );
+            bytes.extend_from_slice(&encode_name(name)?);
+        }
+        let padding = (8 - bytes.len() % 8) % 8;
+        bytes.resize(bytes.len() + padding, 0);
+
+        let length = (DnsSearchListFields::size_of() + bytes.len()) / 8;
+        ensure!(length <= 255, anyhow!("DNS search list is too long."));
+
+        let offset = self.offset + DnsSearchListFields::size_of();
+        let old_len = self.length() as usize * 8 - DnsSearchListFields::size_of();
+        if bytes.len() != old_len {
+            self.mbuf
+                .resize(offset, bytes.len() as isize - old_len as isize)?;
+        }
+        self.mbuf.write_data_slice(offset, &bytes)?;
+
+        self.fields_mut().length = length as u8;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for DnsSearchList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsSearchList")
+            .field("type", &self.option_type())
+            .field("length", &self.length())
+            .field("lifetime", &self.lifetime())
+            .field("domain_names", &self.domain_names().unwrap_or_default())
+            .field("$offset", &self.offset)
+            .finish()
+    }
+}
+
+impl<'a> NdpOption<'a> for DnsSearchList<'a> {
+    /// Returns the option type. Should always be `31`.
+    #[inline]
+    fn option_type(&self) -> NdpOptionType {
+        NdpOptionType(self.fields().option_type)
+    }
+
+    /// Returns the length of the option measured in units of 8 octets.
+    #[inline]
+    fn length(&self) -> u8 {
+        self.fields().length
+    }
+
+    /// Parses the buffer at offset as DNS search list option.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `option_type` is not set to `DnsSearchList`.
+    /// Returns an error if the option length is less than 2.
+    #[inline]
+    fn try_parse(
+        mbuf: &'a mut Mbuf,
+        offset: usize,
+        _internal: Internal,
+    ) -> Result<DnsSearchList<'a>> {
+        let fields = mbuf.read_data::<DnsSearchListFields>(offset)?;
+        let option = DnsSearchList {
+            mbuf,
+            fields,
+            offset,
+        };
+
+        ensure!(
+            option.option_type() == NdpOptionTypes::DnsSearchList,
+            anyhow!("not DNS search list.")
+        );
+
+        ensure!(
+            option.length() >= 2,
+            anyhow!("invalid DNS search list option length.")
+        );
+
+        Ok(option)
+    }
+
+    /// Pushes a new DNS search list option with no domain names to the
+    /// buffer at offset.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(
+        mbuf: &'a mut Mbuf,
+        offset: usize,
+        _internal: Internal,
+    ) -> Result<DnsSearchList<'a>> {
+        mbuf.extend(offset, DnsSearchListFields::size_of())?;
+        let fields = mbuf.write_data(offset, &DnsSearchListFields::default())?;
+        Ok(DnsSearchList {
+            mbuf,
+            fields,
+            offset,
+        })
+    }
+}
+
+/// DNS search list option fields, excluding the domain names.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct DnsSearchListFields {
+    option_type: u8,
+    length: u8,
+    reserved: u16be,
+    lifetime: u32be,
+}
+
+impl Default for DnsSearchListFields {
+    fn default() -> DnsSearchListFields {
+        DnsSearchListFields {
+            option_type: NdpOptionTypes::DnsSearchList.0,
+            length: 1,
+            reserved: u16be::default(),
+            lifetime: u32be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::icmp::v6::ndp::{NdpPacket, RouterAdvertisement};
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::{Ethernet, Packet};
+
+    #[test]
+    fn size_of_dns_search_list_fields() {
+        assert_eq!(8, DnsSearchListFields::size_of());
+    }
+
+    #[capsule::test]
+    fn push_and_set_dns_search_list() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut advert = ipv6.push::<RouterAdvertisement<Ipv6>>().unwrap();
+        let mut options = advert.options_mut();
+        let mut dnssl = options.append::<DnsSearchList<'_>>().unwrap();
+
+        assert_eq!(NdpOptionTypes::DnsSearchList, dnssl.option_type());
+        assert_eq!(1, dnssl.length());
+        assert!(dnssl.domain_names().unwrap().is_empty());
+        assert!(dnssl.set_domain_names(&[]).is_err());
+
+        dnssl.set_lifetime(600);
+        assert_eq!(600, dnssl.lifetime());
+
+        // 13 + 10 octets padded to 24.
+        dnssl
+            .set_domain_names(&["example.com", "corp.lan."])
+            .unwrap();
+        assert_eq!(4, dnssl.length());
+        assert_eq!(
+            vec!["example.com".to_string(), "corp.lan".to_string()],
+            dnssl.domain_names().unwrap()
+        );
+
+        dnssl.set_domain_names(&["lan"]).unwrap();
+        assert_eq!(2, dnssl.length());
+        assert_eq!(vec!["lan".to_string()], dnssl.domain_names().unwrap());
+
+        let mut iter = advert.options_iter();
+        let mut option = iter.next().unwrap().unwrap();
+        assert_eq!(2, option.length());
+        assert!(option.downcast::<DnsSearchList<'_>>().is_ok());
+        assert!(iter.next().unwrap().is_none());
+    }
+}
@@ -19,12 +19,20 @@
 // https://github.com/rust-lang/rust/issues/57411
 #![allow(unreachable_pub)]
 
+mod dnssl;
 mod link_layer_addr;
 mod mtu;
+mod nonce;
 mod prefix_info;
+mod rdnss;
 mod redirected;
+mod route_info;
 
+pub use self::dnssl::*;
 pub use self::link_layer_addr::*;
 pub use self::mtu::*;
+pub use self::nonce::*;
 pub use self::prefix_info::*;
+pub use self::rdnss::*;
 pub use self::redirected::*;
+pub use self::route_info::*;
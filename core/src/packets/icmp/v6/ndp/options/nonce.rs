@@ -0,0 +1,245 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::icmp::v6::ndp::{NdpOption, NdpOptionType, NdpOptionTypes};
+use crate::packets::Internal;
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// The minimum length of the nonce.
+const MIN_NONCE_LEN: usize = 6;
+
+/// Nonce option defined in [IETF RFC 3971].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     Type      |    Length     |                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               |
+/// .                                                               .
+/// .                             Nonce                             .
+/// .                                                               .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Type*:           14
+///
+/// - *Length*:         The length of the option in units of 8 octets.
+///
+/// - *Nonce*:          A field containing a random number selected by the
+///                     sender of the solicitation message. The length of
+///                     the random number MUST be at least 6 octets.
+///
+/// The option is also used by [IETF RFC 7527] for enhanced duplicate
+/// address detection.
+///
+/// [IETF RFC 3971]: https://tools.ietf.org/html/rfc3971#section-5.3.2
+/// [IETF RFC 7527]: https://tools.ietf.org/html/rfc7527
+pub struct Nonce<'a> {
+    mbuf: &'a mut Mbuf,
+    fields: NonNull<NonceFields>,
+    offset: usize,
+}
+
+impl Nonce<'_> {
+    #[inline]
+    fn fields(&self) -> &NonceFields {
+        unsafe { self.fields.as_ref() }
+    }
+
+    #[inline]
+    fn fields_mut(&mut self) -> &mut NonceFields {
+        unsafe { self.fields.as_mut() }
+    }
+
+    /// Returns the nonce.
+    #[inline]
+    pub fn nonce(&self) -> &[u8] {
+        let offset = self.offset + NonceFields::size_of();
+        let len = self.length() as usize * 8 - NonceFields::size_of();
+
+        if let Ok(data) = self.mbuf.read_data_slice(offset, len) {
+            unsafe { &*data.as_ptr() }
+        } else {
+            &[]
+        }
+    }
+
+    /// Sets the nonce. The option is resized to fit the nonce.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the nonce is shorter than 6 octets, or the
+    /// option length including the nonce is not a multiple of 8 octets.
+    /// Returns an error if the buffer does not have enough free space.
+    pub fn set_nonce(&mut self, nonce: &[u8]) -> Result<()> {
+        let length = (NonceFields::size_of() + nonce.len()) / 8;
+        ensure!(
+            nonce.len() >= MIN_NONCE_LEN
+                && (NonceFields::size_of() + nonce.len()) % 8 == 0
+                && length <= 255,
+            anyhow!("invalid nonce length.")
+        );
+
+        let offset = self.offset + NonceFields::size_of();
+        let old_len = self.length() as usize * 8 - NonceFields::size_of();
+        if nonce.len() != old_len {
+            self.mbuf
+                .resize(offset, nonce.len() as isize - old_len as isize)?;
+        }
+        self.mbuf.write_data_slice(offset, nonce)?;
+
+        self.fields_mut().length = length as u8;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Nonce<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Nonce")
+            .field("type", &self.option_type())
+            .field("length", &self.length())
+            .field("nonce", &self.nonce())
+            .field("$offset", &self.offset)
+            .finish()
+    }
+}
+
+impl<'a> NdpOption<'a> for Nonce<'a> {
+    /// Returns the option type. Should always be `14`.
+    #[inline]
+    fn option_type(&self) -> NdpOptionType {
+        NdpOptionType(self.fields().option_type)
+    }
+
+    /// Returns the length of the option measured in units of 8 octets.
+    #[inline]
+    fn length(&self) -> u8 {
+        self.fields().length
+    }
+
+    /// Parses the buffer at offset as nonce option.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `option_type` is not set to `Nonce`. Returns
+    /// an error if the option length is 0.
+    #[inline]
+    fn try_parse(mbuf: &'a mut Mbuf, offset: usize, _internal: Internal) -> Result<Nonce<'a>> {
+        let fields = mbuf.read_data::<NonceFields>(offset)?;
+        let option = Nonce {
+            mbuf,
+            fields,
+            offset,
+        };
+
+        ensure!(
+            option.option_type() == NdpOptionTypes::Nonce,
+            anyhow!("not nonce.")
+        );
+
+        ensure!(
+            option.length() >= 1,
+            anyhow!("invalid nonce option length.")
+        );
+
+        Ok(option)
+    }
+
+    /// Pushes a new nonce option with a 6 octets zero nonce to the buffer
+    /// at offset.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mbuf: &'a mut Mbuf, offset: usize, _internal: Internal) -> Result<Nonce<'a>> {
+        mbuf.extend(offset, NonceFields::size_of() + MIN_NONCE_LEN)?;
+        let fields = mbuf.write_data(offset, &NonceFields::default())?;
+        mbuf.write_data_slice(offset + NonceFields::size_of(), &[0u8; MIN_NONCE_LEN])?;
+        Ok(Nonce {
+            mbuf,
+            fields,
+            offset,
+        })
+    }
+}
+
+/// Nonce option fields, excluding the nonce.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct NonceFields {
+    option_type: u8,
+    length: u8,
+}
+
+impl Default for NonceFields {
+    fn default() -> NonceFields {
+        NonceFields {
+            option_type: NdpOptionTypes::Nonce.0,
+            length: 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::icmp::v6::ndp::{NdpPacket, NeighborSolicitation};
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::{Ethernet, Packet};
+
+    #[test]
+    fn size_of_nonce_fields() {
+        assert_eq!(2, NonceFields::size_of());
+    }
+
+    #[capsule::test]
+    fn push_and_set_nonce() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut solicit = ipv6.push::<NeighborSolicitation<Ipv6>>().unwrap();
+        let mut options = solicit.options_mut();
+        let mut nonce = options.append::<Nonce<'_>>().unwrap();
+
+        assert_eq!(NdpOptionTypes::Nonce, nonce.option_type());
+        assert_eq!(1, nonce.length());
+        assert_eq!(&[0; 6], nonce.nonce());
+
+        assert!(nonce.set_nonce(&[1; 4]).is_err());
+        assert!(nonce.set_nonce(&[1; 7]).is_err());
+
+        nonce.set_nonce(&[1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(1, nonce.length());
+        assert_eq!(&[1, 2, 3, 4, 5, 6], nonce.nonce());
+
+        nonce.set_nonce(&[7; 14]).unwrap();
+        assert_eq!(2, nonce.length());
+        assert_eq!(&[7; 14], nonce.nonce());
+
+        let mut iter = solicit.options_iter();
+        let mut option = iter.next().unwrap().unwrap();
+        assert_eq!(2, option.length());
+        assert!(option.downcast::<Nonce<'_>>().is_ok());
+        assert!(iter.next().unwrap().is_none());
+    }
+}
@@ -0,0 +1,335 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::icmp::v6::ndp::{NdpOption, NdpOptionType, NdpOptionTypes};
+use crate::packets::types::{u16be, u32be};
+use crate::packets::Internal;
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::ptr::NonNull;
+
+/// The length of an IPv6 address.
+const ADDR_LEN: usize = 16;
+
+/// Recursive DNS Server option defined in [IETF RFC 8106].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     Type      |     Length    |           Reserved            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                           Lifetime                            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// :            Addresses of IPv6 Recursive DNS Servers            :
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Type*:           25
+///
+/// - *Length*:         The length of the option in units of 8 octets. The
+///                     minimum value is 3 if one IPv6 address is contained
+///                     in the option. Every additional address increases
+///                     the length by 2.
+///
+/// - *Reserved*:       This field is unused. It MUST be initialized to
+///                     zero by the sender and MUST be ignored by the
+///                     receiver.
+///
+/// - *Lifetime*:       32-bit unsigned integer. The maximum time in seconds
+///                     over which these RDNSS addresses MAY be used for
+///                     name resolution.
+///
+/// - *Addresses of IPv6 Recursive DNS Servers*:
+///                     One or more 128-bit IPv6 addresses of the recursive
+///                     DNS servers.
+///
+/// # Remarks
+///
+/// The option is pushed without any addresses. Use [`set_servers`] to add
+/// the addresses before the packet is sent.
+///
+/// ```
+/// let mut advert = ipv6.push::<RouterAdvertisement<Ipv6>>()?;
+/// let mut options = advert.options_mut();
+/// let mut rdnss = options.append::<RecursiveDnsServer<'_>>()?;
+/// rdnss.set_lifetime(600);
+/// rdnss.set_servers(&[dns1, dns2])?;
+/// ```
+///
+/// [IETF RFC 8106]: https://tools.ietf.org/html/rfc8106#section-5.1
+/// [`set_servers`]: RecursiveDnsServer::set_servers
+pub struct RecursiveDnsServer<'a> {
+    mbuf: &'a mut Mbuf,
+    fields: NonNull<RecursiveDnsServerFields>,
+    offset: usize,
+}
+
+impl RecursiveDnsServer<'_> {
+    #[inline]
+    fn fields(&self) -> &RecursiveDnsServerFields {
+        unsafe { self.fields.as_ref() }
+    }
+
+    #[inline]
+    fn fields_mut(&mut self) -> &mut RecursiveDnsServerFields {
+        unsafe { self.fields.as_mut() }
+    }
+
+    /// Returns the maximum time in seconds over which the servers may be
+    /// used for name resolution.
+    #[inline]
+    pub fn lifetime(&self) -> u32 {
+        self.fields().lifetime.into()
+    }
+
+    /// Sets the lifetime.
+    #[inline]
+    pub fn set_lifetime(&mut self, lifetime: u32) {
+        self.fields_mut().lifetime = lifetime.into();
+    }
+
+    /// Returns the addresses of the recursive DNS servers.
+    pub fn servers(&self) -> Vec<Ipv6Addr> {
+        let offset = self.offset + RecursiveDnsServerFields::size_of();
+        let count = (self.length() as usize * 8 - RecursiveDnsServerFields::size_of()) / ADDR_LEN;
+
+        (0..count)
+            .filter_map(|i| {
+                self.mbuf
+                    .read_data::<Ipv6Addr>(offset + i * ADDR_LEN)
+                    .ok()
+                    .map(|addr| unsafe { *addr.as_ptr() })
+            })
+            .collect()
+    }
+
+    /// Sets the addresses of the recursive DNS servers, replacing the
+    /// existing ones. The option is resized to fit the addresses.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `servers` is empty or has more than 127
+    /// addresses, or if the buffer does not have enough free space.
+    pub fn set_servers(&mut self, servers: &[Ipv6Addr]) -> Result<()> {
+        ensure!(
+            !servers.is_empty() && servers.len() <= 127,
+            anyhow!("invalid number of recursive DNS servers.")
+        );
+
+        let offset = self.offset + RecursiveDnsServerFields::size_of();
+        let old_len = self.length() as usize * 8 - RecursiveDnsServerFields::size_of();
+        let new_len = servers.len() * ADDR_LEN;
+        if new_len != old_len {
+            self.mbuf
+                .resize(offset, new_len as isize - old_len as isize)?;
+        }
+
+        for (i, server) in servers.iter().enumerate() {
+            self.mbuf
+                .write_data_slice(offset + i * ADDR_LEN, &server.octets())?;
+        }
+
+        self.fields_mut().length = (1 + servers.len() * 2) as u8;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for RecursiveDnsServer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecursiveDnsServer")
+            .field("type", &self.option_type())
+            .field("length", &self.length())
+            .field("lifetime", &self.lifetime())
+            .field("servers", &self.servers())
+            .field("$offset", &self.offset)
+            .finish()
+    }
+}
+
+impl<'a> NdpOption<'a> for RecursiveDnsServer<'a> {
+    /// Returns the option type. Should always be `25`.
+    #[inline]
+    fn option_type(&self) -> NdpOptionType {
+        NdpOptionType(self.fields().option_type)
+    }
+
+    /// Returns the length of the option measured in units of 8 octets.
+    #[inline]
+    fn length(&self) -> u8 {
+        self.fields().length
+    }
+
+    /// Parses the buffer at offset as recursive DNS server option.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `option_type` is not set to
+    /// `RecursiveDnsServer`. Returns an error if the option length is not
+    /// an odd number of at least 3.
+    #[inline]
+    fn try_parse(
+        mbuf: &'a mut Mbuf,
+        offset: usize,
+        _internal: Internal,
+    ) -> Result<RecursiveDnsServer<'a>> {
+        let fields = mbuf.read_data::<RecursiveDnsServerFields>(offset)?;
+        let option = RecursiveDnsServer {
+            mbuf,
+            fields,
+            offset,
+        };
+
+        ensure!(
+            option.option_type() == NdpOptionTypes::RecursiveDnsServer,
+            anyhow!("not recursive DNS server.")
+        );
+
+        ensure!(
+            option.length() >= 3 && option.length() % 2 == 1,
+            anyhow!("invalid recursive DNS server option length.")
+        );
+
+        Ok(option)
+    }
+
+    /// Pushes a new recursive DNS server option with no addresses to the
+    /// buffer at offset.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(
+        mbuf: &'a mut Mbuf,
+        offset: usize,
+        _internal: Internal,
+    ) -> Result<RecursiveDnsServer<'a>> {
+        mbuf.extend(offset, RecursiveDnsServerFields::size_of())?;
+        let fields = mbuf.write_data(offset, &RecursiveDnsServerFields::default())?;
+        Ok(RecursiveDnsServer {
+            mbuf,
+            fields,
+            offset,
+        })
+    }
+}
+
+/// Recursive DNS server option fields, excluding the addresses.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct RecursiveDnsServerFields {
+    option_type: u8,
+    length: u8,
+    reserved: u16be,
+    lifetime: u32be,
+}
+
+impl Default for RecursiveDnsServerFields {
+    fn default() -> RecursiveDnsServerFields {
+        RecursiveDnsServerFields {
+            option_type: NdpOptionTypes::RecursiveDnsServer.0,
+            length: 1,
+            reserved: u16be::default(),
+            lifetime: u32be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::icmp::v6::ndp::{NdpPacket, RouterAdvertisement};
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::{Ethernet, Packet};
+    use crate::testils::byte_arrays::ROUTER_ADVERT_PACKET;
+
+    #[test]
+    fn size_of_recursive_dns_server_fields() {
+        assert_eq!(8, RecursiveDnsServerFields::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_recursive_dns_server() {
+        let packet = Mbuf::from_bytes(&ROUTER_ADVERT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let mut advert = ipv6.parse::<RouterAdvertisement<Ipv6>>().unwrap();
+        let mut options = advert.options_mut();
+        let mut iter = options.iter();
+
+        let mut pass = false;
+        while let Some(mut option) = iter.next().unwrap() {
+            if let Ok(rdnss) = option.downcast::<RecursiveDnsServer<'_>>() {
+                assert_eq!(NdpOptionTypes::RecursiveDnsServer, rdnss.option_type());
+                assert_eq!(3, rdnss.length());
+                assert_eq!(u32::MAX, rdnss.lifetime());
+                assert_eq!(
+                    vec![Ipv6Addr::new(
+                        0x2607, 0xfcc8, 0xf142, 0xb0f0, 0xd4f0, 0x45ff, 0xfe0c, 0x664b
+                    )],
+                    rdnss.servers()
+                );
+
+                pass = true;
+                break;
+            }
+        }
+
+        assert!(pass);
+    }
+
+    #[capsule::test]
+    fn push_and_set_recursive_dns_server() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut advert = ipv6.push::<RouterAdvertisement<Ipv6>>().unwrap();
+        let mut options = advert.options_mut();
+        let mut rdnss = options.append::<RecursiveDnsServer<'_>>().unwrap();
+
+        assert_eq!(NdpOptionTypes::RecursiveDnsServer, rdnss.option_type());
+        assert_eq!(1, rdnss.length());
+        assert_eq!(0, rdnss.lifetime());
+        assert!(rdnss.servers().is_empty());
+        assert!(rdnss.set_servers(&[]).is_err());
+
+        rdnss.set_lifetime(600);
+        assert_eq!(600, rdnss.lifetime());
+
+        let servers = [Ipv6Addr::LOCALHOST, Ipv6Addr::UNSPECIFIED];
+        rdnss.set_servers(&servers).unwrap();
+        assert_eq!(5, rdnss.length());
+        assert_eq!(&servers[..], &rdnss.servers()[..]);
+
+        rdnss.set_servers(&servers[..1]).unwrap();
+        assert_eq!(3, rdnss.length());
+        assert_eq!(&servers[..1], &rdnss.servers()[..]);
+
+        // the appended option is picked up by the generic iterator.
+        let mut iter = advert.options_iter();
+        let mut option = iter.next().unwrap().unwrap();
+        assert_eq!(3, option.length());
+        assert!(option.downcast::<RecursiveDnsServer<'_>>().is_ok());
+        assert!(iter.next().unwrap().is_none());
+    }
+}
@@ -0,0 +1,381 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::icmp::v6::ndp::{NdpOption, NdpOptionType, NdpOptionTypes};
+use crate::packets::types::u32be;
+use crate::packets::Internal;
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::ptr::NonNull;
+
+/// Masks.
+const PRF: u8 = 0b0001_1000;
+
+/// The length of an IPv6 address.
+const ADDR_LEN: usize = 16;
+
+/// Route Information option defined in [IETF RFC 4191].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     Type      |    Length     | Prefix Length |Resvd|Prf|Resvd|
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                        Route Lifetime                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                   Prefix (Variable Length)                    |
+/// .                                                               .
+/// .                                                               .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Type*:           24
+///
+/// - *Length*:         The length of the option in units of 8 octets. The
+///                     value is 1, 2 or 3 depending on the prefix length.
+///
+/// - *Prefix Length*:  8-bit unsigned integer. The number of leading bits
+///                     in the Prefix that are valid. The value ranges
+///                     from 0 to 128.
+///
+/// - *Prf*:            2-bit signed integer. The route preference, where
+///                     `01` is high, `00` is medium and `11` is low.
+///
+/// - *Resvd*:          Two 3-bit unused fields. They MUST be initialized
+///                     to zero by the sender and MUST be ignored by the
+///                     receiver.
+///
+/// - *Route Lifetime*: 32-bit unsigned integer. The length of time in
+///                     seconds (relative to the time the packet is sent)
+///                     that the prefix is valid for route determination.
+///
+/// - *Prefix*:         Variable-length field containing an IP address or a
+///                     prefix of an IP address. The bits after the prefix
+///                     length are zero.
+///
+/// # Remarks
+///
+/// The option is pushed with the full 16 octets prefix. Use [`set_prefix`]
+/// to set the prefix and shorten the option to the fewest octets the prefix
+/// length needs.
+///
+/// [IETF RFC 4191]: https://tools.ietf.org/html/rfc4191#section-2.3
+/// [`set_prefix`]: RouteInformation::set_prefix
+pub struct RouteInformation<'a> {
+    mbuf: &'a mut Mbuf,
+    fields: NonNull<RouteInformationFields>,
+    offset: usize,
+}
+
+impl RouteInformation<'_> {
+    #[inline]
+    fn fields(&self) -> &RouteInformationFields {
+        unsafe { self.fields.as_ref() }
+    }
+
+    #[inline]
+    fn fields_mut(&mut self) -> &mut RouteInformationFields {
+        unsafe { self.fields.as_mut() }
+    }
+
+    /// Returns the number of leading bits in the prefix that are valid.
+    #[inline]
+    pub fn prefix_length(&self) -> u8 {
+        self.fields().prefix_length
+    }
+
+    /// Returns the route preference. `1` is high, `0` is medium and `-1`
+    /// is low. The reserved value `-2` should be treated as medium.
+    #[inline]
+    pub fn preference(&self) -> i8 {
+        match (self.fields().flags & PRF) >> 3 {
+            0b01 => 1,
+            0b11 => -1,
+            0b10 => -2,
+            _ => 0,
+        }
+    }
+
+    /// Sets the route preference.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the preference is not `1`, `0` or `-1`.
+    #[inline]
+    pub fn set_preference(&mut self, preference: i8) -> Result<()> {
+        let prf = match preference {
+            1 => 0b01,
+            0 => 0b00,
+            -1 => 0b11,
+            _ => return Err(anyhow!("invalid route preference {}.", preference)),
+        };
+        let flags = &mut self.fields_mut().flags;
+        *flags = (*flags & !PRF) | (prf << 3);
+        Ok(())
+    }
+
+    /// Returns the length of time in seconds that the prefix is valid for
+    /// route determination.
+    #[inline]
+    pub fn route_lifetime(&self) -> u32 {
+        self.fields().route_lifetime.into()
+    }
+
+    /// Sets the route lifetime.
+    #[inline]
+    pub fn set_route_lifetime(&mut self, route_lifetime: u32) {
+        self.fields_mut().route_lifetime = route_lifetime.into();
+    }
+
+    /// Returns the IPv6 prefix. The octets not carried in the option are
+    /// zero.
+    pub fn prefix(&self) -> Ipv6Addr {
+        let offset = self.offset + RouteInformationFields::size_of();
+        let len = (self.length() as usize * 8 - RouteInformationFields::size_of()).min(ADDR_LEN);
+
+        let mut octets = [0u8; ADDR_LEN];
+        if len > 0 {
+            if let Ok(data) = self.mbuf.read_data_slice::<u8>(offset, len) {
+                octets[..len].copy_from_slice(unsafe { data.as_ref() });
+            }
+        }
+
+        octets.into()
+    }
+
+    /// Sets the IPv6 prefix and the prefix length. The bits after the
+    /// prefix length are cleared, and the option is resized to the fewest
+    /// octets needed to carry the prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prefix length is greater than 128. Returns
+    /// an error if the buffer does not have enough free space.
+    pub fn set_prefix(&mut self, prefix: Ipv6Addr, prefix_length: u8) -> Result<()> {
+        ensure!(
+            prefix_length <= 128,
+            anyhow!("invalid prefix length {}.", prefix_length)
+        );
+
+        let mask = u128::MAX
+            .checked_shl(128 - prefix_length as u32)
+            .unwrap_or(0);
+        let octets = (u128::from(prefix) & mask).to_be_bytes();
+
+        let new_len = match prefix_length {
+            0 => 0,
+            1..=64 => 8,
+            _ => ADDR_LEN,
+        };
+
+        let offset = self.offset + RouteInformationFields::size_of();
+        let old_len = self.length() as usize * 8 - RouteInformationFields::size_of();
+        if new_len != old_len {
+            self.mbuf
+                .resize(offset, new_len as isize - old_len as isize)?;
+        }
+        if new_len > 0 {
+            self.mbuf.write_data_slice(offset, &octets[..new_len])?;
+        }
+
+        let fields = self.fields_mut();
+        fields.length = ((RouteInformationFields::size_of() + new_len) / 8) as u8;
+        fields.prefix_length = prefix_length;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for RouteInformation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RouteInformation")
+            .field("type", &self.option_type())
+            .field("length", &self.length())
+            .field("prefix_length", &self.prefix_length())
+            .field("preference", &self.preference())
+            .field("route_lifetime", &self.route_lifetime())
+            .field("prefix", &self.prefix())
+            .field("$offset", &self.offset)
+            .finish()
+    }
+}
+
+impl<'a> NdpOption<'a> for RouteInformation<'a> {
+    /// Returns the option type. Should always be `24`.
+    #[inline]
+    fn option_type(&self) -> NdpOptionType {
+        NdpOptionType(self.fields().option_type)
+    }
+
+    /// Returns the length of the option measured in units of 8 octets.
+    #[inline]
+    fn length(&self) -> u8 {
+        self.fields().length
+    }
+
+    /// Parses the buffer at offset as route information option.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `option_type` is not set to `RouteInformation`.
+    /// Returns an error if the option length is not between 1 and 3, or is
+    /// too short for the prefix length.
+    #[inline]
+    fn try_parse(
+        mbuf: &'a mut Mbuf,
+        offset: usize,
+        _internal: Internal,
+    ) -> Result<RouteInformation<'a>> {
+        let fields = mbuf.read_data::<RouteInformationFields>(offset)?;
+        let option = RouteInformation {
+            mbuf,
+            fields,
+            offset,
+        };
+
+        ensure!(
+            option.option_type() == NdpOptionTypes::RouteInformation,
+            anyhow!("not route information.")
+        );
+
+        let min_length = match option.prefix_length() {
+            0 => 1,
+            1..=64 => 2,
+            65..=128 => 3,
+            _ => u8::MAX,
+        };
+        ensure!(
+            option.length() >= min_length && option.length() <= 3,
+            anyhow!("invalid route information option length.")
+        );
+
+        Ok(option)
+    }
+
+    /// Pushes a new route information option to the buffer at offset.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(
+        mbuf: &'a mut Mbuf,
+        offset: usize,
+        _internal: Internal,
+    ) -> Result<RouteInformation<'a>> {
+        mbuf.extend(offset, RouteInformationFields::size_of() + ADDR_LEN)?;
+        let fields = mbuf.write_data(offset, &RouteInformationFields::default())?;
+        mbuf.write_data_slice(
+            offset + RouteInformationFields::size_of(),
+            &Ipv6Addr::UNSPECIFIED.octets(),
+        )?;
+        Ok(RouteInformation {
+            mbuf,
+            fields,
+            offset,
+        })
+    }
+}
+
+/// Route information option fields, excluding the prefix.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct RouteInformationFields {
+    option_type: u8,
+    length: u8,
+    prefix_length: u8,
+    flags: u8,
+    route_lifetime: u32be,
+}
+
+impl Default for RouteInformationFields {
+    fn default() -> RouteInformationFields {
+        RouteInformationFields {
+            option_type: NdpOptionTypes::RouteInformation.0,
+            length: 3,
+            prefix_length: 0,
+            flags: 0,
+            route_lifetime: u32be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::icmp::v6::ndp::{NdpPacket, RouterAdvertisement};
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::{Ethernet, Packet};
+
+    #[test]
+    fn size_of_route_information_fields() {
+        assert_eq!(8, RouteInformationFields::size_of());
+    }
+
+    #[capsule::test]
+    fn push_and_set_route_information() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut advert = ipv6.push::<RouterAdvertisement<Ipv6>>().unwrap();
+        let mut options = advert.options_mut();
+        let mut route = options.append::<RouteInformation<'_>>().unwrap();
+
+        assert_eq!(NdpOptionTypes::RouteInformation, route.option_type());
+        assert_eq!(3, route.length());
+        assert_eq!(0, route.prefix_length());
+        assert_eq!(0, route.preference());
+        assert_eq!(0, route.route_lifetime());
+        assert_eq!(Ipv6Addr::UNSPECIFIED, route.prefix());
+
+        route.set_preference(-1).unwrap();
+        assert_eq!(-1, route.preference());
+        route.set_preference(1).unwrap();
+        assert_eq!(1, route.preference());
+        assert!(route.set_preference(2).is_err());
+        route.set_route_lifetime(1800);
+        assert_eq!(1800, route.route_lifetime());
+
+        // the host bits are cleared and the option shrinks to 2.
+        let prefix = Ipv6Addr::new(0x2001, 0xdb8, 0xa, 0xb, 0, 0, 0, 1);
+        route.set_prefix(prefix, 48).unwrap();
+        assert_eq!(2, route.length());
+        assert_eq!(48, route.prefix_length());
+        assert_eq!(
+            Ipv6Addr::new(0x2001, 0xdb8, 0xa, 0, 0, 0, 0, 0),
+            route.prefix()
+        );
+
+        route.set_prefix(prefix, 128).unwrap();
+        assert_eq!(3, route.length());
+        assert_eq!(prefix, route.prefix());
+
+        route.set_prefix(prefix, 0).unwrap();
+        assert_eq!(1, route.length());
+        assert_eq!(Ipv6Addr::UNSPECIFIED, route.prefix());
+        assert!(route.set_prefix(prefix, 129).is_err());
+
+        let mut iter = advert.options_iter();
+        let mut option = iter.next().unwrap().unwrap();
+        assert_eq!(1, option.length());
+        assert!(option.downcast::<RouteInformation<'_>>().is_ok());
+        assert!(iter.next().unwrap().is_none());
+    }
+}
@@ -0,0 +1,1024 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::{IpPacket, ProtocolNumbers};
+use crate::packets::types::u16be;
+use crate::packets::{checksum, Internal, Packet};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::ptr::NonNull;
+use std::time::Duration;
+
+/// The length of the IGMPv3 query fields following the common header.
+const V3_QUERY_LEN: usize = 4;
+
+/// The length of the fixed fields of a group record.
+const GROUP_RECORD_FIXED_LEN: usize = 8;
+
+/// IGMP message type.
+///
+/// See [`IgmpTypes`] for which are current supported.
+///
+/// [`IgmpTypes`]: crate::packets::IgmpTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct IgmpType(pub u8);
+
+impl IgmpType {
+    /// Creates a new IGMP message type.
+    pub fn new(value: u8) -> Self {
+        IgmpType(value)
+    }
+}
+
+/// Supported IGMP message types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod IgmpTypes {
+    use super::IgmpType;
+
+    /// Membership query, for all versions of IGMP.
+    pub const MembershipQuery: IgmpType = IgmpType(0x11);
+
+    /// Version 1 membership report.
+    pub const V1MembershipReport: IgmpType = IgmpType(0x12);
+
+    /// Version 2 membership report.
+    pub const V2MembershipReport: IgmpType = IgmpType(0x16);
+
+    /// Version 2 leave group.
+    pub const LeaveGroup: IgmpType = IgmpType(0x17);
+
+    /// Version 3 membership report.
+    pub const V3MembershipReport: IgmpType = IgmpType(0x22);
+}
+
+impl fmt::Display for IgmpType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                IgmpTypes::MembershipQuery => "Membership Query".to_string(),
+                IgmpTypes::V1MembershipReport => "Version 1 Membership Report".to_string(),
+                IgmpTypes::V2MembershipReport => "Version 2 Membership Report".to_string(),
+                IgmpTypes::LeaveGroup => "Leave Group".to_string(),
+                IgmpTypes::V3MembershipReport => "Version 3 Membership Report".to_string(),
+                _ => format!("0x{:02x}", self.0),
+            }
+        )
+    }
+}
+
+/// The type of a group record in an IGMPv3 or an MLDv2 report.
+///
+/// See [`GroupRecordTypes`] for which are current supported.
+///
+/// [`GroupRecordTypes`]: crate::packets::GroupRecordTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct GroupRecordType(pub u8);
+
+impl GroupRecordType {
+    /// Creates a new group record type.
+    pub fn new(value: u8) -> Self {
+        GroupRecordType(value)
+    }
+}
+
+/// Supported group record types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod GroupRecordTypes {
+    use super::GroupRecordType;
+
+    /// The interface has a filter mode of include for the group.
+    pub const ModeIsInclude: GroupRecordType = GroupRecordType(1);
+
+    /// The interface has a filter mode of exclude for the group.
+    pub const ModeIsExclude: GroupRecordType = GroupRecordType(2);
+
+    /// The filter mode of the interface changed to include.
+    pub const ChangeToIncludeMode: GroupRecordType = GroupRecordType(3);
+
+    /// The filter mode of the interface changed to exclude.
+    pub const ChangeToExcludeMode: GroupRecordType = GroupRecordType(4);
+
+    /// The sources are added to the interface's source list.
+    pub const AllowNewSources: GroupRecordType = GroupRecordType(5);
+
+    /// The sources are removed from the interface's source list.
+    pub const BlockOldSources: GroupRecordType = GroupRecordType(6);
+}
+
+impl fmt::Display for GroupRecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                GroupRecordTypes::ModeIsInclude => "MODE_IS_INCLUDE".to_string(),
+                GroupRecordTypes::ModeIsExclude => "MODE_IS_EXCLUDE".to_string(),
+                GroupRecordTypes::ChangeToIncludeMode => "CHANGE_TO_INCLUDE_MODE".to_string(),
+                GroupRecordTypes::ChangeToExcludeMode => "CHANGE_TO_EXCLUDE_MODE".to_string(),
+                GroupRecordTypes::AllowNewSources => "ALLOW_NEW_SOURCES".to_string(),
+                GroupRecordTypes::BlockOldSources => "BLOCK_OLD_SOURCES".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// Internet Group Management Protocol packet based on [IETF RFC 2236]
+/// and [IETF RFC 3376].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |      Type     | Max Resp Code |           Checksum            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         Group Address                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | Resv  |S| QRV |     QQIC      |     Number of Sources (N)     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                       Source Address [1]                      |
+/// +-                              .                              -+
+/// |                       Source Address [N]                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Type*:               Indicates the type of the message.
+///
+/// - *Max Resp Code*:      The maximum time allowed before sending a
+///                         report in response to a query, in 1/10 second
+///                         units. Values of 128 and above are a floating
+///                         point encoding in IGMPv3.
+///
+/// - *Checksum*:           The 16-bit one's complement of the one's
+///                         complement sum of the whole IGMP message.
+///
+/// - *Group Address*:      The multicast group being queried, reported or
+///                         left. Zero in a general query.
+///
+/// The *S*, *QRV*, *QQIC* and source fields are only present in an IGMPv3
+/// query. A version is told from the other by the length of the IP
+/// payload.
+///
+/// An IGMPv3 report replaces the group address with a count of group
+/// records, which follow the common header.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Type = 0x22  |    Reserved   |           Checksum            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           Reserved            |  Number of Group Records (M)  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// .                        Group Record [1..M]                    .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// [IETF RFC 2236]: https://tools.ietf.org/html/rfc2236#section-2
+/// [IETF RFC 3376]: https://tools.ietf.org/html/rfc3376#section-4
+pub struct Igmp {
+    envelope: Ipv4,
+    header: NonNull<IgmpHeader>,
+    offset: usize,
+}
+
+impl Igmp {
+    #[inline]
+    fn header(&self) -> &IgmpHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut IgmpHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the length of the IGMP message.
+    ///
+    /// The length is derived from the IPv4 total length instead of the
+    /// buffer, because a short message is padded to the minimum Ethernet
+    /// frame size on the wire.
+    #[inline]
+    fn msg_len(&self) -> usize {
+        let ipv4 = self.envelope();
+        let payload_len = (ipv4.total_length() as usize).saturating_sub(ipv4.header_len());
+        payload_len.min(self.len())
+    }
+
+    /// Extends the message at offset by `len` bytes, and keeps the IPv4
+    /// total length in sync with the message length.
+    #[inline]
+    fn extend(&mut self, offset: usize, len: usize) -> Result<()> {
+        self.mbuf_mut().extend(offset, len)?;
+        let total_length = self.envelope().total_length() as usize + len;
+        self.envelope_mut().set_total_length(total_length as u16);
+        Ok(())
+    }
+
+    #[inline]
+    fn read_bytes(&self, offset: usize, len: usize) -> Result<&[u8]> {
+        let bytes = self.mbuf().read_data_slice::<u8>(offset, len)?;
+        Ok(unsafe { &*bytes.as_ptr() })
+    }
+
+    /// Returns the message type.
+    #[inline]
+    pub fn msg_type(&self) -> IgmpType {
+        IgmpType(self.header().msg_type)
+    }
+
+    /// Sets the message type.
+    #[inline]
+    pub fn set_msg_type(&mut self, msg_type: IgmpType) {
+        self.header_mut().msg_type = msg_type.0;
+    }
+
+    /// Returns the max response code.
+    #[inline]
+    pub fn max_resp_code(&self) -> u8 {
+        self.header().max_resp_code
+    }
+
+    /// Sets the max response code.
+    #[inline]
+    pub fn set_max_resp_code(&mut self, code: u8) {
+        self.header_mut().max_resp_code = code;
+    }
+
+    /// Returns the maximum response time decoded from the max response
+    /// code.
+    ///
+    /// Codes below 128 are in 1/10 second units for all versions. Codes of
+    /// 128 and above use the IGMPv3 floating point encoding.
+    pub fn max_resp_time(&self) -> Duration {
+        let code = self.max_resp_code() as u64;
+        let tenths = if code < 128 {
+            code
+        } else {
+            let exp = (code >> 4) & 0x07;
+            let mant = code & 0x0f;
+            (mant | 0x10) << (exp + 3)
+        };
+        Duration::from_millis(tenths * 100)
+    }
+
+    /// Returns the checksum.
+    #[inline]
+    pub fn checksum(&self) -> u16 {
+        self.header().checksum.into()
+    }
+
+    /// Computes the checksum over the whole message.
+    #[inline]
+    pub fn compute_checksum(&mut self) {
+        self.header_mut().checksum = u16be::default();
+
+        let data = self.mbuf().data_segments(self.offset(), self.msg_len());
+        let checksum = checksum::compute_segments(0, data);
+        self.header_mut().checksum = checksum.into();
+    }
+
+    /// Returns the group address.
+    ///
+    /// The value is not meaningful for an IGMPv3 report.
+    #[inline]
+    pub fn group_addr(&self) -> Ipv4Addr {
+        Ipv4Addr::from(self.header().data)
+    }
+
+    /// Sets the group address.
+    #[inline]
+    pub fn set_group_addr(&mut self, group_addr: Ipv4Addr) {
+        self.header_mut().data = group_addr.octets();
+    }
+
+    /// Returns whether the message is an IGMPv3 query.
+    ///
+    /// The version is told by the length of the IP payload, as defined in
+    /// [IETF RFC 3376 section 7.1].
+    ///
+    /// [IETF RFC 3376 section 7.1]: https://tools.ietf.org/html/rfc3376#section-7.1
+    #[inline]
+    pub fn is_v3_query(&self) -> bool {
+        self.msg_type() == IgmpTypes::MembershipQuery
+            && self.msg_len() >= IgmpHeader::size_of() + V3_QUERY_LEN
+    }
+
+    /// Returns the offset of the IGMPv3 query fields.
+    #[inline]
+    fn v3_query_offset(&self) -> usize {
+        self.offset + IgmpHeader::size_of()
+    }
+
+    /// Reads the IGMPv3 query fields, or `None` if the message is not an
+    /// IGMPv3 query.
+    #[inline]
+    fn v3_query_fields(&self) -> Option<&[u8]> {
+        if self.is_v3_query() {
+            self.read_bytes(self.v3_query_offset(), V3_QUERY_LEN).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Writes the octet of an IGMPv3 query field.
+    fn set_v3_query_field(&mut self, index: usize, mask: u8, value: u8) -> Result<()> {
+        let fields = self
+            .v3_query_fields()
+            .ok_or_else(|| anyhow!("not an IGMPv3 query."))?;
+        let octet = (fields[index] & !mask) | (value & mask);
+        let offset = self.v3_query_offset() + index;
+        self.mbuf_mut().write_data_slice(offset, &[octet])?;
+        Ok(())
+    }
+
+    /// Extends a query with the IGMPv3 fields, with no sources.
+    ///
+    /// Does nothing if the message is already an IGMPv3 query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not a membership query, or the
+    /// buffer does not have enough free space.
+    pub fn make_v3_query(&mut self) -> Result<()> {
+        ensure!(
+            self.msg_type() == IgmpTypes::MembershipQuery,
+            anyhow!("not an IGMP membership query.")
+        );
+
+        if !self.is_v3_query() {
+            let offset = self.v3_query_offset();
+            self.extend(offset, V3_QUERY_LEN)?;
+            self.mbuf_mut()
+                .write_data_slice(offset, &[0u8; V3_QUERY_LEN])?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the suppress router-side processing flag of an IGMPv3 query.
+    #[inline]
+    pub fn suppress_router_processing(&self) -> bool {
+        matches!(self.v3_query_fields(), Some(fields) if fields[0] & 0x08 != 0)
+    }
+
+    /// Sets the suppress router-side processing flag of an IGMPv3 query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not an IGMPv3 query.
+    #[inline]
+    pub fn set_suppress_router_processing(&mut self, suppress: bool) -> Result<()> {
+        self.set_v3_query_field(0, 0x08, if suppress { 0x08 } else { 0 })
+    }
+
+    /// Returns the querier's robustness variable of an IGMPv3 query, or 0
+    /// if the message is not an IGMPv3 query.
+    #[inline]
+    pub fn qrv(&self) -> u8 {
+        self.v3_query_fields().map_or(0, |fields| fields[0] & 0x07)
+    }
+
+    /// Sets the querier's robustness variable of an IGMPv3 query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not an IGMPv3 query.
+    #[inline]
+    pub fn set_qrv(&mut self, qrv: u8) -> Result<()> {
+        self.set_v3_query_field(0, 0x07, qrv)
+    }
+
+    /// Returns the querier's query interval code of an IGMPv3 query, or 0
+    /// if the message is not an IGMPv3 query.
+    #[inline]
+    pub fn qqic(&self) -> u8 {
+        self.v3_query_fields().map_or(0, |fields| fields[1])
+    }
+
+    /// Sets the querier's query interval code of an IGMPv3 query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not an IGMPv3 query.
+    #[inline]
+    pub fn set_qqic(&mut self, qqic: u8) -> Result<()> {
+        self.set_v3_query_field(1, 0xff, qqic)
+    }
+
+    /// Returns the source addresses of an IGMPv3 group-and-source-specific
+    /// query. Other messages have no sources.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is truncated.
+    pub fn query_sources(&self) -> Result<Vec<Ipv4Addr>> {
+        match self.v3_query_fields() {
+            Some(fields) => {
+                let count = u16::from_be_bytes([fields[2], fields[3]]) as usize;
+                let offset = self.v3_query_offset() + V3_QUERY_LEN;
+                ensure!(
+                    offset + count * 4 <= self.offset + self.msg_len(),
+                    anyhow!("IGMP query sources exceed the message.")
+                );
+                Ok(read_addrs(self.mbuf(), offset, count)?)
+            }
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Appends a source address to an IGMPv3 query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not an IGMPv3 query, or the
+    /// buffer does not have enough free space.
+    pub fn append_query_source(&mut self, source: Ipv4Addr) -> Result<()> {
+        let fields = self
+            .v3_query_fields()
+            .ok_or_else(|| anyhow!("not an IGMPv3 query."))?;
+        let count = u16::from_be_bytes([fields[2], fields[3]]);
+        ensure!(count < u16::MAX, anyhow!("too many IGMP query sources."));
+
+        let offset = self.v3_query_offset() + V3_QUERY_LEN + count as usize * 4;
+        self.extend(offset, 4)?;
+        self.mbuf_mut().write_data_slice(offset, &source.octets())?;
+        let count_offset = self.v3_query_offset() + 2;
+        self.mbuf_mut()
+            .write_data_slice(count_offset, &(count + 1).to_be_bytes())?;
+
+        Ok(())
+    }
+
+    /// Returns the number of group records in an IGMPv3 report.
+    #[inline]
+    pub fn group_record_count(&self) -> u16 {
+        if self.msg_type() == IgmpTypes::V3MembershipReport {
+            let data = self.header().data;
+            u16::from_be_bytes([data[2], data[3]])
+        } else {
+            0
+        }
+    }
+
+    /// Returns an iterator that iterates through the group records of an
+    /// IGMPv3 report. Other messages have no group records.
+    #[inline]
+    pub fn group_records_iter(&self) -> IgmpGroupRecordsIterator<'_> {
+        IgmpGroupRecordsIterator {
+            mbuf: self.mbuf(),
+            offset: self.offset + IgmpHeader::size_of(),
+            end: self.offset + self.msg_len(),
+            remaining: self.group_record_count(),
+        }
+    }
+
+    /// Appends a group record to an IGMPv3 report.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut report = ipv4.push::<Igmp>()?;
+    /// report.set_msg_type(IgmpTypes::V3MembershipReport);
+    /// report.set_group_addr(Ipv4Addr::UNSPECIFIED);
+    /// report.append_group_record(&IgmpGroupRecord {
+    ///     record_type: GroupRecordTypes::ChangeToExcludeMode,
+    ///     multicast_addr: "239.1.1.1".parse()?,
+    ///     sources: vec![],
+    ///     aux_data: vec![],
+    /// })?;
+    /// report.reconcile_all();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not an IGMPv3 report, or the
+    /// record is invalid. Returns an error if the buffer does not have
+    /// enough free space.
+    pub fn append_group_record(&mut self, record: &IgmpGroupRecord) -> Result<()> {
+        ensure!(
+            self.msg_type() == IgmpTypes::V3MembershipReport,
+            anyhow!("not an IGMPv3 membership report.")
+        );
+        let count = self.group_record_count();
+        ensure!(count < u16::MAX, anyhow!("too many IGMP group records."));
+
+        // skips the existing records to find the end.
+        let mut iter = self.group_records_iter();
+        while iter.next()?.is_some() {}
+        let offset = iter.offset;
+
+        let bytes = record.encode()?;
+        self.extend(offset, bytes.len())?;
+        self.mbuf_mut().write_data_slice(offset, &bytes)?;
+
+        let count = (count + 1).to_be_bytes();
+        let data = &mut self.header_mut().data;
+        data[2] = count[0];
+        data[3] = count[1];
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Igmp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("igmp")
+            .field("type", &format!("{}", self.msg_type()))
+            .field("max_resp_code", &self.max_resp_code())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("group_addr", &self.group_addr())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Igmp {
+    /// The preceding type for an IGMP packet must be IPv4.
+    type Envelope = Ipv4;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        IgmpHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Igmp {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the IPv4 packet's payload as an IGMP packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the [`protocol`] is not set to
+    /// [`ProtocolNumbers::Igmp`]. Returns an error if the payload does not
+    /// have sufficient data for the header, the sources of an IGMPv3 query,
+    /// or the group records of an IGMPv3 report.
+    ///
+    /// [`protocol`]: crate::packets::ip::v4::Ipv4::protocol
+    /// [`ProtocolNumbers::Igmp`]: crate::packets::ip::ProtocolNumbers::Igmp
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.next_protocol() == ProtocolNumbers::Igmp,
+            anyhow!("not an IGMP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Igmp {
+            envelope,
+            header,
+            offset,
+        };
+
+        ensure!(
+            packet.msg_len() >= IgmpHeader::size_of(),
+            anyhow!("IGMP message is shorter than the header.")
+        );
+
+        packet.query_sources()?;
+        let mut iter = packet.group_records_iter();
+        while iter.next()?.is_some() {}
+
+        Ok(packet)
+    }
+
+    /// Prepends an IGMP general query to the beginning of the IPv4's
+    /// payload.
+    ///
+    /// The message has no IGMPv3 query fields. The type can be changed to
+    /// build a report or a leave message instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, IgmpHeader::size_of())?;
+        let header = mbuf.write_data(offset, &IgmpHeader::default())?;
+
+        envelope.set_next_protocol(ProtocolNumbers::Igmp);
+        let total_length = envelope.len() as u16;
+        envelope.set_total_length(total_length);
+
+        Ok(Igmp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`checksum`] is computed based on the full packet.
+    ///
+    /// [`checksum`]: Igmp::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        self.compute_checksum();
+    }
+}
+
+/// Reads `count` IPv4 addresses at `offset`.
+fn read_addrs(mbuf: &Mbuf, offset: usize, count: usize) -> Result<Vec<Ipv4Addr>> {
+    if count == 0 {
+        return Ok(vec![]);
+    }
+
+    let bytes = mbuf.read_data_slice::<u8>(offset, count * 4)?;
+    let bytes = unsafe { bytes.as_ref() };
+    Ok(bytes
+        .chunks(4)
+        .map(|octets| Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]))
+        .collect())
+}
+
+/// A group record in an IGMPv3 report.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Record Type  |  Aux Data Len |     Number of Sources (N)     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                       Multicast Address                       |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                       Source Address [1..N]                   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// .                         Auxiliary Data                        .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// Group records are read out of and written into the buffer by value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IgmpGroupRecord {
+    /// The type of the record.
+    pub record_type: GroupRecordType,
+    /// The multicast address to which the record pertains.
+    pub multicast_addr: Ipv4Addr,
+    /// The source addresses.
+    pub sources: Vec<Ipv4Addr>,
+    /// The auxiliary data, in multiples of 4 octets.
+    pub aux_data: Vec<u8>,
+}
+
+impl IgmpGroupRecord {
+    /// Returns the length of the record in octets.
+    #[inline]
+    pub fn length(&self) -> usize {
+        GROUP_RECORD_FIXED_LEN + self.sources.len() * 4 + self.aux_data.len()
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        ensure!(
+            self.aux_data.len() % 4 == 0 && self.aux_data.len() <= 255 * 4,
+            anyhow!("invalid auxiliary data length {}.", self.aux_data.len())
+        );
+        ensure!(
+            self.sources.len() <= u16::MAX as usize,
+            anyhow!("too many group record sources.")
+        );
+
+        let mut bytes = Vec::with_capacity(self.length());
+        bytes.push(self.record_type.0);
+        bytes.push((self.aux_data.len() / 4) as u8);
+        bytes.extend_from_slice(&(self.sources.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&self.multicast_addr.octets());
+        for source in &self.sources {
+            bytes.extend_from_slice(&source.octets());
+        }
+        bytes.extend_from_slice(&self.aux_data);
+        Ok(bytes)
+    }
+}
+
+/// An iterator that iterates through the group records of an IGMPv3
+/// report.
+pub struct IgmpGroupRecordsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+    remaining: u16,
+}
+
+impl IgmpGroupRecordsIterator<'_> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<IgmpGroupRecord>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        let fixed = self
+            .mbuf
+            .read_data_slice::<u8>(self.offset, GROUP_RECORD_FIXED_LEN)?;
+        let fixed = unsafe { fixed.as_ref() };
+        let aux_len = fixed[1] as usize * 4;
+        let count = u16::from_be_bytes([fixed[2], fixed[3]]) as usize;
+
+        let sources_offset = self.offset + GROUP_RECORD_FIXED_LEN;
+        let sources = read_addrs(self.mbuf, sources_offset, count)?;
+
+        let aux_offset = sources_offset + count * 4;
+        ensure!(
+            aux_offset + aux_len <= self.end,
+            anyhow!("IGMP group record exceeds the message.")
+        );
+        let aux_data = if aux_len > 0 {
+            let aux = self.mbuf.read_data_slice::<u8>(aux_offset, aux_len)?;
+            unsafe { aux.as_ref() }.to_vec()
+        } else {
+            vec![]
+        };
+
+        self.offset = aux_offset + aux_len;
+        self.remaining -= 1;
+
+        Ok(Some(IgmpGroupRecord {
+            record_type: GroupRecordType(fixed[0]),
+            multicast_addr: Ipv4Addr::new(fixed[4], fixed[5], fixed[6], fixed[7]),
+            sources,
+            aux_data,
+        }))
+    }
+}
+
+impl fmt::Debug for IgmpGroupRecordsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IgmpGroupRecordsIterator")
+            .field("offset", &self.offset)
+            .field("remaining", &self.remaining)
+            .finish()
+    }
+}
+
+/// IGMP header common to all messages.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct IgmpHeader {
+    msg_type: u8,
+    max_resp_code: u8,
+    checksum: u16be,
+    /// The group address, or the number of group records in a v3 report.
+    data: [u8; 4],
+}
+
+impl Default for IgmpHeader {
+    fn default() -> Self {
+        IgmpHeader {
+            msg_type: IgmpTypes::MembershipQuery.0,
+            max_resp_code: 0,
+            checksum: u16be::default(),
+            data: [0; 4],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::Ethernet;
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_igmp_header() {
+        assert_eq!(8, IgmpHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_igmpv2_report() {
+        let packet = Mbuf::from_bytes(&IGMPV2_REPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let igmp = ipv4.parse::<Igmp>().unwrap();
+
+        assert_eq!(IgmpTypes::V2MembershipReport, igmp.msg_type());
+        assert_eq!("239.1.1.1", igmp.group_addr().to_string());
+        assert_eq!(0xf9fc, igmp.checksum());
+        assert!(!igmp.is_v3_query());
+        assert_eq!(0, igmp.group_record_count());
+        assert!(igmp.query_sources().unwrap().is_empty());
+    }
+
+    #[capsule::test]
+    fn parse_padded_igmpv2_query() {
+        let packet = Mbuf::from_bytes(&IGMPV2_QUERY_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut igmp = ipv4.parse::<Igmp>().unwrap();
+
+        // the buffer still holds the Ethernet trailer, but the message
+        // ends at the IPv4 total length.
+        assert_eq!(22, igmp.len());
+        assert_eq!(8, igmp.msg_len());
+        assert_eq!(IgmpTypes::MembershipQuery, igmp.msg_type());
+        assert!(!igmp.is_v3_query());
+        assert_eq!(0, igmp.qrv());
+        assert_eq!(0, igmp.qqic());
+        assert!(igmp.query_sources().unwrap().is_empty());
+
+        igmp.reconcile();
+        assert_eq!(0xee9b, igmp.checksum());
+    }
+
+    #[capsule::test]
+    fn change_igmpv2_report_to_leave() {
+        let packet = Mbuf::from_bytes(&IGMPV2_REPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut igmp = ipv4.parse::<Igmp>().unwrap();
+
+        igmp.set_msg_type(IgmpTypes::LeaveGroup);
+        igmp.reconcile();
+        assert_eq!(0xf8fc, igmp.checksum());
+    }
+
+    #[capsule::test]
+    fn push_igmpv3_query() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut igmp = ipv4.push::<Igmp>().unwrap();
+
+        assert_eq!(ProtocolNumbers::Igmp, igmp.envelope().protocol());
+        assert_eq!(IgmpTypes::MembershipQuery, igmp.msg_type());
+        assert!(!igmp.is_v3_query());
+        assert!(igmp.set_qrv(2).is_err());
+
+        igmp.set_max_resp_code(0x8f);
+        assert_eq!(Duration::from_millis(24800), igmp.max_resp_time());
+
+        igmp.make_v3_query().unwrap();
+        assert!(igmp.is_v3_query());
+        igmp.set_group_addr(Ipv4Addr::new(239, 1, 1, 1));
+        igmp.set_suppress_router_processing(true).unwrap();
+        igmp.set_qrv(2).unwrap();
+        igmp.set_qqic(125).unwrap();
+        igmp.append_query_source(Ipv4Addr::new(10, 0, 0, 1))
+            .unwrap();
+        igmp.append_query_source(Ipv4Addr::new(10, 0, 0, 2))
+            .unwrap();
+
+        assert!(igmp.suppress_router_processing());
+        assert_eq!(2, igmp.qrv());
+        assert_eq!(125, igmp.qqic());
+        assert_eq!(
+            vec![Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)],
+            igmp.query_sources().unwrap()
+        );
+        assert_eq!(8 + 4 + 8, igmp.len());
+        assert_eq!(20 + 8 + 4 + 8, igmp.envelope().total_length());
+    }
+
+    #[capsule::test]
+    fn push_and_parse_igmpv3_report() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut igmp = ipv4.push::<Igmp>().unwrap();
+
+        let exclude = IgmpGroupRecord {
+            record_type: GroupRecordTypes::ChangeToExcludeMode,
+            multicast_addr: Ipv4Addr::new(239, 1, 1, 1),
+            sources: vec![],
+            aux_data: vec![],
+        };
+        let allow = IgmpGroupRecord {
+            record_type: GroupRecordTypes::AllowNewSources,
+            multicast_addr: Ipv4Addr::new(232, 1, 1, 1),
+            sources: vec![Ipv4Addr::new(10, 0, 0, 1)],
+            aux_data: vec![1, 2, 3, 4],
+        };
+
+        assert!(igmp.append_group_record(&exclude).is_err());
+        igmp.set_msg_type(IgmpTypes::V3MembershipReport);
+        igmp.append_group_record(&exclude).unwrap();
+        igmp.append_group_record(&allow).unwrap();
+        assert_eq!(2, igmp.group_record_count());
+        assert_eq!(8 + exclude.length() + allow.length(), igmp.len());
+        igmp.reconcile_all();
+
+        // parses again from the beginning.
+        let ipv4 = igmp.deparse();
+        let igmp = ipv4.parse::<Igmp>().unwrap();
+        let mut iter = igmp.group_records_iter();
+        assert_eq!(exclude, iter.next().unwrap().unwrap());
+        assert_eq!(allow, iter.next().unwrap().unwrap());
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    /// IGMPv2 membership report for 239.1.1.1.
+    #[rustfmt::skip]
+    const IGMPV2_REPORT_PACKET: [u8; 42] = [
+    // Ethernet header
+        0x01, 0x00, 0x5e, 0x01, 0x01, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x08, 0x00,
+    // IPv4 header
+        0x45, 0x00,
+        // total length = 28
+        0x00, 0x1c,
+        0x00, 0x00, 0x00, 0x00,
+        // ttl = 1, protocol = IGMP
+        0x01, 0x02, 0x00, 0x00,
+        // src = 10.0.0.1
+        0x0a, 0x00, 0x00, 0x01,
+        // dst = 239.1.1.1
+        0xef, 0x01, 0x01, 0x01,
+    // IGMP message
+        // type = v2 report, max resp time = 0, checksum = 0xf9fc
+        0x16, 0x00, 0xf9, 0xfc,
+        // group address = 239.1.1.1
+        0xef, 0x01, 0x01, 0x01,
+    ];
+
+    /// IGMPv2 general query, padded to the minimum Ethernet frame size.
+    #[rustfmt::skip]
+    const IGMPV2_QUERY_PACKET: [u8; 60] = [
+    // Ethernet header
+        0x01, 0x00, 0x5e, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x08, 0x00,
+    // IPv4 header
+        0x46, 0xc0,
+        // total length = 32
+        0x00, 0x20,
+        0x00, 0x00, 0x00, 0x00,
+        // ttl = 1, protocol = IGMP, checksum = 0x826d
+        0x01, 0x02, 0x82, 0x6d,
+        // src = 192.168.1.1
+        0xc0, 0xa8, 0x01, 0x01,
+        // dst = 224.0.0.1
+        0xe0, 0x00, 0x00, 0x01,
+        // router alert option
+        0x94, 0x04, 0x00, 0x00,
+    // IGMP message
+        // type = query, max resp time = 10s, checksum = 0xee9b
+        0x11, 0x64, 0xee, 0x9b,
+        // group address = 0.0.0.0
+        0x00, 0x00, 0x00, 0x00,
+    // Ethernet trailer
+        0x5a, 0x5a, 0x5a, 0x5a, 0x5a, 0x5a, 0x5a,
+        0x5a, 0x5a, 0x5a, 0x5a, 0x5a, 0x5a, 0x5a,
+    ];
+}
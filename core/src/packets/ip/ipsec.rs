@@ -0,0 +1,759 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::checksum::PseudoHeader;
+use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::ip::{tunnel, IpPacket, ProtocolNumber, ProtocolNumbers};
+use crate::packets::types::u32be;
+use crate::packets::{Datalink, EtherType, Internal, Packet};
+use crate::{ensure, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::IpAddr;
+use std::ptr::NonNull;
+
+/// The maximum length of an authentication header in octets.
+///
+/// The Payload Len field is 8 bits and measured in 4-octet units, minus 2.
+pub const AH_MAX_HEADER_LEN: usize = (255 + 2) * 4;
+
+/// IP Encapsulating Security Payload packet based on [IETF RFC 4303].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |               Security Parameters Index (SPI)                 |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                      Sequence Number                          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                    Payload Data* (variable)                   |
+/// ~                                                               ~
+/// ```
+///
+/// - *SPI*:                32 bits. An arbitrary value used by the receiver
+///                         to identify the security association.
+///
+/// - *Sequence Number*:    32 bits. A monotonically increasing counter
+///                         value used for anti-replay protection.
+///
+/// The rest of the packet, including the padding, the next header and the
+/// integrity check value, is opaque because it is encrypted. Decryption is
+/// not supported. The SPI can be used to classify the packets.
+///
+/// ```
+/// let mut batch = batch.group_by(
+///     |packet| packet.spi(),
+///     |groups| {
+///         compose!( groups {
+///             0x1000 => |group| {
+///                 group.map(to_tunnel_a)
+///             }
+///             _ => |group| {
+///                 group.map(to_tunnel_b)
+///             }
+///         })
+///     },
+/// );
+/// ```
+///
+/// [IETF RFC 4303]: https://tools.ietf.org/html/rfc4303#section-2
+pub struct Esp<E: IpPacket> {
+    envelope: E,
+    header: NonNull<EspHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Esp<E> {
+    #[inline]
+    fn header(&self) -> &EspHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut EspHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the security parameters index.
+    #[inline]
+    pub fn spi(&self) -> u32 {
+        self.header().spi.into()
+    }
+
+    /// Sets the security parameters index.
+    #[inline]
+    pub fn set_spi(&mut self, spi: u32) {
+        self.header_mut().spi = spi.into();
+    }
+
+    /// Returns the sequence number.
+    #[inline]
+    pub fn sequence_number(&self) -> u32 {
+        self.header().seq_no.into()
+    }
+
+    /// Sets the sequence number.
+    #[inline]
+    pub fn set_sequence_number(&mut self, seq_no: u32) {
+        self.header_mut().seq_no = seq_no.into();
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Esp<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("esp")
+            .field("spi", &format!("0x{:08x}", self.spi()))
+            .field("sequence_number", &self.sequence_number())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Esp<E> {
+    /// The preceding packet type for an ESP packet can be either an IPv4
+    /// packet, an IPv6 packet, or any IPv6 extension packets.
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        EspHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Esp::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an ESP packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`next_protocol`] is not set to
+    /// [`ProtocolNumbers::Esp`]. Returns an error if the payload does not
+    /// have sufficient data for the ESP header.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    /// [`ProtocolNumbers::Esp`]: ProtocolNumbers::Esp
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.next_protocol() == ProtocolNumbers::Esp,
+            anyhow!("not an ESP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        Ok(Esp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an ESP packet to the beginning of the envelope's payload.
+    ///
+    /// [`next_protocol`] is set to [`ProtocolNumbers::Esp`]. The payload
+    /// should be the encrypted data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    /// [`ProtocolNumbers::Esp`]: ProtocolNumbers::Esp
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, EspHeader::size_of())?;
+        let header = mbuf.write_data(offset, &EspHeader::default())?;
+
+        envelope.set_next_protocol(ProtocolNumbers::Esp);
+
+        Ok(Esp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// IP Authentication Header packet based on [IETF RFC 4302].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | Next Header   |  Payload Len  |          RESERVED             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 Security Parameters Index (SPI)               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                    Sequence Number Field                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// +                Integrity Check Value-ICV (variable)           |
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Next Header*:        8 bits. Identifies the type of the next
+///                         payload after the authentication header.
+///
+/// - *Payload Len*:        8 bits. The length of the AH in 4-octet units,
+///                         minus 2.
+///
+/// - *SPI*:                32 bits. An arbitrary value used by the receiver
+///                         to identify the security association.
+///
+/// - *Sequence Number*:    32 bits. A monotonically increasing counter
+///                         value used for anti-replay protection.
+///
+/// - *ICV*:                Variable-length field that contains the
+///                         integrity check value for this packet.
+///
+/// The payload of an AH packet is not encrypted and can be parsed as the
+/// next header, for example TCP, UDP or an IP packet in tunnel mode. In an
+/// IPv6 packet, the authentication header is part of the extension header
+/// chain.
+///
+/// ```
+/// let ah = ipv6.parse::<Ah<Ipv6>>()?;
+/// let tcp = ah.parse::<Tcp<Ah<Ipv6>>>()?;
+/// ```
+///
+/// [IETF RFC 4302]: https://tools.ietf.org/html/rfc4302#section-2
+pub struct Ah<E: IpPacket> {
+    envelope: E,
+    header: NonNull<AhHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Ah<E> {
+    #[inline]
+    fn header(&self) -> &AhHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut AhHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the length of the header in 4-octet units, minus 2.
+    #[inline]
+    pub fn payload_length(&self) -> u8 {
+        self.header().payload_len
+    }
+
+    /// Returns the security parameters index.
+    #[inline]
+    pub fn spi(&self) -> u32 {
+        self.header().spi.into()
+    }
+
+    /// Sets the security parameters index.
+    #[inline]
+    pub fn set_spi(&mut self, spi: u32) {
+        self.header_mut().spi = spi.into();
+    }
+
+    /// Returns the sequence number.
+    #[inline]
+    pub fn sequence_number(&self) -> u32 {
+        self.header().seq_no.into()
+    }
+
+    /// Sets the sequence number.
+    #[inline]
+    pub fn set_sequence_number(&mut self, seq_no: u32) {
+        self.header_mut().seq_no = seq_no.into();
+    }
+
+    /// Returns the integrity check value.
+    #[inline]
+    pub fn icv(&self) -> &[u8] {
+        let len = self.header_len() - AhHeader::size_of();
+        if let Ok(data) = self
+            .mbuf()
+            .read_data_slice(self.offset + AhHeader::size_of(), len)
+        {
+            unsafe { &*data.as_ptr() }
+        } else {
+            // the header length is checked when parsing
+            unreachable!()
+        }
+    }
+
+    /// Sets the integrity check value.
+    ///
+    /// The header is resized to fit the value, and the payload length is
+    /// updated. For IPv6, the length of the header must be a multiple of 8
+    /// octets, so the value should be padded if necessary.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the length of the value is not a multiple of 4,
+    /// or the header would exceed [`AH_MAX_HEADER_LEN`]. Returns an error
+    /// if the buffer does not have enough free space.
+    ///
+    /// [`AH_MAX_HEADER_LEN`]: AH_MAX_HEADER_LEN
+    pub fn set_icv(&mut self, icv: &[u8]) -> Result<()> {
+        let header_len = AhHeader::size_of() + icv.len();
+        ensure!(
+            icv.len() % 4 == 0 && header_len <= AH_MAX_HEADER_LEN,
+            anyhow!("invalid ICV length {}.", icv.len())
+        );
+
+        let offset = self.offset + AhHeader::size_of();
+        let current = self.header_len() - AhHeader::size_of();
+        if icv.len() > current {
+            self.mbuf_mut().extend(offset, icv.len() - current)?;
+        } else if icv.len() < current {
+            self.mbuf_mut().shrink(offset, current - icv.len())?;
+        }
+
+        self.mbuf_mut().write_data_slice(offset, icv)?;
+        self.header_mut().payload_len = (header_len / 4 - 2) as u8;
+
+        Ok(())
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Ah<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ah")
+            .field("next_header", &format!("{}", self.next_protocol()))
+            .field("payload_length", &self.payload_length())
+            .field("spi", &format!("0x{:08x}", self.spi()))
+            .field("sequence_number", &self.sequence_number())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Ah<E> {
+    /// The preceding packet type for an AH packet can be either an IPv4
+    /// packet, an IPv6 packet, or any IPv6 extension packets.
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        (self.payload_length() as usize + 2) * 4
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Ah::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an AH packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`next_protocol`] is not set to
+    /// [`ProtocolNumbers::Ah`]. Returns an error if the payload length is
+    /// less than 1, or if the payload does not have sufficient data for the
+    /// header, including the ICV.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    /// [`ProtocolNumbers::Ah`]: ProtocolNumbers::Ah
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.next_protocol() == ProtocolNumbers::Ah,
+            anyhow!("not an AH packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Ah {
+            envelope,
+            header,
+            offset,
+        };
+
+        ensure!(
+            packet.header_len() >= AhHeader::size_of(),
+            anyhow!("invalid AH payload length.")
+        );
+        ensure!(
+            packet.len() >= packet.header_len(),
+            anyhow!("packet has incomplete AH header.")
+        );
+
+        Ok(packet)
+    }
+
+    /// Prepends an AH packet with no ICV to the beginning of the envelope's
+    /// payload.
+    ///
+    /// The AH's next header is set to the envelope's [`next_protocol`],
+    /// and the envelope's `next_protocol` is set to [`ProtocolNumbers::Ah`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    /// [`ProtocolNumbers::Ah`]: ProtocolNumbers::Ah
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, AhHeader::size_of())?;
+        let header = mbuf.write_data(offset, &AhHeader::default())?;
+
+        let mut packet = Ah {
+            envelope,
+            header,
+            offset,
+        };
+
+        packet.set_next_protocol(packet.envelope().next_protocol());
+        packet.envelope_mut().set_next_protocol(ProtocolNumbers::Ah);
+
+        Ok(packet)
+    }
+
+    /// Removes the AH packet from the message buffer.
+    ///
+    /// The envelope's [`next_protocol`] is set to the AH's next header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have sufficient data to
+    /// remove.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    #[inline]
+    fn remove(mut self) -> Result<Self::Envelope> {
+        let offset = self.offset();
+        let len = self.header_len();
+        let next_header = self.next_protocol();
+        self.mbuf_mut().shrink(offset, len)?;
+        self.envelope_mut().set_next_protocol(next_header);
+        Ok(self.envelope)
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+impl<E: IpPacket> IpPacket for Ah<E> {
+    #[inline]
+    fn next_protocol(&self) -> ProtocolNumber {
+        ProtocolNumber::new(self.header().next_header)
+    }
+
+    #[inline]
+    fn set_next_protocol(&mut self, proto: ProtocolNumber) {
+        self.header_mut().next_header = proto.0;
+    }
+
+    #[inline]
+    fn src(&self) -> IpAddr {
+        self.envelope().src()
+    }
+
+    #[inline]
+    fn set_src(&mut self, src: IpAddr) -> Result<()> {
+        self.envelope_mut().set_src(src)
+    }
+
+    #[inline]
+    fn dst(&self) -> IpAddr {
+        self.envelope().dst()
+    }
+
+    #[inline]
+    fn set_dst(&mut self, dst: IpAddr) -> Result<()> {
+        self.envelope_mut().set_dst(dst)
+    }
+
+    #[inline]
+    fn pseudo_header(&self, packet_len: u16, protocol: ProtocolNumber) -> PseudoHeader {
+        self.envelope().pseudo_header(packet_len, protocol)
+    }
+
+    #[inline]
+    fn truncate(&mut self, mtu: usize) -> Result<()> {
+        self.envelope_mut().truncate(mtu)
+    }
+}
+
+impl<E: Ipv6Packet> Ipv6Packet for Ah<E> {
+    #[inline]
+    fn next_header(&self) -> ProtocolNumber {
+        self.next_protocol()
+    }
+
+    #[inline]
+    fn set_next_header(&mut self, next_header: ProtocolNumber) {
+        self.set_next_protocol(next_header);
+    }
+}
+
+impl<E: IpPacket> Datalink for Ah<E> {
+    /// Returns the type of the IP packet protected in tunnel mode, or an
+    /// unassigned EtherType of 0 if the payload is not an IP packet.
+    #[inline]
+    fn protocol_type(&self) -> EtherType {
+        tunnel::ether_type_of(self.next_protocol())
+    }
+
+    /// Sets the next header to the encapsulation protocol number for an
+    /// IPv4 or IPv6 payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ether_type` is neither IPv4 nor IPv6.
+    #[inline]
+    fn set_protocol_type(&mut self, ether_type: EtherType) -> Result<()> {
+        let protocol = tunnel::protocol_of(ether_type)
+            .ok_or_else(|| anyhow!("{} can't be carried in an IP packet.", ether_type))?;
+        self.set_next_protocol(protocol);
+        Ok(())
+    }
+}
+
+/// ESP header.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct EspHeader {
+    spi: u32be,
+    seq_no: u32be,
+}
+
+/// Authentication header, not including the ICV.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct AhHeader {
+    next_header: u8,
+    payload_len: u8,
+    reserved: [u8; 2],
+    spi: u32be,
+    seq_no: u32be,
+}
+
+impl Default for AhHeader {
+    fn default() -> Self {
+        AhHeader {
+            next_header: 0,
+            // no ICV.
+            payload_len: 1,
+            reserved: [0; 2],
+            spi: u32be::default(),
+            seq_no: u32be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::{EtherTypes, Ethernet, Tcp, Udp};
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, IPV6_TCP_PACKET};
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_ipsec_headers() {
+        assert_eq!(8, EspHeader::size_of());
+        assert_eq!(12, AhHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_esp_packet() {
+        let packet = Mbuf::from_bytes(&ESP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let esp = ipv4.parse::<Esp<Ipv4>>().unwrap();
+
+        assert_eq!(0x1000, esp.spi());
+        assert_eq!(1, esp.sequence_number());
+        assert_eq!(8, esp.header_len());
+        assert_eq!(16, esp.payload_len());
+    }
+
+    #[capsule::test]
+    fn push_esp_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut esp = ipv6.push::<Esp<Ipv6>>().unwrap();
+
+        assert_eq!(ProtocolNumbers::Esp, esp.envelope().next_header());
+
+        esp.set_spi(0x2000);
+        esp.set_sequence_number(7);
+        assert_eq!(0x2000, esp.spi());
+        assert_eq!(7, esp.sequence_number());
+    }
+
+    #[capsule::test]
+    fn push_and_parse_ipv6_ah_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let mut ah = ipv6.push::<Ah<Ipv6>>().unwrap();
+
+        assert_eq!(ProtocolNumbers::Ah, ah.envelope().next_header());
+        assert_eq!(ProtocolNumbers::Tcp, ah.next_header());
+        assert_eq!(12, ah.header_len());
+        assert!(ah.icv().is_empty());
+
+        ah.set_spi(0x2000);
+        ah.set_sequence_number(5);
+        ah.set_icv(&[0xaa; 12]).unwrap();
+        assert_eq!(4, ah.payload_length());
+        assert_eq!(24, ah.header_len());
+        assert!(ah.set_icv(&[0xaa; 10]).is_err());
+        ah.reconcile_all();
+
+        // parses again from the beginning, walking the extension headers.
+        let mbuf = ah.reset();
+        let ethernet = mbuf.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let (protocol, offset) = ipv6.upper_layer().unwrap();
+        assert_eq!(ProtocolNumbers::Tcp, protocol);
+        assert_eq!(14 + 40 + 24, offset);
+
+        let ah = ipv6.parse::<Ah<Ipv6>>().unwrap();
+        assert_eq!(0x2000, ah.spi());
+        assert_eq!(5, ah.sequence_number());
+        assert_eq!(&[0xaa; 12], ah.icv());
+
+        let tcp = ah.parse::<Tcp<Ah<Ipv6>>>().unwrap();
+        assert_eq!(36869, tcp.src_port());
+    }
+
+    #[capsule::test]
+    fn remove_ipv4_ah_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let ah = ipv4.push::<Ah<Ipv4>>().unwrap();
+
+        assert_eq!(ProtocolNumbers::Ah, ah.envelope().protocol());
+        assert!(ah.peek::<Udp<Ah<Ipv4>>>().is_ok());
+
+        let ipv4 = ah.remove().unwrap();
+        assert_eq!(ProtocolNumbers::Udp, ipv4.protocol());
+        assert_eq!(IPV4_UDP_PACKET.len(), ipv4.mbuf().data_len());
+    }
+
+    #[capsule::test]
+    fn parse_ah_tunnel_mode_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let outer = ethernet.push::<Ipv4>().unwrap();
+        let mut ah = outer.push::<Ah<Ipv4>>().unwrap();
+
+        ah.set_protocol_type(EtherTypes::Ipv4).unwrap();
+        assert_eq!(ProtocolNumbers::Ipv4, ah.next_protocol());
+        assert_eq!(EtherTypes::Ipv4, ah.protocol_type());
+
+        let inner = ah.parse::<Ipv4<Ah<Ipv4>>>().unwrap();
+        assert_eq!("139.133.217.110", inner.src().to_string());
+    }
+
+    /// IPv4 ESP packet with 16 octets of encrypted payload.
+    #[rustfmt::skip]
+    const ESP_PACKET: [u8; 58] = [
+    // Ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x08, 0x00,
+    // IPv4 header
+        0x45, 0x00,
+        // total length = 44
+        0x00, 0x2c,
+        0x00, 0x00, 0x40, 0x00,
+        // ttl = 64, protocol = ESP
+        0x40, 0x32, 0x00, 0x00,
+        // src = 10.0.0.1
+        0x0a, 0x00, 0x00, 0x01,
+        // dst = 10.0.0.2
+        0x0a, 0x00, 0x00, 0x02,
+    // ESP header
+        // spi = 0x1000
+        0x00, 0x00, 0x10, 0x00,
+        // sequence number = 1
+        0x00, 0x00, 0x00, 0x01,
+    // encrypted payload
+        0x8f, 0x1c, 0x64, 0x2a, 0x0d, 0x53, 0xe7, 0x91,
+        0x3b, 0xc6, 0x70, 0x4e, 0xa2, 0x19, 0x5d, 0xf8,
+    ];
+}
@@ -18,9 +18,16 @@
 
 //! Internet Protocol v4 and v6.
 
+mod ipsec;
+mod reassembly;
+mod tunnel;
 pub mod v4;
 pub mod v6;
 
+pub use self::ipsec::*;
+pub use self::reassembly::*;
+pub use self::tunnel::*;
+
 use crate::packets::checksum::PseudoHeader;
 use crate::packets::Packet;
 use anyhow::Result;
@@ -55,21 +62,51 @@ impl ProtocolNumber {
 pub mod ProtocolNumbers {
     use super::ProtocolNumber;
 
+    /// Hop-by-Hop Options Header for IPv6.
+    pub const Ipv6HopByHop: ProtocolNumber = ProtocolNumber(0x00);
+
+    /// Internet Group Management Protocol.
+    pub const Igmp: ProtocolNumber = ProtocolNumber(0x02);
+
+    /// IPv4 encapsulation.
+    pub const Ipv4: ProtocolNumber = ProtocolNumber(0x04);
+
     /// Transmission Control Protocol.
     pub const Tcp: ProtocolNumber = ProtocolNumber(0x06);
 
     /// User Datagram Protocol.
     pub const Udp: ProtocolNumber = ProtocolNumber(0x11);
 
+    /// IPv6 encapsulation.
+    pub const Ipv6: ProtocolNumber = ProtocolNumber(0x29);
+
     /// Routing Header for IPv6.
     pub const Ipv6Route: ProtocolNumber = ProtocolNumber(0x2B);
 
     /// Fragment Header for IPv6.
     pub const Ipv6Frag: ProtocolNumber = ProtocolNumber(0x2C);
 
+    /// Generic Routing Encapsulation.
+    pub const Gre: ProtocolNumber = ProtocolNumber(0x2F);
+
+    /// Encapsulating Security Payload.
+    pub const Esp: ProtocolNumber = ProtocolNumber(0x32);
+
+    /// Authentication Header.
+    pub const Ah: ProtocolNumber = ProtocolNumber(0x33);
+
     /// Internet Control Message Protocol for IPv6.
     pub const Icmpv6: ProtocolNumber = ProtocolNumber(0x3A);
 
+    /// No Next Header for IPv6.
+    pub const Ipv6NoNxt: ProtocolNumber = ProtocolNumber(0x3B);
+
+    /// Destination Options Header for IPv6.
+    pub const Ipv6Opts: ProtocolNumber = ProtocolNumber(0x3C);
+
+    /// Stream Control Transmission Protocol.
+    pub const Sctp: ProtocolNumber = ProtocolNumber(0x84);
+
     /// Internet Control Message Protocol for IPv4.
     pub const Icmpv4: ProtocolNumber = ProtocolNumber(0x01);
 }
@@ -80,11 +117,21 @@ impl fmt::Display for ProtocolNumber {
             f,
             "{}",
             match *self {
+                ProtocolNumbers::Ipv6HopByHop => "IPv6 Hop-by-Hop".to_string(),
+                ProtocolNumbers::Igmp => "IGMP".to_string(),
+                ProtocolNumbers::Ipv4 => "IPv4".to_string(),
                 ProtocolNumbers::Tcp => "TCP".to_string(),
                 ProtocolNumbers::Udp => "UDP".to_string(),
+                ProtocolNumbers::Ipv6 => "IPv6".to_string(),
                 ProtocolNumbers::Ipv6Route => "IPv6 Route".to_string(),
                 ProtocolNumbers::Ipv6Frag => "IPv6 Frag".to_string(),
+                ProtocolNumbers::Gre => "GRE".to_string(),
+                ProtocolNumbers::Esp => "ESP".to_string(),
+                ProtocolNumbers::Ah => "AH".to_string(),
                 ProtocolNumbers::Icmpv6 => "ICMPv6".to_string(),
+                ProtocolNumbers::Ipv6NoNxt => "IPv6 NoNxt".to_string(),
+                ProtocolNumbers::Ipv6Opts => "IPv6 Opts".to_string(),
+                ProtocolNumbers::Sctp => "SCTP".to_string(),
                 ProtocolNumbers::Icmpv4 => "ICMPv4".to_string(),
                 _ => format!("0x{:02x}", self.0),
             }
@@ -274,6 +321,7 @@ mod tests {
         assert_eq!("UDP", ProtocolNumbers::Udp.to_string());
         assert_eq!("IPv6 Route", ProtocolNumbers::Ipv6Route.to_string());
         assert_eq!("ICMPv6", ProtocolNumbers::Icmpv6.to_string());
-        assert_eq!("0x00", ProtocolNumber::new(0).to_string());
+        assert_eq!("IPv6 Hop-by-Hop", ProtocolNumbers::Ipv6HopByHop.to_string());
+        assert_eq!("0xff", ProtocolNumber::new(0xff).to_string());
     }
 }
@@ -0,0 +1,847 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+#[cfg(feature = "metrics")]
+use crate::dpdk::CoreId;
+#[cfg(feature = "metrics")]
+use crate::metrics::{self, labels, Counter, SINK};
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::{Fragment, Ipv6, Ipv6Packet};
+use crate::packets::ip::{IpPacket, ProtocolNumber};
+use crate::packets::{Ethernet, Packet};
+use crate::{ensure, Mbuf};
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// The maximum value of the IPv4 total length and the IPv6 payload length.
+const MAX_LEN: usize = 65535;
+
+/// How to handle a fragment that overlaps with the data already received
+/// for the same datagram.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OverlapPolicy {
+    /// Discards the whole datagram.
+    Discard,
+    /// Keeps the data already received and only uses the new fragment to
+    /// fill the gaps.
+    KeepFirst,
+    /// Overwrites the data already received with the new fragment.
+    KeepLast,
+}
+
+/// Configuration of a [`ReassemblyTable`].
+///
+/// [`ReassemblyTable`]: ReassemblyTable
+#[derive(Clone, Copy, Debug)]
+pub struct ReassemblyConfig {
+    /// How long to wait for the missing fragments of a datagram, measured
+    /// from the arrival of its first received fragment. Defaults to 30
+    /// seconds.
+    pub timeout: Duration,
+
+    /// The maximum number of incomplete datagrams. Defaults to 1024.
+    pub max_datagrams: usize,
+
+    /// The maximum number of octets buffered for incomplete datagrams.
+    /// Defaults to 4 MiB.
+    pub max_memory: usize,
+
+    /// How to handle overlapping IPv4 fragments. Defaults to
+    /// [`OverlapPolicy::Discard`]. Overlapping IPv6 fragments always
+    /// discard the datagram, as required by [IETF RFC 5722].
+    ///
+    /// [`OverlapPolicy::Discard`]: OverlapPolicy::Discard
+    /// [IETF RFC 5722]: https://tools.ietf.org/html/rfc5722#section-4
+    pub overlap: OverlapPolicy,
+}
+
+impl Default for ReassemblyConfig {
+    fn default() -> Self {
+        ReassemblyConfig {
+            timeout: Duration::from_secs(30),
+            max_datagrams: 1024,
+            max_memory: 4 * 1024 * 1024,
+            overlap: OverlapPolicy::Discard,
+        }
+    }
+}
+
+/// The fields identifying the fragments of the same datagram.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Key {
+    src: IpAddr,
+    dst: IpAddr,
+    id: u32,
+    protocol: ProtocolNumber,
+}
+
+/// An incomplete datagram.
+struct Datagram {
+    /// When the first fragment is received.
+    created: Instant,
+    /// The headers preceding the payload of the fragment with offset 0.
+    /// Empty until that fragment is received.
+    headers: Vec<u8>,
+    /// The payload received so far.
+    payload: Vec<u8>,
+    /// The start and the end of each fragment received.
+    ranges: Vec<(usize, usize)>,
+    /// The payload length, known once the last fragment is received.
+    len: Option<usize>,
+}
+
+impl Datagram {
+    /// Returns the number of octets buffered.
+    fn size(&self) -> usize {
+        self.headers.len() + self.payload.len()
+    }
+
+    /// Returns whether all the fragments are received.
+    fn is_complete(&self) -> bool {
+        match self.len {
+            Some(len) if !self.headers.is_empty() => {
+                let mut ranges = self.ranges.clone();
+                ranges.sort_unstable();
+                let mut covered = 0;
+                for (start, end) in ranges {
+                    if start > covered {
+                        break;
+                    }
+                    covered = covered.max(end);
+                }
+                covered == len
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A fragment to add to a datagram.
+struct Piece<'a> {
+    /// The headers, if the fragment offset is 0.
+    headers: Option<&'a [u8]>,
+    /// The offset of the data in octets.
+    start: usize,
+    /// The fragment data.
+    data: &'a [u8],
+    /// Whether there are more fragments.
+    more_fragments: bool,
+    /// The maximum payload length of the reassembled datagram.
+    max_len: usize,
+}
+
+#[cfg(feature = "metrics")]
+struct Counters {
+    timeouts: Counter,
+    evictions: Counter,
+}
+
+#[cfg(feature = "metrics")]
+impl Counters {
+    /// Creates the counters if the metrics store is initialized.
+    fn new() -> Option<Self> {
+        if !metrics::is_initialized() {
+            return None;
+        }
+
+        let new_counter = |name| {
+            SINK.scoped("reassembly")
+                .counter_with_labels(name, labels!("core" => CoreId::current().raw().to_string()))
+        };
+
+        Some(Counters {
+            timeouts: new_counter("timeouts"),
+            evictions: new_counter("evictions"),
+        })
+    }
+}
+
+/// A table that reassembles IPv4 and IPv6 fragments back into the original
+/// datagrams, as specified in [IETF RFC 791] and [IETF RFC 8200].
+///
+/// Fragments belong to the same datagram if they have the same source
+/// address, destination address, identification and protocol. The table
+/// buffers a copy of each fragment until all the fragments of the datagram
+/// are received, then emits the reassembled datagram as a new `Mbuf`.
+/// Incomplete datagrams are discarded when the [`timeout`] expires, or
+/// evicted oldest first when the table reaches either the
+/// [`max_datagrams`] or the [`max_memory`] limit.
+///
+/// The table is not thread safe. Each core should have its own table,
+/// typically owned by the pipeline processing the fragments. Because the
+/// fragments of a datagram must all reach the same table, the port should
+/// distribute the packets to the cores based on the IP addresses only.
+///
+/// # Example
+///
+/// ```
+/// let mut table = ReassemblyTable::new(ReassemblyConfig::default());
+///
+/// let mut batch = batch.filter_map(move |packet| {
+///     let v4 = packet.parse::<Ethernet>()?.parse::<Ipv4>()?;
+///     if v4.fragment_offset() == 0 && !v4.more_fragments() {
+///         return Ok(Either::Keep(v4));
+///     }
+///
+///     match table.reassemble_v4(&v4)? {
+///         Some(mbuf) => Ok(Either::Keep(mbuf.parse::<Ethernet>()?.parse::<Ipv4>()?)),
+///         None => Ok(Either::Drop(v4.reset())),
+///     }
+/// });
+/// ```
+///
+/// A reassembled datagram larger than a single segment is stored in
+/// chained segments.
+///
+/// [IETF RFC 791]: https://tools.ietf.org/html/rfc791#section-3.2
+/// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-4.5
+/// [`timeout`]: ReassemblyConfig::timeout
+/// [`max_datagrams`]: ReassemblyConfig::max_datagrams
+/// [`max_memory`]: ReassemblyConfig::max_memory
+pub struct ReassemblyTable {
+    config: ReassemblyConfig,
+    datagrams: HashMap<Key, Datagram>,
+    /// The datagrams in the order they are created. May contain datagrams
+    /// that are already removed.
+    order: VecDeque<(Key, Instant)>,
+    memory: usize,
+    timeouts: u64,
+    evictions: u64,
+    #[cfg(feature = "metrics")]
+    counters: Option<Counters>,
+}
+
+impl ReassemblyTable {
+    /// Creates a new reassembly table.
+    pub fn new(config: ReassemblyConfig) -> Self {
+        ReassemblyTable {
+            config,
+            datagrams: HashMap::new(),
+            order: VecDeque::new(),
+            memory: 0,
+            timeouts: 0,
+            evictions: 0,
+            #[cfg(feature = "metrics")]
+            counters: Counters::new(),
+        }
+    }
+
+    /// Returns the number of incomplete datagrams.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.datagrams.len()
+    }
+
+    /// Returns whether there are no incomplete datagrams.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.datagrams.is_empty()
+    }
+
+    /// Returns the number of octets buffered for incomplete datagrams.
+    #[inline]
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    /// Returns the total number of datagrams discarded because the timeout
+    /// expired.
+    #[inline]
+    pub fn timeouts(&self) -> u64 {
+        self.timeouts
+    }
+
+    /// Returns the total number of datagrams evicted to stay within the
+    /// limits.
+    #[inline]
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Adds an IPv4 fragment to the table.
+    ///
+    /// Returns the reassembled datagram, including the Ethernet header and
+    /// the IPv4 header of the first fragment, if this is the last missing
+    /// fragment. The fragment offset and the more fragments flag are
+    /// cleared, and the total length and the checksum are recomputed.
+    /// Otherwise returns `None`. The fragment is copied, so the caller
+    /// should drop it afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the packet is not a fragment, or the fragment
+    /// is malformed. Returns an error if the fragment overlaps with the
+    /// data already received and the overlap policy is
+    /// [`OverlapPolicy::Discard`], or is inconsistent with the length of
+    /// the datagram. The datagram is discarded in both cases. Returns an
+    /// error if the reassembled datagram can't be allocated.
+    ///
+    /// [`OverlapPolicy::Discard`]: OverlapPolicy::Discard
+    pub fn reassemble_v4(&mut self, packet: &Ipv4) -> Result<Option<Mbuf>> {
+        ensure!(
+            packet.fragment_offset() > 0 || packet.more_fragments(),
+            anyhow!("not an IPv4 fragment.")
+        );
+
+        let total_length = packet.total_length() as usize;
+        ensure!(
+            total_length > packet.header_len()
+                && packet.offset() + total_length <= packet.mbuf().len(),
+            anyhow!("invalid total length {}.", total_length)
+        );
+
+        let start = packet.fragment_offset() as usize * 8;
+        let headers = packet
+            .mbuf()
+            .read_data_slice::<u8>(0, packet.payload_offset())?;
+        let mut data = vec![0; total_length - packet.header_len()];
+        packet
+            .mbuf()
+            .copy_to_slice(packet.payload_offset(), &mut data)?;
+
+        let key = Key {
+            src: IpPacket::src(packet),
+            dst: IpPacket::dst(packet),
+            id: packet.identification().into(),
+            protocol: packet.protocol(),
+        };
+
+        let piece = Piece {
+            headers: if start == 0 {
+                Some(unsafe { headers.as_ref() })
+            } else {
+                None
+            },
+            start,
+            data: &data,
+            more_fragments: packet.more_fragments(),
+            max_len: MAX_LEN - packet.header_len(),
+        };
+
+        let overlap = self.config.overlap;
+        match self.insert(key, piece, overlap)? {
+            Some(datagram) => {
+                let mut ipv4 = build(&datagram)?.parse::<Ethernet>()?.parse::<Ipv4>()?;
+                ipv4.set_fragment_offset(0);
+                ipv4.unset_more_fragments();
+                ipv4.reconcile();
+                Ok(Some(ipv4.reset()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Adds an IPv6 fragment to the table.
+    ///
+    /// Returns the reassembled datagram, including the Ethernet header and
+    /// the IPv6 header of the first fragment, if this is the last missing
+    /// fragment. The fragment header is removed, and the next header and
+    /// the payload length are updated. Otherwise returns `None`. The
+    /// fragment is copied, so the caller should drop it afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the fragment is malformed. Returns an error if
+    /// the fragment overlaps with the data already received, or is
+    /// inconsistent with the length of the datagram. The datagram is
+    /// discarded in both cases. Returns an error if the reassembled
+    /// datagram can't be allocated.
+    pub fn reassemble_v6(&mut self, packet: &Fragment<Ipv6>) -> Result<Option<Mbuf>> {
+        let ipv6 = packet.envelope();
+        let end = ipv6.payload_offset() + ipv6.payload_length() as usize;
+        ensure!(
+            end > packet.payload_offset() && end <= packet.mbuf().len(),
+            anyhow!("invalid payload length {}.", ipv6.payload_length())
+        );
+
+        let start = packet.fragment_offset() as usize * 8;
+        let headers = packet.mbuf().read_data_slice::<u8>(0, packet.offset())?;
+        let mut data = vec![0; end - packet.payload_offset()];
+        packet
+            .mbuf()
+            .copy_to_slice(packet.payload_offset(), &mut data)?;
+
+        let key = Key {
+            src: packet.src(),
+            dst: packet.dst(),
+            id: packet.identification(),
+            protocol: packet.next_header(),
+        };
+
+        let piece = Piece {
+            headers: if start == 0 {
+                Some(unsafe { headers.as_ref() })
+            } else {
+                None
+            },
+            start,
+            data: &data,
+            more_fragments: packet.more_fragments(),
+            max_len: MAX_LEN - (packet.offset() - ipv6.payload_offset()),
+        };
+
+        match self.insert(key, piece, OverlapPolicy::Discard)? {
+            Some(datagram) => {
+                let mut ipv6 = build(&datagram)?.parse::<Ethernet>()?.parse::<Ipv6>()?;
+                ipv6.set_next_header(key.protocol);
+                ipv6.reconcile();
+                Ok(Some(ipv6.reset()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Discards the incomplete datagrams whose timeout expired.
+    ///
+    /// Expired datagrams are also discarded when fragments are added. This
+    /// should be called periodically to release the memory when fragments
+    /// stop arriving.
+    pub fn expire(&mut self) {
+        self.expire_at(Instant::now());
+    }
+
+    fn expire_at(&mut self, now: Instant) {
+        while let Some(&(key, created)) = self.order.front() {
+            if now.duration_since(created) < self.config.timeout {
+                break;
+            }
+
+            self.order.pop_front();
+            if self.remove_if_created(&key, created) {
+                self.timeouts += 1;
+                #[cfg(feature = "metrics")]
+                {
+                    if let Some(counters) = &self.counters {
+                        counters.timeouts.record(1);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Evicts the oldest datagram. Returns `false` if there is none.
+    fn evict_oldest(&mut self) -> bool {
+        while let Some((key, created)) = self.order.pop_front() {
+            if self.remove_if_created(&key, created) {
+                self.evictions += 1;
+                #[cfg(feature = "metrics")]
+                {
+                    if let Some(counters) = &self.counters {
+                        counters.evictions.record(1);
+                    }
+                }
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Removes the datagram if it's the one created at `created`, and not
+    /// a later one with the same key.
+    fn remove_if_created(&mut self, key: &Key, created: Instant) -> bool {
+        match self.datagrams.get(key) {
+            Some(datagram) if datagram.created == created => {
+                self.remove(key);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn remove(&mut self, key: &Key) -> Option<Datagram> {
+        let datagram = self.datagrams.remove(key)?;
+        self.memory -= datagram.size();
+        Some(datagram)
+    }
+
+    /// Adds the fragment to its datagram. Returns the datagram once it's
+    /// complete.
+    fn insert(
+        &mut self,
+        key: Key,
+        piece: Piece<'_>,
+        overlap: OverlapPolicy,
+    ) -> Result<Option<Datagram>> {
+        let start = piece.start;
+        let end = start + piece.data.len();
+        ensure!(
+            !piece.more_fragments || piece.data.len() % 8 == 0,
+            anyhow!(
+                "fragment length {} is not a multiple of 8.",
+                piece.data.len()
+            )
+        );
+        ensure!(
+            end <= piece.max_len,
+            anyhow!("fragment end {} exceeds the maximum length.", end)
+        );
+
+        let now = Instant::now();
+        self.expire_at(now);
+
+        // makes room for the new data, evicting the oldest datagrams.
+        let headers_len = piece.headers.map_or(0, <[u8]>::len);
+        let growth = match self.datagrams.get(&key) {
+            Some(datagram) => {
+                let headers_len = if datagram.headers.is_empty() {
+                    headers_len
+                } else {
+                    0
+                };
+                headers_len + end.saturating_sub(datagram.payload.len())
+            }
+            None => headers_len + end,
+        };
+        ensure!(
+            growth <= self.config.max_memory,
+            anyhow!("fragment exceeds the memory limit.")
+        );
+        while self.memory + growth > self.config.max_memory {
+            if !self.evict_oldest() {
+                break;
+            }
+        }
+
+        if !self.datagrams.contains_key(&key) {
+            ensure!(
+                self.config.max_datagrams > 0,
+                anyhow!("fragment exceeds the datagram limit.")
+            );
+            while self.datagrams.len() >= self.config.max_datagrams {
+                if !self.evict_oldest() {
+                    break;
+                }
+            }
+
+            self.datagrams.insert(
+                key,
+                Datagram {
+                    created: now,
+                    headers: Vec::new(),
+                    payload: Vec::new(),
+                    ranges: Vec::new(),
+                    len: None,
+                },
+            );
+            self.order.push_back((key, now));
+        }
+
+        let datagram = self.datagrams.get_mut(&key).unwrap();
+
+        // exact duplicates are ignored.
+        if datagram.ranges.contains(&(start, end)) {
+            return Ok(None);
+        }
+
+        let consistent = match datagram.len {
+            Some(len) => end <= len && (piece.more_fragments || end == len),
+            None => piece.more_fragments || datagram.ranges.iter().all(|&(_, e)| e <= end),
+        };
+        if !consistent {
+            self.remove(&key);
+            return Err(anyhow!(
+                "fragment is inconsistent with the datagram length."
+            ));
+        }
+
+        let overlapped = datagram.ranges.iter().any(|&(s, e)| s < end && start < e);
+        if overlapped && overlap == OverlapPolicy::Discard {
+            self.remove(&key);
+            return Err(anyhow!("fragment overlaps with the datagram."));
+        }
+
+        let size = datagram.size();
+        if datagram.payload.len() < end {
+            datagram.payload.resize(end, 0);
+        }
+        if overlapped && overlap == OverlapPolicy::KeepFirst {
+            // only fills the gaps between the fragments already received.
+            let mut pos = start;
+            let mut ranges = datagram.ranges.clone();
+            ranges.sort_unstable();
+            for (s, e) in ranges.into_iter().chain(std::iter::once((end, end))) {
+                if e <= pos {
+                    continue;
+                }
+                if s > pos {
+                    let gap = pos..s.min(end);
+                    datagram.payload[gap.clone()]
+                        .copy_from_slice(&piece.data[gap.start - start..gap.end - start]);
+                }
+                pos = pos.max(e);
+                if pos >= end {
+                    break;
+                }
+            }
+        } else {
+            datagram.payload[start..end].copy_from_slice(piece.data);
+        }
+        if let Some(headers) = piece.headers {
+            if datagram.headers.is_empty() {
+                datagram.headers = headers.to_vec();
+            }
+        }
+        datagram.ranges.push((start, end));
+        if !piece.more_fragments {
+            datagram.len = Some(end);
+        }
+        self.memory = self.memory + datagram.size() - size;
+
+        if datagram.is_complete() {
+            Ok(self.remove(&key))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl fmt::Debug for ReassemblyTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReassemblyTable")
+            .field("config", &self.config)
+            .field("datagrams", &self.len())
+            .field("memory", &self.memory)
+            .field("timeouts", &self.timeouts)
+            .field("evictions", &self.evictions)
+            .finish()
+    }
+}
+
+/// Builds the reassembled datagram from the headers and the payload.
+fn build(datagram: &Datagram) -> Result<Mbuf> {
+    let mut mbuf = Mbuf::from_bytes(&datagram.headers)?;
+    let offset = datagram.headers.len();
+    mbuf.extend(offset, datagram.payload.len())?;
+    mbuf.copy_from_slice(offset, &datagram.payload)?;
+    Ok(mbuf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::ProtocolNumbers;
+    use crate::testils::{ipv4_udp_fragment, ipv4_udp_packet, ipv6_udp_fragment, PacketExt};
+    use std::thread;
+
+    #[capsule::test]
+    fn reassemble_ipv4_fragments() {
+        let data = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+        let packet = ipv4_udp_packet(0x1234, &data);
+        let fragments = packet.fragment(500).unwrap();
+        assert_eq!(3, fragments.len());
+
+        let mut table = ReassemblyTable::new(ReassemblyConfig::default());
+        let mut reassembled = None;
+        for mbuf in fragments.into_iter().rev() {
+            let fragment = mbuf.parse::<Ethernet>().unwrap().parse::<Ipv4>().unwrap();
+            assert!(reassembled.is_none());
+            reassembled = table.reassemble_v4(&fragment).unwrap();
+        }
+        assert!(table.is_empty());
+        assert_eq!(0, table.memory());
+
+        let ipv4 = reassembled
+            .unwrap()
+            .parse::<Ethernet>()
+            .unwrap()
+            .parse::<Ipv4>()
+            .unwrap();
+        assert_eq!(1020, ipv4.total_length());
+        assert_eq!(0x1234, ipv4.identification());
+        assert_eq!(0, ipv4.fragment_offset());
+        assert!(!ipv4.more_fragments());
+        assert_eq!(ProtocolNumbers::Udp, ipv4.protocol());
+        assert_eq!(data, ipv4.payload_to_vec());
+    }
+
+    #[capsule::test]
+    fn reassemble_ipv6_fragments() {
+        let data = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+        let mut table = ReassemblyTable::new(ReassemblyConfig::default());
+
+        let second = ipv6_udp_fragment(0x1234, 50, &data[400..800], true);
+        assert!(table.reassemble_v6(&second).unwrap().is_none());
+        let last = ipv6_udp_fragment(0x1234, 100, &data[800..], false);
+        assert!(table.reassemble_v6(&last).unwrap().is_none());
+        let first = ipv6_udp_fragment(0x1234, 0, &data[..400], true);
+        let reassembled = table.reassemble_v6(&first).unwrap().unwrap();
+        assert!(table.is_empty());
+
+        let ipv6 = reassembled
+            .parse::<Ethernet>()
+            .unwrap()
+            .parse::<Ipv6>()
+            .unwrap();
+        assert_eq!(ProtocolNumbers::Udp, ipv6.next_header());
+        assert_eq!(1000, ipv6.payload_length());
+        assert_eq!(data, ipv6.payload_to_vec());
+    }
+
+    #[capsule::test]
+    fn reassemble_ipv4_non_fragment() {
+        let mut table = ReassemblyTable::new(ReassemblyConfig::default());
+        let packet = ipv4_udp_fragment(1, 0, &[0; 16], false);
+        assert!(table.reassemble_v4(&packet).is_err());
+    }
+
+    #[capsule::test]
+    fn ignore_duplicate_fragments() {
+        let mut table = ReassemblyTable::new(ReassemblyConfig::default());
+        let first = ipv6_udp_fragment(1, 0, &[1; 16], true);
+        assert!(table.reassemble_v6(&first).unwrap().is_none());
+        assert!(table.reassemble_v6(&first).unwrap().is_none());
+        assert_eq!(1, table.len());
+
+        let last = ipv6_udp_fragment(1, 2, &[2; 8], false);
+        assert!(table.reassemble_v6(&last).unwrap().is_some());
+    }
+
+    #[capsule::test]
+    fn discard_overlapping_fragments() {
+        let mut table = ReassemblyTable::new(ReassemblyConfig::default());
+        let first = ipv6_udp_fragment(1, 0, &[1; 16], true);
+        assert!(table.reassemble_v6(&first).unwrap().is_none());
+        let overlapping = ipv6_udp_fragment(1, 1, &[2; 16], false);
+        assert!(table.reassemble_v6(&overlapping).is_err());
+        assert!(table.is_empty());
+        assert_eq!(0, table.memory());
+    }
+
+    #[capsule::test]
+    fn keep_first_overlapping_fragments() {
+        let mut table = ReassemblyTable::new(ReassemblyConfig {
+            overlap: OverlapPolicy::KeepFirst,
+            ..ReassemblyConfig::default()
+        });
+        let first = ipv4_udp_fragment(1, 0, &[1; 16], true);
+        assert!(table.reassemble_v4(&first).unwrap().is_none());
+        let last = ipv4_udp_fragment(1, 1, &[2; 16], false);
+        let ipv4 = table
+            .reassemble_v4(&last)
+            .unwrap()
+            .unwrap()
+            .parse::<Ethernet>()
+            .unwrap()
+            .parse::<Ipv4>()
+            .unwrap();
+
+        let mut expected = vec![1; 16];
+        expected.extend(&[2; 8]);
+        assert_eq!(expected, ipv4.payload_to_vec());
+    }
+
+    #[capsule::test]
+    fn keep_last_overlapping_fragments() {
+        let mut table = ReassemblyTable::new(ReassemblyConfig {
+            overlap: OverlapPolicy::KeepLast,
+            ..ReassemblyConfig::default()
+        });
+        let first = ipv4_udp_fragment(1, 0, &[1; 16], true);
+        assert!(table.reassemble_v4(&first).unwrap().is_none());
+        let last = ipv4_udp_fragment(1, 1, &[2; 16], false);
+        let ipv4 = table
+            .reassemble_v4(&last)
+            .unwrap()
+            .unwrap()
+            .parse::<Ethernet>()
+            .unwrap()
+            .parse::<Ipv4>()
+            .unwrap();
+
+        let mut expected = vec![1; 8];
+        expected.extend(&[2; 16]);
+        assert_eq!(expected, ipv4.payload_to_vec());
+    }
+
+    #[capsule::test]
+    fn discard_inconsistent_fragments() {
+        let mut table = ReassemblyTable::new(ReassemblyConfig::default());
+        let last = ipv6_udp_fragment(1, 4, &[1; 16], false);
+        assert!(table.reassemble_v6(&last).unwrap().is_none());
+        let beyond = ipv6_udp_fragment(1, 8, &[2; 16], true);
+        assert!(table.reassemble_v6(&beyond).is_err());
+        assert!(table.is_empty());
+
+        let unaligned = ipv6_udp_fragment(2, 0, &[1; 12], true);
+        assert!(table.reassemble_v6(&unaligned).is_err());
+    }
+
+    #[capsule::test]
+    fn expire_incomplete_datagrams() {
+        let mut table = ReassemblyTable::new(ReassemblyConfig {
+            timeout: Duration::from_millis(10),
+            ..ReassemblyConfig::default()
+        });
+        let first = ipv4_udp_fragment(1, 0, &[1; 16], true);
+        assert!(table.reassemble_v4(&first).unwrap().is_none());
+
+        thread::sleep(Duration::from_millis(20));
+        table.expire();
+        assert!(table.is_empty());
+        assert_eq!(0, table.memory());
+        assert_eq!(1, table.timeouts());
+
+        // the late fragment starts a new datagram.
+        let last = ipv4_udp_fragment(1, 2, &[2; 16], false);
+        assert!(table.reassemble_v4(&last).unwrap().is_none());
+        assert_eq!(1, table.len());
+    }
+
+    #[capsule::test]
+    fn evict_oldest_datagrams() {
+        let mut table = ReassemblyTable::new(ReassemblyConfig {
+            max_datagrams: 2,
+            ..ReassemblyConfig::default()
+        });
+        for id in 1..=3 {
+            let fragment = ipv4_udp_fragment(id, 0, &[1; 16], true);
+            assert!(table.reassemble_v4(&fragment).unwrap().is_none());
+        }
+        assert_eq!(2, table.len());
+        assert_eq!(1, table.evictions());
+
+        // the first datagram is evicted and can't be completed.
+        let last = ipv4_udp_fragment(1, 2, &[2; 16], false);
+        assert!(table.reassemble_v4(&last).unwrap().is_none());
+        let last = ipv4_udp_fragment(3, 2, &[2; 16], false);
+        assert!(table.reassemble_v4(&last).unwrap().is_some());
+    }
+
+    #[capsule::test]
+    fn evict_datagrams_over_memory_limit() {
+        let mut table = ReassemblyTable::new(ReassemblyConfig {
+            max_memory: 100,
+            ..ReassemblyConfig::default()
+        });
+        let first = ipv6_udp_fragment(1, 0, &[1; 16], true);
+        assert!(table.reassemble_v6(&first).unwrap().is_none());
+        assert_eq!(70, table.memory());
+
+        let second = ipv6_udp_fragment(2, 0, &[1; 16], true);
+        assert!(table.reassemble_v6(&second).unwrap().is_none());
+        assert_eq!(1, table.len());
+        assert_eq!(1, table.evictions());
+
+        let large = ipv6_udp_fragment(3, 0, &[1; 128], true);
+        assert!(table.reassemble_v6(&large).is_err());
+    }
+}
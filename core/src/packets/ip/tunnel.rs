@@ -0,0 +1,400 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::ensure;
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::{IpPacket, ProtocolNumber, ProtocolNumbers};
+use crate::packets::{Datalink, EtherType, EtherTypes, Packet};
+use anyhow::{anyhow, Result};
+
+// ECN codepoints.
+const NOT_ECT: u8 = 0b00;
+const ECT_1: u8 = 0b01;
+const CE: u8 = 0b11;
+
+/// Returns the EtherType of the IP packet encapsulated by an IP packet with
+/// `protocol` as the next protocol. Returns an unassigned EtherType of 0 if
+/// the payload is not an IP packet.
+pub(crate) fn ether_type_of(protocol: ProtocolNumber) -> EtherType {
+    match protocol {
+        ProtocolNumbers::Ipv4 => EtherTypes::Ipv4,
+        ProtocolNumbers::Ipv6 => EtherTypes::Ipv6,
+        _ => EtherType::new(0),
+    }
+}
+
+/// Returns the protocol number used to encapsulate the IP packet of
+/// `ether_type` in another IP packet.
+pub(crate) fn protocol_of(ether_type: EtherType) -> Option<ProtocolNumber> {
+    match ether_type {
+        EtherTypes::Ipv4 => Some(ProtocolNumbers::Ipv4),
+        EtherTypes::Ipv6 => Some(ProtocolNumbers::Ipv6),
+        _ => None,
+    }
+}
+
+/// Returns the ECN field of the decapsulated packet based on the ECN fields
+/// of the outer and the inner headers, as specified in [IETF RFC 6040].
+///
+/// # Errors
+///
+/// Returns an error if the outer header is marked CE but the inner header
+/// is not ECN-capable. The packet must be dropped.
+///
+/// [IETF RFC 6040]: https://tools.ietf.org/html/rfc6040#section-4.2
+fn decap_ecn(outer: u8, inner: u8) -> Result<u8> {
+    match (outer, inner) {
+        (CE, NOT_ECT) => Err(anyhow!("CE marked packet is not ECN-capable.")),
+        (CE, _) => Ok(CE),
+        (ECT_1, NOT_ECT) | (ECT_1, CE) => Ok(inner),
+        (ECT_1, _) => Ok(ECT_1),
+        _ => Ok(inner),
+    }
+}
+
+/// A trait implemented by IPv4 and IPv6 packets that can be carried inside
+/// another IPv4 or IPv6 packet.
+///
+/// The supported combinations are IPv4 in IPv4 ([IETF RFC 2003]), IPv6 in
+/// IPv4, also known as 6in4 ([IETF RFC 4213]), and IPv4 or IPv6 in IPv6
+/// ([IETF RFC 2473]). The outer packet's next protocol is set to 4 for an
+/// IPv4 payload and to 41 for an IPv6 payload.
+///
+/// ```
+/// let ipv6 = ethernet.parse::<Ipv6>()?;
+/// let mut ipv4 = ipv6.encap::<Ipv4>()?;
+/// ipv4.set_src(local);
+/// ipv4.set_dst(remote);
+/// ipv4.reconcile();
+///
+/// let ipv6 = ipv4.parse::<Ipv6<Ipv4>>()?;
+/// ```
+///
+/// [IETF RFC 2003]: https://tools.ietf.org/html/rfc2003
+/// [IETF RFC 4213]: https://tools.ietf.org/html/rfc4213#section-3.5
+/// [IETF RFC 2473]: https://tools.ietf.org/html/rfc2473
+pub trait IpTunnel: IpPacket + Datalink {
+    /// Returns the differentiated services code point.
+    fn dscp(&self) -> u8;
+
+    /// Sets the differentiated services code point.
+    fn set_dscp(&mut self, dscp: u8);
+
+    /// Returns the explicit congestion notification.
+    fn ecn(&self) -> u8;
+
+    /// Sets the explicit congestion notification.
+    fn set_ecn(&mut self, ecn: u8);
+
+    /// Returns the time to live for IPv4, or the hop limit for IPv6.
+    fn ttl(&self) -> u8;
+
+    /// Sets the time to live for IPv4, or the hop limit for IPv6.
+    fn set_ttl(&mut self, ttl: u8);
+
+    /// Encapsulates the packet in a new outer IP packet `O`.
+    ///
+    /// The packet's TTL or hop limit is decremented by one, because it is
+    /// forwarded into the tunnel. The outer packet has the default TTL or
+    /// hop limit. The DSCP is copied from the inner packet, and so is the
+    /// ECN, in the normal mode of [IETF RFC 6040]. The outer packet is
+    /// reconciled, but its addresses are not set. It should be reconciled
+    /// again after the addresses are set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TTL or hop limit of the packet would reach
+    /// 0. Returns an error if the buffer does not have enough free space.
+    ///
+    /// [IETF RFC 6040]: https://tools.ietf.org/html/rfc6040#section-4.1
+    fn encap<O>(mut self) -> Result<O>
+    where
+        Self: Sized,
+        Self::Envelope: Datalink,
+        O: IpTunnel<Envelope = Self::Envelope>,
+    {
+        let ttl = self.ttl();
+        ensure!(ttl > 1, anyhow!("time to live exceeded in transit."));
+        self.set_ttl(ttl - 1);
+        self.reconcile();
+
+        let dscp = self.dscp();
+        let ecn = self.ecn();
+        let protocol_type = self.envelope().protocol_type();
+
+        let mut outer = self.deparse().push::<O>()?;
+        outer.set_protocol_type(protocol_type)?;
+        outer.set_dscp(dscp);
+        outer.set_ecn(ecn);
+        outer.reconcile();
+
+        Ok(outer)
+    }
+
+    /// Decapsulates the inner IP packet `I` by removing the packet.
+    ///
+    /// The ECN of the inner packet is updated from the outer packet as
+    /// specified in [IETF RFC 6040]. The inner DSCP and TTL or hop limit
+    /// are not changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload is not an IP packet or not an `I`
+    /// packet. Returns an error if the outer packet is marked CE but the
+    /// inner packet is not ECN-capable, in which case the packet must be
+    /// dropped.
+    ///
+    /// [IETF RFC 6040]: https://tools.ietf.org/html/rfc6040#section-4.2
+    fn decap<I>(self) -> Result<I>
+    where
+        Self: Sized,
+        Self::Envelope: Datalink,
+        I: IpTunnel<Envelope = Self::Envelope>,
+    {
+        let protocol_type = self.protocol_type();
+        ensure!(
+            protocol_of(protocol_type).is_some(),
+            anyhow!("payload is not an IP packet.")
+        );
+        let ecn = self.ecn();
+
+        let mut envelope = self.remove()?;
+        envelope.set_protocol_type(protocol_type)?;
+
+        let mut inner = envelope.parse::<I>()?;
+        let inner_ecn = decap_ecn(ecn, inner.ecn())?;
+        inner.set_ecn(inner_ecn);
+        inner.reconcile();
+
+        Ok(inner)
+    }
+}
+
+impl<E: Datalink> IpTunnel for Ipv4<E> {
+    #[inline]
+    fn dscp(&self) -> u8 {
+        Ipv4::dscp(self)
+    }
+
+    #[inline]
+    fn set_dscp(&mut self, dscp: u8) {
+        Ipv4::set_dscp(self, dscp)
+    }
+
+    #[inline]
+    fn ecn(&self) -> u8 {
+        Ipv4::ecn(self)
+    }
+
+    #[inline]
+    fn set_ecn(&mut self, ecn: u8) {
+        Ipv4::set_ecn(self, ecn)
+    }
+
+    #[inline]
+    fn ttl(&self) -> u8 {
+        Ipv4::ttl(self)
+    }
+
+    #[inline]
+    fn set_ttl(&mut self, ttl: u8) {
+        Ipv4::set_ttl(self, ttl)
+    }
+}
+
+impl<E: Datalink> IpTunnel for Ipv6<E> {
+    #[inline]
+    fn dscp(&self) -> u8 {
+        Ipv6::dscp(self)
+    }
+
+    #[inline]
+    fn set_dscp(&mut self, dscp: u8) {
+        Ipv6::set_dscp(self, dscp)
+    }
+
+    #[inline]
+    fn ecn(&self) -> u8 {
+        Ipv6::ecn(self)
+    }
+
+    #[inline]
+    fn set_ecn(&mut self, ecn: u8) {
+        Ipv6::set_ecn(self, ecn)
+    }
+
+    #[inline]
+    fn ttl(&self) -> u8 {
+        self.hop_limit()
+    }
+
+    #[inline]
+    fn set_ttl(&mut self, ttl: u8) {
+        self.set_hop_limit(ttl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::{Ethernet, Tcp, Udp};
+    use crate::testils::byte_arrays::{IPV4_TCP_PACKET, IPV4_UDP_PACKET, IPV6_TCP_PACKET};
+    use crate::Mbuf;
+
+    // ECN codepoint not defined above.
+    const ECT_0: u8 = 0b10;
+
+    #[test]
+    fn decap_ecn_combinations() {
+        // outer Not-ECT and ECT(0) leave the inner ECN unchanged.
+        for &inner in &[NOT_ECT, ECT_0, ECT_1, CE] {
+            assert_eq!(inner, decap_ecn(NOT_ECT, inner).unwrap());
+            assert_eq!(inner, decap_ecn(ECT_0, inner).unwrap());
+        }
+
+        assert_eq!(NOT_ECT, decap_ecn(ECT_1, NOT_ECT).unwrap());
+        assert_eq!(ECT_1, decap_ecn(ECT_1, ECT_0).unwrap());
+        assert_eq!(ECT_1, decap_ecn(ECT_1, ECT_1).unwrap());
+        assert_eq!(CE, decap_ecn(ECT_1, CE).unwrap());
+
+        assert!(decap_ecn(CE, NOT_ECT).is_err());
+        assert_eq!(CE, decap_ecn(CE, ECT_0).unwrap());
+        assert_eq!(CE, decap_ecn(CE, ECT_1).unwrap());
+        assert_eq!(CE, decap_ecn(CE, CE).unwrap());
+    }
+
+    #[capsule::test]
+    fn encap_ipv4_in_ipv4() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut inner = ethernet.parse::<Ipv4>().unwrap();
+        inner.set_dscp(0x2e);
+        inner.set_ecn(ECT_0);
+
+        let outer = inner.encap::<Ipv4>().unwrap();
+        assert_eq!(EtherTypes::Ipv4, outer.envelope().ether_type());
+        assert_eq!(ProtocolNumbers::Ipv4, outer.protocol());
+        assert_eq!(EtherTypes::Ipv4, outer.protocol_type());
+        assert_eq!(0x2e, outer.dscp());
+        assert_eq!(ECT_0, outer.ecn());
+        assert_eq!(64, outer.ttl());
+        assert_eq!(
+            (IPV4_UDP_PACKET.len() - 14 + 20) as u16,
+            outer.total_length()
+        );
+
+        let inner = outer.parse::<Ipv4<Ipv4>>().unwrap();
+        assert_eq!(254, inner.ttl());
+        assert_eq!("139.133.217.110", inner.src().to_string());
+
+        let udp = inner.parse::<Udp<Ipv4<Ipv4>>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+    }
+
+    #[capsule::test]
+    fn encap_ipv6_in_ipv4() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let inner = ethernet.parse::<Ipv6>().unwrap();
+
+        let outer = inner.encap::<Ipv4>().unwrap();
+        assert_eq!(ProtocolNumbers::Ipv6, outer.protocol());
+        assert_eq!(
+            (IPV6_TCP_PACKET.len() - 14 + 20) as u16,
+            outer.total_length()
+        );
+
+        let inner = outer.parse::<Ipv6<Ipv4>>().unwrap();
+        assert_eq!(1, inner.hop_limit());
+
+        let tcp = inner.parse::<Tcp<Ipv6<Ipv4>>>().unwrap();
+        assert_eq!(36869, tcp.src_port());
+    }
+
+    #[capsule::test]
+    fn encap_with_expiring_hop_limit() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut inner = ethernet.parse::<Ipv6>().unwrap();
+        inner.set_hop_limit(1);
+
+        assert!(inner.encap::<Ipv4>().is_err());
+    }
+
+    #[capsule::test]
+    fn decap_ipv4_in_ipv6() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut inner = ethernet.parse::<Ipv4>().unwrap();
+        inner.set_ecn(ECT_0);
+
+        let mut outer = inner.encap::<Ipv6>().unwrap();
+        assert_eq!(ProtocolNumbers::Ipv4, outer.next_protocol());
+        outer.set_ecn(CE);
+
+        let inner = outer.decap::<Ipv4>().unwrap();
+        assert_eq!(EtherTypes::Ipv4, inner.envelope().ether_type());
+        assert_eq!(CE, inner.ecn());
+        assert_eq!(IPV4_UDP_PACKET.len(), inner.mbuf().data_len());
+
+        let udp = inner.parse::<Udp<Ipv4>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+    }
+
+    #[capsule::test]
+    fn decap_ce_marked_not_ect_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let inner = ethernet.parse::<Ipv6>().unwrap();
+
+        let mut outer = inner.encap::<Ipv4>().unwrap();
+        outer.set_ecn(CE);
+
+        assert!(outer.decap::<Ipv6>().is_err());
+    }
+
+    #[capsule::test]
+    fn decap_wrong_inner_type() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let inner = ethernet.parse::<Ipv6>().unwrap();
+
+        let outer = inner.encap::<Ipv4>().unwrap();
+        assert!(outer.decap::<Ipv4>().is_err());
+    }
+
+    #[capsule::test]
+    fn decap_not_a_tunnel() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.decap::<Ipv4>().is_err());
+    }
+
+    #[capsule::test]
+    fn set_non_ip_protocol_type() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.set_protocol_type(EtherTypes::Arp).is_err());
+        assert_eq!(ProtocolNumbers::Tcp, ipv4.protocol());
+    }
+}
@@ -0,0 +1,324 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::{Ipv4, IPV4_MIN_MTU};
+use crate::packets::{Ethernet, Packet};
+use crate::{ensure, Mbuf};
+use anyhow::{anyhow, Result};
+use thiserror::Error;
+
+/// Error returned when a packet exceeds the MTU but has the don't fragment
+/// flag set.
+///
+/// The packet should be dropped and an ICMP destination unreachable
+/// message with the fragmentation needed code and the next-hop MTU set to
+/// `mtu` sent back to the source, as specified in [IETF RFC 1191].
+///
+/// [IETF RFC 1191]: https://tools.ietf.org/html/rfc1191#section-4
+#[derive(Debug, Error)]
+#[error("Fragmentation needed for MTU {mtu}, but don't fragment flag is set.")]
+pub struct FragmentationNeeded {
+    /// The MTU of the next hop.
+    pub mtu: usize,
+}
+
+impl Ipv4 {
+    /// Fragments the packet into new packets that fit in `mtu`, as
+    /// specified in [IETF RFC 791].
+    ///
+    /// Each fragment is a copy of the Ethernet header, the IPv4 header
+    /// and a slice of the payload. The first fragment has all the options.
+    /// The other fragments have only the options with the copied flag set.
+    /// The identification is preserved, the fragment offset and the more
+    /// fragments flag are set, and the total length and the checksum are
+    /// recomputed. Fragments of an already fragmented packet are placed
+    /// relative to its fragment offset.
+    ///
+    /// Returns an empty `Vec` if the packet already fits in `mtu`. The
+    /// packet itself is never changed. It should be dropped after being
+    /// fragmented.
+    ///
+    /// ```
+    /// let mut batch = batch.filter_map(move |packet| {
+    ///     let v4 = packet.parse::<Ethernet>()?.parse::<Ipv4>()?;
+    ///     let fragments = v4.fragment(1500)?;
+    ///     if fragments.is_empty() {
+    ///         Ok(Either::Keep(v4))
+    ///     } else {
+    ///         tx.transmit(fragments);
+    ///         Ok(Either::Drop(v4.reset()))
+    ///     }
+    /// });
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FragmentationNeeded`] if the packet does not fit in `mtu`
+    /// and the don't fragment flag is set. Returns an error if `mtu` is
+    /// less than [`IPV4_MIN_MTU`], or the total length is inconsistent
+    /// with the buffer. Returns an error if the fragments can't be
+    /// allocated.
+    ///
+    /// [IETF RFC 791]: https://tools.ietf.org/html/rfc791#section-3.2
+    /// [`FragmentationNeeded`]: FragmentationNeeded
+    /// [`IPV4_MIN_MTU`]: IPV4_MIN_MTU
+    pub fn fragment(&self, mtu: usize) -> Result<Vec<Mbuf>> {
+        ensure!(
+            mtu >= IPV4_MIN_MTU,
+            anyhow!("MTU {} must be greater than {}.", mtu, IPV4_MIN_MTU)
+        );
+
+        let total_length = self.total_length() as usize;
+        if total_length <= mtu {
+            return Ok(vec![]);
+        }
+
+        ensure!(!self.dont_fragment(), FragmentationNeeded { mtu });
+
+        let header_end = self.offset() + self.header_len();
+        ensure!(
+            total_length > self.header_len() && self.offset() + total_length <= self.mbuf().len(),
+            anyhow!("invalid total length {}.", total_length)
+        );
+
+        let headers = self.mbuf().read_data_slice::<u8>(0, header_end)?;
+        let headers = unsafe { headers.as_ref() };
+        let mut payload = vec![0; self.offset() + total_length - header_end];
+        self.mbuf().copy_to_slice(header_end, &mut payload)?;
+
+        // the headers of the other fragments, with only the copied options.
+        // the size is taken from the rebuilt header because the padding
+        // and any no operation options are kept.
+        let mut other = Mbuf::from_bytes(headers)?
+            .parse::<Ethernet>()?
+            .parse::<Ipv4>()?;
+        other
+            .options_mut()
+            .retain(|option| option.option_type().copied())?;
+        let other_header_end = other.offset() + other.header_len();
+        let mut other_headers = vec![0; other_header_end];
+        other.mbuf().copy_to_slice(0, &mut other_headers)?;
+
+        let base_offset = self.fragment_offset() as usize * 8;
+        let more_fragments = self.more_fragments();
+
+        let mut fragments = Vec::new();
+        let mut pos = 0;
+        while pos < payload.len() {
+            let (headers, header_end) = if pos == 0 {
+                (headers, header_end)
+            } else {
+                (&other_headers[..], other_header_end)
+            };
+            let header_len = header_end - self.offset();
+            let len = ((mtu - header_len) & !7).min(payload.len() - pos);
+
+            let frag_offset = (base_offset + pos) / 8;
+            ensure!(
+                frag_offset <= 0x1fff,
+                anyhow!("fragment offset {} is out of range.", frag_offset)
+            );
+
+            let mut mbuf = Mbuf::from_bytes(headers)?;
+            mbuf.extend(header_end, len)?;
+            mbuf.copy_from_slice(header_end, &payload[pos..pos + len])?;
+
+            let mut fragment = mbuf.parse::<Ethernet>()?.parse::<Ipv4>()?;
+            fragment.set_fragment_offset(frag_offset as u16);
+            if pos + len < payload.len() || more_fragments {
+                fragment.set_more_fragments();
+            } else {
+                fragment.unset_more_fragments();
+            }
+            fragment.reconcile();
+
+            fragments.push(fragment.reset());
+            pos += len;
+        }
+
+        Ok(fragments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::checksum;
+    use crate::packets::ip::v4::{Ipv4Header, Ipv4Option};
+    use crate::packets::ip::ProtocolNumbers;
+    use crate::testils::{ipv4_udp_packet, PacketExt};
+    use crate::SizeOf;
+    use std::net::Ipv4Addr;
+
+    fn header_checksum(ipv4: &Ipv4) -> u16 {
+        let header = ipv4
+            .mbuf()
+            .read_data_slice::<u8>(ipv4.offset(), ipv4.header_len())
+            .unwrap();
+        checksum::compute(0, unsafe { header.as_ref() })
+    }
+
+    #[capsule::test]
+    fn fragment_ipv4_packet() {
+        let data = (0..2000).map(|i| i as u8).collect::<Vec<_>>();
+        let ipv4 = ipv4_udp_packet(0x1234, &data);
+        let fragments = ipv4.fragment(1500).unwrap();
+        assert_eq!(2, fragments.len());
+        let mut fragments = fragments.into_iter().map(PacketExt::into_v4);
+
+        let first = fragments.next().unwrap();
+        assert_eq!(1500, first.total_length());
+        assert_eq!(0x1234, first.identification());
+        assert_eq!(0, first.fragment_offset());
+        assert!(first.more_fragments());
+        assert_eq!(ProtocolNumbers::Udp, first.protocol());
+        assert_eq!(0, header_checksum(&first));
+
+        let second = fragments.next().unwrap();
+        assert_eq!(540, second.total_length());
+        assert_eq!(0x1234, second.identification());
+        assert_eq!(185, second.fragment_offset());
+        assert!(!second.more_fragments());
+        assert_eq!(0, header_checksum(&second));
+
+        let mut payload = first.payload_to_vec();
+        payload.extend(second.payload_to_vec());
+        assert_eq!(data, payload);
+    }
+
+    #[capsule::test]
+    fn fragment_ipv4_packet_with_options() {
+        let mut ipv4 = ipv4_udp_packet(0x1234, &[0; 200]);
+        ipv4.options_mut()
+            .append(&Ipv4Option::RecordRoute {
+                pointer: 4,
+                route: vec![Ipv4Addr::UNSPECIFIED],
+            })
+            .unwrap();
+        ipv4.options_mut()
+            .append(&Ipv4Option::RouterAlert(0))
+            .unwrap();
+        assert_eq!(32, ipv4.header_len());
+
+        // 64 octets in the first, then 72 and 64 after the record route
+        // option is dropped.
+        let fragments = ipv4.fragment(100).unwrap();
+        assert_eq!(3, fragments.len());
+
+        let lens = [(32, 64, 0), (24, 72, 8), (24, 64, 17)];
+        for (mbuf, &(header_len, len, offset)) in fragments.into_iter().zip(lens.iter()) {
+            let fragment = mbuf.into_v4();
+            assert_eq!(header_len, fragment.header_len());
+            assert_eq!(len, fragment.payload_len());
+            assert_eq!(offset, fragment.fragment_offset());
+            assert_eq!(Some(0), fragment.router_alert().unwrap());
+            assert_eq!(0, header_checksum(&fragment));
+        }
+    }
+
+    #[capsule::test]
+    fn fragment_ipv4_packet_with_no_operation_option() {
+        let data = (0..200).map(|i| i as u8).collect::<Vec<_>>();
+        let mut ipv4 = ipv4_udp_packet(0x1234, &data);
+        ipv4.options_mut()
+            .append(&Ipv4Option::RecordRoute {
+                pointer: 4,
+                route: vec![Ipv4Addr::UNSPECIFIED; 2],
+            })
+            .unwrap();
+        // turns the padding after the record route option into a no
+        // operation option that is kept with the router alert option.
+        let offset = ipv4.offset() + Ipv4Header::size_of() + 11;
+        ipv4.mbuf_mut().write_data_slice(offset, &[1u8]).unwrap();
+        ipv4.options_mut()
+            .append(&Ipv4Option::RouterAlert(0))
+            .unwrap();
+        assert_eq!(36, ipv4.header_len());
+
+        let mtu = 100;
+        let fragments = ipv4.fragment(mtu).unwrap();
+        assert_eq!(3, fragments.len());
+
+        let mut payload = vec![];
+        for (i, mbuf) in fragments.into_iter().enumerate() {
+            let fragment = mbuf.into_v4();
+            assert!(fragment.total_length() as usize <= mtu);
+            assert_eq!(if i == 0 { 36 } else { 28 }, fragment.header_len());
+            assert_eq!(Some(0), fragment.router_alert().unwrap());
+            assert_eq!(0, header_checksum(&fragment));
+            payload.extend(fragment.payload_to_vec());
+        }
+        assert_eq!(data, payload);
+    }
+
+    #[capsule::test]
+    fn fragment_ipv4_fragment() {
+        let mut ipv4 = ipv4_udp_packet(0x1234, &[0; 200]);
+        ipv4.set_fragment_offset(100);
+        ipv4.set_more_fragments();
+        ipv4.reconcile();
+
+        let fragments = ipv4.fragment(124).unwrap();
+        assert_eq!(2, fragments.len());
+        let mut fragments = fragments.into_iter().map(PacketExt::into_v4);
+
+        let first = fragments.next().unwrap();
+        assert_eq!(100, first.fragment_offset());
+        assert!(first.more_fragments());
+
+        // the last fragment keeps the more fragments flag.
+        let last = fragments.next().unwrap();
+        assert_eq!(113, last.fragment_offset());
+        assert!(last.more_fragments());
+    }
+
+    #[capsule::test]
+    fn fragment_jumbo_ipv4_packet() {
+        let data = (0..9000).map(|i| i as u8).collect::<Vec<_>>();
+        let ipv4 = ipv4_udp_packet(0x1234, &data);
+        assert!(ipv4.mbuf().num_segments() > 1);
+
+        let fragments = ipv4.fragment(1500).unwrap();
+        assert_eq!(7, fragments.len());
+
+        let mut payload = vec![];
+        for mbuf in fragments.into_iter() {
+            let fragment = mbuf.into_v4();
+            assert!(fragment.total_length() <= 1500);
+            payload.extend(fragment.payload_to_vec());
+        }
+        assert_eq!(data, payload);
+    }
+
+    #[capsule::test]
+    fn fragment_small_ipv4_packet() {
+        let ipv4 = ipv4_udp_packet(0x1234, &[0; 100]);
+        assert!(ipv4.fragment(1500).unwrap().is_empty());
+        assert!(ipv4.fragment(60).is_err());
+    }
+
+    #[capsule::test]
+    fn fragment_dont_fragment_ipv4_packet() {
+        let mut ipv4 = ipv4_udp_packet(0x1234, &[0; 2000]);
+        ipv4.set_dont_fragment();
+
+        let err = ipv4.fragment(1500).unwrap_err();
+        let err = err.downcast_ref::<FragmentationNeeded>().unwrap();
+        assert_eq!(1500, err.mtu);
+    }
+}
@@ -18,10 +18,17 @@
 
 //! Internet Protocol v4.
 
+mod fragment;
+mod options;
+
+pub use self::fragment::*;
+pub use self::options::*;
+
+use crate::dpdk::BufferError;
 use crate::packets::checksum::{self, PseudoHeader};
-use crate::packets::ip::{IpPacket, ProtocolNumber, DEFAULT_IP_TTL};
+use crate::packets::ip::{tunnel, IpPacket, ProtocolNumber, DEFAULT_IP_TTL};
 use crate::packets::types::u16be;
-use crate::packets::{EtherTypes, Ethernet, Internal, Packet};
+use crate::packets::{Datalink, EtherType, EtherTypes, Ethernet, Internal, Packet};
 use crate::{ensure, SizeOf};
 use anyhow::{anyhow, Result};
 use std::fmt;
@@ -142,13 +149,13 @@ const FLAGS_MF: u16be = u16be(u16::to_be(0b0010_0000_0000_0000));
 /// [IETF RFC 791]: https://tools.ietf.org/html/rfc791#section-3.1
 /// [IETF RFC 2474]: https://tools.ietf.org/html/rfc2474
 /// [IETF RFC 3168]: https://tools.ietf.org/html/rfc3168
-pub struct Ipv4 {
-    envelope: Ethernet,
+pub struct Ipv4<E: Datalink = Ethernet> {
+    envelope: E,
     header: NonNull<Ipv4Header>,
     offset: usize,
 }
 
-impl Ipv4 {
+impl<E: Datalink> Ipv4<E> {
     #[inline]
     fn header(&self) -> &Ipv4Header {
         unsafe { self.header.as_ref() }
@@ -169,13 +176,13 @@ impl Ipv4 {
     /// 32-bit words. This indicates where the data begins.
     #[inline]
     pub fn ihl(&self) -> u8 {
-        self.header().version_ihl & 0x0f
+        self.header().ihl()
     }
 
     #[allow(dead_code)]
     #[inline]
     fn set_ihl(&mut self, ihl: u8) {
-        self.header_mut().version_ihl = (self.header().version_ihl & 0x0f) | (ihl & 0x0f);
+        self.header_mut().set_ihl(ihl);
     }
 
     /// Returns the differentiated services codepoint.
@@ -211,7 +218,7 @@ impl Ipv4 {
 
     /// Sets the length of the packet.
     #[inline]
-    fn set_total_length(&mut self, total_length: u16) {
+    pub(crate) fn set_total_length(&mut self, total_length: u16) {
         self.header_mut().total_length = total_length.into();
     }
 
@@ -322,14 +329,56 @@ impl Ipv4 {
     fn compute_checksum(&mut self) {
         self.set_checksum(0);
 
-        if let Ok(data) = self.mbuf().read_data_slice(self.offset, self.header_len()) {
-            let data = unsafe { data.as_ref() };
-            let checksum = checksum::compute(0, data);
-            self.set_checksum(checksum);
-        } else {
-            // we are reading the entire header, should never run out
-            unreachable!()
+        let data = self.mbuf().data_segments(self.offset, self.header_len());
+        let checksum = checksum::compute_segments(0, data);
+        self.set_checksum(checksum);
+    }
+
+    /// Returns an iterator that iterates through the options in the IPv4
+    /// header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let ipv4 = ethernet.parse::<Ipv4>()?;
+    /// let mut iter = ipv4.options_iter();
+    ///
+    /// while let Some(option) = iter.next()? {
+    ///     println!("{:?}", option);
+    /// }
+    /// ```
+    #[inline]
+    pub fn options_iter(&self) -> Ipv4OptionsIterator<'_> {
+        let start = self.offset + Ipv4Header::size_of();
+        let end = self.offset + self.header_len();
+        Ipv4OptionsIterator::new(self.mbuf(), start, end)
+    }
+
+    /// Returns a mutable accessor to the options in the IPv4 header.
+    ///
+    /// Adding or removing options updates the IHL, the total length and
+    /// the checksum.
+    #[inline]
+    pub fn options_mut(&mut self) -> Ipv4Options<'_> {
+        let header = self.header;
+        let offset = self.offset;
+        Ipv4Options::new(self.mbuf_mut(), header, offset)
+    }
+
+    /// Returns the router alert option value if present.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options are malformed.
+    #[inline]
+    pub fn router_alert(&self) -> Result<Option<u16>> {
+        let mut iter = self.options_iter();
+        while let Some(option) = iter.next()? {
+            if let Ipv4Option::RouterAlert(value) = option {
+                return Ok(Some(value));
+            }
         }
+        Ok(None)
     }
 
     /// Returns the source address.
@@ -357,7 +406,7 @@ impl Ipv4 {
     }
 }
 
-impl fmt::Debug for Ipv4 {
+impl<E: Datalink> fmt::Debug for Ipv4<E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ipv4")
             .field("src", &format!("{}", self.src()))
@@ -380,9 +429,11 @@ impl fmt::Debug for Ipv4 {
     }
 }
 
-impl Packet for Ipv4 {
-    /// The preceding type for an IPv4 packet must be Ethernet.
-    type Envelope = Ethernet;
+impl<E: Datalink> Packet for Ipv4<E> {
+    /// The preceding type for an IPv4 packet is typically Ethernet. It can
+    /// also be a tunneling packet, such as GRE or another IP packet, that
+    /// carries an IPv4 packet.
+    type Envelope = E;
 
     #[inline]
     fn envelope(&self) -> &Self::Envelope {
@@ -401,7 +452,7 @@ impl Packet for Ipv4 {
 
     #[inline]
     fn header_len(&self) -> usize {
-        Ipv4Header::size_of()
+        self.ihl() as usize * 4
     }
 
     #[inline]
@@ -413,20 +464,21 @@ impl Packet for Ipv4 {
         }
     }
 
-    /// Parses the Ethernet's payload as an IPv4 packet.
+    /// Parses the envelope's payload as an IPv4 packet.
     ///
     /// # Errors
     ///
-    /// Returns an error if [`ether_type`] is not set to [`EtherTypes::Ipv4`].
-    /// Returns an error if the payload does not have sufficient data for the
-    /// IPv4 header.
+    /// Returns an error if [`protocol_type`] is not set to
+    /// [`EtherTypes::Ipv4`]. Returns an error if the IHL is less than 5, or
+    /// if the payload does not have sufficient data for the IPv4 header,
+    /// including the options.
     ///
-    /// [`ether_type`]: Ethernet::ether_type
+    /// [`protocol_type`]: Datalink::protocol_type
     /// [`EtherTypes::Ipv4`]: EtherTypes::Ipv4
     #[inline]
     fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
         ensure!(
-            envelope.ether_type() == EtherTypes::Ipv4,
+            envelope.protocol_type() == EtherTypes::Ipv4,
             anyhow!("not an IPv4 packet.")
         );
 
@@ -434,22 +486,33 @@ impl Packet for Ipv4 {
         let offset = envelope.payload_offset();
         let header = mbuf.read_data(offset)?;
 
-        Ok(Ipv4 {
+        let packet = Ipv4 {
             envelope,
             header,
             offset,
-        })
+        };
+
+        ensure!(
+            packet.header_len() >= Ipv4Header::size_of(),
+            anyhow!("invalid IPv4 IHL.")
+        );
+        ensure!(
+            packet.len() >= packet.header_len(),
+            BufferError::OutOfBuffer(packet.header_len(), packet.len())
+        );
+
+        Ok(packet)
     }
 
-    /// Prepends an IPv4 packet to the beginning of the Ethernet's payload.
+    /// Prepends an IPv4 packet to the beginning of the envelope's payload.
     ///
-    /// [`ether_type`] is set to [`EtherTypes::Ipv4`].
+    /// [`protocol_type`] is set to [`EtherTypes::Ipv4`].
     ///
     /// # Errors
     ///
     /// Returns an error if the buffer does not have enough free space.
     ///
-    /// [`ether_type`]: Ethernet::ether_type
+    /// [`protocol_type`]: Datalink::protocol_type
     /// [`EtherTypes::Ipv4`]: EtherTypes::Ipv4
     #[inline]
     fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
@@ -459,7 +522,7 @@ impl Packet for Ipv4 {
         mbuf.extend(offset, Ipv4Header::size_of())?;
         let header = mbuf.write_data(offset, &Ipv4Header::default())?;
 
-        envelope.set_ether_type(EtherTypes::Ipv4);
+        envelope.set_protocol_type(EtherTypes::Ipv4)?;
 
         Ok(Ipv4 {
             envelope,
@@ -478,7 +541,8 @@ impl Packet for Ipv4 {
     ///
     /// * [`total_length`] is set to the total length of the header and the
     /// payload.
-    /// * [`checksum`] is computed based on the IPv4 header.
+    /// * [`checksum`] is computed based on the IPv4 header, including the
+    /// options.
     ///
     /// [`total_length`]: Ipv4::total_length
     /// [`checksum`]: Ipv4::checksum
@@ -489,7 +553,34 @@ impl Packet for Ipv4 {
     }
 }
 
-impl IpPacket for Ipv4 {
+impl<E: Datalink> Datalink for Ipv4<E> {
+    /// Returns [`EtherTypes::Ipv4`] or [`EtherTypes::Ipv6`] if the payload
+    /// is an encapsulated IPv4 or IPv6 packet. Otherwise returns an
+    /// unassigned EtherType of 0.
+    ///
+    /// [`EtherTypes::Ipv4`]: EtherTypes::Ipv4
+    /// [`EtherTypes::Ipv6`]: EtherTypes::Ipv6
+    #[inline]
+    fn protocol_type(&self) -> EtherType {
+        tunnel::ether_type_of(self.protocol())
+    }
+
+    /// Sets the protocol to the encapsulation protocol number for an
+    /// IPv4 or IPv6 payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ether_type` is neither IPv4 nor IPv6.
+    #[inline]
+    fn set_protocol_type(&mut self, ether_type: EtherType) -> Result<()> {
+        let protocol = tunnel::protocol_of(ether_type)
+            .ok_or_else(|| anyhow!("{} can't be carried in an IP packet.", ether_type))?;
+        self.set_protocol(protocol);
+        Ok(())
+    }
+}
+
+impl<E: Datalink> IpPacket for Ipv4<E> {
     #[inline]
     fn next_protocol(&self) -> ProtocolNumber {
         self.protocol()
@@ -591,6 +682,18 @@ struct Ipv4Header {
     dst: Ipv4Addr,
 }
 
+impl Ipv4Header {
+    #[inline]
+    fn ihl(&self) -> u8 {
+        self.version_ihl & 0x0f
+    }
+
+    #[inline]
+    fn set_ihl(&mut self, ihl: u8) {
+        self.version_ihl = (self.version_ihl & 0xf0) | (ihl & 0x0f);
+    }
+}
+
 impl Default for Ipv4Header {
     fn default() -> Ipv4Header {
         Ipv4Header {
@@ -612,6 +715,7 @@ impl Default for Ipv4Header {
 mod tests {
     use super::*;
     use crate::packets::ip::ProtocolNumbers;
+    use crate::packets::Udp4;
     use crate::testils::byte_arrays::{IPV4_UDP_PACKET, IPV6_TCP_PACKET};
     use crate::Mbuf;
 
@@ -658,6 +762,8 @@ mod tests {
 
         // Fields
         ipv4.set_ihl(ipv4.ihl());
+        assert_eq!(4, ipv4.version());
+        assert_eq!(5, ipv4.ihl());
 
         // Flags
         assert_eq!(true, ipv4.dont_fragment());
@@ -726,4 +832,74 @@ mod tests {
         ipv4.reconcile_all();
         assert_eq!(expected, ipv4.checksum());
     }
+
+    #[capsule::test]
+    fn parse_ipv4_packet_with_options() {
+        let packet = Mbuf::from_bytes(&IPV4_ROUTER_ALERT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert_eq!(6, ipv4.ihl());
+        assert_eq!(24, ipv4.header_len());
+        assert_eq!(Some(0), ipv4.router_alert().unwrap());
+
+        // the options are not parsed as the UDP header.
+        let udp = ipv4.parse::<Udp4>().unwrap();
+        assert_eq!(53, udp.src_port());
+        assert_eq!(53, udp.dst_port());
+    }
+
+    #[capsule::test]
+    fn parse_ipv4_packet_with_malformed_options() {
+        let mut bytes = IPV4_ROUTER_ALERT_PACKET;
+        // the router alert option length runs past the header.
+        bytes[35] = 0x08;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.router_alert().is_err());
+    }
+
+    #[capsule::test]
+    fn parse_ipv4_packet_with_invalid_ihl() {
+        let mut bytes = IPV4_UDP_PACKET;
+        // ihl = 4
+        bytes[14] = 0x44;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        assert!(ethernet.parse::<Ipv4>().is_err());
+
+        let mut bytes = IPV4_UDP_PACKET;
+        // ihl = 15, runs past the end of the buffer
+        bytes[14] = 0x4f;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        assert!(ethernet.parse::<Ipv4>().is_err());
+    }
+
+    /// IPv4 UDP packet with a router alert option.
+    #[rustfmt::skip]
+    const IPV4_ROUTER_ALERT_PACKET: [u8; 50] = [
+        // ** ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x08, 0x00,
+        // ** IPv4 header
+        // ihl = 6
+        0x46, 0x00,
+        // total length
+        0x00, 0x24,
+        0x00, 0x01, 0x00, 0x00,
+        0x01, 0x11, 0x00, 0x00,
+        0x0a, 0x00, 0x00, 0x01,
+        0x0a, 0x00, 0x00, 0x02,
+        // router alert
+        0x94, 0x04, 0x00, 0x00,
+        // ** UDP header
+        0x00, 0x35, 0x00, 0x35,
+        0x00, 0x0c, 0x00, 0x00,
+        // ** payload
+        0x01, 0x02, 0x03, 0x04,
+    ];
 }
@@ -0,0 +1,811 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::Ipv4Header;
+use crate::packets::checksum;
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::ptr::NonNull;
+
+/// The maximum length of the IPv4 options in octets.
+///
+/// The IHL field is 4 bits, so the header can be at most 15 32-bit words,
+/// or 60 octets. 20 of which are taken by the fixed header.
+pub const IPV4_MAX_OPTIONS_LEN: usize = 40;
+
+/// [IANA] assigned IPv4 option type.
+///
+/// The type octet is made of the copied flag, the option class and the
+/// option number. A list of supported types is under [`Ipv4OptionTypes`].
+///
+/// [IANA]: https://www.iana.org/assignments/ip-parameters/ip-parameters.xhtml#ip-parameters-1
+/// [`Ipv4OptionTypes`]: Ipv4OptionTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct Ipv4OptionType(pub u8);
+
+impl Ipv4OptionType {
+    /// Returns whether the option must be copied into all fragments on
+    /// fragmentation.
+    #[inline]
+    pub fn copied(self) -> bool {
+        self.0 & 0x80 != 0
+    }
+}
+
+/// Supported IPv4 option types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod Ipv4OptionTypes {
+    use super::Ipv4OptionType;
+
+    /// End of option list.
+    pub const EndOfOptionList: Ipv4OptionType = Ipv4OptionType(0);
+
+    /// No operation.
+    pub const NoOperation: Ipv4OptionType = Ipv4OptionType(1);
+
+    /// Record route.
+    pub const RecordRoute: Ipv4OptionType = Ipv4OptionType(7);
+
+    /// Internet timestamp.
+    pub const Timestamp: Ipv4OptionType = Ipv4OptionType(68);
+
+    /// Router alert.
+    pub const RouterAlert: Ipv4OptionType = Ipv4OptionType(148);
+}
+
+impl fmt::Display for Ipv4OptionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Ipv4OptionTypes::EndOfOptionList => "End of Option List".to_string(),
+                Ipv4OptionTypes::NoOperation => "No Operation".to_string(),
+                Ipv4OptionTypes::RecordRoute => "Record Route".to_string(),
+                Ipv4OptionTypes::Timestamp => "Timestamp".to_string(),
+                Ipv4OptionTypes::RouterAlert => "Router Alert".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// Timestamp option flag for timestamps only.
+pub const IPV4_TS_ONLY: u8 = 0;
+
+/// Timestamp option flag for each timestamp preceded by the internet
+/// address of the registering entity.
+pub const IPV4_TS_AND_ADDR: u8 = 1;
+
+/// Timestamp option flag for timestamps registered by prespecified
+/// internet addresses.
+pub const IPV4_TS_PRESPEC: u8 = 3;
+
+/// An IPv4 header option.
+///
+/// Options are read out of and written into the buffer by value. The
+/// single octet end of option list and no operation options are padding
+/// and are never returned by the options iterator.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Ipv4Option {
+    /// Record route defined in [IETF RFC 791].
+    ///
+    /// `pointer` is the octet offset, relative to the option, of the next
+    /// area to store a route address.
+    ///
+    /// [IETF RFC 791]: https://tools.ietf.org/html/rfc791#page-20
+    RecordRoute {
+        /// Pointer to the next free slot.
+        pointer: u8,
+        /// The route data area.
+        route: Vec<Ipv4Addr>,
+    },
+
+    /// Internet timestamp defined in [IETF RFC 791].
+    ///
+    /// When `flag` is [`IPV4_TS_ONLY`], entries do not carry an address.
+    /// Otherwise every entry is an address and timestamp pair.
+    ///
+    /// [IETF RFC 791]: https://tools.ietf.org/html/rfc791#page-22
+    /// [`IPV4_TS_ONLY`]: IPV4_TS_ONLY
+    Timestamp {
+        /// Pointer to the next free slot.
+        pointer: u8,
+        /// Number of modules that could not register timestamps.
+        overflow: u8,
+        /// The timestamp format flag.
+        flag: u8,
+        /// The timestamp data area.
+        entries: Vec<(Option<Ipv4Addr>, u32)>,
+    },
+
+    /// Router alert defined in [IETF RFC 2113].
+    ///
+    /// [IETF RFC 2113]: https://tools.ietf.org/html/rfc2113
+    RouterAlert(u16),
+
+    /// Option with a type not known to the parser and its raw data,
+    /// excluding the type and length octets.
+    Unknown(Ipv4OptionType, Vec<u8>),
+}
+
+impl Ipv4Option {
+    /// Returns the option type.
+    pub fn option_type(&self) -> Ipv4OptionType {
+        match self {
+            Ipv4Option::RecordRoute { .. } => Ipv4OptionTypes::RecordRoute,
+            Ipv4Option::Timestamp { .. } => Ipv4OptionTypes::Timestamp,
+            Ipv4Option::RouterAlert(_) => Ipv4OptionTypes::RouterAlert,
+            Ipv4Option::Unknown(option_type, _) => *option_type,
+        }
+    }
+
+    /// Returns the length of the option in octets, including the type and
+    /// length octets.
+    pub fn length(&self) -> usize {
+        match self {
+            Ipv4Option::RecordRoute { route, .. } => 3 + route.len() * 4,
+            Ipv4Option::Timestamp { flag, entries, .. } => {
+                if *flag == IPV4_TS_ONLY {
+                    4 + entries.len() * 4
+                } else {
+                    4 + entries.len() * 8
+                }
+            }
+            Ipv4Option::RouterAlert(_) => 4,
+            Ipv4Option::Unknown(_, data) => 2 + data.len(),
+        }
+    }
+
+    /// Decodes the option data, excluding the type and length octets.
+    fn decode(option_type: Ipv4OptionType, data: &[u8]) -> Result<Self> {
+        let option = match option_type {
+            Ipv4OptionTypes::RecordRoute => {
+                ensure!(
+                    !data.is_empty() && (data.len() - 1) % 4 == 0,
+                    anyhow!("invalid record route option length.")
+                );
+                Ipv4Option::RecordRoute {
+                    pointer: data[0],
+                    route: data[1..].chunks_exact(4).map(read_addr).collect(),
+                }
+            }
+            Ipv4OptionTypes::Timestamp => {
+                ensure!(data.len() >= 2, anyhow!("invalid timestamp option length."));
+                let flag = data[1] & 0x0f;
+                let entry_len = if flag == IPV4_TS_ONLY { 4 } else { 8 };
+                let entries = data[2..].chunks_exact(entry_len);
+                ensure!(
+                    entries.remainder().is_empty(),
+                    anyhow!("invalid timestamp option length.")
+                );
+                Ipv4Option::Timestamp {
+                    pointer: data[0],
+                    overflow: data[1] >> 4,
+                    flag,
+                    entries: entries
+                        .map(|entry| {
+                            if flag == IPV4_TS_ONLY {
+                                (None, read_u32(entry))
+                            } else {
+                                (Some(read_addr(&entry[..4])), read_u32(&entry[4..]))
+                            }
+                        })
+                        .collect(),
+                }
+            }
+            Ipv4OptionTypes::RouterAlert => {
+                ensure!(
+                    data.len() == 2,
+                    anyhow!("invalid router alert option length.")
+                );
+                Ipv4Option::RouterAlert(u16::from_be_bytes([data[0], data[1]]))
+            }
+            _ => Ipv4Option::Unknown(option_type, data.to_vec()),
+        };
+
+        Ok(option)
+    }
+
+    /// Encodes the option including the type and length octets.
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.length());
+        bytes.push(self.option_type().0);
+        bytes.push(self.length() as u8);
+
+        match self {
+            Ipv4Option::RecordRoute { pointer, route } => {
+                bytes.push(*pointer);
+                for addr in route {
+                    bytes.extend_from_slice(&addr.octets());
+                }
+            }
+            Ipv4Option::Timestamp {
+                pointer,
+                overflow,
+                flag,
+                entries,
+            } => {
+                bytes.push(*pointer);
+                bytes.push((overflow << 4) | (flag & 0x0f));
+                for (addr, timestamp) in entries {
+                    if *flag != IPV4_TS_ONLY {
+                        let addr = addr.unwrap_or(Ipv4Addr::UNSPECIFIED);
+                        bytes.extend_from_slice(&addr.octets());
+                    }
+                    bytes.extend_from_slice(&timestamp.to_be_bytes());
+                }
+            }
+            Ipv4Option::RouterAlert(value) => bytes.extend_from_slice(&value.to_be_bytes()),
+            Ipv4Option::Unknown(_, data) => bytes.extend_from_slice(data),
+        }
+
+        bytes
+    }
+}
+
+#[inline]
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[inline]
+fn read_addr(bytes: &[u8]) -> Ipv4Addr {
+    Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])
+}
+
+#[inline]
+fn read_u8(mbuf: &Mbuf, offset: usize) -> Result<u8> {
+    let value = mbuf.read_data::<u8>(offset)?;
+    Ok(unsafe { *value.as_ref() })
+}
+
+/// Reads the type and the length in octets of the option at offset. The
+/// end of option list and no operation options have a length of 1.
+///
+/// # Errors
+///
+/// Returns an error if the option length is less than 2 or the option
+/// runs past the end offset.
+fn read_type_and_length(mbuf: &Mbuf, offset: usize, end: usize) -> Result<(Ipv4OptionType, usize)> {
+    let option_type = Ipv4OptionType(read_u8(mbuf, offset)?);
+
+    match option_type {
+        Ipv4OptionTypes::EndOfOptionList | Ipv4OptionTypes::NoOperation => Ok((option_type, 1)),
+        _ => {
+            ensure!(
+                offset + 1 < end,
+                anyhow!("truncated IPv4 option {}.", option_type)
+            );
+            let length = read_u8(mbuf, offset + 1)? as usize;
+            ensure!(
+                length >= 2 && offset + length <= end,
+                anyhow!("invalid IPv4 option {} length {}.", option_type, length)
+            );
+            Ok((option_type, length))
+        }
+    }
+}
+
+/// Reads the option of `option_type` and `length` at offset.
+fn read_option(
+    mbuf: &Mbuf,
+    offset: usize,
+    option_type: Ipv4OptionType,
+    length: usize,
+) -> Result<Ipv4Option> {
+    let data = if length > 2 {
+        let data = mbuf.read_data_slice::<u8>(offset + 2, length - 2)?;
+        unsafe { &*data.as_ptr() }
+    } else {
+        &[]
+    };
+
+    Ipv4Option::decode(option_type, data)
+}
+
+/// An iterator that iterates through the options in the IPv4 header.
+pub struct Ipv4OptionsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> Ipv4OptionsIterator<'a> {
+    pub(super) fn new(mbuf: &'a Mbuf, offset: usize, end: usize) -> Self {
+        Ipv4OptionsIterator { mbuf, offset, end }
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Padding is skipped and the iteration finishes at the end of option
+    /// list marker or at the end of the header, whichever comes first.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<Ipv4Option>> {
+        while self.offset < self.end {
+            let (option_type, length) = read_type_and_length(self.mbuf, self.offset, self.end)?;
+
+            match option_type {
+                Ipv4OptionTypes::EndOfOptionList => self.offset = self.end,
+                Ipv4OptionTypes::NoOperation => self.offset += length,
+                _ => {
+                    let option = read_option(self.mbuf, self.offset, option_type, length)?;
+                    // advances the offset to the next option
+                    self.offset += length;
+                    return Ok(Some(option));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl fmt::Debug for Ipv4OptionsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ipv4OptionsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// Options in the IPv4 header.
+///
+/// Adding or removing options resizes the header. The options are always
+/// padded with end of option list octets to a 32-bit boundary. The IHL,
+/// the total length and the header checksum are updated after every
+/// change.
+pub struct Ipv4Options<'a> {
+    mbuf: &'a mut Mbuf,
+    header: NonNull<Ipv4Header>,
+    offset: usize,
+}
+
+impl<'a> Ipv4Options<'a> {
+    pub(super) fn new(mbuf: &'a mut Mbuf, header: NonNull<Ipv4Header>, offset: usize) -> Self {
+        Ipv4Options {
+            mbuf,
+            header,
+            offset,
+        }
+    }
+
+    #[inline]
+    fn header(&self) -> &Ipv4Header {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut Ipv4Header {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the buffer offset where the options begin.
+    #[inline]
+    fn start_offset(&self) -> usize {
+        self.offset + Ipv4Header::size_of()
+    }
+
+    /// Returns the buffer offset where the IPv4 header ends.
+    #[inline]
+    fn end_offset(&self) -> usize {
+        self.offset + self.header().ihl() as usize * 4
+    }
+
+    /// Returns the length of the options in use, not including the end of
+    /// option list marker and the padding that follows.
+    fn used_len(&self) -> Result<usize> {
+        let start = self.start_offset();
+        let end = self.end_offset();
+        let mut offset = start;
+
+        while offset < end {
+            let (option_type, length) = read_type_and_length(self.mbuf, offset, end)?;
+            if option_type == Ipv4OptionTypes::EndOfOptionList {
+                break;
+            }
+            offset += length;
+        }
+
+        Ok(offset - start)
+    }
+
+    /// Resizes the options from `current` length to fit `used` octets of
+    /// options, pads the rest with end of option list octets, then fixes
+    /// the IHL and the total length.
+    fn pad(&mut self, current: usize, used: usize) -> Result<()> {
+        let start = self.start_offset();
+        let padded = (used + 3) & !3;
+
+        ensure!(
+            padded <= IPV4_MAX_OPTIONS_LEN,
+            anyhow!("IPv4 options exceed {} octets.", IPV4_MAX_OPTIONS_LEN)
+        );
+
+        let total_length = u16::from(self.header().total_length) as usize;
+        let total_length = (total_length + padded)
+            .checked_sub(current)
+            .ok_or_else(|| anyhow!("invalid IPv4 total length {}.", total_length))?;
+
+        if padded > current {
+            self.mbuf.extend(start + current, padded - current)?;
+        } else if padded < current {
+            self.mbuf.shrink(start + padded, current - padded)?;
+        }
+
+        if padded > used {
+            self.mbuf
+                .write_data_slice(start + used, &vec![0u8; padded - used])?;
+        }
+
+        let header_len = Ipv4Header::size_of() + padded;
+        self.header_mut().set_ihl((header_len / 4) as u8);
+        self.header_mut().total_length = (total_length as u16).into();
+
+        Ok(())
+    }
+
+    /// Recomputes the header checksum.
+    fn compute_checksum(&mut self) -> Result<()> {
+        self.header_mut().checksum = 0.into();
+        let header_len = self.end_offset() - self.offset;
+        let data = self.mbuf.read_data_slice::<u8>(self.offset, header_len)?;
+        let checksum = checksum::compute(0, unsafe { data.as_ref() });
+        self.header_mut().checksum = checksum.into();
+        Ok(())
+    }
+
+    /// Returns an iterator to read the options.
+    #[inline]
+    pub fn iter(&self) -> Ipv4OptionsIterator<'_> {
+        Ipv4OptionsIterator::new(self.mbuf, self.start_offset(), self.end_offset())
+    }
+
+    /// Appends a new option at the end of the options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options would exceed [`IPV4_MAX_OPTIONS_LEN`]
+    /// or the buffer does not have enough free space.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut ipv4 = ethernet.parse::<Ipv4>()?;
+    /// let mut options = ipv4.options_mut();
+    /// options.append(&Ipv4Option::RouterAlert(0))?;
+    /// ```
+    ///
+    /// [`IPV4_MAX_OPTIONS_LEN`]: IPV4_MAX_OPTIONS_LEN
+    pub fn append(&mut self, option: &Ipv4Option) -> Result<()> {
+        let bytes = option.encode();
+        let used = self.used_len()?;
+
+        ensure!(
+            used + bytes.len() <= IPV4_MAX_OPTIONS_LEN,
+            anyhow!("IPv4 options exceed {} octets.", IPV4_MAX_OPTIONS_LEN)
+        );
+
+        let current = self.end_offset() - self.start_offset();
+        self.pad(current, used + bytes.len())?;
+
+        let offset = self.start_offset() + used;
+        self.mbuf.write_data_slice(offset, &bytes)?;
+
+        self.compute_checksum()
+    }
+
+    /// Removes all options of `option_type`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut ipv4 = ethernet.parse::<Ipv4>()?;
+    /// ipv4.options_mut().remove(Ipv4OptionTypes::RecordRoute)?;
+    /// ```
+    #[inline]
+    pub fn remove(&mut self, option_type: Ipv4OptionType) -> Result<()> {
+        self.retain(|option| option.option_type() != option_type)
+    }
+
+    /// Removes all the options, shrinking the header back to its fixed
+    /// size.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the total length is less than the header
+    /// length.
+    #[inline]
+    pub fn clear(&mut self) -> Result<()> {
+        let current = self.end_offset() - self.start_offset();
+        self.pad(current, 0)?;
+        self.compute_checksum()
+    }
+
+    /// Retains only the options specified by the predicate.
+    ///
+    /// In other words, remove all options `o` such that `f(o)` returns false.
+    /// Padding is preserved. If an error occurs, all removals done prior to
+    /// the error cannot be undone.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the total length is less than the header
+    /// length, or if the options are malformed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut ipv4 = ethernet.parse::<Ipv4>()?;
+    /// let mut options = ipv4.options_mut();
+    /// options.retain(|option| option.option_type().copied())?;
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&Ipv4Option) -> bool,
+    {
+        let start = self.start_offset();
+        let mut end = self.end_offset();
+        let mut offset = start;
+
+        let total_length = u16::from(self.header().total_length) as usize;
+        ensure!(
+            total_length >= end - self.offset,
+            anyhow!("invalid IPv4 total length {}.", total_length)
+        );
+
+        while offset < end {
+            let (option_type, length) = read_type_and_length(self.mbuf, offset, end)?;
+
+            match option_type {
+                Ipv4OptionTypes::EndOfOptionList => break,
+                Ipv4OptionTypes::NoOperation => offset += length,
+                _ => {
+                    let option = read_option(self.mbuf, offset, option_type, length)?;
+                    if f(&option) {
+                        offset += length;
+                    } else {
+                        self.mbuf.shrink(offset, length)?;
+                        end -= length;
+                    }
+                }
+            }
+        }
+
+        // the total length still includes the removed options.
+        let removed = self.end_offset() - end;
+        self.header_mut().total_length = ((total_length - removed) as u16).into();
+
+        self.pad(end - start, offset - start)?;
+        self.compute_checksum()
+    }
+}
+
+impl fmt::Debug for Ipv4Options<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ipv4Options")
+            .field("offset", &self.start_offset())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::ip::ProtocolNumbers;
+    use crate::packets::{Ethernet, Packet, Udp4};
+    use crate::testils::byte_arrays::IPV4_UDP_PACKET;
+
+    #[test]
+    fn ipv4_option_type_to_string() {
+        assert_eq!("Router Alert", Ipv4OptionTypes::RouterAlert.to_string());
+        assert_eq!("130", Ipv4OptionType(130).to_string());
+    }
+
+    #[test]
+    fn ipv4_option_type_copied() {
+        assert!(Ipv4OptionTypes::RouterAlert.copied());
+        assert!(!Ipv4OptionTypes::RecordRoute.copied());
+        assert!(!Ipv4OptionTypes::Timestamp.copied());
+    }
+
+    #[test]
+    fn encode_and_decode_ipv4_options() {
+        let options = [
+            Ipv4Option::RecordRoute {
+                pointer: 8,
+                route: vec![Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::UNSPECIFIED],
+            },
+            Ipv4Option::Timestamp {
+                pointer: 9,
+                overflow: 1,
+                flag: IPV4_TS_ONLY,
+                entries: vec![(None, 1000), (None, 0)],
+            },
+            Ipv4Option::Timestamp {
+                pointer: 13,
+                overflow: 0,
+                flag: IPV4_TS_AND_ADDR,
+                entries: vec![(Some(Ipv4Addr::new(10, 0, 0, 1)), 1000)],
+            },
+            Ipv4Option::RouterAlert(0),
+            Ipv4Option::Unknown(Ipv4OptionType(130), vec![0; 9]),
+        ];
+
+        for option in options.iter() {
+            let bytes = option.encode();
+            assert_eq!(option.length(), bytes.len());
+            assert_eq!(option.option_type().0, bytes[0]);
+            assert_eq!(option.length() as u8, bytes[1]);
+
+            let decoded = Ipv4Option::decode(option.option_type(), &bytes[2..]).unwrap();
+            assert_eq!(*option, decoded);
+        }
+
+        assert!(Ipv4Option::decode(Ipv4OptionTypes::RouterAlert, &[0]).is_err());
+        assert!(Ipv4Option::decode(Ipv4OptionTypes::RecordRoute, &[4, 0, 0]).is_err());
+    }
+
+    #[capsule::test]
+    fn iterate_ipv4_options() {
+        let packet = Mbuf::from_bytes(&IPV4_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        let mut iter = ipv4.options_iter();
+        assert_eq!(Some(Ipv4Option::RouterAlert(0)), iter.next().unwrap());
+        assert_eq!(
+            Some(Ipv4Option::RecordRoute {
+                pointer: 4,
+                route: vec![Ipv4Addr::UNSPECIFIED],
+            }),
+            iter.next().unwrap()
+        );
+        assert_eq!(None, iter.next().unwrap());
+    }
+
+    #[capsule::test]
+    fn append_ipv4_option() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let total_length = ipv4.total_length();
+
+        let mut options = ipv4.options_mut();
+        assert!(options.append(&Ipv4Option::RouterAlert(0)).is_ok());
+
+        assert_eq!(6, ipv4.ihl());
+        assert_eq!(total_length + 4, ipv4.total_length());
+        assert_eq!(Some(0), ipv4.router_alert().unwrap());
+
+        // the checksum is already up to date.
+        let checksum = ipv4.checksum();
+        ipv4.reconcile();
+        assert_eq!(checksum, ipv4.checksum());
+
+        // the payload is still intact.
+        let udp = ipv4.parse::<Udp4>().unwrap();
+        assert_eq!(39376, udp.src_port());
+        assert_eq!(1087, udp.dst_port());
+    }
+
+    #[capsule::test]
+    fn append_ipv4_option_exceeds_max_len() {
+        let packet = Mbuf::from_bytes(&IPV4_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        let mut options = ipv4.options_mut();
+        let record_route = Ipv4Option::RecordRoute {
+            pointer: 4,
+            route: vec![Ipv4Addr::UNSPECIFIED; 8],
+        };
+        assert!(options.append(&record_route).is_err());
+
+        // options are left untouched
+        assert_eq!(8, ipv4.ihl());
+    }
+
+    #[capsule::test]
+    fn remove_ipv4_option() {
+        let packet = Mbuf::from_bytes(&IPV4_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let total_length = ipv4.total_length();
+
+        let mut options = ipv4.options_mut();
+        assert!(options.remove(Ipv4OptionTypes::RecordRoute).is_ok());
+
+        assert_eq!(6, ipv4.ihl());
+        assert_eq!(total_length - 8, ipv4.total_length());
+        assert_eq!(Some(0), ipv4.router_alert().unwrap());
+
+        let checksum = ipv4.checksum();
+        ipv4.reconcile();
+        assert_eq!(checksum, ipv4.checksum());
+
+        assert_eq!(ProtocolNumbers::Udp, ipv4.protocol());
+        assert!(ipv4.parse::<Udp4>().is_ok());
+    }
+
+    #[capsule::test]
+    fn clear_ipv4_options() {
+        let packet = Mbuf::from_bytes(&IPV4_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let total_length = ipv4.total_length();
+
+        assert!(ipv4.options_mut().clear().is_ok());
+
+        assert_eq!(5, ipv4.ihl());
+        assert_eq!(total_length - 12, ipv4.total_length());
+        assert_eq!(None, ipv4.options_iter().next().unwrap());
+    }
+
+    #[capsule::test]
+    fn remove_ipv4_options_with_invalid_total_length() {
+        let packet = Mbuf::from_bytes(&IPV4_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        ipv4.set_total_length(0);
+
+        assert!(ipv4
+            .options_mut()
+            .remove(Ipv4OptionTypes::RecordRoute)
+            .is_err());
+        assert!(ipv4.options_mut().clear().is_err());
+        assert_eq!(8, ipv4.ihl());
+    }
+
+    /// IPv4 UDP packet with router alert and record route options.
+    #[rustfmt::skip]
+    const IPV4_OPTIONS_PACKET: [u8; 58] = [
+        // ** ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x08, 0x00,
+        // ** IPv4 header
+        // ihl = 8
+        0x48, 0x00,
+        // total length
+        0x00, 0x2c,
+        0x00, 0x01, 0x00, 0x00,
+        0x01, 0x11, 0x00, 0x00,
+        0x0a, 0x00, 0x00, 0x01,
+        0x0a, 0x00, 0x00, 0x02,
+        // router alert
+        0x94, 0x04, 0x00, 0x00,
+        // record route
+        0x07, 0x07, 0x04, 0x00, 0x00, 0x00, 0x00,
+        // end of option list
+        0x00,
+        // ** UDP header
+        0x00, 0x35, 0x00, 0x35,
+        0x00, 0x0c, 0x00, 0x00,
+        // ** payload
+        0x01, 0x02, 0x03, 0x04,
+    ];
+}
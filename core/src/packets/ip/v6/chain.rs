@@ -0,0 +1,548 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::checksum::PseudoHeader;
+use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::ip::{IpPacket, ProtocolNumber, ProtocolNumbers};
+use crate::packets::{Internal, Packet};
+use crate::{ensure, Mbuf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::IpAddr;
+use std::ptr::NonNull;
+
+/// Returns whether the protocol number identifies an IPv6 extension header
+/// the chain walker knows how to step over.
+#[inline]
+pub fn is_extension_header(protocol: ProtocolNumber) -> bool {
+    matches!(
+        protocol,
+        ProtocolNumbers::Ipv6HopByHop
+            | ProtocolNumbers::Ipv6Route
+            | ProtocolNumbers::Ipv6Frag
+            | ProtocolNumbers::Ipv6Opts
+            | ProtocolNumbers::Ah
+    )
+}
+
+/// An extension header found while walking the IPv6 header chain.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ExtensionHeader {
+    /// The protocol number identifying the extension header.
+    pub protocol: ProtocolNumber,
+    /// The buffer offset where the extension header begins.
+    pub offset: usize,
+    /// The length of the extension header in octets.
+    pub len: usize,
+    /// The protocol number of the header immediately follows.
+    pub next_header: ProtocolNumber,
+}
+
+#[inline]
+fn read_u8(mbuf: &Mbuf, offset: usize) -> Result<u8> {
+    let value = mbuf.read_data::<u8>(offset)?;
+    Ok(unsafe { *value.as_ref() })
+}
+
+/// An iterator that walks the IPv6 extension header chain.
+///
+/// The walk stops at the first header that is not an extension header,
+/// which is the upper-layer header, or at a fragment header with a nonzero
+/// fragment offset, because the data following it is not a header.
+pub struct ExtensionHeadersIterator<'a> {
+    mbuf: &'a Mbuf,
+    next_header: ProtocolNumber,
+    offset: usize,
+}
+
+impl<'a> ExtensionHeadersIterator<'a> {
+    /// Creates an iterator starting at `offset` with a header of
+    /// `next_header` type.
+    pub(crate) fn new(mbuf: &'a Mbuf, next_header: ProtocolNumber, offset: usize) -> Self {
+        ExtensionHeadersIterator {
+            mbuf,
+            next_header,
+            offset,
+        }
+    }
+
+    /// Returns the protocol number of the header at the current position
+    /// of the walk. Once the iteration is finished, this is the upper-layer
+    /// protocol.
+    #[inline]
+    pub fn next_header(&self) -> ProtocolNumber {
+        self.next_header
+    }
+
+    /// Returns the buffer offset of the current position of the walk. Once
+    /// the iteration is finished, this is where the upper-layer header
+    /// begins.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns whether the current position is a fragment header that is
+    /// not the first fragment.
+    fn at_non_first_fragment(&self) -> Result<bool> {
+        if self.next_header == ProtocolNumbers::Ipv6Frag {
+            let frag_res_m = self.mbuf.read_data::<[u8; 2]>(self.offset + 2)?;
+            let frag_res_m = u16::from_be_bytes(unsafe { *frag_res_m.as_ref() });
+            Ok(frag_res_m >> 3 != 0)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<ExtensionHeader>> {
+        if !is_extension_header(self.next_header) || self.at_non_first_fragment()? {
+            return Ok(None);
+        }
+
+        let next_header = ProtocolNumber::new(read_u8(self.mbuf, self.offset)?);
+        let len = match self.next_header {
+            ProtocolNumbers::Ipv6Frag => 8,
+            // the AH length is in 4-octet units, minus 2.
+            ProtocolNumbers::Ah => (read_u8(self.mbuf, self.offset + 1)? as usize + 2) * 4,
+            _ => (read_u8(self.mbuf, self.offset + 1)? as usize + 1) * 8,
+        };
+
+        ensure!(
+            self.offset + len <= self.mbuf.data_len(),
+            anyhow!("truncated IPv6 extension header {}.", self.next_header)
+        );
+
+        let header = ExtensionHeader {
+            protocol: self.next_header,
+            offset: self.offset,
+            len,
+            next_header,
+        };
+
+        self.next_header = next_header;
+        self.offset += len;
+
+        Ok(Some(header))
+    }
+}
+
+impl fmt::Debug for ExtensionHeadersIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtensionHeadersIterator")
+            .field("next_header", &format!("{}", self.next_header))
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// The chain of IPv6 extension headers preceding the upper-layer header,
+/// treated as a single packet.
+///
+/// Parsing walks whatever extension headers are present, in whatever order
+/// they appear, so the upper-layer packet can be reached without knowing the
+/// chain statically. [`next_header`] is the upper-layer protocol, which is
+/// the `next_header` field of the last extension header, or of the envelope
+/// if there are no extension headers.
+///
+/// ```
+/// let ipv6 = ethernet.parse::<Ipv6>()?;
+/// let chain = ipv6.parse::<ExtensionHeaders<Ipv6>>()?;
+/// if chain.next_header() == ProtocolNumbers::Tcp {
+///     let tcp = chain.parse::<Tcp<ExtensionHeaders<Ipv6>>>()?;
+/// }
+/// ```
+///
+/// # Remarks
+///
+/// If the packet is a fragment other than the first, the walk stops at the
+/// fragment header and [`next_header`] is [`ProtocolNumbers::Ipv6Frag`].
+///
+/// [`next_header`]: Ipv6Packet::next_header
+/// [`ProtocolNumbers::Ipv6Frag`]: ProtocolNumbers::Ipv6Frag
+pub struct ExtensionHeaders<E: Ipv6Packet> {
+    envelope: E,
+    last: Option<NonNull<u8>>,
+    offset: usize,
+    len: usize,
+}
+
+impl<E: Ipv6Packet> ExtensionHeaders<E> {
+    /// Returns an iterator that iterates through the extension headers in
+    /// the chain.
+    #[inline]
+    pub fn iter(&self) -> ExtensionHeadersIterator<'_> {
+        ExtensionHeadersIterator::new(self.mbuf(), self.envelope().next_header(), self.offset)
+    }
+}
+
+impl<E: Ipv6Packet> fmt::Debug for ExtensionHeaders<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("extension headers")
+            .field("next_header", &format!("{}", self.next_header()))
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: Ipv6Packet> Packet for ExtensionHeaders<E> {
+    /// The preceding type for the extension header chain is typically the
+    /// IPv6 packet itself.
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        ExtensionHeaders::<E> {
+            envelope: self.envelope.clone(internal),
+            last: self.last,
+            offset: self.offset,
+            len: self.len,
+        }
+    }
+
+    /// Walks the extension headers at the beginning of the envelope's
+    /// payload.
+    ///
+    /// The chain may be empty, in which case [`next_header`] is the same as
+    /// the envelope's.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload does not have sufficient data for
+    /// the extension headers.
+    ///
+    /// [`next_header`]: Ipv6Packet::next_header
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mut iter =
+            ExtensionHeadersIterator::new(envelope.mbuf(), envelope.next_header(), offset);
+
+        let mut last = None;
+        while let Some(header) = iter.next()? {
+            last = Some(header.offset);
+        }
+
+        let len = iter.offset() - offset;
+        let last = match last {
+            Some(last) => Some(envelope.mbuf().read_data(last)?),
+            None => None,
+        };
+
+        Ok(ExtensionHeaders {
+            envelope,
+            last,
+            offset,
+            len,
+        })
+    }
+
+    /// Prepends an empty extension header chain to the beginning of the
+    /// envelope's payload.
+    ///
+    /// Nothing is written to the buffer. Extension headers can be pushed
+    /// onto the empty chain.
+    #[inline]
+    fn try_push(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+
+        Ok(ExtensionHeaders {
+            envelope,
+            last: None,
+            offset,
+            len: 0,
+        })
+    }
+
+    /// Removes all the extension headers in the chain from the message
+    /// buffer.
+    ///
+    /// The envelope's [`next_header`] field is set to the upper-layer
+    /// protocol.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have sufficient data to
+    /// remove.
+    ///
+    /// [`next_header`]: Ipv6Packet::next_header
+    #[inline]
+    fn remove(mut self) -> Result<Self::Envelope> {
+        let offset = self.offset();
+        let len = self.header_len();
+        let next_header = self.next_header();
+        if len > 0 {
+            self.mbuf_mut().shrink(offset, len)?;
+        }
+        self.envelope_mut().set_next_header(next_header);
+        Ok(self.envelope)
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+impl<E: Ipv6Packet> IpPacket for ExtensionHeaders<E> {
+    #[inline]
+    fn next_protocol(&self) -> ProtocolNumber {
+        self.next_header()
+    }
+
+    #[inline]
+    fn set_next_protocol(&mut self, proto: ProtocolNumber) {
+        self.set_next_header(proto);
+    }
+
+    #[inline]
+    fn src(&self) -> IpAddr {
+        self.envelope().src()
+    }
+
+    #[inline]
+    fn set_src(&mut self, src: IpAddr) -> Result<()> {
+        self.envelope_mut().set_src(src)
+    }
+
+    #[inline]
+    fn dst(&self) -> IpAddr {
+        self.envelope().dst()
+    }
+
+    #[inline]
+    fn set_dst(&mut self, dst: IpAddr) -> Result<()> {
+        self.envelope_mut().set_dst(dst)
+    }
+
+    #[inline]
+    fn pseudo_header(&self, packet_len: u16, protocol: ProtocolNumber) -> PseudoHeader {
+        self.envelope().pseudo_header(packet_len, protocol)
+    }
+
+    #[inline]
+    fn truncate(&mut self, mtu: usize) -> Result<()> {
+        self.envelope_mut().truncate(mtu)
+    }
+}
+
+impl<E: Ipv6Packet> Ipv6Packet for ExtensionHeaders<E> {
+    #[inline]
+    fn next_header(&self) -> ProtocolNumber {
+        match self.last {
+            Some(last) => ProtocolNumber::new(unsafe { *last.as_ref() }),
+            None => self.envelope().next_header(),
+        }
+    }
+
+    #[inline]
+    fn set_next_header(&mut self, next_header: ProtocolNumber) {
+        match self.last {
+            Some(mut last) => unsafe { *last.as_mut() = next_header.0 },
+            None => self.envelope_mut().set_next_header(next_header),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v6::{Fragment, Ipv6};
+    use crate::packets::{Ethernet, Tcp, Udp};
+    use crate::testils::byte_arrays::{IPV6_FRAGMENT_PACKET, IPV6_TCP_PACKET, SR_TCP_PACKET};
+
+    #[capsule::test]
+    fn walk_empty_chain() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert_eq!(
+            (ProtocolNumbers::Tcp, ipv6.payload_offset()),
+            ipv6.upper_layer().unwrap()
+        );
+
+        let chain = ipv6.parse::<ExtensionHeaders<Ipv6>>().unwrap();
+        assert_eq!(0, chain.header_len());
+        assert_eq!(None, chain.iter().next().unwrap());
+
+        let tcp = chain.parse::<Tcp<ExtensionHeaders<Ipv6>>>().unwrap();
+        assert_eq!(36869, tcp.src_port());
+    }
+
+    #[capsule::test]
+    fn walk_chain_to_tcp() {
+        let packet = Mbuf::from_bytes(&SR_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        let mut iter = ipv6.ext_headers_iter();
+        let header = iter.next().unwrap().unwrap();
+        assert_eq!(ProtocolNumbers::Ipv6Route, header.protocol);
+        assert_eq!(54, header.offset);
+        assert_eq!(56, header.len);
+        assert_eq!(ProtocolNumbers::Tcp, header.next_header);
+        assert_eq!(None, iter.next().unwrap());
+        assert_eq!(ProtocolNumbers::Tcp, iter.next_header());
+        assert_eq!(110, iter.offset());
+
+        let chain = ipv6.parse::<ExtensionHeaders<Ipv6>>().unwrap();
+        assert_eq!(ProtocolNumbers::Tcp, chain.next_header());
+        assert_eq!(56, chain.header_len());
+
+        let tcp = chain.parse::<Tcp<ExtensionHeaders<Ipv6>>>().unwrap();
+        assert_eq!(3464, tcp.src_port());
+        assert_eq!(1024, tcp.dst_port());
+    }
+
+    #[capsule::test]
+    fn walk_mixed_chain_to_udp() {
+        let packet = Mbuf::from_bytes(&MIXED_CHAIN_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        let mut protocols = vec![];
+        let mut iter = ipv6.ext_headers_iter();
+        while let Some(header) = iter.next().unwrap() {
+            protocols.push(header.protocol);
+        }
+
+        assert_eq!(
+            vec![
+                ProtocolNumbers::Ipv6HopByHop,
+                ProtocolNumbers::Ipv6Opts,
+                ProtocolNumbers::Ipv6Frag
+            ],
+            protocols
+        );
+        assert_eq!((ProtocolNumbers::Udp, 78), ipv6.upper_layer().unwrap());
+
+        let chain = ipv6.parse::<ExtensionHeaders<Ipv6>>().unwrap();
+        let udp = chain.parse::<Udp<ExtensionHeaders<Ipv6>>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+        assert_eq!(1087, udp.dst_port());
+    }
+
+    #[capsule::test]
+    fn walk_stops_at_non_first_fragment() {
+        let packet = Mbuf::from_bytes(&IPV6_FRAGMENT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert_eq!(
+            (ProtocolNumbers::Ipv6Frag, ipv6.payload_offset()),
+            ipv6.upper_layer().unwrap()
+        );
+
+        let chain = ipv6.parse::<ExtensionHeaders<Ipv6>>().unwrap();
+        assert_eq!(ProtocolNumbers::Ipv6Frag, chain.next_header());
+        assert!(chain.parse::<Fragment<ExtensionHeaders<Ipv6>>>().is_ok());
+    }
+
+    #[capsule::test]
+    fn walk_truncated_chain() {
+        let packet = Mbuf::from_bytes(&MIXED_CHAIN_PACKET[..70]).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert!(ipv6.upper_layer().is_err());
+        assert!(ipv6.parse::<ExtensionHeaders<Ipv6>>().is_err());
+    }
+
+    #[capsule::test]
+    fn remove_chain() {
+        let packet = Mbuf::from_bytes(&MIXED_CHAIN_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let chain = ipv6.parse::<ExtensionHeaders<Ipv6>>().unwrap();
+
+        let payload_len = chain.payload_len();
+        let ipv6 = chain.remove().unwrap();
+
+        assert_eq!(ProtocolNumbers::Udp, ipv6.next_header());
+        assert_eq!(payload_len, ipv6.payload_len());
+        assert!(ipv6.parse::<Udp<Ipv6>>().is_ok());
+    }
+
+    /// IPv6 UDP packet with a hop-by-hop options header, a destination
+    /// options header and a first fragment header.
+    #[rustfmt::skip]
+    const MIXED_CHAIN_PACKET: [u8; 94] = [
+    // Ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x86, 0xDD,
+    // IPv6 header
+        0x60, 0x00, 0x00, 0x00,
+        // payload length
+        0x00, 0x28,
+        // next header = hop-by-hop
+        0x00,
+        0x02,
+        0x20, 0x01, 0x0d, 0xb8, 0x85, 0xa3, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x20, 0x01, 0x0d, 0xb8, 0x85, 0xa3, 0x00, 0x00, 0x00, 0x00, 0x8a, 0x2e, 0x03, 0x70, 0x73, 0x34,
+    // Hop-by-hop options header
+        // next header = destination options, hdr ext len = 0
+        0x3c, 0x00,
+        0x01, 0x04, 0x00, 0x00, 0x00, 0x00,
+    // Destination options header
+        // next header = fragment, hdr ext len = 0
+        0x2c, 0x00,
+        0x01, 0x04, 0x00, 0x00, 0x00, 0x00,
+    // Fragment header
+        // next header = UDP
+        0x11, 0x00,
+        // fragment offset = 0, more fragments
+        0x00, 0x01,
+        // identification
+        0xf8, 0x8e, 0xb4, 0x66,
+    // UDP header
+        // src_port = 39376, dst_port = 1087
+        0x99, 0xd0, 0x04, 0x3f,
+        // length = 16, checksum
+        0x00, 0x10, 0x00, 0x00,
+    // UDP payload
+        0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c
+    ];
+}
@@ -0,0 +1,118 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::ip::v6::{Ipv6OptionsKind, Ipv6OptionsPacket};
+use crate::packets::ip::{ProtocolNumber, ProtocolNumbers};
+
+/// IPv6 Destination Options Extension packet based on [IETF RFC 8200].
+///
+/// The header format is described in [`Ipv6OptionsPacket`].
+///
+/// # Remarks
+///
+/// The Destination Options header may appear both before the routing
+/// header, to be processed by every destination listed, and before the
+/// upper-layer header, to be processed only by the final destination.
+///
+/// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-4.6
+/// [`Ipv6OptionsPacket`]: Ipv6OptionsPacket
+pub type DestinationOptions<E> = Ipv6OptionsPacket<E, DestinationOptionsKind>;
+
+/// Marks an [`Ipv6OptionsPacket`] as a Destination Options header.
+///
+/// [`Ipv6OptionsPacket`]: Ipv6OptionsPacket
+#[derive(Debug)]
+pub enum DestinationOptionsKind {}
+
+impl Ipv6OptionsKind for DestinationOptionsKind {
+    #[inline]
+    fn protocol() -> ProtocolNumber {
+        ProtocolNumbers::Ipv6Opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v6::{Ipv6, Ipv6Option, Ipv6OptionType, Ipv6Packet};
+    use crate::packets::{Ethernet, Packet, Udp};
+    use crate::Mbuf;
+
+    #[capsule::test]
+    fn parse_destination_options_packet() {
+        let packet = Mbuf::from_bytes(&DESTINATION_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let dest_opts = ipv6.parse::<DestinationOptions<Ipv6>>().unwrap();
+
+        assert_eq!(ProtocolNumbers::Udp, dest_opts.next_header());
+        assert_eq!(1, dest_opts.hdr_ext_len());
+        assert_eq!(16, dest_opts.header_len());
+
+        let mut iter = dest_opts.options_iter();
+        assert_eq!(
+            Some(Ipv6Option::Unknown(
+                Ipv6OptionType(0x1e),
+                vec![0xde, 0xad, 0xbe, 0xef]
+            )),
+            iter.next().unwrap()
+        );
+        assert_eq!(Some(Ipv6Option::Pad1), iter.next().unwrap());
+        assert_eq!(Some(Ipv6Option::PadN(5)), iter.next().unwrap());
+        assert_eq!(None, iter.next().unwrap());
+
+        let udp = dest_opts.parse::<Udp<DestinationOptions<Ipv6>>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+        assert_eq!(1087, udp.dst_port());
+    }
+
+    /// IPv6 UDP packet with a destination options header carrying an
+    /// experimental option.
+    #[rustfmt::skip]
+    const DESTINATION_OPTIONS_PACKET: [u8; 86] = [
+    // Ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x86, 0xDD,
+    // IPv6 header
+        0x60, 0x00, 0x00, 0x00,
+        // payload length
+        0x00, 0x20,
+        // next header = destination options
+        0x3c,
+        0x02,
+        0x20, 0x01, 0x0d, 0xb8, 0x85, 0xa3, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x20, 0x01, 0x0d, 0xb8, 0x85, 0xa3, 0x00, 0x00, 0x00, 0x00, 0x8a, 0x2e, 0x03, 0x70, 0x73, 0x34,
+    // Destination options header
+        // next header = UDP, hdr ext len = 1
+        0x11, 0x01,
+        // experimental option 0x1e
+        0x1e, 0x04, 0xde, 0xad, 0xbe, 0xef,
+        // Pad1
+        0x00,
+        // PadN
+        0x01, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // UDP header
+        // src_port = 39376, dst_port = 1087
+        0x99, 0xd0, 0x04, 0x3f,
+        // length = 16, checksum
+        0x00, 0x10, 0x00, 0x00,
+    // UDP payload
+        0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c
+    ];
+}
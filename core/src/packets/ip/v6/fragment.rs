@@ -17,13 +17,16 @@
 */
 
 use crate::packets::checksum::PseudoHeader;
-use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::ip::v6::{Ipv6, Ipv6Packet, IPV6_MIN_MTU};
 use crate::packets::ip::{IpPacket, ProtocolNumber, ProtocolNumbers};
 use crate::packets::types::{u16be, u32be};
-use crate::packets::{Internal, Packet};
-use crate::{ensure, SizeOf};
+use crate::packets::{Ethernet, Internal, Packet};
+use crate::{ensure, Mbuf, SizeOf};
 use anyhow::{anyhow, Result};
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
 use std::fmt;
+use std::hash::{BuildHasher, Hasher};
 use std::net::IpAddr;
 use std::ptr::NonNull;
 
@@ -316,6 +319,146 @@ impl<E: Ipv6Packet> Ipv6Packet for Fragment<E> {
     }
 }
 
+thread_local! {
+    static IDENTIFICATION: Cell<u32> = Cell::new(RandomState::new().build_hasher().finish() as u32);
+}
+
+/// Returns the next fragment identification of the current core.
+///
+/// The identifications start at a random value and increment, so a
+/// value is not reused until 2^32 packets are fragmented on the core.
+fn next_identification() -> u32 {
+    IDENTIFICATION.with(|tls| {
+        let identification = tls.get();
+        tls.set(identification.wrapping_add(1));
+        identification
+    })
+}
+
+impl Ipv6 {
+    /// Fragments the packet into new packets that fit in `mtu`, as
+    /// specified in [IETF RFC 8200].
+    ///
+    /// The packet is split after the unfragmentable part, which is the
+    /// IPv6 header followed by the Hop-by-Hop Options header, the Routing
+    /// header including [`SegmentRouting`], and any Destination Options
+    /// header preceding the Routing header. Each fragment is a copy of the
+    /// Ethernet header and the unfragmentable part, followed by a fragment
+    /// header and a slice of the rest of the packet. All the fragments have
+    /// the same identification, assigned from a per core sequence that
+    /// starts at a random value. The payload length is recomputed.
+    ///
+    /// Returns an empty `Vec` if the packet already fits in `mtu`. The
+    /// packet itself is never changed. It should be dropped after being
+    /// fragmented.
+    ///
+    /// ```
+    /// let mut batch = batch.filter_map(move |packet| {
+    ///     let v6 = packet.parse::<Ethernet>()?.parse::<Ipv6>()?;
+    ///     let v6 = v6.encap::<Ipv6>()?;
+    ///     let fragments = v6.fragment(1500)?;
+    ///     if fragments.is_empty() {
+    ///         Ok(Either::Keep(v6))
+    ///     } else {
+    ///         tx.transmit(fragments);
+    ///         Ok(Either::Drop(v6.reset()))
+    ///     }
+    /// });
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `mtu` is less than [`IPV6_MIN_MTU`], the packet
+    /// is already a fragment, or the payload length is inconsistent with
+    /// the buffer. Returns an error if the unfragmentable part doesn't
+    /// leave room for any data in `mtu`. Returns an error if the fragments
+    /// can't be allocated.
+    ///
+    /// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-4.5
+    /// [`SegmentRouting`]: crate::packets::ip::v6::SegmentRouting
+    /// [`IPV6_MIN_MTU`]: IPV6_MIN_MTU
+    pub fn fragment(&self, mtu: usize) -> Result<Vec<Mbuf>> {
+        ensure!(
+            mtu >= IPV6_MIN_MTU,
+            anyhow!("MTU {} must be greater than {}.", mtu, IPV6_MIN_MTU)
+        );
+
+        let end = self.payload_offset() + self.payload_length() as usize;
+        ensure!(
+            end <= self.mbuf().len(),
+            anyhow!("invalid payload length {}.", self.payload_length())
+        );
+        if end - self.offset() <= mtu {
+            return Ok(vec![]);
+        }
+
+        // finds the end of the unfragmentable part, and the offset of the
+        // next header field that will point to the fragment header.
+        let mut unfragmentable = self.payload_offset();
+        let mut next_header = self.next_header();
+        let mut next_header_offset = self.offset() + 6;
+        let mut iter = self.ext_headers_iter();
+        while let Some(header) = iter.next()? {
+            match header.protocol {
+                ProtocolNumbers::Ipv6HopByHop | ProtocolNumbers::Ipv6Route => {
+                    unfragmentable = header.offset + header.len;
+                    next_header = header.next_header;
+                    next_header_offset = header.offset;
+                }
+                ProtocolNumbers::Ipv6Opts => (),
+                ProtocolNumbers::Ipv6Frag => {
+                    return Err(anyhow!("packet is already a fragment."));
+                }
+                _ => break,
+            }
+        }
+
+        let per_fragment = unfragmentable - self.offset() + FragmentHeader::size_of();
+        ensure!(
+            per_fragment + 8 <= mtu && unfragmentable < end,
+            anyhow!("unfragmentable part is too long for MTU {}.", mtu)
+        );
+        let max_len = (mtu - per_fragment) & !7;
+
+        let headers = self.mbuf().read_data_slice::<u8>(0, unfragmentable)?;
+        let headers = unsafe { headers.as_ref() };
+        let mut payload = vec![0; end - unfragmentable];
+        self.mbuf().copy_to_slice(unfragmentable, &mut payload)?;
+
+        let identification = next_identification();
+        let data_offset = unfragmentable + FragmentHeader::size_of();
+
+        let mut fragments = Vec::new();
+        let mut pos = 0;
+        while pos < payload.len() {
+            let len = max_len.min(payload.len() - pos);
+            let more = if pos + len < payload.len() { 1 } else { 0 };
+
+            let mut mbuf = Mbuf::from_bytes(headers)?;
+            mbuf.extend(unfragmentable, FragmentHeader::size_of() + len)?;
+            mbuf.write_data(next_header_offset, &ProtocolNumbers::Ipv6Frag.0)?;
+            mbuf.write_data(
+                unfragmentable,
+                &FragmentHeader {
+                    next_header: next_header.0,
+                    reserved: 0,
+                    frag_res_m: u16be::from(((pos / 8) as u16) << 3 | more),
+                    identification: identification.into(),
+                },
+            )?;
+            mbuf.copy_from_slice(data_offset, &payload[pos..pos + len])?;
+
+            let mut fragment = mbuf.parse::<Ethernet>()?.parse::<Ipv6>()?;
+            fragment.reconcile();
+
+            fragments.push(fragment.reset());
+            pos += len;
+        }
+
+        Ok(fragments)
+    }
+}
+
 /// IPv6 fragment extension header.
 #[derive(Clone, Copy, Debug, Default, SizeOf)]
 #[repr(C, packed)]
@@ -329,10 +472,10 @@ struct FragmentHeader {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::packets::ip::v6::Ipv6;
-    use crate::packets::Ethernet;
+    use crate::packets::ip::v6::SegmentRouting;
+    use crate::packets::ip::{ReassemblyConfig, ReassemblyTable};
     use crate::testils::byte_arrays::{IPV6_FRAGMENT_PACKET, IPV6_TCP_PACKET};
-    use crate::Mbuf;
+    use crate::testils::{ipv6_udp_packet, PacketExt};
 
     #[test]
     fn size_of_fragment_header() {
@@ -418,4 +561,86 @@ mod tests {
         assert_eq!(next_header, ipv6.next_header());
         assert_eq!(payload_len, ipv6.payload_len());
     }
+
+    #[capsule::test]
+    fn fragment_ipv6_packet() {
+        let data = (0..1800).map(|i| i as u8).collect::<Vec<_>>();
+        let ipv6 = ipv6_udp_packet(&data);
+        let fragments = ipv6.fragment(1280).unwrap();
+        assert_eq!(2, fragments.len());
+
+        let mut table = ReassemblyTable::new(ReassemblyConfig::default());
+        let mut identifications = Vec::new();
+        let mut reassembled = None;
+        let expected = [(1240, 0, true), (576, 154, false)];
+        for (mbuf, &(payload_length, offset, more)) in fragments.into_iter().zip(expected.iter()) {
+            let ethernet = mbuf.parse::<Ethernet>().unwrap();
+            let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+            assert_eq!(payload_length, ipv6.payload_length());
+            assert_eq!(ProtocolNumbers::Ipv6Frag, ipv6.next_header());
+
+            let frag = ipv6.parse::<Fragment<Ipv6>>().unwrap();
+            assert_eq!(ProtocolNumbers::Udp, frag.next_header());
+            assert_eq!(offset, frag.fragment_offset());
+            assert_eq!(more, frag.more_fragments());
+            identifications.push(frag.identification());
+
+            reassembled = table.reassemble_v6(&frag).unwrap();
+        }
+        assert_eq!(identifications[0], identifications[1]);
+
+        let reassembled = reassembled
+            .unwrap()
+            .parse::<Ethernet>()
+            .unwrap()
+            .parse::<Ipv6>()
+            .unwrap();
+        assert_eq!(ProtocolNumbers::Udp, reassembled.next_header());
+        assert_eq!(data, reassembled.payload_to_vec());
+    }
+
+    #[capsule::test]
+    fn fragment_ipv6_packet_after_segment_routing() {
+        let ipv6 = ipv6_udp_packet(&[0; 1800]);
+        let mut srh = ipv6.push::<SegmentRouting<Ipv6>>().unwrap();
+        srh.reconcile_all();
+        let ipv6 = srh.deparse();
+
+        let fragments = ipv6.fragment(1280).unwrap();
+        assert_eq!(2, fragments.len());
+
+        let ethernet = fragments[0].peek::<Ethernet>().unwrap();
+        let ipv6 = ethernet.peek::<Ipv6>().unwrap();
+        assert_eq!(1240, ipv6.payload_length());
+        let srh = ipv6.peek::<SegmentRouting<Ipv6>>().unwrap();
+        assert_eq!(ProtocolNumbers::Ipv6Frag, srh.next_header());
+        let frag = srh.peek::<Fragment<SegmentRouting<Ipv6>>>().unwrap();
+        assert_eq!(ProtocolNumbers::Udp, frag.next_header());
+        assert_eq!(1208, frag.payload_len());
+        assert!(frag.more_fragments());
+
+        let ethernet = fragments[1].peek::<Ethernet>().unwrap();
+        let ipv6 = ethernet.peek::<Ipv6>().unwrap();
+        let srh = ipv6.peek::<SegmentRouting<Ipv6>>().unwrap();
+        let frag = srh.peek::<Fragment<SegmentRouting<Ipv6>>>().unwrap();
+        assert_eq!(151, frag.fragment_offset());
+        assert_eq!(592, frag.payload_len());
+        assert!(!frag.more_fragments());
+    }
+
+    #[capsule::test]
+    fn fragment_small_ipv6_packet() {
+        let ipv6 = ipv6_udp_packet(&[0; 100]);
+        assert!(ipv6.fragment(1500).unwrap().is_empty());
+        assert!(ipv6.fragment(1200).is_err());
+    }
+
+    #[capsule::test]
+    fn fragment_ipv6_fragment() {
+        let ipv6 = ipv6_udp_packet(&[0; 1800]);
+        let frag = ipv6.push::<Fragment<Ipv6>>().unwrap();
+        let mut ipv6 = frag.deparse();
+        ipv6.reconcile();
+        assert!(ipv6.fragment(1280).is_err());
+    }
 }
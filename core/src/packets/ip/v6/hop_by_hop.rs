@@ -0,0 +1,108 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::ip::v6::{Ipv6OptionsKind, Ipv6OptionsPacket};
+use crate::packets::ip::{ProtocolNumber, ProtocolNumbers};
+
+/// IPv6 Hop-by-Hop Options Extension packet based on [IETF RFC 8200].
+///
+/// The header format is described in [`Ipv6OptionsPacket`].
+///
+/// # Remarks
+///
+/// The Hop-by-Hop Options header, when present, must immediately follow
+/// the IPv6 header. This is not enforced by either `parse` or `push`.
+///
+/// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-4.3
+/// [`Ipv6OptionsPacket`]: Ipv6OptionsPacket
+pub type HopByHop<E> = Ipv6OptionsPacket<E, HopByHopKind>;
+
+/// Marks an [`Ipv6OptionsPacket`] as a Hop-by-Hop Options header.
+///
+/// [`Ipv6OptionsPacket`]: Ipv6OptionsPacket
+#[derive(Debug)]
+pub enum HopByHopKind {}
+
+impl Ipv6OptionsKind for HopByHopKind {
+    #[inline]
+    fn protocol() -> ProtocolNumber {
+        ProtocolNumbers::Ipv6HopByHop
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v6::{Ipv6, Ipv6Option, Ipv6Packet};
+    use crate::packets::{Ethernet, Packet, Tcp};
+    use crate::Mbuf;
+
+    #[capsule::test]
+    fn parse_hop_by_hop_packet() {
+        let packet = Mbuf::from_bytes(&HOP_BY_HOP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let hop_by_hop = ipv6.parse::<HopByHop<Ipv6>>().unwrap();
+
+        assert_eq!(ProtocolNumbers::Tcp, hop_by_hop.next_header());
+        assert_eq!(0, hop_by_hop.hdr_ext_len());
+        assert_eq!(8, hop_by_hop.header_len());
+
+        let mut iter = hop_by_hop.options_iter();
+        assert_eq!(Some(Ipv6Option::RouterAlert(0)), iter.next().unwrap());
+        assert_eq!(Some(Ipv6Option::PadN(0)), iter.next().unwrap());
+        assert_eq!(None, iter.next().unwrap());
+
+        let tcp = hop_by_hop.parse::<Tcp<HopByHop<Ipv6>>>().unwrap();
+        assert_eq!(36869, tcp.src_port());
+        assert_eq!(23, tcp.dst_port());
+    }
+
+    /// IPv6 TCP packet with a hop-by-hop options header carrying a
+    /// router alert option.
+    #[rustfmt::skip]
+    const HOP_BY_HOP_PACKET: [u8; 86] = [
+    // Ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x86, 0xDD,
+    // IPv6 header
+        0x60, 0x00, 0x00, 0x00,
+        // payload length
+        0x00, 0x20,
+        // next header = hop-by-hop
+        0x00,
+        0x02,
+        0x20, 0x01, 0x0d, 0xb8, 0x85, 0xa3, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x20, 0x01, 0x0d, 0xb8, 0x85, 0xa3, 0x00, 0x00, 0x00, 0x00, 0x8a, 0x2e, 0x03, 0x70, 0x73, 0x34,
+    // Hop-by-hop options header
+        // next header = TCP, hdr ext len = 0
+        0x06, 0x00,
+        // router alert = 0 (MLD)
+        0x05, 0x02, 0x00, 0x00,
+        // PadN
+        0x01, 0x00,
+    // TCP header
+        0x90, 0x05, 0x00, 0x17,
+        0x72, 0x14, 0xf1, 0x14,
+        0x00, 0x00, 0x00, 0x00,
+        0x60, 0x02,
+        0x22, 0x38, 0xa9, 0x2c, 0x00, 0x00,
+        0x02, 0x04, 0x05, 0xb4
+    ];
+}
@@ -18,16 +18,24 @@
 
 //! Internet Protocol v6 and extension headers.
 
+mod chain;
+mod destination;
 mod fragment;
+mod hop_by_hop;
+mod options;
 mod srh;
 
+pub use self::chain::*;
+pub use self::destination::*;
 pub use self::fragment::*;
+pub use self::hop_by_hop::*;
+pub use self::options::*;
 pub use self::srh::*;
 
 use crate::packets::checksum::PseudoHeader;
-use crate::packets::ip::{IpPacket, ProtocolNumber, DEFAULT_IP_TTL};
+use crate::packets::ip::{tunnel, IpPacket, ProtocolNumber, DEFAULT_IP_TTL};
 use crate::packets::types::{u16be, u32be};
-use crate::packets::{EtherTypes, Ethernet, Internal, Packet};
+use crate::packets::{Datalink, EtherType, EtherTypes, Ethernet, Internal, Packet};
 use crate::{ensure, SizeOf};
 use anyhow::{anyhow, Result};
 use std::fmt;
@@ -95,13 +103,13 @@ const FLOW: u32be = u32be(u32::to_be(0xfffff));
 /// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-3
 /// [IETF RFC 2474]: https://tools.ietf.org/html/rfc2474
 /// [IETF RFC 3168]: https://tools.ietf.org/html/rfc3168
-pub struct Ipv6 {
-    envelope: Ethernet,
+pub struct Ipv6<E: Datalink = Ethernet> {
+    envelope: E,
     header: NonNull<Ipv6Header>,
     offset: usize,
 }
 
-impl Ipv6 {
+impl<E: Datalink> Ipv6<E> {
     #[inline]
     fn header(&self) -> &Ipv6Header {
         unsafe { self.header.as_ref() }
@@ -207,9 +215,29 @@ impl Ipv6 {
     pub fn set_dst(&mut self, dst: Ipv6Addr) {
         self.header_mut().dst = dst;
     }
+
+    /// Returns an iterator that walks the extension header chain following
+    /// the IPv6 header.
+    #[inline]
+    pub fn ext_headers_iter(&self) -> ExtensionHeadersIterator<'_> {
+        ExtensionHeadersIterator::new(self.mbuf(), self.next_header(), self.payload_offset())
+    }
+
+    /// Walks the extension header chain and returns the upper-layer protocol
+    /// and the buffer offset where the upper-layer header begins.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have sufficient data for the
+    /// extension headers.
+    pub fn upper_layer(&self) -> Result<(ProtocolNumber, usize)> {
+        let mut iter = self.ext_headers_iter();
+        while iter.next()?.is_some() {}
+        Ok((iter.next_header(), iter.offset()))
+    }
 }
 
-impl fmt::Debug for Ipv6 {
+impl<E: Datalink> fmt::Debug for Ipv6<E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ipv6")
             .field("src", &format!("{}", self.src()))
@@ -227,9 +255,11 @@ impl fmt::Debug for Ipv6 {
     }
 }
 
-impl Packet for Ipv6 {
-    /// The preceding type for IPv6 packet must be Ethernet.
-    type Envelope = Ethernet;
+impl<E: Datalink> Packet for Ipv6<E> {
+    /// The preceding type for IPv6 packet is typically Ethernet. It can
+    /// also be a tunneling packet, such as GRE or another IP packet, that
+    /// carries an IPv6 packet.
+    type Envelope = E;
 
     #[inline]
     fn envelope(&self) -> &Self::Envelope {
@@ -260,20 +290,20 @@ impl Packet for Ipv6 {
         }
     }
 
-    /// Parses the Ethernet's payload as an IPv6 packet.
+    /// Parses the envelope's payload as an IPv6 packet.
     ///
     /// # Errors
     ///
-    /// Returns an error if [`ether_type`] is not set to [`EtherTypes::Ipv6`].
-    /// Returns an error if the payload does not have sufficient data for the
-    /// IPv6 header.
+    /// Returns an error if [`protocol_type`] is not set to
+    /// [`EtherTypes::Ipv6`]. Returns an error if the payload does not have
+    /// sufficient data for the IPv6 header.
     ///
-    /// [`ether_type`]: Ethernet::ether_type
+    /// [`protocol_type`]: Datalink::protocol_type
     /// [`EtherTypes::Ipv6`]: EtherTypes::Ipv6
     #[inline]
     fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
         ensure!(
-            envelope.ether_type() == EtherTypes::Ipv6,
+            envelope.protocol_type() == EtherTypes::Ipv6,
             anyhow!("not an IPv6 packet.")
         );
 
@@ -288,15 +318,15 @@ impl Packet for Ipv6 {
         })
     }
 
-    /// Prepends an IPv6 packet to the beginning of the Ethernet's payload.
+    /// Prepends an IPv6 packet to the beginning of the envelope's payload.
     ///
-    /// [`ether_type`] is set to [`EtherTypes::Ipv6`].
+    /// [`protocol_type`] is set to [`EtherTypes::Ipv6`].
     ///
     /// # Errors
     ///
     /// Returns an error if the buffer does not have enough free space.
     ///
-    /// [`ether_type`]: Ethernet::ether_type
+    /// [`protocol_type`]: Datalink::protocol_type
     /// [`EtherTypes::Ipv6`]: EtherTypes::Ipv6
     #[inline]
     fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
@@ -306,7 +336,7 @@ impl Packet for Ipv6 {
         mbuf.extend(offset, Ipv6Header::size_of())?;
         let header = mbuf.write_data(offset, &Ipv6Header::default())?;
 
-        envelope.set_ether_type(EtherTypes::Ipv6);
+        envelope.set_protocol_type(EtherTypes::Ipv6)?;
 
         Ok(Ipv6 {
             envelope,
@@ -334,7 +364,34 @@ impl Packet for Ipv6 {
     }
 }
 
-impl IpPacket for Ipv6 {
+impl<E: Datalink> Datalink for Ipv6<E> {
+    /// Returns [`EtherTypes::Ipv4`] or [`EtherTypes::Ipv6`] if the payload
+    /// is an encapsulated IPv4 or IPv6 packet. Otherwise returns an
+    /// unassigned EtherType of 0.
+    ///
+    /// [`EtherTypes::Ipv4`]: EtherTypes::Ipv4
+    /// [`EtherTypes::Ipv6`]: EtherTypes::Ipv6
+    #[inline]
+    fn protocol_type(&self) -> EtherType {
+        tunnel::ether_type_of(self.next_header())
+    }
+
+    /// Sets the next header to the encapsulation protocol number for an
+    /// IPv4 or IPv6 payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ether_type` is neither IPv4 nor IPv6.
+    #[inline]
+    fn set_protocol_type(&mut self, ether_type: EtherType) -> Result<()> {
+        let protocol = tunnel::protocol_of(ether_type)
+            .ok_or_else(|| anyhow!("{} can't be carried in an IP packet.", ether_type))?;
+        self.set_next_header(protocol);
+        Ok(())
+    }
+}
+
+impl<E: Datalink> IpPacket for Ipv6<E> {
     #[inline]
     fn next_protocol(&self) -> ProtocolNumber {
         self.next_header()
@@ -417,7 +474,7 @@ impl IpPacket for Ipv6 {
     }
 }
 
-impl Ipv6Packet for Ipv6 {
+impl<E: Datalink> Ipv6Packet for Ipv6<E> {
     #[inline]
     fn next_header(&self) -> ProtocolNumber {
         ProtocolNumber::new(self.header().next_header)
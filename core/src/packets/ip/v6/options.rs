@@ -0,0 +1,940 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::checksum::PseudoHeader;
+use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::ip::{IpPacket, ProtocolNumber};
+use crate::packets::{Internal, Packet};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::marker::PhantomData;
+use std::net::IpAddr;
+use std::ptr::NonNull;
+
+/// The maximum length of a Hop-by-Hop or Destination Options header in
+/// octets.
+///
+/// The Hdr Ext Len field is 8 bits and measured in 8-octet units, not
+/// including the first 8 octets.
+pub const IPV6_MAX_OPTIONS_HEADER_LEN: usize = 256 * 8;
+
+/// [IANA] assigned option type for the IPv6 Hop-by-Hop and Destination
+/// Options headers.
+///
+/// The two highest-order bits specify the action to take if the option is
+/// not recognized, and the third-highest-order bit specifies whether the
+/// option data can change en route. A list of supported types is under
+/// [`Ipv6OptionTypes`].
+///
+/// [IANA]: https://www.iana.org/assignments/ipv6-parameters/ipv6-parameters.xhtml#ipv6-parameters-2
+/// [`Ipv6OptionTypes`]: Ipv6OptionTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct Ipv6OptionType(pub u8);
+
+impl Ipv6OptionType {
+    /// Returns the action to take if the processing node does not
+    /// recognize the option type, encoded in the two highest-order bits.
+    ///
+    /// - `0`: skip over this option and continue processing the header.
+    /// - `1`: discard the packet.
+    /// - `2`: discard the packet and send an ICMP Parameter Problem.
+    /// - `3`: discard the packet and send an ICMP Parameter Problem only
+    ///   if the destination is not a multicast address.
+    #[inline]
+    pub fn action(self) -> u8 {
+        self.0 >> 6
+    }
+
+    /// Returns whether the option data may change en route to the packet's
+    /// final destination.
+    #[inline]
+    pub fn mutable(self) -> bool {
+        self.0 & 0x20 != 0
+    }
+}
+
+/// Supported IPv6 option types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod Ipv6OptionTypes {
+    use super::Ipv6OptionType;
+
+    /// Single octet padding.
+    pub const Pad1: Ipv6OptionType = Ipv6OptionType(0x00);
+
+    /// Multiple octets padding.
+    pub const PadN: Ipv6OptionType = Ipv6OptionType(0x01);
+
+    /// Router alert.
+    pub const RouterAlert: Ipv6OptionType = Ipv6OptionType(0x05);
+
+    /// Jumbo payload.
+    pub const JumboPayload: Ipv6OptionType = Ipv6OptionType(0xC2);
+}
+
+impl fmt::Display for Ipv6OptionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Ipv6OptionTypes::Pad1 => "Pad1".to_string(),
+                Ipv6OptionTypes::PadN => "PadN".to_string(),
+                Ipv6OptionTypes::RouterAlert => "Router Alert".to_string(),
+                Ipv6OptionTypes::JumboPayload => "Jumbo Payload".to_string(),
+                _ => format!("0x{:02x}", self.0),
+            }
+        )
+    }
+}
+
+/// An option in the IPv6 Hop-by-Hop or Destination Options header.
+///
+/// Options are read out of and written into the buffer by value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Ipv6Option {
+    /// Single octet padding defined in [IETF RFC 8200].
+    ///
+    /// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-4.2
+    Pad1,
+
+    /// Multiple octets padding with the number of zero-valued data octets,
+    /// defined in [IETF RFC 8200].
+    ///
+    /// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-4.2
+    PadN(u8),
+
+    /// Router alert value defined in [IETF RFC 2711].
+    ///
+    /// [IETF RFC 2711]: https://tools.ietf.org/html/rfc2711
+    RouterAlert(u16),
+
+    /// Jumbo payload length defined in [IETF RFC 2675].
+    ///
+    /// [IETF RFC 2675]: https://tools.ietf.org/html/rfc2675
+    JumboPayload(u32),
+
+    /// Option with a type not known to the parser and its raw data,
+    /// excluding the type and length octets.
+    Unknown(Ipv6OptionType, Vec<u8>),
+}
+
+impl Ipv6Option {
+    /// Returns the option type.
+    pub fn option_type(&self) -> Ipv6OptionType {
+        match self {
+            Ipv6Option::Pad1 => Ipv6OptionTypes::Pad1,
+            Ipv6Option::PadN(_) => Ipv6OptionTypes::PadN,
+            Ipv6Option::RouterAlert(_) => Ipv6OptionTypes::RouterAlert,
+            Ipv6Option::JumboPayload(_) => Ipv6OptionTypes::JumboPayload,
+            Ipv6Option::Unknown(option_type, _) => *option_type,
+        }
+    }
+
+    /// Returns the length of the option in octets, including the type and
+    /// length octets.
+    pub fn length(&self) -> usize {
+        match self {
+            Ipv6Option::Pad1 => 1,
+            Ipv6Option::PadN(len) => 2 + *len as usize,
+            Ipv6Option::RouterAlert(_) => 4,
+            Ipv6Option::JumboPayload(_) => 6,
+            Ipv6Option::Unknown(_, data) => 2 + data.len(),
+        }
+    }
+
+    /// Returns whether the option is padding.
+    #[inline]
+    pub fn is_padding(&self) -> bool {
+        matches!(self, Ipv6Option::Pad1 | Ipv6Option::PadN(_))
+    }
+
+    /// Decodes the option data, excluding the type and length octets.
+    fn decode(option_type: Ipv6OptionType, data: &[u8]) -> Result<Self> {
+        let option = match option_type {
+            Ipv6OptionTypes::PadN => Ipv6Option::PadN(data.len() as u8),
+            Ipv6OptionTypes::RouterAlert => {
+                ensure!(
+                    data.len() == 2,
+                    anyhow!("invalid router alert option length.")
+                );
+                Ipv6Option::RouterAlert(u16::from_be_bytes([data[0], data[1]]))
+            }
+            Ipv6OptionTypes::JumboPayload => {
+                ensure!(
+                    data.len() == 4,
+                    anyhow!("invalid jumbo payload option length.")
+                );
+                Ipv6Option::JumboPayload(u32::from_be_bytes([data[0], data[1], data[2], data[3]]))
+            }
+            _ => Ipv6Option::Unknown(option_type, data.to_vec()),
+        };
+
+        Ok(option)
+    }
+
+    /// Encodes the option including the type and length octets.
+    fn encode(&self) -> Vec<u8> {
+        if let Ipv6Option::Pad1 = self {
+            return vec![Ipv6OptionTypes::Pad1.0];
+        }
+
+        let mut bytes = Vec::with_capacity(self.length());
+        bytes.push(self.option_type().0);
+        bytes.push((self.length() - 2) as u8);
+
+        match self {
+            Ipv6Option::PadN(len) => bytes.resize(2 + *len as usize, 0),
+            Ipv6Option::RouterAlert(value) => bytes.extend_from_slice(&value.to_be_bytes()),
+            Ipv6Option::JumboPayload(len) => bytes.extend_from_slice(&len.to_be_bytes()),
+            Ipv6Option::Unknown(_, data) => bytes.extend_from_slice(data),
+            Ipv6Option::Pad1 => unreachable!(),
+        }
+
+        bytes
+    }
+}
+
+#[inline]
+fn read_u8(mbuf: &Mbuf, offset: usize) -> Result<u8> {
+    let value = mbuf.read_data::<u8>(offset)?;
+    Ok(unsafe { *value.as_ref() })
+}
+
+/// Reads the option at offset and returns it with its length in octets.
+///
+/// # Errors
+///
+/// Returns an error if the option runs past the end offset.
+fn read_option(mbuf: &Mbuf, offset: usize, end: usize) -> Result<(Ipv6Option, usize)> {
+    let option_type = Ipv6OptionType(read_u8(mbuf, offset)?);
+
+    if option_type == Ipv6OptionTypes::Pad1 {
+        return Ok((Ipv6Option::Pad1, 1));
+    }
+
+    ensure!(
+        offset + 1 < end,
+        anyhow!("truncated IPv6 option {}.", option_type)
+    );
+    let data_len = read_u8(mbuf, offset + 1)? as usize;
+    let length = 2 + data_len;
+    ensure!(
+        offset + length <= end,
+        anyhow!("invalid IPv6 option {} length {}.", option_type, data_len)
+    );
+
+    let data = if data_len > 0 {
+        let data = mbuf.read_data_slice::<u8>(offset + 2, data_len)?;
+        unsafe { &*data.as_ptr() }
+    } else {
+        &[]
+    };
+
+    Ok((Ipv6Option::decode(option_type, data)?, length))
+}
+
+/// Returns the padding option that fills `len` octets.
+fn padding(len: usize) -> Option<Ipv6Option> {
+    match len {
+        0 => None,
+        1 => Some(Ipv6Option::Pad1),
+        _ => Some(Ipv6Option::PadN((len - 2) as u8)),
+    }
+}
+
+/// An iterator that iterates through the options in an IPv6 Hop-by-Hop
+/// or Destination Options header, including the padding options.
+pub struct Ipv6OptionsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> Ipv6OptionsIterator<'a> {
+    pub(crate) fn new(mbuf: &'a Mbuf, offset: usize, end: usize) -> Self {
+        Ipv6OptionsIterator { mbuf, offset, end }
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<Ipv6Option>> {
+        if self.offset < self.end {
+            let (option, length) = read_option(self.mbuf, self.offset, self.end)?;
+            // advances the offset to the next option
+            self.offset += length;
+            Ok(Some(option))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl fmt::Debug for Ipv6OptionsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ipv6OptionsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// Options in an IPv6 Hop-by-Hop or Destination Options header.
+///
+/// Adding or removing options resizes the header and updates its header
+/// extension length. The options are always padded with Pad1 or PadN
+/// options so the header is a multiple of 8 octets. The envelope's
+/// payload length is not updated until the packet is reconciled.
+pub struct Ipv6Options<'a> {
+    mbuf: &'a mut Mbuf,
+    header: NonNull<OptionsHeader>,
+    offset: usize,
+}
+
+impl<'a> Ipv6Options<'a> {
+    pub(crate) fn new(mbuf: &'a mut Mbuf, header: NonNull<OptionsHeader>, offset: usize) -> Self {
+        Ipv6Options {
+            mbuf,
+            header,
+            offset,
+        }
+    }
+
+    #[inline]
+    fn header(&self) -> &OptionsHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut OptionsHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the buffer offset where the options begin.
+    #[inline]
+    fn start_offset(&self) -> usize {
+        self.offset + OptionsHeader::size_of()
+    }
+
+    /// Returns the buffer offset where the header ends.
+    #[inline]
+    fn end_offset(&self) -> usize {
+        self.offset + self.header().len()
+    }
+
+    /// Returns the length of the options up to `end`, not including the
+    /// trailing padding.
+    fn used_len(&self, end: usize) -> Result<usize> {
+        let start = self.start_offset();
+        let mut offset = start;
+        let mut used = 0;
+
+        while offset < end {
+            let (option, length) = read_option(self.mbuf, offset, end)?;
+            offset += length;
+            if !option.is_padding() {
+                used = offset - start;
+            }
+        }
+
+        Ok(used)
+    }
+
+    /// Resizes the options from `current` length to fit `used` octets of
+    /// options, pads the rest and updates the header extension length.
+    fn pad(&mut self, current: usize, used: usize) -> Result<()> {
+        let start = self.start_offset();
+        let header_len = (OptionsHeader::size_of() + used + 7) & !7;
+        let padded = header_len - OptionsHeader::size_of();
+
+        ensure!(
+            header_len <= IPV6_MAX_OPTIONS_HEADER_LEN,
+            anyhow!(
+                "IPv6 options header exceeds {} octets.",
+                IPV6_MAX_OPTIONS_HEADER_LEN
+            )
+        );
+
+        if padded > current {
+            self.mbuf.extend(start + current, padded - current)?;
+        } else if padded < current {
+            self.mbuf.shrink(start + padded, current - padded)?;
+        }
+
+        if let Some(option) = padding(padded - used) {
+            self.mbuf.write_data_slice(start + used, &option.encode())?;
+        }
+
+        self.header_mut().hdr_ext_len = (header_len / 8 - 1) as u8;
+
+        Ok(())
+    }
+
+    /// Returns an iterator to read the options.
+    #[inline]
+    pub fn iter(&self) -> Ipv6OptionsIterator<'_> {
+        Ipv6OptionsIterator::new(self.mbuf, self.start_offset(), self.end_offset())
+    }
+
+    /// Appends a new option after the last non-padding option.
+    ///
+    /// No alignment padding is inserted in front of the option.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `option` is padding, if the header would exceed
+    /// [`IPV6_MAX_OPTIONS_HEADER_LEN`] or the buffer does not have enough
+    /// free space.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut hop_by_hop = ipv6.push::<HopByHop<Ipv6>>()?;
+    /// let mut options = hop_by_hop.options_mut();
+    /// options.append(&Ipv6Option::RouterAlert(0))?;
+    /// ```
+    ///
+    /// [`IPV6_MAX_OPTIONS_HEADER_LEN`]: IPV6_MAX_OPTIONS_HEADER_LEN
+    pub fn append(&mut self, option: &Ipv6Option) -> Result<()> {
+        ensure!(
+            !option.is_padding(),
+            anyhow!("can't append padding option.")
+        );
+
+        let bytes = option.encode();
+        let used = self.used_len(self.end_offset())?;
+        let current = self.end_offset() - self.start_offset();
+        self.pad(current, used + bytes.len())?;
+
+        let offset = self.start_offset() + used;
+        self.mbuf.write_data_slice(offset, &bytes)?;
+
+        Ok(())
+    }
+
+    /// Removes all options of `option_type`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut hop_by_hop = ipv6.parse::<HopByHop<Ipv6>>()?;
+    /// hop_by_hop.options_mut().remove(Ipv6OptionTypes::RouterAlert)?;
+    /// ```
+    #[inline]
+    pub fn remove(&mut self, option_type: Ipv6OptionType) -> Result<()> {
+        self.retain(|option| option.option_type() != option_type)
+    }
+
+    /// Retains only the options specified by the predicate.
+    ///
+    /// In other words, remove all options `o` such that `f(o)` returns false.
+    /// The predicate is not called for padding options. If an error occurs,
+    /// all removals done prior to the error cannot be undone.
+    pub fn retain<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&Ipv6Option) -> bool,
+    {
+        let start = self.start_offset();
+        let mut end = self.end_offset();
+        let mut offset = start;
+
+        while offset < end {
+            let (option, length) = read_option(self.mbuf, offset, end)?;
+
+            if option.is_padding() || f(&option) {
+                offset += length;
+            } else {
+                self.mbuf.shrink(offset, length)?;
+                end -= length;
+            }
+        }
+
+        // the trailing padding is rewritten to realign the header.
+        let used = self.used_len(end)?;
+        self.pad(end - start, used)
+    }
+}
+
+impl fmt::Debug for Ipv6Options<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ipv6Options")
+            .field("offset", &self.start_offset())
+            .finish()
+    }
+}
+
+/// An IPv6 extension header that carries options.
+///
+/// The Hop-by-Hop and Destination Options headers share the same format
+/// and differ only in the protocol number that identifies them. See
+/// [`HopByHop`] and [`DestinationOptions`].
+///
+/// [`HopByHop`]: crate::packets::ip::v6::HopByHop
+/// [`DestinationOptions`]: crate::packets::ip::v6::DestinationOptions
+pub trait Ipv6OptionsKind {
+    /// Returns the protocol number assigned to the extension header.
+    fn protocol() -> ProtocolNumber;
+}
+
+/// IPv6 Options Extension packet based on [IETF RFC 8200].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Next Header  |  Hdr Ext Len  |                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               +
+/// |                                                               |
+/// .                                                               .
+/// .                            Options                            .
+/// .                                                               .
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Next Header*:      8-bit selector.  Identifies the type of header
+///                       immediately following the Options header. Uses
+///                       the same values as the IPv4 Protocol field
+///                       [IANA-PN].
+///
+/// - *Hdr Ext Len*:      8-bit unsigned integer.  Length of the Options
+///                       header in 8-octet units, not including the first
+///                       8 octets.
+///
+/// - *Options*:          Variable-length field, of length such that the
+///                       complete Options header is an integer multiple
+///                       of 8 octets long. Contains one or more
+///                       TLV-encoded options.
+///
+/// `K` selects which of the options headers the packet is. Use the
+/// [`HopByHop`] and [`DestinationOptions`] aliases instead of naming this
+/// type directly.
+///
+/// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-4.3
+/// [`HopByHop`]: crate::packets::ip::v6::HopByHop
+/// [`DestinationOptions`]: crate::packets::ip::v6::DestinationOptions
+pub struct Ipv6OptionsPacket<E: Ipv6Packet, K: Ipv6OptionsKind> {
+    envelope: E,
+    header: NonNull<OptionsHeader>,
+    offset: usize,
+    _phantom: PhantomData<K>,
+}
+
+impl<E: Ipv6Packet, K: Ipv6OptionsKind> Ipv6OptionsPacket<E, K> {
+    #[inline]
+    fn header(&self) -> &OptionsHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut OptionsHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the length of the header in 8-octet units, not including
+    /// the first 8 octets.
+    #[inline]
+    pub fn hdr_ext_len(&self) -> u8 {
+        self.header().hdr_ext_len
+    }
+
+    /// Returns an iterator that iterates through the options, including
+    /// the padding options.
+    #[inline]
+    pub fn options_iter(&self) -> Ipv6OptionsIterator<'_> {
+        let start = self.offset() + OptionsHeader::size_of();
+        let end = self.offset() + self.header_len();
+        Ipv6OptionsIterator::new(self.mbuf(), start, end)
+    }
+
+    /// Returns the options for modification.
+    #[inline]
+    pub fn options_mut(&mut self) -> Ipv6Options<'_> {
+        let header = self.header;
+        let offset = self.offset;
+        Ipv6Options::new(self.mbuf_mut(), header, offset)
+    }
+}
+
+impl<E: Ipv6Packet, K: Ipv6OptionsKind> fmt::Debug for Ipv6OptionsPacket<E, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ipv6 options")
+            .field("type", &format!("{}", K::protocol()))
+            .field("next_header", &format!("{}", self.next_header()))
+            .field("hdr_ext_len", &self.hdr_ext_len())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: Ipv6Packet, K: Ipv6OptionsKind> Packet for Ipv6OptionsPacket<E, K> {
+    /// The preceding type for an IPv6 options packet is either the IPv6
+    /// packet itself or another IPv6 extension packet.
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        self.header().len()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Ipv6OptionsPacket {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Parses the envelope's payload as an IPv6 options packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`next_header`] is not set to the protocol
+    /// number of `K`. Returns an error if the payload does not have
+    /// sufficient data for the options extension header.
+    ///
+    /// [`next_header`]: Ipv6Packet::next_header
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.next_header() == K::protocol(),
+            anyhow!("not an {} packet.", K::protocol())
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Ipv6OptionsPacket {
+            envelope,
+            header,
+            offset,
+            _phantom: PhantomData,
+        };
+
+        ensure!(
+            packet.len() >= packet.header_len(),
+            anyhow!("packet has incomplete {} header.", K::protocol())
+        );
+
+        Ok(packet)
+    }
+
+    /// Prepends an IPv6 options packet to the beginning of the envelope's
+    /// payload.
+    ///
+    /// The header is 8 octets long and contains only padding. [`next_header`]
+    /// is set to the value of the `next_header` field of the envelope, and
+    /// the envelope is set to the protocol number of `K`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`next_header`]: Ipv6Packet::next_header
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, OptionsHeader::size_of() + DEFAULT_OPTIONS.len())?;
+        let header = mbuf.write_data(offset, &OptionsHeader::default())?;
+        mbuf.write_data_slice(offset + OptionsHeader::size_of(), &DEFAULT_OPTIONS)?;
+
+        let mut packet = Ipv6OptionsPacket {
+            envelope,
+            header,
+            offset,
+            _phantom: PhantomData,
+        };
+
+        packet.set_next_header(packet.envelope().next_header());
+        packet.envelope_mut().set_next_header(K::protocol());
+
+        Ok(packet)
+    }
+
+    /// Removes IPv6 options packet from the message buffer.
+    ///
+    /// The envelope's [`next_header`] field is set to the value of the
+    /// `next_header` field on the options packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have sufficient data to
+    /// remove.
+    ///
+    /// [`next_header`]: Ipv6Packet::next_header
+    #[inline]
+    fn remove(mut self) -> Result<Self::Envelope> {
+        let offset = self.offset();
+        let len = self.header_len();
+        let next_header = self.next_header();
+        self.mbuf_mut().shrink(offset, len)?;
+        self.envelope_mut().set_next_header(next_header);
+        Ok(self.envelope)
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+impl<E: Ipv6Packet, K: Ipv6OptionsKind> IpPacket for Ipv6OptionsPacket<E, K> {
+    #[inline]
+    fn next_protocol(&self) -> ProtocolNumber {
+        self.next_header()
+    }
+
+    #[inline]
+    fn set_next_protocol(&mut self, proto: ProtocolNumber) {
+        self.set_next_header(proto);
+    }
+
+    #[inline]
+    fn src(&self) -> IpAddr {
+        self.envelope().src()
+    }
+
+    #[inline]
+    fn set_src(&mut self, src: IpAddr) -> Result<()> {
+        self.envelope_mut().set_src(src)
+    }
+
+    #[inline]
+    fn dst(&self) -> IpAddr {
+        self.envelope().dst()
+    }
+
+    #[inline]
+    fn set_dst(&mut self, dst: IpAddr) -> Result<()> {
+        self.envelope_mut().set_dst(dst)
+    }
+
+    #[inline]
+    fn pseudo_header(&self, packet_len: u16, protocol: ProtocolNumber) -> PseudoHeader {
+        self.envelope().pseudo_header(packet_len, protocol)
+    }
+
+    #[inline]
+    fn truncate(&mut self, mtu: usize) -> Result<()> {
+        self.envelope_mut().truncate(mtu)
+    }
+}
+
+impl<E: Ipv6Packet, K: Ipv6OptionsKind> Ipv6Packet for Ipv6OptionsPacket<E, K> {
+    #[inline]
+    fn next_header(&self) -> ProtocolNumber {
+        ProtocolNumber::new(self.header().next_header)
+    }
+
+    #[inline]
+    fn set_next_header(&mut self, next_header: ProtocolNumber) {
+        self.header_mut().next_header = next_header.0;
+    }
+}
+
+/// The fixed portion of the IPv6 Hop-by-Hop and Destination Options
+/// headers.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+pub(crate) struct OptionsHeader {
+    pub(crate) next_header: u8,
+    pub(crate) hdr_ext_len: u8,
+}
+
+impl OptionsHeader {
+    /// Returns the length of the whole header in octets.
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        (self.hdr_ext_len as usize + 1) * 8
+    }
+}
+
+/// The default options following a newly pushed options header, a PadN
+/// option that pads the header to 8 octets.
+pub(crate) const DEFAULT_OPTIONS: [u8; 6] = [0x01, 0x04, 0x00, 0x00, 0x00, 0x00];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v6::{DestinationOptionsKind, HopByHop, HopByHopKind, Ipv6};
+    use crate::packets::{Ethernet, Tcp};
+    use crate::testils::byte_arrays::IPV6_TCP_PACKET;
+
+    #[test]
+    fn size_of_options_header() {
+        assert_eq!(2, OptionsHeader::size_of());
+    }
+
+    #[test]
+    fn ipv6_option_type_to_string() {
+        assert_eq!("Router Alert", Ipv6OptionTypes::RouterAlert.to_string());
+        assert_eq!("0x1e", Ipv6OptionType(0x1e).to_string());
+    }
+
+    #[test]
+    fn ipv6_option_type_bits() {
+        assert_eq!(3, Ipv6OptionTypes::JumboPayload.action());
+        assert!(!Ipv6OptionTypes::JumboPayload.mutable());
+        assert_eq!(0, Ipv6OptionTypes::RouterAlert.action());
+        assert!(Ipv6OptionType(0x3e).mutable());
+    }
+
+    #[test]
+    fn encode_and_decode_ipv6_options() {
+        let options = [
+            Ipv6Option::PadN(3),
+            Ipv6Option::RouterAlert(0),
+            Ipv6Option::JumboPayload(70000),
+            Ipv6Option::Unknown(Ipv6OptionType(0x1e), vec![1, 2, 3]),
+        ];
+
+        for option in options.iter() {
+            let bytes = option.encode();
+            assert_eq!(option.length(), bytes.len());
+            assert_eq!(option.option_type().0, bytes[0]);
+            assert_eq!((option.length() - 2) as u8, bytes[1]);
+
+            let decoded = Ipv6Option::decode(option.option_type(), &bytes[2..]).unwrap();
+            assert_eq!(*option, decoded);
+        }
+
+        assert_eq!(vec![0], Ipv6Option::Pad1.encode());
+        assert!(Ipv6Option::decode(Ipv6OptionTypes::JumboPayload, &[0; 2]).is_err());
+    }
+
+    #[test]
+    fn padding_options() {
+        assert_eq!(None, padding(0));
+        assert_eq!(Some(Ipv6Option::Pad1), padding(1));
+        assert_eq!(Some(Ipv6Option::PadN(0)), padding(2));
+        assert_eq!(Some(Ipv6Option::PadN(4)), padding(6));
+    }
+
+    fn parse_non_options_packet<K: Ipv6OptionsKind>() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert!(ipv6.parse::<Ipv6OptionsPacket<Ipv6, K>>().is_err());
+    }
+
+    #[capsule::test]
+    fn parse_non_options_packets() {
+        parse_non_options_packet::<HopByHopKind>();
+        parse_non_options_packet::<DestinationOptionsKind>();
+    }
+
+    fn push_and_remove_options_packet<K: Ipv6OptionsKind>() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        let next_header = ipv6.next_header();
+        let payload_len = ipv6.payload_len();
+        let mut options = ipv6.push::<Ipv6OptionsPacket<Ipv6, K>>().unwrap();
+
+        assert_eq!(8, options.len() - payload_len);
+        assert_eq!(K::protocol(), options.envelope().next_header());
+        assert_eq!(next_header, options.next_header());
+        assert_eq!(payload_len, options.payload_len());
+
+        let mut iter = options.options_iter();
+        assert_eq!(Some(Ipv6Option::PadN(4)), iter.next().unwrap());
+        assert_eq!(None, iter.next().unwrap());
+
+        options.reconcile_all();
+        assert_eq!(payload_len as u16 + 8, options.envelope().payload_length());
+
+        let ipv6 = options.remove().unwrap();
+        assert_eq!(next_header, ipv6.next_header());
+        assert_eq!(payload_len, ipv6.payload_len());
+    }
+
+    #[capsule::test]
+    fn push_and_remove_options_packets() {
+        push_and_remove_options_packet::<HopByHopKind>();
+        push_and_remove_options_packet::<DestinationOptionsKind>();
+    }
+
+    #[capsule::test]
+    fn append_and_remove_options() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let payload_len = ipv6.payload_len();
+        let mut hop_by_hop = ipv6.push::<HopByHop<Ipv6>>().unwrap();
+
+        let mut options = hop_by_hop.options_mut();
+        options.append(&Ipv6Option::RouterAlert(0)).unwrap();
+        options.append(&Ipv6Option::JumboPayload(70000)).unwrap();
+        assert!(options.append(&Ipv6Option::Pad1).is_err());
+
+        // 2 + 4 + 6 octets, padded to 16.
+        assert_eq!(1, hop_by_hop.hdr_ext_len());
+        assert_eq!(16, hop_by_hop.header_len());
+        assert_eq!(payload_len, hop_by_hop.payload_len());
+
+        let mut iter = hop_by_hop.options_iter();
+        assert_eq!(Some(Ipv6Option::RouterAlert(0)), iter.next().unwrap());
+        assert_eq!(Some(Ipv6Option::JumboPayload(70000)), iter.next().unwrap());
+        assert_eq!(Some(Ipv6Option::PadN(2)), iter.next().unwrap());
+        assert_eq!(None, iter.next().unwrap());
+
+        hop_by_hop
+            .options_mut()
+            .remove(Ipv6OptionTypes::JumboPayload)
+            .unwrap();
+
+        assert_eq!(0, hop_by_hop.hdr_ext_len());
+        assert_eq!(payload_len, hop_by_hop.payload_len());
+
+        let mut iter = hop_by_hop.options_iter();
+        assert_eq!(Some(Ipv6Option::RouterAlert(0)), iter.next().unwrap());
+        assert_eq!(Some(Ipv6Option::PadN(0)), iter.next().unwrap());
+        assert_eq!(None, iter.next().unwrap());
+
+        let tcp = hop_by_hop.parse::<Tcp<HopByHop<Ipv6>>>().unwrap();
+        assert_eq!(36869, tcp.src_port());
+    }
+}
@@ -16,17 +16,190 @@
 * SPDX-License-Identifier: Apache-2.0
 */
 
+use crate::net::MacAddr;
 use crate::packets::checksum::PseudoHeader;
-use crate::packets::ip::v6::Ipv6Packet;
-use crate::packets::ip::{IpPacket, ProtocolNumber, ProtocolNumbers};
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::{Ipv6, Ipv6Packet};
+use crate::packets::ip::{IpPacket, IpTunnel, ProtocolNumber, ProtocolNumbers};
 use crate::packets::types::u16be;
-use crate::packets::{Internal, Packet};
-use crate::{ensure, SizeOf};
+use crate::packets::{Datalink, Internal, Packet};
+use crate::{ensure, Mbuf, SizeOf};
 use anyhow::{anyhow, Result};
 use std::fmt;
 use std::net::{IpAddr, Ipv6Addr};
 use std::ptr::NonNull;
 
+// TLV types.
+const PAD1: u8 = 0;
+const PADN: u8 = 4;
+const HMAC: u8 = 5;
+
+/// The maximum length of the HMAC in an HMAC TLV.
+const MAX_HMAC_LEN: usize = 32;
+
+/// A type length value object in the segment routing header, based on
+/// [IETF RFC 8754].
+///
+/// The Pad1 and PadN TLVs are not represented. They are skipped when the
+/// TLVs are read, and added as needed when the TLVs are written, so the
+/// HMAC TLV is 8-octet aligned and the header is a multiple of 8 octets.
+///
+/// [IETF RFC 8754]: https://tools.ietf.org/html/rfc8754#section-2.1
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SegmentRoutingTlv {
+    /// Type 5, the HMAC TLV.
+    Hmac {
+        /// When set, the destination address must be checked against the
+        /// segment list.
+        destination_check: bool,
+        /// The key ID identifying the pre-shared key and the algorithm.
+        key_id: u32,
+        /// The HMAC, up to 32 octets in multiples of 8 octets.
+        hmac: Vec<u8>,
+    },
+    /// Any other TLV.
+    Other {
+        /// The TLV type.
+        tlv_type: u8,
+        /// The TLV value.
+        value: Vec<u8>,
+    },
+}
+
+impl SegmentRoutingTlv {
+    /// Decodes the value of the TLV with `tlv_type`.
+    fn decode(tlv_type: u8, value: &[u8]) -> Result<Self> {
+        if tlv_type == HMAC {
+            ensure!(
+                value.len() >= 6 && value.len() - 6 <= MAX_HMAC_LEN,
+                anyhow!("invalid HMAC TLV length.")
+            );
+            Ok(SegmentRoutingTlv::Hmac {
+                destination_check: value[0] & 0x80 > 0,
+                key_id: u32::from_be_bytes([value[2], value[3], value[4], value[5]]),
+                hmac: value[6..].to_vec(),
+            })
+        } else {
+            Ok(SegmentRoutingTlv::Other {
+                tlv_type,
+                value: value.to_vec(),
+            })
+        }
+    }
+
+    /// Encodes the TLV, including the type and length fields.
+    fn encode(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        match self {
+            SegmentRoutingTlv::Hmac {
+                destination_check,
+                key_id,
+                hmac,
+            } => {
+                ensure!(
+                    hmac.len() <= MAX_HMAC_LEN && hmac.len() % 8 == 0,
+                    anyhow!("invalid HMAC length {}.", hmac.len())
+                );
+                bytes.push(HMAC);
+                bytes.push((6 + hmac.len()) as u8);
+                bytes.push(if *destination_check { 0x80 } else { 0 });
+                bytes.push(0);
+                bytes.extend_from_slice(&key_id.to_be_bytes());
+                bytes.extend_from_slice(hmac);
+            }
+            SegmentRoutingTlv::Other { tlv_type, value } => {
+                ensure!(
+                    *tlv_type != PAD1 && *tlv_type != PADN,
+                    anyhow!("padding TLVs are added automatically.")
+                );
+                ensure!(
+                    value.len() <= u8::MAX as usize,
+                    anyhow!("TLV {} is too long.", tlv_type)
+                );
+                bytes.push(*tlv_type);
+                bytes.push(value.len() as u8);
+                bytes.extend_from_slice(value);
+            }
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Appends a Pad1 or PadN TLV to align `bytes` to 8 octets.
+fn pad(bytes: &mut Vec<u8>) {
+    let len = (8 - bytes.len() % 8) % 8;
+    match len {
+        0 => (),
+        1 => bytes.push(PAD1),
+        _ => {
+            bytes.push(PADN);
+            bytes.push((len - 2) as u8);
+            bytes.resize(bytes.len() + len - 2, 0);
+        }
+    }
+}
+
+/// An iterator that iterates through the TLVs in the segment routing
+/// header, skipping the padding.
+pub struct SegmentRoutingTlvsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl SegmentRoutingTlvsIterator<'_> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<SegmentRoutingTlv>> {
+        while self.offset < self.end {
+            let tlv_type = unsafe { *self.mbuf.read_data::<u8>(self.offset)?.as_ptr() };
+            if tlv_type == PAD1 {
+                self.offset += 1;
+                continue;
+            }
+
+            ensure!(
+                self.offset + 2 <= self.end,
+                anyhow!("TLV {} is truncated.", tlv_type)
+            );
+            let len = unsafe { *self.mbuf.read_data::<u8>(self.offset + 1)?.as_ptr() } as usize;
+            let value_offset = self.offset + 2;
+            ensure!(
+                value_offset + len <= self.end,
+                anyhow!("TLV {} is truncated.", tlv_type)
+            );
+            self.offset = value_offset + len;
+
+            if tlv_type == PADN {
+                continue;
+            }
+
+            let value = if len > 0 {
+                let value = self.mbuf.read_data_slice::<u8>(value_offset, len)?;
+                unsafe { &*value.as_ptr() }
+            } else {
+                &[]
+            };
+
+            return SegmentRoutingTlv::decode(tlv_type, value).map(Some);
+        }
+
+        Ok(None)
+    }
+}
+
+impl fmt::Debug for SegmentRoutingTlvsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SegmentRoutingTlvsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
 /// IPv6 Segment Routing based on [IETF DRAFT].
 ///
 /// Routing Headers are defined in [IETF RFC 8200]. The Segment Routing
@@ -96,10 +269,28 @@ use std::ptr::NonNull;
 ///
 /// # Remarks
 ///
-/// TLVs are not supported yet.
+/// The TLVs are read with [`tlvs_iter`] and replaced with [`set_tlvs`].
+///
+/// The SRv6 endpoint behaviors End, End.X, End.DT4 and End.DT6, and the
+/// headend behaviors H.Encaps and H.Insert defined in [IETF RFC 8986]
+/// are available when the envelope is an IPv6 packet. They keep the IPv6
+/// destination, the segments left and the hop limit consistent.
+///
+/// ```
+/// let mut srh = ipv6.parse::<SegmentRouting<Ipv6>>()?;
+/// if srh.segments_left() > 0 {
+///     srh.end()?;
+///     srh.reconcile_all();
+/// } else {
+///     let inner = srh.end_dt6()?;
+/// }
+/// ```
 ///
 /// [IETF Draft]: https://tools.ietf.org/html/draft-ietf-6man-segment-routing-header-26#section-2
 /// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-4.4
+/// [IETF RFC 8986]: https://tools.ietf.org/html/rfc8986#section-4
+/// [`tlvs_iter`]: SegmentRouting::tlvs_iter
+/// [`set_tlvs`]: SegmentRouting::set_tlvs
 pub struct SegmentRouting<E: Ipv6Packet> {
     envelope: E,
     header: NonNull<SegmentRoutingHeader>,
@@ -204,13 +395,21 @@ impl<E: Ipv6Packet> SegmentRouting<E> {
     ///
     /// # Errors
     ///
-    /// Returns an error if the segments length is 0. Returns an error if the
-    /// buffer does not have enough free space for the segments.
+    /// Returns an error if the segments length is 0, or the header would
+    /// be too long. Returns an error if the buffer does not have enough
+    /// free space for the segments.
     #[inline]
     pub fn set_segments(&mut self, segments: &[Ipv6Addr]) -> Result<()> {
         if !segments.is_empty() {
-            let old_len = self.last_entry() + 1;
-            let new_len = segments.len() as u8;
+            let old_len = self.segments().len();
+            let new_len = segments.len();
+            let tlvs_len = self.tlvs_len();
+
+            let hdr_ext_len = new_len * 2 + tlvs_len / 8;
+            ensure!(
+                hdr_ext_len <= u8::MAX as usize,
+                anyhow!("segment list is too long.")
+            );
 
             let segments_offset = self.offset + SegmentRoutingHeader::size_of();
 
@@ -224,13 +423,234 @@ impl<E: Ipv6Packet> SegmentRouting<E> {
                 )?;
             }
             self.segments = mbuf.write_data_slice(segments_offset, segments)?;
-            self.set_hdr_ext_len(new_len * 2);
-            self.set_last_entry(new_len - 1);
+            self.set_hdr_ext_len(hdr_ext_len as u8);
+            self.set_last_entry((new_len - 1) as u8);
             Ok(())
         } else {
             Err(anyhow!("segment list length must be greater than 0."))
         }
     }
+
+    /// Returns the buffer offset where the TLVs begin.
+    #[inline]
+    fn tlvs_offset(&self) -> usize {
+        self.offset + SegmentRoutingHeader::size_of() + self.segments().len() * Ipv6Addr::size_of()
+    }
+
+    /// Returns the length of the TLVs, including the padding.
+    #[inline]
+    fn tlvs_len(&self) -> usize {
+        self.header_len() - (self.tlvs_offset() - self.offset)
+    }
+
+    /// Returns an iterator that iterates through the TLVs after the
+    /// segment list. The padding is skipped.
+    #[inline]
+    pub fn tlvs_iter(&self) -> SegmentRoutingTlvsIterator<'_> {
+        let offset = self.tlvs_offset();
+        SegmentRoutingTlvsIterator {
+            mbuf: self.mbuf(),
+            offset,
+            end: offset + self.tlvs_len(),
+        }
+    }
+
+    /// Sets the TLVs after the segment list, replacing the existing ones.
+    ///
+    /// The HMAC TLV is aligned to 8 octets and the TLVs are padded to a
+    /// multiple of 8 octets with the Pad1 and PadN TLVs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a TLV is invalid, or the header would be too
+    /// long. Returns an error if the buffer does not have enough free space.
+    pub fn set_tlvs(&mut self, tlvs: &[SegmentRoutingTlv]) -> Result<()> {
+        let mut bytes = Vec::new();
+        for tlv in tlvs {
+            if let SegmentRoutingTlv::Hmac { .. } = tlv {
+                pad(&mut bytes);
+            }
+            bytes.extend_from_slice(&tlv.encode()?);
+        }
+        pad(&mut bytes);
+
+        let hdr_ext_len = self.segments().len() * 2 + bytes.len() / 8;
+        ensure!(
+            hdr_ext_len <= u8::MAX as usize,
+            anyhow!("segment routing header is too long.")
+        );
+
+        let offset = self.tlvs_offset();
+        let old_len = self.tlvs_len();
+        if bytes.len() != old_len {
+            self.mbuf_mut()
+                .resize(offset, bytes.len() as isize - old_len as isize)?;
+        }
+        if !bytes.is_empty() {
+            self.mbuf_mut().write_data_slice(offset, &bytes)?;
+        }
+        self.set_hdr_ext_len(hdr_ext_len as u8);
+
+        Ok(())
+    }
+}
+
+impl<D: Datalink> SegmentRouting<Ipv6<D>> {
+    /// Performs the End behavior, the endpoint behavior with the next
+    /// segment in the segment list.
+    ///
+    /// The hop limit and the segments left are decremented, and the IPv6
+    /// destination is set to the next segment. The packet should be
+    /// reconciled and forwarded after.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no segments left, in which case the
+    /// packet is for this node and the next header should be processed
+    /// instead. Returns an error if the hop limit would reach 0, or the
+    /// segments left exceeds the segment list.
+    pub fn end(&mut self) -> Result<()> {
+        let segments_left = self.segments_left();
+        ensure!(segments_left > 0, anyhow!("no segments left."));
+
+        let hop_limit = self.envelope().hop_limit();
+        ensure!(hop_limit > 1, anyhow!("hop limit exceeded in transit."));
+
+        ensure!(
+            segments_left as usize <= self.segments().len(),
+            anyhow!("segments left exceeds the segment list.")
+        );
+
+        let segments_left = segments_left - 1;
+        let dst = self.segments()[segments_left as usize];
+        self.set_segments_left(segments_left);
+        self.envelope_mut().set_hop_limit(hop_limit - 1);
+        self.envelope_mut().set_dst(dst);
+
+        Ok(())
+    }
+
+    /// Performs the End.DT4 behavior, the endpoint behavior with
+    /// decapsulation of the inner IPv4 packet.
+    ///
+    /// The outer IPv6 packet is removed along with the segment routing
+    /// header. The IPv4 routing table lookup is left to the caller.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are segments left, or the payload is not
+    /// an IPv4 packet.
+    pub fn end_dt4(self) -> Result<Ipv4<D>> {
+        self.decap_end::<Ipv4<D>>(ProtocolNumbers::Ipv4)
+    }
+
+    /// Performs the End.DT6 behavior, the endpoint behavior with
+    /// decapsulation of the inner IPv6 packet.
+    ///
+    /// The outer IPv6 packet is removed along with the segment routing
+    /// header. The IPv6 routing table lookup is left to the caller.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are segments left, or the payload is not
+    /// an IPv6 packet.
+    pub fn end_dt6(self) -> Result<Ipv6<D>> {
+        self.decap_end::<Ipv6<D>>(ProtocolNumbers::Ipv6)
+    }
+
+    fn decap_end<I: IpTunnel<Envelope = D>>(self, protocol: ProtocolNumber) -> Result<I> {
+        ensure!(
+            self.segments_left() == 0,
+            anyhow!("segments left must be 0 to decapsulate.")
+        );
+        ensure!(
+            self.next_header() == protocol,
+            anyhow!("payload is not a {} packet.", protocol)
+        );
+
+        self.remove()?.decap::<I>()
+    }
+
+    /// Performs the H.Encaps behavior, encapsulating the packet in an
+    /// outer IPv6 packet with a segment routing header.
+    ///
+    /// `segments` is the SR policy in the order the segments are visited.
+    /// The segment list is encoded in the reverse order, the segments left
+    /// is set to the index of the first segment, and the outer destination
+    /// is set to the first segment. The inner packet is handled as by
+    /// [`IpTunnel::encap`]. The outer packet is reconciled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `segments` is empty, or the hop limit of the
+    /// inner packet would reach 0. Returns an error if the buffer does not
+    /// have enough free space.
+    ///
+    /// [`IpTunnel::encap`]: IpTunnel::encap
+    pub fn h_encaps<I: IpTunnel<Envelope = D>>(
+        inner: I,
+        src: Ipv6Addr,
+        segments: &[Ipv6Addr],
+    ) -> Result<Self> {
+        ensure!(!segments.is_empty(), anyhow!("empty SR policy."));
+
+        let mut outer = inner.encap::<Ipv6<D>>()?;
+        outer.set_src(src);
+
+        let list = segments.iter().rev().copied().collect::<Vec<_>>();
+        outer.push::<Self>()?.apply_policy(&list)
+    }
+
+    /// Performs the H.Insert behavior, inserting a segment routing header
+    /// into the IPv6 packet.
+    ///
+    /// `segments` is the SR policy in the order the segments are visited.
+    /// The original destination becomes the last segment in the segment
+    /// list, and the destination is set to the first segment. The packet
+    /// is reconciled. The upper layer checksum does not change because the
+    /// final destination does not change.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `segments` is empty. Returns an error if the
+    /// buffer does not have enough free space.
+    pub fn h_insert(ipv6: Ipv6<D>, segments: &[Ipv6Addr]) -> Result<Self> {
+        ensure!(!segments.is_empty(), anyhow!("empty SR policy."));
+
+        let mut list = vec![ipv6.dst()];
+        list.extend(segments.iter().rev());
+        ipv6.push::<Self>()?.apply_policy(&list)
+    }
+
+    /// Sets the segment list and points the destination at the first
+    /// segment to visit.
+    fn apply_policy(mut self, list: &[Ipv6Addr]) -> Result<Self> {
+        self.set_segments(list)?;
+
+        let segments_left = list.len() - 1;
+        self.set_segments_left(segments_left as u8);
+        self.envelope_mut().set_dst(list[segments_left]);
+        self.reconcile_all();
+
+        Ok(self)
+    }
+}
+
+impl SegmentRouting<Ipv6> {
+    /// Performs the End.X behavior, the End behavior with a cross-connect
+    /// to a layer 3 adjacency.
+    ///
+    /// After the End behavior, the Ethernet destination is set to `next_hop`,
+    /// the MAC address of the adjacency.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the End behavior fails.
+    pub fn end_x(&mut self, next_hop: MacAddr) -> Result<()> {
+        self.end()?;
+        self.envelope_mut().envelope_mut().set_dst(next_hop);
+        Ok(())
+    }
 }
 
 impl<E: Ipv6Packet> fmt::Debug for SegmentRouting<E> {
@@ -270,9 +690,11 @@ impl<E: Ipv6Packet> Packet for SegmentRouting<E> {
         self.offset
     }
 
+    /// Returns the length of the header, including the segment list and
+    /// the TLVs.
     #[inline]
     fn header_len(&self) -> usize {
-        SegmentRoutingHeader::size_of() + self.segments().len() * Ipv6Addr::size_of()
+        SegmentRoutingHeader::size_of() + self.hdr_ext_len() as usize * 8
     }
 
     #[inline]
@@ -306,24 +728,28 @@ impl<E: Ipv6Packet> Packet for SegmentRouting<E> {
         let offset = envelope.payload_offset();
         let header = mbuf.read_data::<SegmentRoutingHeader>(offset)?;
 
-        let hdr_ext_len = unsafe { header.as_ref().hdr_ext_len };
-        let segments_len = unsafe { header.as_ref().last_entry + 1 };
+        let hdr_ext_len = unsafe { header.as_ref().hdr_ext_len } as usize;
+        let segments_len = unsafe { header.as_ref().last_entry } as usize + 1;
 
-        if hdr_ext_len != 0 && (2 * segments_len == hdr_ext_len) {
-            let segments = mbuf.read_data_slice::<Ipv6Addr>(
-                offset + SegmentRoutingHeader::size_of(),
-                segments_len as usize,
-            )?;
+        // the TLVs, if any, follow the segment list.
+        ensure!(
+            hdr_ext_len != 0 && 2 * segments_len <= hdr_ext_len,
+            anyhow!("Packet has inconsistent segment list length.")
+        );
+        ensure!(
+            offset + SegmentRoutingHeader::size_of() + hdr_ext_len * 8 <= mbuf.data_len(),
+            anyhow!("Packet has truncated segment routing header.")
+        );
 
-            Ok(SegmentRouting {
-                envelope,
-                header,
-                segments,
-                offset,
-            })
-        } else {
-            Err(anyhow!("Packet has inconsistent segment list length."))
-        }
+        let segments = mbuf
+            .read_data_slice::<Ipv6Addr>(offset + SegmentRoutingHeader::size_of(), segments_len)?;
+
+        Ok(SegmentRouting {
+            envelope,
+            header,
+            segments,
+            offset,
+        })
     }
 
     /// Prepends an IPv6 segment routing packet with a segment list of one
@@ -525,9 +951,8 @@ mod tests {
     use super::*;
     use crate::packets::ip::v6::Ipv6;
     use crate::packets::ip::ProtocolNumbers;
-    use crate::packets::{Ethernet, Tcp, Tcp6};
-    use crate::testils::byte_arrays::{IPV6_TCP_PACKET, SR_TCP_PACKET};
-    use crate::Mbuf;
+    use crate::packets::{EtherTypes, Ethernet, Tcp, Tcp6, Udp};
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, IPV6_TCP_PACKET, SR_TCP_PACKET};
 
     #[test]
     fn size_of_segment_routing_header() {
@@ -696,4 +1121,171 @@ mod tests {
         let tcp = ipv6.parse::<Tcp6>().unwrap();
         assert_eq!(3464, tcp.src_port());
     }
+
+    #[capsule::test]
+    fn set_and_iterate_tlvs() {
+        let packet = Mbuf::from_bytes(&SR_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let mut srh = ipv6.parse::<SegmentRouting<Ipv6>>().unwrap();
+        assert!(srh.tlvs_iter().next().unwrap().is_none());
+
+        let other = SegmentRoutingTlv::Other {
+            tlv_type: 128,
+            value: vec![1, 2, 3],
+        };
+        let hmac = SegmentRoutingTlv::Hmac {
+            destination_check: true,
+            key_id: 42,
+            hmac: vec![0xaa; 32],
+        };
+        assert!(srh
+            .set_tlvs(&[SegmentRoutingTlv::Hmac {
+                destination_check: false,
+                key_id: 1,
+                hmac: vec![0; 7],
+            }])
+            .is_err());
+
+        // 5 octets, 3 octets of padding, then the 40 octets of HMAC.
+        srh.set_tlvs(&[other.clone(), hmac.clone()]).unwrap();
+        assert_eq!(12, srh.hdr_ext_len());
+        assert_eq!(104, srh.header_len());
+        assert_eq!(3, srh.segments().len());
+
+        // the segments are resized in front of the TLVs.
+        let segment1: Ipv6Addr = "::1".parse().unwrap();
+        srh.set_segments(&[segment1]).unwrap();
+        assert_eq!(8, srh.hdr_ext_len());
+
+        let mut ipv6 = srh.deparse();
+        ipv6.reconcile();
+        let srh = ipv6.parse::<SegmentRouting<Ipv6>>().unwrap();
+        let mut iter = srh.tlvs_iter();
+        assert_eq!(other, iter.next().unwrap().unwrap());
+        assert_eq!(hmac, iter.next().unwrap().unwrap());
+        assert!(iter.next().unwrap().is_none());
+
+        // make sure rest of the packet still valid
+        let tcp = srh.parse::<Tcp<SegmentRouting<Ipv6>>>().unwrap();
+        assert_eq!(3464, tcp.src_port());
+
+        let mut srh = tcp.deparse();
+        srh.set_tlvs(&[]).unwrap();
+        assert_eq!(2, srh.hdr_ext_len());
+        assert!(srh.tlvs_iter().next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn end_behavior() {
+        let packet = Mbuf::from_bytes(&SR_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        ipv6.set_hop_limit(64);
+        let mut srh = ipv6.parse::<SegmentRouting<Ipv6>>().unwrap();
+        srh.set_segments_left(2);
+
+        srh.end().unwrap();
+        assert_eq!(1, srh.segments_left());
+        assert_eq!(63, srh.envelope().hop_limit());
+        assert_eq!(srh.segments()[1], srh.envelope().dst());
+
+        let next_hop = MacAddr::new(0x02, 0, 0, 0, 0, 0x01);
+        srh.end_x(next_hop).unwrap();
+        assert_eq!(0, srh.segments_left());
+        assert_eq!(62, srh.envelope().hop_limit());
+        assert_eq!(srh.segments()[0], srh.envelope().dst());
+        assert_eq!(next_hop, srh.envelope().envelope().dst());
+
+        assert!(srh.end().is_err());
+
+        srh.set_segments_left(1);
+        srh.envelope_mut().set_hop_limit(1);
+        assert!(srh.end().is_err());
+    }
+
+    #[capsule::test]
+    fn h_encaps_and_end_dt6() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let inner = ethernet.parse::<Ipv6>().unwrap();
+        let inner_dst = inner.dst();
+
+        let src: Ipv6Addr = "fc00::1".parse().unwrap();
+        let segment1: Ipv6Addr = "fc00::a".parse().unwrap();
+        let segment2: Ipv6Addr = "fc00::b".parse().unwrap();
+
+        let mut srh = SegmentRouting::h_encaps(inner, src, &[segment1, segment2]).unwrap();
+        assert_eq!(ProtocolNumbers::Ipv6, srh.next_header());
+        assert_eq!(1, srh.segments_left());
+        assert_eq!(&[segment2, segment1], srh.segments());
+        assert_eq!(src, srh.envelope().src());
+        assert_eq!(segment1, srh.envelope().dst());
+        assert_eq!(
+            (IPV6_TCP_PACKET.len() - 14 + 40) as u16,
+            srh.envelope().payload_length()
+        );
+
+        srh.end().unwrap();
+        assert_eq!(0, srh.segments_left());
+        assert_eq!(segment2, srh.envelope().dst());
+
+        let inner = srh.end_dt6().unwrap();
+        assert_eq!(inner_dst, inner.dst());
+        assert_eq!(EtherTypes::Ipv6, inner.envelope().ether_type());
+
+        let tcp = inner.parse::<Tcp6>().unwrap();
+        assert_eq!(36869, tcp.src_port());
+    }
+
+    #[capsule::test]
+    fn h_encaps_and_end_dt4() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let inner = ethernet.parse::<Ipv4>().unwrap();
+
+        let src: Ipv6Addr = "fc00::1".parse().unwrap();
+        let segment1: Ipv6Addr = "fc00::a".parse().unwrap();
+
+        let srh = SegmentRouting::h_encaps(inner, src, &[segment1, segment1]).unwrap();
+        assert!(srh.end_dt4().is_err());
+
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let inner = ethernet.parse::<Ipv4>().unwrap();
+
+        let srh = SegmentRouting::h_encaps(inner, src, &[segment1]).unwrap();
+        assert_eq!(ProtocolNumbers::Ipv4, srh.next_header());
+        assert_eq!(0, srh.segments_left());
+        assert_eq!(EtherTypes::Ipv6, srh.envelope().envelope().ether_type());
+
+        let inner = srh.end_dt4().unwrap();
+        assert_eq!(EtherTypes::Ipv4, inner.envelope().ether_type());
+        assert_eq!(254, inner.ttl());
+
+        let udp = inner.parse::<Udp<Ipv4>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+    }
+
+    #[capsule::test]
+    fn h_insert() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let dst = ipv6.dst();
+        let payload_len = ipv6.payload_len();
+
+        let segment1: Ipv6Addr = "fc00::a".parse().unwrap();
+        let segment2: Ipv6Addr = "fc00::b".parse().unwrap();
+
+        let srh = SegmentRouting::h_insert(ipv6, &[segment1, segment2]).unwrap();
+        assert_eq!(ProtocolNumbers::Tcp, srh.next_header());
+        assert_eq!(2, srh.segments_left());
+        assert_eq!(&[dst, segment2, segment1], srh.segments());
+        assert_eq!(segment1, srh.envelope().dst());
+        assert_eq!((payload_len + 56) as u16, srh.envelope().payload_length());
+
+        let tcp = srh.parse::<Tcp<SegmentRouting<Ipv6>>>().unwrap();
+        assert_eq!(36869, tcp.src_port());
+    }
 }
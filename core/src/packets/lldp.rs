@@ -0,0 +1,700 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::net::MacAddr;
+use crate::packets::{EtherTypes, Ethernet, Internal, Packet};
+use crate::{ensure, Mbuf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+
+/// The nearest bridge multicast address. Frames sent to this address are
+/// not forwarded by any bridge.
+pub const LLDP_NEAREST_BRIDGE: MacAddr = MacAddr::new(0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e);
+
+/// The maximum length of a TLV's value in octets.
+pub const LLDP_MAX_TLV_LEN: usize = 511;
+
+/// Chassis ID subtype of a MAC address.
+pub const LLDP_CHASSIS_ID_MAC_ADDR: u8 = 4;
+
+/// Chassis ID subtype of a locally assigned name.
+pub const LLDP_CHASSIS_ID_LOCAL: u8 = 7;
+
+/// Port ID subtype of a MAC address.
+pub const LLDP_PORT_ID_MAC_ADDR: u8 = 3;
+
+/// Port ID subtype of an interface name.
+pub const LLDP_PORT_ID_INTERFACE_NAME: u8 = 5;
+
+/// Port ID subtype of a locally assigned name.
+pub const LLDP_PORT_ID_LOCAL: u8 = 7;
+
+// TLV types.
+const END: u8 = 0;
+const CHASSIS_ID: u8 = 1;
+const PORT_ID: u8 = 2;
+const TTL: u8 = 3;
+const PORT_DESCRIPTION: u8 = 4;
+const SYSTEM_NAME: u8 = 5;
+const SYSTEM_DESCRIPTION: u8 = 6;
+const ORG_SPECIFIC: u8 = 127;
+
+/// The length of the TLV type and length fields.
+const TLV_HEADER_LEN: usize = 2;
+
+/// An LLDP TLV based on [IEEE 802.1AB].
+///
+/// ```
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  TLV Type   |  Length (9 bits)  |   Value (0-511 octets)      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// TLVs are read out of and written into the buffer by value. The end of
+/// LLDPDU TLV is handled by the iterator and the builder, and is not
+/// exposed. TLVs not modeled here are returned as `Other`.
+///
+/// [IEEE 802.1AB]: https://standards.ieee.org/standard/802_1AB-2016.html
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LldpTlv {
+    /// Type 1, identifies the chassis of the sender.
+    ChassisId {
+        /// The chassis ID subtype, for example [`LLDP_CHASSIS_ID_MAC_ADDR`].
+        ///
+        /// [`LLDP_CHASSIS_ID_MAC_ADDR`]: LLDP_CHASSIS_ID_MAC_ADDR
+        subtype: u8,
+        /// The chassis ID.
+        id: Vec<u8>,
+    },
+    /// Type 2, identifies the port of the sender.
+    PortId {
+        /// The port ID subtype, for example [`LLDP_PORT_ID_INTERFACE_NAME`].
+        ///
+        /// [`LLDP_PORT_ID_INTERFACE_NAME`]: LLDP_PORT_ID_INTERFACE_NAME
+        subtype: u8,
+        /// The port ID.
+        id: Vec<u8>,
+    },
+    /// Type 3, the number of seconds the information is valid. Zero means
+    /// the information should be removed right away.
+    Ttl(u16),
+    /// Type 4, a description of the port.
+    PortDescription(String),
+    /// Type 5, the administratively assigned name of the system.
+    SystemName(String),
+    /// Type 6, a description of the system.
+    SystemDescription(String),
+    /// Type 127, an organizationally specific TLV.
+    OrgSpecific {
+        /// The organizationally unique identifier.
+        oui: [u8; 3],
+        /// The organizationally defined subtype.
+        subtype: u8,
+        /// The organizationally defined information.
+        info: Vec<u8>,
+    },
+    /// Any other TLV.
+    Other {
+        /// The TLV type.
+        tlv_type: u8,
+        /// The TLV value.
+        value: Vec<u8>,
+    },
+}
+
+impl LldpTlv {
+    /// Returns the TLV type.
+    pub fn tlv_type(&self) -> u8 {
+        match self {
+            LldpTlv::ChassisId { .. } => CHASSIS_ID,
+            LldpTlv::PortId { .. } => PORT_ID,
+            LldpTlv::Ttl(_) => TTL,
+            LldpTlv::PortDescription(_) => PORT_DESCRIPTION,
+            LldpTlv::SystemName(_) => SYSTEM_NAME,
+            LldpTlv::SystemDescription(_) => SYSTEM_DESCRIPTION,
+            LldpTlv::OrgSpecific { .. } => ORG_SPECIFIC,
+            LldpTlv::Other { tlv_type, .. } => *tlv_type,
+        }
+    }
+
+    /// Decodes the value of the TLV with `tlv_type`.
+    fn decode(tlv_type: u8, value: &[u8]) -> Result<Self> {
+        let tlv = match tlv_type {
+            CHASSIS_ID | PORT_ID => {
+                ensure!(
+                    value.len() >= 2,
+                    anyhow!("invalid LLDP TLV {} length.", tlv_type)
+                );
+                let subtype = value[0];
+                let id = value[1..].to_vec();
+                if tlv_type == CHASSIS_ID {
+                    LldpTlv::ChassisId { subtype, id }
+                } else {
+                    LldpTlv::PortId { subtype, id }
+                }
+            }
+            TTL => {
+                ensure!(value.len() == 2, anyhow!("invalid LLDP TTL length."));
+                LldpTlv::Ttl(u16::from_be_bytes([value[0], value[1]]))
+            }
+            PORT_DESCRIPTION => {
+                LldpTlv::PortDescription(String::from_utf8_lossy(value).into_owned())
+            }
+            SYSTEM_NAME => LldpTlv::SystemName(String::from_utf8_lossy(value).into_owned()),
+            SYSTEM_DESCRIPTION => {
+                LldpTlv::SystemDescription(String::from_utf8_lossy(value).into_owned())
+            }
+            ORG_SPECIFIC => {
+                ensure!(
+                    value.len() >= 4,
+                    anyhow!("invalid LLDP organizationally specific TLV length.")
+                );
+                LldpTlv::OrgSpecific {
+                    oui: [value[0], value[1], value[2]],
+                    subtype: value[3],
+                    info: value[4..].to_vec(),
+                }
+            }
+            _ => LldpTlv::Other {
+                tlv_type,
+                value: value.to_vec(),
+            },
+        };
+
+        Ok(tlv)
+    }
+
+    /// Encodes the TLV, including the type and length fields.
+    fn encode(&self) -> Result<Vec<u8>> {
+        let value = match self {
+            LldpTlv::ChassisId { subtype, id } | LldpTlv::PortId { subtype, id } => {
+                ensure!(!id.is_empty(), anyhow!("LLDP ID can't be empty."));
+                let mut value = vec![*subtype];
+                value.extend_from_slice(id);
+                value
+            }
+            LldpTlv::Ttl(ttl) => ttl.to_be_bytes().to_vec(),
+            LldpTlv::PortDescription(s)
+            | LldpTlv::SystemName(s)
+            | LldpTlv::SystemDescription(s) => s.as_bytes().to_vec(),
+            LldpTlv::OrgSpecific { oui, subtype, info } => {
+                let mut value = oui.to_vec();
+                value.push(*subtype);
+                value.extend_from_slice(info);
+                value
+            }
+            LldpTlv::Other { tlv_type, value } => {
+                ensure!(
+                    *tlv_type != END && *tlv_type < 128,
+                    anyhow!("invalid LLDP TLV type {}.", tlv_type)
+                );
+                value.clone()
+            }
+        };
+
+        ensure!(
+            value.len() <= LLDP_MAX_TLV_LEN,
+            anyhow!("LLDP TLV {} is too long.", self.tlv_type())
+        );
+
+        let header = (self.tlv_type() as u16) << 9 | value.len() as u16;
+        let mut bytes = Vec::with_capacity(TLV_HEADER_LEN + value.len());
+        bytes.extend_from_slice(&header.to_be_bytes());
+        bytes.extend_from_slice(&value);
+        Ok(bytes)
+    }
+}
+
+/// Link Layer Discovery Protocol data unit based on [IEEE 802.1AB].
+///
+/// ```
+/// +------------+------------+------------+------------+-----+------------+
+/// | Chassis ID |  Port ID   |    TTL     | Optional   | ... | End of     |
+/// |    TLV     |    TLV     |    TLV     |    TLV     |     | LLDPDU TLV |
+/// +------------+------------+------------+------------+-----+------------+
+/// ```
+///
+/// An LLDPDU has no fixed header. It is a sequence of [`LldpTlv`]s, which
+/// must start with the chassis ID, the port ID and the TTL TLVs, in that
+/// order, and is terminated by the end of LLDPDU TLV. The whole sequence
+/// is treated as the header of the packet.
+///
+/// Combined with [`add_periodic_pipeline_to_core`], a function can
+/// advertise itself to the adjacent switches.
+///
+/// ```
+/// fn install(qs: HashMap<String, PortQueue>) -> impl Pipeline {
+///     let src_mac = qs["eth1"].mac_addr();
+///
+///     batch::poll_fn(|| Mbuf::alloc_bulk(1).unwrap())
+///         .map(move |packet| {
+///             let mut ethernet = packet.push::<Ethernet>()?;
+///             ethernet.set_src(src_mac);
+///
+///             let mut lldp = ethernet.push::<Lldp>()?;
+///             let mut tlvs = lldp.tlvs_mut();
+///             tlvs.append(&LldpTlv::ChassisId {
+///                 subtype: LLDP_CHASSIS_ID_MAC_ADDR,
+///                 id: src_mac.octets().to_vec(),
+///             })?;
+///             tlvs.append(&LldpTlv::PortId {
+///                 subtype: LLDP_PORT_ID_INTERFACE_NAME,
+///                 id: b"eth1".to_vec(),
+///             })?;
+///             tlvs.append(&LldpTlv::Ttl(120))?;
+///             tlvs.append(&LldpTlv::SystemName("capsule".to_owned()))?;
+///             Ok(lldp)
+///         })
+///         .send(qs["eth1"].clone())
+/// }
+///
+/// Runtime::build(config)?
+///     .add_periodic_pipeline_to_core(1, install, Duration::from_secs(30))?
+///     .execute()
+/// ```
+///
+/// [IEEE 802.1AB]: https://standards.ieee.org/standard/802_1AB-2016.html
+/// [`LldpTlv`]: LldpTlv
+/// [`add_periodic_pipeline_to_core`]: crate::Runtime::add_periodic_pipeline_to_core
+pub struct Lldp {
+    envelope: Ethernet,
+    offset: usize,
+    len: usize,
+}
+
+impl Lldp {
+    /// Returns an iterator that iterates through the TLVs.
+    #[inline]
+    pub fn tlvs_iter(&self) -> LldpTlvsIterator<'_> {
+        LldpTlvsIterator {
+            mbuf: self.mbuf(),
+            offset: self.offset,
+            end: self.offset + self.len,
+        }
+    }
+
+    /// Returns the TLVs for modification.
+    #[inline]
+    pub fn tlvs_mut(&mut self) -> LldpTlvs<'_> {
+        LldpTlvs {
+            mbuf: self.envelope.mbuf_mut(),
+            offset: self.offset,
+            len: &mut self.len,
+        }
+    }
+}
+
+impl fmt::Debug for Lldp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("lldp")
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Lldp {
+    /// The preceding type for an LLDP packet must be Ethernet.
+    type Envelope = Ethernet;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the TLVs, including the end of LLDPDU TLV.
+    #[inline]
+    fn header_len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Lldp {
+            envelope: self.envelope.clone(internal),
+            offset: self.offset,
+            len: self.len,
+        }
+    }
+
+    /// Parses the Ethernet payload as an LLDP packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the [`ether_type`] is not set to
+    /// [`EtherTypes::Lldp`]. Returns an error if a TLV is truncated, or the
+    /// LLDPDU is not terminated by the end of LLDPDU TLV.
+    ///
+    /// [`ether_type`]: Ethernet::ether_type
+    /// [`EtherTypes::Lldp`]: EtherTypes::Lldp
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.ether_type() == EtherTypes::Lldp,
+            anyhow!("not an LLDP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let end = mbuf.data_len();
+
+        let mut cursor = offset;
+        loop {
+            ensure!(cursor < end, anyhow!("missing end of LLDPDU TLV."));
+            let (tlv_type, len) = read_tlv(mbuf, cursor, end)?;
+            cursor += len;
+            if tlv_type == END {
+                break;
+            }
+        }
+
+        Ok(Lldp {
+            envelope,
+            offset,
+            len: cursor - offset,
+        })
+    }
+
+    /// Prepends an LLDP packet with only the end of LLDPDU TLV to the
+    /// beginning of the Ethernet's payload.
+    ///
+    /// [`ether_type`] is set to [`EtherTypes::Lldp`], and the destination
+    /// is set to [`LLDP_NEAREST_BRIDGE`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`ether_type`]: Ethernet::ether_type
+    /// [`EtherTypes::Lldp`]: EtherTypes::Lldp
+    /// [`LLDP_NEAREST_BRIDGE`]: LLDP_NEAREST_BRIDGE
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, TLV_HEADER_LEN)?;
+        mbuf.write_data_slice(offset, &[0u8; TLV_HEADER_LEN])?;
+
+        envelope.set_ether_type(EtherTypes::Lldp);
+        envelope.set_dst(LLDP_NEAREST_BRIDGE);
+
+        Ok(Lldp {
+            envelope,
+            offset,
+            len: TLV_HEADER_LEN,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// Reads the type and the total length of the TLV at `offset`.
+fn read_tlv(mbuf: &Mbuf, offset: usize, end: usize) -> Result<(u8, usize)> {
+    ensure!(
+        offset + TLV_HEADER_LEN <= end,
+        anyhow!("LLDP TLV is truncated.")
+    );
+    let header = mbuf.read_data_slice::<u8>(offset, TLV_HEADER_LEN)?;
+    let header = unsafe { header.as_ref() };
+    let header = u16::from_be_bytes([header[0], header[1]]);
+
+    let tlv_type = (header >> 9) as u8;
+    let len = TLV_HEADER_LEN + (header & 0x01ff) as usize;
+    ensure!(
+        offset + len <= end,
+        anyhow!("LLDP TLV {} is truncated.", tlv_type)
+    );
+
+    Ok((tlv_type, len))
+}
+
+/// Reads and decodes the TLV at `offset`, returning `None` for the end of
+/// LLDPDU TLV.
+fn decode_tlv(mbuf: &Mbuf, offset: usize, end: usize) -> Result<(Option<LldpTlv>, usize)> {
+    let (tlv_type, len) = read_tlv(mbuf, offset, end)?;
+    if tlv_type == END {
+        return Ok((None, len));
+    }
+
+    let value = if len > TLV_HEADER_LEN {
+        let value = mbuf.read_data_slice::<u8>(offset + TLV_HEADER_LEN, len - TLV_HEADER_LEN)?;
+        unsafe { &*value.as_ptr() }
+    } else {
+        &[]
+    };
+
+    Ok((Some(LldpTlv::decode(tlv_type, value)?), len))
+}
+
+/// An iterator that iterates through the LLDP TLVs.
+pub struct LldpTlvsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl LldpTlvsIterator<'_> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<LldpTlv>> {
+        if self.offset >= self.end {
+            return Ok(None);
+        }
+
+        let (tlv, len) = decode_tlv(self.mbuf, self.offset, self.end)?;
+        self.offset += len;
+        Ok(tlv)
+    }
+}
+
+impl fmt::Debug for LldpTlvsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LldpTlvsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// TLVs in an LLDP packet.
+///
+/// Adding or removing TLVs resizes the packet. The end of LLDPDU TLV always
+/// stays last.
+pub struct LldpTlvs<'a> {
+    mbuf: &'a mut Mbuf,
+    offset: usize,
+    /// The length of the TLVs, including the end of LLDPDU TLV.
+    len: &'a mut usize,
+}
+
+impl LldpTlvs<'_> {
+    /// Returns an iterator to read the TLVs.
+    #[inline]
+    pub fn iter(&self) -> LldpTlvsIterator<'_> {
+        LldpTlvsIterator {
+            mbuf: self.mbuf,
+            offset: self.offset,
+            end: self.offset + *self.len,
+        }
+    }
+
+    /// Appends a new TLV before the end of LLDPDU TLV.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TLV value is longer than 511 octets, or the
+    /// buffer does not have enough free space.
+    pub fn append(&mut self, tlv: &LldpTlv) -> Result<()> {
+        let bytes = tlv.encode()?;
+        let offset = self.offset + *self.len - TLV_HEADER_LEN;
+
+        self.mbuf.extend(offset, bytes.len())?;
+        self.mbuf.write_data_slice(offset, &bytes)?;
+        *self.len += bytes.len();
+
+        Ok(())
+    }
+
+    /// Retains only the TLVs specified by the predicate.
+    ///
+    /// In other words, remove all TLVs `t` such that `f(t)` returns false.
+    /// If an error occurs, all removals done prior to the error cannot be
+    /// undone.
+    pub fn retain<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&LldpTlv) -> bool,
+    {
+        let mut offset = self.offset;
+        loop {
+            let end = self.offset + *self.len;
+            let (tlv, len) = decode_tlv(self.mbuf, offset, end)?;
+            match tlv {
+                Some(tlv) if !f(&tlv) => {
+                    self.mbuf.shrink(offset, len)?;
+                    *self.len -= len;
+                }
+                Some(_) => offset += len,
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for LldpTlvs<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LldpTlvs")
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_and_decode_lldp_tlvs() {
+        let tlv = LldpTlv::Ttl(120);
+        let bytes = tlv.encode().unwrap();
+        assert_eq!(vec![0x06, 0x02, 0x00, 0x78], bytes);
+        assert_eq!(tlv, LldpTlv::decode(TTL, &bytes[2..]).unwrap());
+
+        let tlv = LldpTlv::Other {
+            tlv_type: 8,
+            value: vec![0; 300],
+        };
+        let bytes = tlv.encode().unwrap();
+        assert_eq!([0x11, 0x2c], bytes[..2]);
+
+        assert!(LldpTlv::SystemName("a".repeat(512)).encode().is_err());
+        assert!(LldpTlv::decode(PORT_ID, &[5]).is_err());
+    }
+
+    #[capsule::test]
+    fn parse_lldp_packet() {
+        let packet = Mbuf::from_bytes(&LLDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let lldp = ethernet.parse::<Lldp>().unwrap();
+
+        assert_eq!(LLDP_PACKET.len() - 14, lldp.header_len());
+
+        let mut iter = lldp.tlvs_iter();
+        assert_eq!(
+            LldpTlv::ChassisId {
+                subtype: LLDP_CHASSIS_ID_MAC_ADDR,
+                id: vec![0x00, 0x01, 0x30, 0xf9, 0xad, 0xa0],
+            },
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(
+            LldpTlv::PortId {
+                subtype: LLDP_PORT_ID_INTERFACE_NAME,
+                id: b"1/1".to_vec(),
+            },
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(LldpTlv::Ttl(120), iter.next().unwrap().unwrap());
+        assert_eq!(
+            LldpTlv::SystemName("sw1".to_string()),
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(
+            LldpTlv::OrgSpecific {
+                oui: [0x00, 0x12, 0x0f],
+                subtype: 1,
+                info: vec![0x03],
+            },
+            iter.next().unwrap().unwrap()
+        );
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn parse_lldp_packet_without_end() {
+        let packet = Mbuf::from_bytes(&LLDP_PACKET[..LLDP_PACKET.len() - 2]).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        assert!(ethernet.parse::<Lldp>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_lldp_packet() {
+        let packet = Mbuf::new().unwrap();
+        let mut ethernet = packet.push::<Ethernet>().unwrap();
+        ethernet.set_src(MacAddr::new(0x00, 0x01, 0x30, 0xf9, 0xad, 0xa0));
+        let mut lldp = ethernet.push::<Lldp>().unwrap();
+
+        assert_eq!(EtherTypes::Lldp, lldp.envelope().ether_type());
+        assert_eq!(LLDP_NEAREST_BRIDGE, lldp.envelope().dst());
+        assert_eq!(2, lldp.header_len());
+        assert!(lldp.tlvs_iter().next().unwrap().is_none());
+
+        let mut tlvs = lldp.tlvs_mut();
+        tlvs.append(&LldpTlv::ChassisId {
+            subtype: LLDP_CHASSIS_ID_MAC_ADDR,
+            id: vec![0x00, 0x01, 0x30, 0xf9, 0xad, 0xa0],
+        })
+        .unwrap();
+        tlvs.append(&LldpTlv::PortId {
+            subtype: LLDP_PORT_ID_INTERFACE_NAME,
+            id: b"1/1".to_vec(),
+        })
+        .unwrap();
+        tlvs.append(&LldpTlv::Ttl(120)).unwrap();
+        tlvs.append(&LldpTlv::SystemDescription("test".to_string()))
+            .unwrap();
+        tlvs.append(&LldpTlv::SystemName("sw1".to_string()))
+            .unwrap();
+        tlvs.append(&LldpTlv::OrgSpecific {
+            oui: [0x00, 0x12, 0x0f],
+            subtype: 1,
+            info: vec![0x03],
+        })
+        .unwrap();
+        tlvs.retain(|tlv| !matches!(tlv, LldpTlv::SystemDescription(_)))
+            .unwrap();
+
+        assert_eq!(LLDP_PACKET.len() - 14, lldp.header_len());
+        let bytes = lldp
+            .mbuf()
+            .read_data_slice::<u8>(0, LLDP_PACKET.len())
+            .unwrap();
+        assert_eq!(&LLDP_PACKET[..], unsafe { bytes.as_ref() });
+    }
+
+    /// LLDP packet with the mandatory TLVs, the system name and an 802.3
+    /// organizationally specific TLV.
+    #[rustfmt::skip]
+    const LLDP_PACKET: [u8; 47] = [
+    // Ethernet header
+        0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e,
+        0x00, 0x01, 0x30, 0xf9, 0xad, 0xa0,
+        0x88, 0xcc,
+    // chassis id, mac address
+        0x02, 0x07, 0x04, 0x00, 0x01, 0x30, 0xf9, 0xad, 0xa0,
+    // port id, interface name "1/1"
+        0x04, 0x04, 0x05, 0x31, 0x2f, 0x31,
+    // ttl = 120
+        0x06, 0x02, 0x00, 0x78,
+    // system name "sw1"
+        0x0a, 0x03, 0x73, 0x77, 0x31,
+    // IEEE 802.3 organizationally specific TLV
+        0xfe, 0x05, 0x00, 0x12, 0x0f, 0x01, 0x03,
+    // end of LLDPDU
+        0x00, 0x00,
+    ];
+}
@@ -20,16 +20,36 @@
 
 pub mod arp;
 pub mod checksum;
+mod dhcpv4;
+mod dns;
 mod ethernet;
+mod geneve;
+mod gre;
+mod gtpu;
 pub mod icmp;
+mod igmp;
 pub mod ip;
+mod lldp;
+mod mpls;
+mod sctp;
 mod tcp;
 pub mod types;
 mod udp;
+mod vxlan;
 
+pub use self::dhcpv4::*;
+pub use self::dns::*;
 pub use self::ethernet::*;
+pub use self::geneve::*;
+pub use self::gre::*;
+pub use self::gtpu::*;
+pub use self::igmp::*;
+pub use self::lldp::*;
+pub use self::mpls::*;
+pub use self::sctp::*;
 pub use self::tcp::*;
 pub use self::udp::*;
+pub use self::vxlan::*;
 
 use crate::Mbuf;
 use anyhow::{Context, Result};
@@ -0,0 +1,676 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::ip::DEFAULT_IP_TTL;
+use crate::packets::{Datalink, EtherType, EtherTypes, Ethernet, Internal, Packet};
+use crate::{ensure, Mbuf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+
+/// The largest MPLS label value, which is 20 bits.
+pub const MPLS_MAX_LABEL: u32 = 0x000f_ffff;
+
+// Label stack entry length in octets.
+const ENTRY_LEN: usize = 4;
+
+// Masks.
+const LABEL: u32 = 0xffff_f000;
+const TC: u32 = 0x0000_0e00;
+const BOTTOM_OF_STACK: u32 = 0x0000_0100;
+const TTL: u32 = 0x0000_00ff;
+
+/// An MPLS label stack entry.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                Label                  | TC  |S|       TTL     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Label*:              20 bits. The label value.
+///
+/// - *Traffic Class*:      3 bits. Used for QoS and ECN, as defined in
+///                         [IETF RFC 5462].
+///
+/// - *Bottom of Stack*:    1 bit. Set to one for the last entry in the
+///                         label stack, and zero for all other entries.
+///
+/// - *Time to Live*:       8 bits. The time to live value.
+///
+/// Entries are read out of and written into the buffer by value.
+///
+/// [IETF RFC 5462]: https://tools.ietf.org/html/rfc5462
+#[derive(Clone, Copy, Default, Eq, Hash, PartialEq)]
+pub struct LabelStackEntry(u32);
+
+impl LabelStackEntry {
+    /// Creates a new label stack entry with the bottom of stack flag unset.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the label is larger than [`MPLS_MAX_LABEL`].
+    ///
+    /// [`MPLS_MAX_LABEL`]: MPLS_MAX_LABEL
+    pub fn new(label: u32, tc: u8, ttl: u8) -> Result<Self> {
+        let mut entry = LabelStackEntry::default();
+        entry.set_label(label)?;
+        entry.set_tc(tc);
+        entry.set_ttl(ttl);
+        Ok(entry)
+    }
+
+    /// Returns the label value.
+    #[inline]
+    pub fn label(self) -> u32 {
+        (self.0 & LABEL) >> 12
+    }
+
+    /// Sets the label value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the label is larger than [`MPLS_MAX_LABEL`].
+    ///
+    /// [`MPLS_MAX_LABEL`]: MPLS_MAX_LABEL
+    #[inline]
+    pub fn set_label(&mut self, label: u32) -> Result<()> {
+        ensure!(
+            label <= MPLS_MAX_LABEL,
+            anyhow!("label {} is larger than 20 bits.", label)
+        );
+
+        self.0 = (self.0 & !LABEL) | (label << 12);
+        Ok(())
+    }
+
+    /// Returns the traffic class.
+    #[inline]
+    pub fn tc(self) -> u8 {
+        ((self.0 & TC) >> 9) as u8
+    }
+
+    /// Sets the traffic class. Only the lowest 3 bits are used.
+    #[inline]
+    pub fn set_tc(&mut self, tc: u8) {
+        self.0 = (self.0 & !TC) | ((u32::from(tc) << 9) & TC);
+    }
+
+    /// Returns whether the entry is the last one in the label stack.
+    #[inline]
+    pub fn bottom_of_stack(self) -> bool {
+        self.0 & BOTTOM_OF_STACK != 0
+    }
+
+    #[inline]
+    fn set_bottom_of_stack(&mut self, bottom_of_stack: bool) {
+        if bottom_of_stack {
+            self.0 |= BOTTOM_OF_STACK;
+        } else {
+            self.0 &= !BOTTOM_OF_STACK;
+        }
+    }
+
+    /// Returns the time to live.
+    #[inline]
+    pub fn ttl(self) -> u8 {
+        (self.0 & TTL) as u8
+    }
+
+    /// Sets the time to live.
+    #[inline]
+    pub fn set_ttl(&mut self, ttl: u8) {
+        self.0 = (self.0 & !TTL) | u32::from(ttl);
+    }
+}
+
+impl fmt::Debug for LabelStackEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LabelStackEntry")
+            .field("label", &self.label())
+            .field("tc", &self.tc())
+            .field("bottom_of_stack", &self.bottom_of_stack())
+            .field("ttl", &self.ttl())
+            .finish()
+    }
+}
+
+/// Multiprotocol Label Switching label stack based on [IETF RFC 3032].
+///
+/// The packet spans the entire label stack, from the top entry to the
+/// entry with the bottom of stack flag set. The label stack entries are
+/// accessed by their index, starting with 0 for the top of the stack.
+///
+/// MPLS does not identify the type of the payload after the label stack.
+/// Unless the type is set explicitly with [`set_protocol_type`], it is
+/// guessed from the first nibble of the payload, the version field of an
+/// IPv4 or IPv6 packet.
+///
+/// ```
+/// let mut mpls = ethernet.parse::<Mpls>()?;
+/// if mpls.protocol_type() == EtherTypes::Ipv4 {
+///     let ipv4 = mpls.parse::<Ipv4<Mpls>>()?;
+/// }
+///
+/// // or with an explicit choice.
+/// mpls.set_protocol_type(EtherTypes::Ipv6)?;
+/// let ipv6 = mpls.parse::<Ipv6<Mpls>>()?;
+/// ```
+///
+/// [IETF RFC 3032]: https://tools.ietf.org/html/rfc3032#section-2.1
+/// [`set_protocol_type`]: Datalink::set_protocol_type
+pub struct Mpls<E: Datalink = Ethernet> {
+    envelope: E,
+    offset: usize,
+    depth: usize,
+    payload_type: Option<EtherType>,
+}
+
+impl<E: Datalink> Mpls<E> {
+    /// Returns the number of entries in the label stack.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Reads the entry at the buffer offset.
+    #[inline]
+    fn read_entry(mbuf: &Mbuf, offset: usize) -> Result<LabelStackEntry> {
+        let entry = mbuf.read_data_slice::<u8>(offset, ENTRY_LEN)?;
+        let entry = unsafe { entry.as_ref() };
+        Ok(LabelStackEntry(u32::from_be_bytes([
+            entry[0], entry[1], entry[2], entry[3],
+        ])))
+    }
+
+    /// Returns the label stack entry at `index`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index is out of the label stack.
+    #[inline]
+    pub fn entry(&self, index: usize) -> Result<LabelStackEntry> {
+        ensure!(
+            index < self.depth,
+            anyhow!("label stack index {} is out of bounds.", index)
+        );
+
+        Mpls::<E>::read_entry(self.mbuf(), self.offset + index * ENTRY_LEN)
+    }
+
+    /// Returns the entry at the top of the label stack.
+    #[inline]
+    pub fn top(&self) -> LabelStackEntry {
+        // the stack always has at least one entry.
+        self.entry(0).unwrap()
+    }
+
+    /// Sets the label stack entry at `index`.
+    ///
+    /// The bottom of stack flag of the entry is ignored. It is set only for
+    /// the last entry in the stack.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index is out of the label stack.
+    #[inline]
+    pub fn set_entry(&mut self, index: usize, mut entry: LabelStackEntry) -> Result<()> {
+        ensure!(
+            index < self.depth,
+            anyhow!("label stack index {} is out of bounds.", index)
+        );
+
+        entry.set_bottom_of_stack(index == self.depth - 1);
+        let offset = self.offset + index * ENTRY_LEN;
+        self.mbuf_mut()
+            .write_data_slice(offset, &entry.0.to_be_bytes())?;
+        Ok(())
+    }
+
+    /// Pushes a new entry on top of the label stack.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    pub fn push_label(&mut self, mut entry: LabelStackEntry) -> Result<()> {
+        let offset = self.offset;
+        entry.set_bottom_of_stack(false);
+
+        self.mbuf_mut().extend(offset, ENTRY_LEN)?;
+        self.mbuf_mut()
+            .write_data_slice(offset, &entry.0.to_be_bytes())?;
+        self.depth += 1;
+
+        Ok(())
+    }
+
+    /// Pops the entry at the top of the label stack and returns it.
+    ///
+    /// To pop the bottom of stack entry, the packet should be removed with
+    /// [`remove`] instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry at the top is the bottom of stack.
+    ///
+    /// [`remove`]: Packet::remove
+    pub fn pop_label(&mut self) -> Result<LabelStackEntry> {
+        ensure!(
+            self.depth > 1,
+            anyhow!("cannot pop the bottom of stack entry.")
+        );
+
+        let entry = self.top();
+        let offset = self.offset;
+        self.mbuf_mut().shrink(offset, ENTRY_LEN)?;
+        self.depth -= 1;
+
+        Ok(entry)
+    }
+
+    /// Swaps the label at the top of the label stack with a new label.
+    ///
+    /// The traffic class and the time to live are not changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the label is larger than [`MPLS_MAX_LABEL`].
+    ///
+    /// [`MPLS_MAX_LABEL`]: MPLS_MAX_LABEL
+    pub fn swap_label(&mut self, label: u32) -> Result<()> {
+        let mut entry = self.top();
+        entry.set_label(label)?;
+        self.set_entry(0, entry)
+    }
+
+    /// Guesses the type of the payload from the first nibble.
+    fn guess_payload_type(&self) -> EtherType {
+        let version = self
+            .mbuf()
+            .read_data_slice::<u8>(self.payload_offset(), 1)
+            .map(|nibble| unsafe { nibble.as_ref()[0] } >> 4);
+
+        match version {
+            Ok(4) => EtherTypes::Ipv4,
+            Ok(6) => EtherTypes::Ipv6,
+            _ => EtherType::new(0),
+        }
+    }
+}
+
+impl<E: Datalink> fmt::Debug for Mpls<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = (0..self.depth)
+            .filter_map(|index| self.entry(index).ok())
+            .collect::<Vec<_>>();
+
+        f.debug_struct("mpls")
+            .field("entries", &entries)
+            .field("protocol_type", &format!("{}", self.protocol_type()))
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: Datalink> Packet for Mpls<E> {
+    /// The preceding type for an MPLS packet is typically Ethernet. It can
+    /// also be a tunneling packet, such as GRE, that carries an MPLS packet.
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the label stack.
+    #[inline]
+    fn header_len(&self) -> usize {
+        self.depth * ENTRY_LEN
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Mpls {
+            envelope: self.envelope.clone(internal),
+            offset: self.offset,
+            depth: self.depth,
+            payload_type: self.payload_type,
+        }
+    }
+
+    /// Parses the envelope's payload as an MPLS label stack.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`protocol_type`] is set to neither
+    /// [`EtherTypes::MplsUnicast`] nor [`EtherTypes::MplsMulticast`].
+    /// Returns an error if the payload does not have sufficient data for
+    /// the label stack up to the bottom of stack entry.
+    ///
+    /// [`protocol_type`]: Datalink::protocol_type
+    /// [`EtherTypes::MplsUnicast`]: EtherTypes::MplsUnicast
+    /// [`EtherTypes::MplsMulticast`]: EtherTypes::MplsMulticast
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let protocol_type = envelope.protocol_type();
+        ensure!(
+            protocol_type == EtherTypes::MplsUnicast || protocol_type == EtherTypes::MplsMulticast,
+            anyhow!("not an MPLS packet.")
+        );
+
+        let offset = envelope.payload_offset();
+        let mut depth = 0;
+        loop {
+            let entry = Mpls::<E>::read_entry(envelope.mbuf(), offset + depth * ENTRY_LEN)?;
+            depth += 1;
+            if entry.bottom_of_stack() {
+                break;
+            }
+        }
+
+        Ok(Mpls {
+            envelope,
+            offset,
+            depth,
+            payload_type: None,
+        })
+    }
+
+    /// Prepends an MPLS label stack with a single entry to the beginning of
+    /// the envelope's payload.
+    ///
+    /// The entry has the label 0, which is the IPv4 explicit null label,
+    /// and the default time to live. [`protocol_type`] is set to
+    /// [`EtherTypes::MplsUnicast`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`protocol_type`]: Datalink::protocol_type
+    /// [`EtherTypes::MplsUnicast`]: EtherTypes::MplsUnicast
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mut entry = LabelStackEntry::default();
+        entry.set_bottom_of_stack(true);
+        entry.set_ttl(DEFAULT_IP_TTL);
+
+        let mbuf = envelope.mbuf_mut();
+        mbuf.extend(offset, ENTRY_LEN)?;
+        mbuf.write_data_slice(offset, &entry.0.to_be_bytes())?;
+
+        envelope.set_protocol_type(EtherTypes::MplsUnicast)?;
+
+        Ok(Mpls {
+            envelope,
+            offset,
+            depth: 1,
+            payload_type: None,
+        })
+    }
+
+    /// Removes the entire label stack from the message buffer.
+    ///
+    /// The envelope's [`protocol_type`] is set to the type of the payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the type of the payload is not set explicitly
+    /// and the payload is neither IPv4 nor IPv6. Returns an error if the
+    /// buffer does not have sufficient data to remove.
+    ///
+    /// [`protocol_type`]: Datalink::protocol_type
+    #[inline]
+    fn remove(mut self) -> Result<Self::Envelope> {
+        let protocol_type = self.protocol_type();
+        ensure!(
+            self.payload_type.is_some()
+                || protocol_type == EtherTypes::Ipv4
+                || protocol_type == EtherTypes::Ipv6,
+            anyhow!("unknown MPLS payload type.")
+        );
+
+        let offset = self.offset();
+        let len = self.header_len();
+        self.mbuf_mut().shrink(offset, len)?;
+        self.envelope_mut().set_protocol_type(protocol_type)?;
+
+        Ok(self.envelope)
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+impl<E: Datalink> Datalink for Mpls<E> {
+    /// Returns the type of the payload after the label stack.
+    ///
+    /// Returns the type set with [`set_protocol_type`] if there is one.
+    /// Otherwise returns [`EtherTypes::Ipv4`] or [`EtherTypes::Ipv6`] based
+    /// on the first nibble of the payload, or an unassigned EtherType of 0
+    /// if the nibble is neither 4 nor 6.
+    ///
+    /// [`set_protocol_type`]: Datalink::set_protocol_type
+    /// [`EtherTypes::Ipv4`]: EtherTypes::Ipv4
+    /// [`EtherTypes::Ipv6`]: EtherTypes::Ipv6
+    #[inline]
+    fn protocol_type(&self) -> EtherType {
+        self.payload_type
+            .unwrap_or_else(|| self.guess_payload_type())
+    }
+
+    /// Sets the type of the payload after the label stack.
+    ///
+    /// The type is not written to the buffer because MPLS does not have a
+    /// payload type field.
+    #[inline]
+    fn set_protocol_type(&mut self, ether_type: EtherType) -> Result<()> {
+        self.payload_type = Some(ether_type);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::Udp;
+    use crate::testils::byte_arrays::IPV4_UDP_PACKET;
+
+    #[test]
+    fn label_stack_entry_fields() {
+        let mut entry = LabelStackEntry::new(MPLS_MAX_LABEL, 5, 63).unwrap();
+        assert_eq!(MPLS_MAX_LABEL, entry.label());
+        assert_eq!(5, entry.tc());
+        assert!(!entry.bottom_of_stack());
+        assert_eq!(63, entry.ttl());
+
+        entry.set_tc(0xff);
+        assert_eq!(7, entry.tc());
+        assert_eq!(MPLS_MAX_LABEL, entry.label());
+        assert_eq!(63, entry.ttl());
+
+        assert!(LabelStackEntry::new(MPLS_MAX_LABEL + 1, 0, 0).is_err());
+        assert!(entry.set_label(MPLS_MAX_LABEL + 1).is_err());
+    }
+
+    #[capsule::test]
+    fn parse_mpls_packet() {
+        let packet = Mbuf::from_bytes(&MPLS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mpls = ethernet.parse::<Mpls>().unwrap();
+
+        assert_eq!(2, mpls.depth());
+        assert_eq!(8, mpls.header_len());
+
+        let top = mpls.top();
+        assert_eq!(100, top.label());
+        assert_eq!(0, top.tc());
+        assert!(!top.bottom_of_stack());
+        assert_eq!(64, top.ttl());
+
+        let bottom = mpls.entry(1).unwrap();
+        assert_eq!(200, bottom.label());
+        assert_eq!(5, bottom.tc());
+        assert!(bottom.bottom_of_stack());
+        assert_eq!(63, bottom.ttl());
+        assert!(mpls.entry(2).is_err());
+
+        assert_eq!(EtherTypes::Ipv4, mpls.protocol_type());
+        let ipv4 = mpls.parse::<Ipv4<Mpls>>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4<Mpls>>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+    }
+
+    #[capsule::test]
+    fn parse_non_mpls_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert!(ethernet.parse::<Mpls>().is_err());
+    }
+
+    #[capsule::test]
+    fn parse_mpls_payload_with_explicit_type() {
+        let packet = Mbuf::from_bytes(&MPLS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut mpls = ethernet.parse::<Mpls>().unwrap();
+
+        mpls.set_protocol_type(EtherTypes::Ipv6).unwrap();
+        assert_eq!(EtherTypes::Ipv6, mpls.protocol_type());
+        assert!(mpls.peek::<Ipv4<Mpls>>().is_err());
+
+        mpls.set_protocol_type(EtherTypes::Ipv4).unwrap();
+        assert!(mpls.peek::<Ipv4<Mpls>>().is_ok());
+    }
+
+    #[capsule::test]
+    fn push_pop_and_swap_labels() {
+        let packet = Mbuf::from_bytes(&MPLS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut mpls = ethernet.parse::<Mpls>().unwrap();
+
+        mpls.push_label(LabelStackEntry::new(300, 1, 10).unwrap())
+            .unwrap();
+        assert_eq!(3, mpls.depth());
+        assert_eq!(300, mpls.top().label());
+        assert!(!mpls.top().bottom_of_stack());
+        assert_eq!(100, mpls.entry(1).unwrap().label());
+        assert!(mpls.entry(2).unwrap().bottom_of_stack());
+
+        mpls.swap_label(400).unwrap();
+        assert_eq!(400, mpls.top().label());
+        assert_eq!(1, mpls.top().tc());
+        assert_eq!(10, mpls.top().ttl());
+        assert!(mpls.swap_label(MPLS_MAX_LABEL + 1).is_err());
+
+        assert_eq!(400, mpls.pop_label().unwrap().label());
+        assert_eq!(100, mpls.pop_label().unwrap().label());
+        assert_eq!(1, mpls.depth());
+        assert!(mpls.pop_label().is_err());
+
+        let ethernet = mpls.remove().unwrap();
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        assert_eq!(IPV4_UDP_PACKET.len(), ethernet.len());
+
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert_eq!("139.133.217.110", ipv4.src().to_string());
+    }
+
+    #[capsule::test]
+    fn push_mpls_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let mut mpls = ethernet.push::<Mpls>().unwrap();
+
+        assert_eq!(EtherTypes::MplsUnicast, mpls.envelope().ether_type());
+        assert_eq!(1, mpls.depth());
+        assert_eq!(0, mpls.top().label());
+        assert!(mpls.top().bottom_of_stack());
+        assert_eq!(DEFAULT_IP_TTL, mpls.top().ttl());
+
+        let mut entry = LabelStackEntry::new(16, 0, 255).unwrap();
+        entry.set_tc(3);
+        mpls.set_entry(0, entry).unwrap();
+        assert_eq!(16, mpls.top().label());
+        assert!(mpls.top().bottom_of_stack());
+
+        let ipv6 = mpls.push::<Ipv6<Mpls>>().unwrap();
+        assert_eq!(EtherTypes::Ipv6, ipv6.envelope().protocol_type());
+    }
+
+    #[capsule::test]
+    fn remove_mpls_packet_with_unknown_payload() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let mpls = ethernet.push::<Mpls>().unwrap();
+
+        assert!(mpls.remove().is_err());
+
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let mut mpls = ethernet.push::<Mpls>().unwrap();
+
+        mpls.set_protocol_type(EtherTypes::Arp).unwrap();
+        let ethernet = mpls.remove().unwrap();
+        assert_eq!(EtherTypes::Arp, ethernet.ether_type());
+        assert_eq!(0, ethernet.payload_len());
+    }
+
+    /// IPv4 UDP packet with a label stack of two entries.
+    #[rustfmt::skip]
+    const MPLS_PACKET: [u8; 60] = [
+    // Ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x88, 0x47,
+    // label stack
+        // label = 100, tc = 0, s = 0, ttl = 64
+        0x00, 0x06, 0x40, 0x40,
+        // label = 200, tc = 5, s = 1, ttl = 63
+        0x00, 0x0c, 0x8b, 0x3f,
+    // IPv4 header
+        0x45, 0x00, 0x00, 0x26,
+        0xab, 0x49, 0x40, 0x00,
+        0xff, 0x11, 0xf7, 0x00,
+        0x8b, 0x85, 0xd9, 0x6e,
+        0x8b, 0x85, 0xe9, 0x02,
+    // UDP header
+        0x99, 0xd0, 0x04, 0x3f,
+        0x00, 0x12, 0x72, 0x28,
+    // UDP payload
+        0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+    ];
+}
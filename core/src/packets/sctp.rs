@@ -0,0 +1,660 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::{Flow, IpPacket, ProtocolNumbers};
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{checksum, Internal, Packet};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+// Length of the chunk header in octets.
+const CHUNK_HEADER_LEN: usize = 4;
+
+/// [IANA] assigned SCTP chunk type.
+///
+/// A list of supported types is under [`SctpChunkTypes`].
+///
+/// [IANA]: https://www.iana.org/assignments/sctp-parameters/sctp-parameters.xhtml#sctp-parameters-1
+/// [`SctpChunkTypes`]: SctpChunkTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct SctpChunkType(pub u8);
+
+/// Supported SCTP chunk types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod SctpChunkTypes {
+    use super::SctpChunkType;
+
+    /// Payload data.
+    pub const Data: SctpChunkType = SctpChunkType(0);
+
+    /// Initiation.
+    pub const Init: SctpChunkType = SctpChunkType(1);
+
+    /// Initiation acknowledgement.
+    pub const InitAck: SctpChunkType = SctpChunkType(2);
+
+    /// Selective acknowledgement.
+    pub const Sack: SctpChunkType = SctpChunkType(3);
+
+    /// Heartbeat request.
+    pub const Heartbeat: SctpChunkType = SctpChunkType(4);
+
+    /// Heartbeat acknowledgement.
+    pub const HeartbeatAck: SctpChunkType = SctpChunkType(5);
+
+    /// Abort.
+    pub const Abort: SctpChunkType = SctpChunkType(6);
+
+    /// Shutdown.
+    pub const Shutdown: SctpChunkType = SctpChunkType(7);
+
+    /// Shutdown acknowledgement.
+    pub const ShutdownAck: SctpChunkType = SctpChunkType(8);
+
+    /// Operation error.
+    pub const Error: SctpChunkType = SctpChunkType(9);
+
+    /// State cookie.
+    pub const CookieEcho: SctpChunkType = SctpChunkType(10);
+
+    /// Cookie acknowledgement.
+    pub const CookieAck: SctpChunkType = SctpChunkType(11);
+
+    /// Shutdown complete.
+    pub const ShutdownComplete: SctpChunkType = SctpChunkType(14);
+}
+
+impl fmt::Display for SctpChunkType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                SctpChunkTypes::Data => "DATA".to_string(),
+                SctpChunkTypes::Init => "INIT".to_string(),
+                SctpChunkTypes::InitAck => "INIT ACK".to_string(),
+                SctpChunkTypes::Sack => "SACK".to_string(),
+                SctpChunkTypes::Heartbeat => "HEARTBEAT".to_string(),
+                SctpChunkTypes::HeartbeatAck => "HEARTBEAT ACK".to_string(),
+                SctpChunkTypes::Abort => "ABORT".to_string(),
+                SctpChunkTypes::Shutdown => "SHUTDOWN".to_string(),
+                SctpChunkTypes::ShutdownAck => "SHUTDOWN ACK".to_string(),
+                SctpChunkTypes::Error => "ERROR".to_string(),
+                SctpChunkTypes::CookieEcho => "COOKIE ECHO".to_string(),
+                SctpChunkTypes::CookieAck => "COOKIE ACK".to_string(),
+                SctpChunkTypes::ShutdownComplete => "SHUTDOWN COMPLETE".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// Stream Control Transmission Protocol packet based on [IETF RFC 4960].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     Source Port Number        |     Destination Port Number   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                      Verification Tag                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                           Checksum                            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                     Chunk #1 ... Chunk #n                     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Source Port Number*:         16 bits. The SCTP sender's port number.
+///
+/// - *Destination Port Number*:    16 bits. The SCTP port number to which
+///                                 this packet is destined.
+///
+/// - *Verification Tag*:           32 bits. The receiver uses the tag to
+///                                 validate the sender of this packet.
+///
+/// - *Checksum*:                   32 bits. The CRC32c checksum of the
+///                                 SCTP packet, defined in [IETF RFC 3309].
+///
+/// The chunks in the packet can be iterated with [`chunks_iter`].
+///
+/// [IETF RFC 4960]: https://tools.ietf.org/html/rfc4960#section-3
+/// [IETF RFC 3309]: https://tools.ietf.org/html/rfc3309
+/// [`chunks_iter`]: Sctp::chunks_iter
+pub struct Sctp<E: IpPacket> {
+    envelope: E,
+    header: NonNull<SctpHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Sctp<E> {
+    #[inline]
+    fn header(&self) -> &SctpHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut SctpHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the source port.
+    #[inline]
+    pub fn src_port(&self) -> u16 {
+        self.header().src_port.into()
+    }
+
+    /// Sets the source port.
+    #[inline]
+    pub fn set_src_port(&mut self, src_port: u16) {
+        self.header_mut().src_port = src_port.into();
+    }
+
+    /// Returns the destination port.
+    #[inline]
+    pub fn dst_port(&self) -> u16 {
+        self.header().dst_port.into()
+    }
+
+    /// Sets the destination port.
+    #[inline]
+    pub fn set_dst_port(&mut self, dst_port: u16) {
+        self.header_mut().dst_port = dst_port.into();
+    }
+
+    /// Returns the verification tag.
+    #[inline]
+    pub fn verification_tag(&self) -> u32 {
+        self.header().verification_tag.into()
+    }
+
+    /// Sets the verification tag.
+    #[inline]
+    pub fn set_verification_tag(&mut self, verification_tag: u32) {
+        self.header_mut().verification_tag = verification_tag.into();
+    }
+
+    /// Returns the checksum.
+    #[inline]
+    pub fn checksum(&self) -> u32 {
+        // the CRC32c checksum is transmitted in little-endian order.
+        u32::from_le_bytes(self.header().checksum)
+    }
+
+    #[inline]
+    fn set_checksum(&mut self, checksum: u32) {
+        self.header_mut().checksum = checksum.to_le_bytes();
+    }
+
+    #[inline]
+    fn compute_checksum(&mut self) {
+        self.set_checksum(0);
+
+        let data = self.mbuf().data_segments(self.offset, self.len());
+        let checksum = checksum::crc32c_segments(data);
+        self.set_checksum(checksum);
+    }
+
+    /// Returns the 5-tuple that uniquely identifies an SCTP association.
+    #[inline]
+    pub fn flow(&self) -> Flow {
+        Flow::new(
+            self.envelope().src(),
+            self.envelope().dst(),
+            self.src_port(),
+            self.dst_port(),
+            ProtocolNumbers::Sctp,
+        )
+    }
+
+    /// Returns an iterator that iterates through the chunks.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let sctp = ipv4.parse::<Sctp<Ipv4>>()?;
+    /// let mut iter = sctp.chunks_iter();
+    ///
+    /// while let Some(chunk) = iter.next()? {
+    ///     if chunk.chunk_type() == SctpChunkTypes::Data {
+    ///         println!("{:?}", chunk.value());
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn chunks_iter(&self) -> SctpChunksIterator<'_> {
+        SctpChunksIterator {
+            mbuf: self.mbuf(),
+            offset: self.payload_offset(),
+        }
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Sctp<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("sctp")
+            .field("src_port", &self.src_port())
+            .field("dst_port", &self.dst_port())
+            .field(
+                "verification_tag",
+                &format!("0x{:08x}", self.verification_tag()),
+            )
+            .field("checksum", &format!("0x{:08x}", self.checksum()))
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Sctp<E> {
+    /// The preceding packet type for an SCTP packet can be either an [IPv4]
+    /// packet, an [IPv6] packet, or any IPv6 extension packets.
+    ///
+    /// [IPv4]: crate::packets::ip::v4::Ipv4
+    /// [IPv6]: crate::packets::ip::v6::Ipv6
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        SctpHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Sctp::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an SCTP packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`next_protocol`] is not set to
+    /// [`ProtocolNumbers::Sctp`]. Returns an error if the payload does not
+    /// have sufficient data for the SCTP common header.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    /// [`ProtocolNumbers::Sctp`]: ProtocolNumbers::Sctp
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.next_protocol() == ProtocolNumbers::Sctp,
+            anyhow!("not an SCTP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        Ok(Sctp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an SCTP packet to the beginning of the envelope's payload.
+    ///
+    /// [`next_protocol`] is set to [`ProtocolNumbers::Sctp`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    /// [`ProtocolNumbers::Sctp`]: ProtocolNumbers::Sctp
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, SctpHeader::size_of())?;
+        let header = mbuf.write_data(offset, &SctpHeader::default())?;
+
+        envelope.set_next_protocol(ProtocolNumbers::Sctp);
+
+        Ok(Sctp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`checksum`] is computed based on the full packet.
+    ///
+    /// [`checksum`]: Sctp::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        self.compute_checksum();
+    }
+}
+
+/// A type alias for an IPv4 SCTP packet.
+pub type Sctp4 = Sctp<Ipv4>;
+
+/// A type alias for an IPv6 SCTP packet.
+pub type Sctp6 = Sctp<Ipv6>;
+
+/// An SCTP chunk.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   Chunk Type  | Chunk  Flags  |        Chunk Length           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// \                                                               \
+/// /                          Chunk Value                          /
+/// \                                                               \
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// The chunk value borrows the underlying buffer and does not include the
+/// padding to the 4-octet boundary.
+pub struct SctpChunk<'a> {
+    chunk_type: SctpChunkType,
+    flags: u8,
+    value: &'a [u8],
+}
+
+impl SctpChunk<'_> {
+    /// Returns the chunk type.
+    #[inline]
+    pub fn chunk_type(&self) -> SctpChunkType {
+        self.chunk_type
+    }
+
+    /// Returns the chunk flags. The meaning of the flags depends on the
+    /// chunk type.
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// Returns the length of the chunk in octets, including the chunk
+    /// header but not the padding.
+    #[inline]
+    pub fn length(&self) -> u16 {
+        (CHUNK_HEADER_LEN + self.value.len()) as u16
+    }
+
+    /// Returns the chunk value.
+    #[inline]
+    pub fn value(&self) -> &[u8] {
+        self.value
+    }
+}
+
+impl fmt::Debug for SctpChunk<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SctpChunk")
+            .field("chunk_type", &format!("{}", self.chunk_type()))
+            .field("flags", &format!("0x{:02x}", self.flags()))
+            .field("length", &self.length())
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the chunks in an SCTP packet.
+pub struct SctpChunksIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+}
+
+impl<'a> SctpChunksIterator<'a> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<SctpChunk<'a>>> {
+        if self.mbuf.data_len() < self.offset + CHUNK_HEADER_LEN {
+            return Ok(None);
+        }
+
+        let header = self
+            .mbuf
+            .read_data_slice::<u8>(self.offset, CHUNK_HEADER_LEN)?;
+        let header = unsafe { header.as_ref() };
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        ensure!(
+            length >= CHUNK_HEADER_LEN,
+            anyhow!("invalid SCTP chunk length {}.", length)
+        );
+
+        let value = if length > CHUNK_HEADER_LEN {
+            let value = self
+                .mbuf
+                .read_data_slice::<u8>(self.offset + CHUNK_HEADER_LEN, length - CHUNK_HEADER_LEN)?;
+            unsafe { &*value.as_ptr() }
+        } else {
+            &[]
+        };
+
+        let chunk = SctpChunk {
+            chunk_type: SctpChunkType(header[0]),
+            flags: header[1],
+            value,
+        };
+
+        // advances the offset to the next chunk, skipping the padding
+        self.offset += (length + 3) & !3;
+
+        Ok(Some(chunk))
+    }
+}
+
+impl fmt::Debug for SctpChunksIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SctpChunksIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// SCTP common header.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct SctpHeader {
+    src_port: u16be,
+    dst_port: u16be,
+    verification_tag: u32be,
+    checksum: [u8; 4],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::ProtocolNumbers;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::IPV4_UDP_PACKET;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn size_of_sctp_header() {
+        assert_eq!(12, SctpHeader::size_of());
+    }
+
+    #[test]
+    fn chunk_type_to_string() {
+        assert_eq!("DATA", SctpChunkTypes::Data.to_string());
+        assert_eq!("INIT ACK", SctpChunkTypes::InitAck.to_string());
+        assert_eq!("200", SctpChunkType(200).to_string());
+    }
+
+    #[capsule::test]
+    fn parse_sctp_packet() {
+        let packet = Mbuf::from_bytes(&SCTP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let sctp = ipv4.parse::<Sctp<Ipv4>>().unwrap();
+
+        assert_eq!(5000, sctp.src_port());
+        assert_eq!(36412, sctp.dst_port());
+        assert_eq!(0x0102_0304, sctp.verification_tag());
+        assert_eq!(0xecd6_7b23, sctp.checksum());
+
+        let mut iter = sctp.chunks_iter();
+
+        let data = iter.next().unwrap().unwrap();
+        assert_eq!(SctpChunkTypes::Data, data.chunk_type());
+        assert_eq!(0x03, data.flags());
+        assert_eq!(19, data.length());
+        assert_eq!(b"abc", &data.value()[12..]);
+
+        let sack = iter.next().unwrap().unwrap();
+        assert_eq!(SctpChunkTypes::Sack, sack.chunk_type());
+        assert_eq!(16, sack.length());
+
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn parse_non_sctp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Sctp<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn sctp_flow_v4() {
+        let packet = Mbuf::from_bytes(&SCTP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let sctp = ipv4.parse::<Sctp<Ipv4>>().unwrap();
+        let flow = sctp.flow();
+
+        assert_eq!("10.0.0.1", flow.src_ip().to_string());
+        assert_eq!("10.0.0.2", flow.dst_ip().to_string());
+        assert_eq!(5000, flow.src_port());
+        assert_eq!(36412, flow.dst_port());
+        assert_eq!(ProtocolNumbers::Sctp, flow.protocol());
+    }
+
+    #[capsule::test]
+    fn compute_checksum() {
+        let packet = Mbuf::from_bytes(&SCTP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut sctp = ipv4.parse::<Sctp<Ipv4>>().unwrap();
+
+        let expected = sctp.checksum();
+        // no payload change but force a checksum recompute anyway
+        sctp.reconcile_all();
+        assert_eq!(expected, sctp.checksum());
+
+        sctp.set_verification_tag(0x0506_0708);
+        sctp.reconcile_all();
+        assert_ne!(expected, sctp.checksum());
+    }
+
+    #[capsule::test]
+    fn push_sctp_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.push::<Ipv4>().unwrap();
+        ipv4.set_src(Ipv4Addr::new(10, 0, 0, 1));
+        let sctp = ipv4.push::<Sctp<Ipv4>>().unwrap();
+
+        assert_eq!(SctpHeader::size_of(), sctp.len());
+        assert_eq!(ProtocolNumbers::Sctp, sctp.envelope().protocol());
+        assert!(sctp.chunks_iter().next().unwrap().is_none());
+    }
+
+    /// SCTP packet with a DATA chunk and a SACK chunk.
+    #[rustfmt::skip]
+    const SCTP_PACKET: [u8; 82] = [
+    // Ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x08, 0x00,
+    // IPv4 header
+        0x45, 0x00,
+        // total length = 68
+        0x00, 0x44,
+        0x00, 0x00, 0x40, 0x00,
+        // ttl = 64, protocol = SCTP
+        0x40, 0x84, 0x00, 0x00,
+        // src = 10.0.0.1
+        0x0a, 0x00, 0x00, 0x01,
+        // dst = 10.0.0.2
+        0x0a, 0x00, 0x00, 0x02,
+    // SCTP header
+        // src_port = 5000, dst_port = 36412
+        0x13, 0x88, 0x8e, 0x3c,
+        // verification tag = 0x01020304
+        0x01, 0x02, 0x03, 0x04,
+        // checksum = 0xecd67b23
+        0x23, 0x7b, 0xd6, 0xec,
+    // DATA chunk
+        // type = DATA, flags = B|E, length = 19
+        0x00, 0x03, 0x00, 0x13,
+        // tsn = 1
+        0x00, 0x00, 0x00, 0x01,
+        // stream id = 0, stream sequence = 0
+        0x00, 0x00, 0x00, 0x00,
+        // payload protocol id = 18
+        0x00, 0x00, 0x00, 0x12,
+        // user data "abc" and padding
+        0x61, 0x62, 0x63, 0x00,
+    // SACK chunk
+        // type = SACK, flags = 0, length = 16
+        0x03, 0x00, 0x00, 0x10,
+        // cumulative tsn ack = 1
+        0x00, 0x00, 0x00, 0x01,
+        // a_rwnd = 32768
+        0x00, 0x00, 0x80, 0x00,
+        // no gap ack blocks, no duplicate tsns
+        0x00, 0x00, 0x00, 0x00,
+    ];
+}
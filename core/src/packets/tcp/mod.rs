@@ -16,6 +16,11 @@
 * SPDX-License-Identifier: Apache-2.0
 */
 
+mod options;
+
+pub use self::options::*;
+
+use crate::dpdk::BufferError;
 use crate::packets::ip::v4::Ipv4;
 use crate::packets::ip::v6::Ipv6;
 use crate::packets::ip::{Flow, IpPacket, ProtocolNumbers};
@@ -186,14 +191,7 @@ impl<E: IpPacket> Tcp<E> {
     /// where the data begins.
     #[inline]
     pub fn data_offset(&self) -> u8 {
-        (self.header().offset_to_ns & 0xf0) >> 4
-    }
-
-    // TODO: support tcp header options.
-    #[allow(dead_code)]
-    #[inline]
-    fn set_data_offset(&mut self, data_offset: u8) {
-        self.header_mut().offset_to_ns = (self.header().offset_to_ns & 0x0f) | (data_offset << 4);
+        self.header().data_offset()
     }
 
     /// Returns the nonce sum bit.
@@ -458,22 +456,177 @@ impl<E: IpPacket> Tcp<E> {
         Ok(())
     }
 
+    /// Returns an iterator that iterates through the options in the TCP
+    /// header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let tcp = ipv4.parse::<Tcp4>()?;
+    /// let mut iter = tcp.options_iter();
+    ///
+    /// while let Some(option) = iter.next()? {
+    ///     println!("{:?}", option);
+    /// }
+    /// ```
+    #[inline]
+    pub fn options_iter(&self) -> TcpOptionsIterator<'_> {
+        let start = self.offset + TcpHeader::size_of();
+        let end = self.offset + self.header_len();
+        TcpOptionsIterator::new(self.mbuf(), start, end)
+    }
+
+    /// Returns a mutable accessor to the options in the TCP header.
+    ///
+    /// Adding or removing options updates the data offset. The checksum
+    /// is recomputed when the packet is reconciled.
+    #[inline]
+    pub fn options_mut(&mut self) -> TcpOptions<'_> {
+        let header = self.header;
+        let offset = self.offset;
+        TcpOptions::new(self.mbuf_mut(), header, offset)
+    }
+
+    /// Returns the first option of `kind` if present.
+    fn find_option(&self, kind: TcpOptionKind) -> Result<Option<TcpOption>> {
+        let mut iter = self.options_iter();
+        while let Some(option) = iter.next()? {
+            if option.kind() == kind {
+                return Ok(Some(option));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the maximum segment size option if present.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options are malformed.
+    #[inline]
+    pub fn mss(&self) -> Result<Option<u16>> {
+        match self.find_option(TcpOptionKinds::MaxSegmentSize)? {
+            Some(TcpOption::Mss(mss)) => Ok(Some(mss)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Sets the maximum segment size option, adding it if not present.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the option does not fit in the TCP header.
+    #[inline]
+    pub fn set_mss(&mut self, mss: u16) -> Result<()> {
+        self.options_mut().set(&TcpOption::Mss(mss))
+    }
+
+    /// Returns the window scale shift count option if present.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options are malformed.
+    #[inline]
+    pub fn window_scale(&self) -> Result<Option<u8>> {
+        match self.find_option(TcpOptionKinds::WindowScale)? {
+            Some(TcpOption::WindowScale(shift)) => Ok(Some(shift)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Sets the window scale shift count option, adding it if not present.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the option does not fit in the TCP header.
+    #[inline]
+    pub fn set_window_scale(&mut self, shift: u8) -> Result<()> {
+        self.options_mut().set(&TcpOption::WindowScale(shift))
+    }
+
+    /// Returns whether the SACK permitted option is present.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options are malformed.
+    #[inline]
+    pub fn sack_permitted(&self) -> Result<bool> {
+        Ok(self.find_option(TcpOptionKinds::SackPermitted)?.is_some())
+    }
+
+    /// Adds the SACK permitted option if not present.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the option does not fit in the TCP header.
+    #[inline]
+    pub fn set_sack_permitted(&mut self) -> Result<()> {
+        self.options_mut().set(&TcpOption::SackPermitted)
+    }
+
+    /// Returns the selective acknowledgment blocks as pairs of left edge
+    /// and right edge if present.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options are malformed.
+    #[inline]
+    pub fn sack_blocks(&self) -> Result<Option<Vec<(u32, u32)>>> {
+        match self.find_option(TcpOptionKinds::Sack)? {
+            Some(TcpOption::Sack(blocks)) => Ok(Some(blocks)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Sets the selective acknowledgment blocks, replacing any existing
+    /// blocks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `blocks` is empty or the option does not fit in
+    /// the TCP header.
+    #[inline]
+    pub fn set_sack_blocks(&mut self, blocks: &[(u32, u32)]) -> Result<()> {
+        ensure!(!blocks.is_empty(), anyhow!("no SACK blocks."));
+        self.options_mut().set(&TcpOption::Sack(blocks.to_vec()))
+    }
+
+    /// Returns the timestamp value and timestamp echo reply if present.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options are malformed.
+    #[inline]
+    pub fn timestamps(&self) -> Result<Option<(u32, u32)>> {
+        match self.find_option(TcpOptionKinds::Timestamps)? {
+            Some(TcpOption::Timestamps(value, echo_reply)) => Ok(Some((value, echo_reply))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Sets the timestamp value and timestamp echo reply, adding the
+    /// option if not present.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the option does not fit in the TCP header.
+    #[inline]
+    pub fn set_timestamps(&mut self, value: u32, echo_reply: u32) -> Result<()> {
+        self.options_mut()
+            .set(&TcpOption::Timestamps(value, echo_reply))
+    }
+
     #[inline]
     fn compute_checksum(&mut self) {
         self.set_checksum(0);
 
-        if let Ok(data) = self.mbuf().read_data_slice(self.offset, self.len()) {
-            let data = unsafe { data.as_ref() };
-            let pseudo_header_sum = self
-                .envelope()
-                .pseudo_header(data.len() as u16, ProtocolNumbers::Tcp)
-                .sum();
-            let checksum = checksum::compute(pseudo_header_sum, data);
-            self.set_checksum(checksum);
-        } else {
-            // we are reading till the end of buffer, should never run out
-            unreachable!()
-        }
+        let pseudo_header_sum = self
+            .envelope()
+            .pseudo_header(self.len() as u16, ProtocolNumbers::Tcp)
+            .sum();
+        let data = self.mbuf().data_segments(self.offset, self.len());
+        let checksum = checksum::compute_segments(pseudo_header_sum, data);
+        self.set_checksum(checksum);
     }
 }
 
@@ -529,7 +682,7 @@ impl<E: IpPacket> Packet for Tcp<E> {
 
     #[inline]
     fn header_len(&self) -> usize {
-        TcpHeader::size_of()
+        self.data_offset() as usize * 4
     }
 
     #[inline]
@@ -548,8 +701,9 @@ impl<E: IpPacket> Packet for Tcp<E> {
     /// If the envelope is IPv4, returns an error if [`Ipv4::protocol`] is
     /// not set to [`ProtocolNumbers::Tcp`]. If the envelope is IPv6 or an
     /// extension header, returns an error if [`next_header`] is not set to
-    /// `ProtocolNumbers::Tcp`. Returns an error if the payload does not
-    /// have sufficient data for the TCP header.
+    /// `ProtocolNumbers::Tcp`. Returns an error if the data offset is
+    /// less than the size of the fixed header, or if the payload does not
+    /// have sufficient data for the TCP header, including the options.
     ///
     /// [`Ipv4::protocol`]: crate::packets::ip::v4::Ipv4::protocol
     /// [`ProtocolNumbers::Tcp`]: crate::packets::ip::ProtocolNumbers::Tcp
@@ -565,11 +719,22 @@ impl<E: IpPacket> Packet for Tcp<E> {
         let offset = envelope.payload_offset();
         let header = mbuf.read_data(offset)?;
 
-        Ok(Tcp {
+        let packet = Tcp {
             envelope,
             header,
             offset,
-        })
+        };
+
+        ensure!(
+            packet.header_len() >= TcpHeader::size_of(),
+            anyhow!("invalid TCP data offset.")
+        );
+        ensure!(
+            packet.len() >= packet.header_len(),
+            BufferError::OutOfBuffer(packet.header_len(), packet.len())
+        );
+
+        Ok(packet)
     }
 
     /// Prepends a TCP packet to the beginning of the envelope's payload.
@@ -611,9 +776,13 @@ impl<E: IpPacket> Packet for Tcp<E> {
     /// the packet.
     ///
     /// * [`checksum`] is computed based on the [`pseudo-header`] and the
-    /// full packet.
+    /// full packet, including the options.
+    ///
+    /// The [`data_offset`] is kept up to date as options are added or
+    /// removed, so it is not recomputed here.
     ///
     /// [`checksum`]: Tcp::checksum
+    /// [`data_offset`]: Tcp::data_offset
     /// [`pseudo-header`]: crate::packets::checksum::PseudoHeader
     #[inline]
     fn reconcile(&mut self) {
@@ -645,6 +814,18 @@ struct TcpHeader {
     urgent_pointer: u16be,
 }
 
+impl TcpHeader {
+    #[inline]
+    fn data_offset(&self) -> u8 {
+        (self.offset_to_ns & 0xf0) >> 4
+    }
+
+    #[inline]
+    fn set_data_offset(&mut self, data_offset: u8) {
+        self.offset_to_ns = (self.offset_to_ns & 0x0f) | (data_offset << 4);
+    }
+}
+
 impl Default for TcpHeader {
     fn default() -> TcpHeader {
         TcpHeader {
@@ -790,4 +971,62 @@ mod tests {
         // make sure the next protocol is fixed
         assert_eq!(ProtocolNumbers::Tcp, tcp.envelope().next_protocol());
     }
+
+    #[capsule::test]
+    fn tcp_header_len_with_options() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let tcp = ipv4.parse::<Tcp4>().unwrap();
+
+        assert_eq!(6, tcp.data_offset());
+        assert_eq!(24, tcp.header_len());
+        assert_eq!(tcp.offset() + 24, tcp.payload_offset());
+    }
+
+    #[capsule::test]
+    fn get_and_set_tcp_options() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut tcp = ipv4.parse::<Tcp4>().unwrap();
+
+        assert_eq!(Some(1460), tcp.mss().unwrap());
+        assert_eq!(None, tcp.window_scale().unwrap());
+        assert!(!tcp.sack_permitted().unwrap());
+        assert_eq!(None, tcp.timestamps().unwrap());
+
+        assert!(tcp.set_mss(1360).is_ok());
+        assert!(tcp.set_sack_permitted().is_ok());
+        assert!(tcp.set_timestamps(1, 0).is_ok());
+        assert!(tcp.set_sack_blocks(&[(100, 200)]).is_ok());
+
+        assert_eq!(Some(1360), tcp.mss().unwrap());
+        assert!(tcp.sack_permitted().unwrap());
+        assert_eq!(Some((1, 0)), tcp.timestamps().unwrap());
+        assert_eq!(Some(vec![(100, 200)]), tcp.sack_blocks().unwrap());
+
+        // 4 + 2 + 10 + 10 bytes of options, padded to 28.
+        assert_eq!(12, tcp.data_offset());
+
+        // the checksum covers the options.
+        tcp.reconcile_all();
+        assert_eq!(0x3735, tcp.checksum());
+    }
+
+    #[capsule::test]
+    fn push_tcp_packet_with_options() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut tcp = ipv4.push::<Tcp4>().unwrap();
+
+        assert!(tcp.set_mss(1460).is_ok());
+        assert!(tcp.set_window_scale(7).is_ok());
+
+        assert_eq!(7, tcp.data_offset());
+        assert_eq!(28, tcp.len());
+        assert_eq!(Some(1460), tcp.mss().unwrap());
+        assert_eq!(Some(7), tcp.window_scale().unwrap());
+    }
 }
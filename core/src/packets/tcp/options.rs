@@ -0,0 +1,796 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::TcpHeader;
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// The maximum length of the TCP options in octets.
+///
+/// The data offset field is 4 bits, so the header can be at most 15 32-bit
+/// words, or 60 octets. 20 of which are taken by the fixed header.
+pub const TCP_MAX_OPTIONS_LEN: usize = 40;
+
+/// [IANA] assigned TCP option kind.
+///
+/// A list of supported kinds is under [`TcpOptionKinds`].
+///
+/// [IANA]: https://www.iana.org/assignments/tcp-parameters/tcp-parameters.xhtml#tcp-parameters-1
+/// [`TcpOptionKinds`]: TcpOptionKinds
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct TcpOptionKind(pub u8);
+
+/// Supported TCP option kinds.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod TcpOptionKinds {
+    use super::TcpOptionKind;
+
+    /// End of option list.
+    pub const EndOfOptionList: TcpOptionKind = TcpOptionKind(0);
+
+    /// No-operation, used to align options on word boundaries.
+    pub const NoOperation: TcpOptionKind = TcpOptionKind(1);
+
+    /// Maximum segment size.
+    pub const MaxSegmentSize: TcpOptionKind = TcpOptionKind(2);
+
+    /// Window scale.
+    pub const WindowScale: TcpOptionKind = TcpOptionKind(3);
+
+    /// Selective acknowledgment permitted.
+    pub const SackPermitted: TcpOptionKind = TcpOptionKind(4);
+
+    /// Selective acknowledgment.
+    pub const Sack: TcpOptionKind = TcpOptionKind(5);
+
+    /// Timestamps.
+    pub const Timestamps: TcpOptionKind = TcpOptionKind(8);
+}
+
+impl fmt::Display for TcpOptionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                TcpOptionKinds::EndOfOptionList => "End of Option List".to_string(),
+                TcpOptionKinds::NoOperation => "No-Operation".to_string(),
+                TcpOptionKinds::MaxSegmentSize => "Maximum Segment Size".to_string(),
+                TcpOptionKinds::WindowScale => "Window Scale".to_string(),
+                TcpOptionKinds::SackPermitted => "SACK Permitted".to_string(),
+                TcpOptionKinds::Sack => "SACK".to_string(),
+                TcpOptionKinds::Timestamps => "Timestamps".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// A TCP header option.
+///
+/// Options are read out of and written into the buffer by value. The
+/// single octet end of option list and no-operation options are padding
+/// and are never returned by the options iterator.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TcpOption {
+    /// Maximum segment size defined in [IETF RFC 793].
+    ///
+    /// [IETF RFC 793]: https://tools.ietf.org/html/rfc793#section-3.1
+    Mss(u16),
+
+    /// Window scale shift count defined in [IETF RFC 7323].
+    ///
+    /// [IETF RFC 7323]: https://tools.ietf.org/html/rfc7323#section-2.2
+    WindowScale(u8),
+
+    /// SACK permitted defined in [IETF RFC 2018].
+    ///
+    /// [IETF RFC 2018]: https://tools.ietf.org/html/rfc2018#section-2
+    SackPermitted,
+
+    /// Selective acknowledgment blocks, as pairs of left edge and right
+    /// edge, defined in [IETF RFC 2018].
+    ///
+    /// [IETF RFC 2018]: https://tools.ietf.org/html/rfc2018#section-3
+    Sack(Vec<(u32, u32)>),
+
+    /// Timestamp value and timestamp echo reply defined in [IETF RFC 7323].
+    ///
+    /// [IETF RFC 7323]: https://tools.ietf.org/html/rfc7323#section-3.2
+    Timestamps(u32, u32),
+
+    /// Option with a kind not known to the parser and its raw data,
+    /// excluding the kind and length octets.
+    Unknown(TcpOptionKind, Vec<u8>),
+}
+
+impl TcpOption {
+    /// Returns the option kind.
+    pub fn kind(&self) -> TcpOptionKind {
+        match self {
+            TcpOption::Mss(_) => TcpOptionKinds::MaxSegmentSize,
+            TcpOption::WindowScale(_) => TcpOptionKinds::WindowScale,
+            TcpOption::SackPermitted => TcpOptionKinds::SackPermitted,
+            TcpOption::Sack(_) => TcpOptionKinds::Sack,
+            TcpOption::Timestamps(_, _) => TcpOptionKinds::Timestamps,
+            TcpOption::Unknown(kind, _) => *kind,
+        }
+    }
+
+    /// Returns the length of the option in octets, including the kind and
+    /// length octets.
+    pub fn length(&self) -> usize {
+        match self {
+            TcpOption::Mss(_) => 4,
+            TcpOption::WindowScale(_) => 3,
+            TcpOption::SackPermitted => 2,
+            TcpOption::Sack(blocks) => 2 + blocks.len() * 8,
+            TcpOption::Timestamps(_, _) => 10,
+            TcpOption::Unknown(_, data) => 2 + data.len(),
+        }
+    }
+
+    /// Decodes the option data, excluding the kind and length octets.
+    fn decode(kind: TcpOptionKind, data: &[u8]) -> Result<Self> {
+        let option = match kind {
+            TcpOptionKinds::MaxSegmentSize => {
+                ensure!(data.len() == 2, anyhow!("invalid MSS option length."));
+                TcpOption::Mss(u16::from_be_bytes([data[0], data[1]]))
+            }
+            TcpOptionKinds::WindowScale => {
+                ensure!(
+                    data.len() == 1,
+                    anyhow!("invalid window scale option length.")
+                );
+                TcpOption::WindowScale(data[0])
+            }
+            TcpOptionKinds::SackPermitted => {
+                ensure!(
+                    data.is_empty(),
+                    anyhow!("invalid SACK permitted option length.")
+                );
+                TcpOption::SackPermitted
+            }
+            TcpOptionKinds::Sack => {
+                let blocks = data.chunks_exact(8);
+                ensure!(
+                    !data.is_empty() && blocks.remainder().is_empty(),
+                    anyhow!("invalid SACK option length.")
+                );
+                TcpOption::Sack(
+                    blocks
+                        .map(|block| (read_u32(&block[..4]), read_u32(&block[4..])))
+                        .collect(),
+                )
+            }
+            TcpOptionKinds::Timestamps => {
+                ensure!(
+                    data.len() == 8,
+                    anyhow!("invalid timestamps option length.")
+                );
+                TcpOption::Timestamps(read_u32(&data[..4]), read_u32(&data[4..]))
+            }
+            _ => TcpOption::Unknown(kind, data.to_vec()),
+        };
+
+        Ok(option)
+    }
+
+    /// Encodes the option including the kind and length octets.
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.length());
+        bytes.push(self.kind().0);
+        bytes.push(self.length() as u8);
+
+        match self {
+            TcpOption::Mss(mss) => bytes.extend_from_slice(&mss.to_be_bytes()),
+            TcpOption::WindowScale(shift) => bytes.push(*shift),
+            TcpOption::SackPermitted => (),
+            TcpOption::Sack(blocks) => {
+                for (left, right) in blocks {
+                    bytes.extend_from_slice(&left.to_be_bytes());
+                    bytes.extend_from_slice(&right.to_be_bytes());
+                }
+            }
+            TcpOption::Timestamps(value, echo_reply) => {
+                bytes.extend_from_slice(&value.to_be_bytes());
+                bytes.extend_from_slice(&echo_reply.to_be_bytes());
+            }
+            TcpOption::Unknown(_, data) => bytes.extend_from_slice(data),
+        }
+
+        bytes
+    }
+}
+
+#[inline]
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[inline]
+fn read_u8(mbuf: &Mbuf, offset: usize) -> Result<u8> {
+    let value = mbuf.read_data::<u8>(offset)?;
+    Ok(unsafe { *value.as_ref() })
+}
+
+/// Reads the kind and the length in octets of the option at offset. The
+/// end of option list and no-operation options have a length of 1.
+///
+/// # Errors
+///
+/// Returns an error if the option length is less than 2 or the option
+/// runs past the end offset.
+fn read_kind_and_length(mbuf: &Mbuf, offset: usize, end: usize) -> Result<(TcpOptionKind, usize)> {
+    let kind = TcpOptionKind(read_u8(mbuf, offset)?);
+
+    match kind {
+        TcpOptionKinds::EndOfOptionList | TcpOptionKinds::NoOperation => Ok((kind, 1)),
+        _ => {
+            ensure!(offset + 1 < end, anyhow!("truncated TCP option {}.", kind));
+            let length = read_u8(mbuf, offset + 1)? as usize;
+            ensure!(
+                length >= 2 && offset + length <= end,
+                anyhow!("invalid TCP option {} length {}.", kind, length)
+            );
+            Ok((kind, length))
+        }
+    }
+}
+
+/// Reads the option of `kind` and `length` at offset.
+fn read_option(
+    mbuf: &Mbuf,
+    offset: usize,
+    kind: TcpOptionKind,
+    length: usize,
+) -> Result<TcpOption> {
+    let data = if length > 2 {
+        let data = mbuf.read_data_slice::<u8>(offset + 2, length - 2)?;
+        unsafe { &*data.as_ptr() }
+    } else {
+        &[]
+    };
+
+    TcpOption::decode(kind, data)
+}
+
+/// An iterator that iterates through the options in the TCP header.
+pub struct TcpOptionsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> TcpOptionsIterator<'a> {
+    pub(super) fn new(mbuf: &'a Mbuf, offset: usize, end: usize) -> Self {
+        TcpOptionsIterator { mbuf, offset, end }
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Padding is skipped and the iteration finishes at the end of option
+    /// list marker or at the end of the header, whichever comes first.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<TcpOption>> {
+        while self.offset < self.end {
+            let (kind, length) = read_kind_and_length(self.mbuf, self.offset, self.end)?;
+
+            match kind {
+                TcpOptionKinds::EndOfOptionList => self.offset = self.end,
+                TcpOptionKinds::NoOperation => self.offset += length,
+                _ => {
+                    let option = read_option(self.mbuf, self.offset, kind, length)?;
+                    // advances the offset to the next option
+                    self.offset += length;
+                    return Ok(Some(option));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl fmt::Debug for TcpOptionsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TcpOptionsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// Options in the TCP header.
+///
+/// Adding or removing options resizes the header and updates the data
+/// offset of the TCP packet. The options are always padded with end of
+/// option list octets to a 32-bit boundary. The envelopes' length fields
+/// and the checksum are not updated until the packet is reconciled.
+pub struct TcpOptions<'a> {
+    mbuf: &'a mut Mbuf,
+    header: NonNull<TcpHeader>,
+    offset: usize,
+}
+
+impl<'a> TcpOptions<'a> {
+    pub(super) fn new(mbuf: &'a mut Mbuf, header: NonNull<TcpHeader>, offset: usize) -> Self {
+        TcpOptions {
+            mbuf,
+            header,
+            offset,
+        }
+    }
+
+    #[inline]
+    fn header(&self) -> &TcpHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut TcpHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the buffer offset where the options begin.
+    #[inline]
+    fn start_offset(&self) -> usize {
+        self.offset + TcpHeader::size_of()
+    }
+
+    /// Returns the buffer offset where the TCP header ends.
+    #[inline]
+    fn end_offset(&self) -> usize {
+        self.offset + self.header().data_offset() as usize * 4
+    }
+
+    /// Returns the length of the options in use, not including the end of
+    /// option list marker and the padding that follows.
+    fn used_len(&self) -> Result<usize> {
+        let start = self.start_offset();
+        let end = self.end_offset();
+        let mut offset = start;
+
+        while offset < end {
+            let (kind, length) = read_kind_and_length(self.mbuf, offset, end)?;
+            if kind == TcpOptionKinds::EndOfOptionList {
+                break;
+            }
+            offset += length;
+        }
+
+        Ok(offset - start)
+    }
+
+    /// Returns the buffer offset and the length of the first option of
+    /// `kind`.
+    fn find(&self, kind: TcpOptionKind) -> Result<Option<(usize, usize)>> {
+        let end = self.end_offset();
+        let mut offset = self.start_offset();
+
+        while offset < end {
+            let (found, length) = read_kind_and_length(self.mbuf, offset, end)?;
+            if found == TcpOptionKinds::EndOfOptionList {
+                break;
+            } else if found == kind {
+                return Ok(Some((offset, length)));
+            }
+            offset += length;
+        }
+
+        Ok(None)
+    }
+
+    /// Resizes the options from `current` length to fit `used` octets of
+    /// options, pads the rest with end of option list octets and updates
+    /// the data offset.
+    fn pad(&mut self, current: usize, used: usize) -> Result<()> {
+        let start = self.start_offset();
+        let padded = (used + 3) & !3;
+
+        ensure!(
+            padded <= TCP_MAX_OPTIONS_LEN,
+            anyhow!("TCP options exceed {} octets.", TCP_MAX_OPTIONS_LEN)
+        );
+
+        if padded > current {
+            self.mbuf.extend(start + current, padded - current)?;
+        } else if padded < current {
+            self.mbuf.shrink(start + padded, current - padded)?;
+        }
+
+        if padded > used {
+            self.mbuf
+                .write_data_slice(start + used, &vec![0u8; padded - used])?;
+        }
+
+        let data_offset = ((TcpHeader::size_of() + padded) / 4) as u8;
+        self.header_mut().set_data_offset(data_offset);
+
+        Ok(())
+    }
+
+    /// Returns an iterator to read the options.
+    #[inline]
+    pub fn iter(&self) -> TcpOptionsIterator<'_> {
+        TcpOptionsIterator::new(self.mbuf, self.start_offset(), self.end_offset())
+    }
+
+    /// Appends a new option at the end of the options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options would exceed [`TCP_MAX_OPTIONS_LEN`]
+    /// or the buffer does not have enough free space.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut tcp = ipv4.parse::<Tcp4>()?;
+    /// let mut options = tcp.options_mut();
+    /// options.append(&TcpOption::SackPermitted)?;
+    /// ```
+    ///
+    /// [`TCP_MAX_OPTIONS_LEN`]: TCP_MAX_OPTIONS_LEN
+    pub fn append(&mut self, option: &TcpOption) -> Result<()> {
+        let bytes = option.encode();
+        let used = self.used_len()?;
+
+        ensure!(
+            used + bytes.len() <= TCP_MAX_OPTIONS_LEN,
+            anyhow!("TCP options exceed {} octets.", TCP_MAX_OPTIONS_LEN)
+        );
+
+        let current = self.end_offset() - self.start_offset();
+        self.pad(current, used + bytes.len())?;
+
+        let offset = self.start_offset() + used;
+        self.mbuf.write_data_slice(offset, &bytes)?;
+
+        Ok(())
+    }
+
+    /// Sets an option.
+    ///
+    /// If an option of the same kind and length already exists, its value
+    /// is overwritten in place. Otherwise any existing option of the same
+    /// kind is removed and the new option is appended at the end.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options would exceed [`TCP_MAX_OPTIONS_LEN`]
+    /// or the buffer does not have enough free space.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut tcp = ipv4.parse::<Tcp4>()?;
+    /// tcp.options_mut().set(&TcpOption::Mss(1360))?;
+    /// ```
+    ///
+    /// [`TCP_MAX_OPTIONS_LEN`]: TCP_MAX_OPTIONS_LEN
+    pub fn set(&mut self, option: &TcpOption) -> Result<()> {
+        let bytes = option.encode();
+
+        if let Some((offset, length)) = self.find(option.kind())? {
+            if length == bytes.len() {
+                self.mbuf.write_data_slice(offset, &bytes)?;
+                return Ok(());
+            }
+        }
+
+        self.remove(option.kind())?;
+        self.append(option)
+    }
+
+    /// Removes all options of `kind`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut tcp = ipv4.parse::<Tcp4>()?;
+    /// tcp.options_mut().remove(TcpOptionKinds::Timestamps)?;
+    /// ```
+    #[inline]
+    pub fn remove(&mut self, kind: TcpOptionKind) -> Result<()> {
+        self.retain(|option| option.kind() != kind)
+    }
+
+    /// Retains only the options specified by the predicate.
+    ///
+    /// In other words, remove all options `o` such that `f(o)` returns false.
+    /// Padding is preserved. If an error occurs, all removals done prior to
+    /// the error cannot be undone.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut tcp = ipv4.parse::<Tcp4>()?;
+    /// let mut options = tcp.options_mut();
+    /// options.retain(|option| option.kind() == TcpOptionKinds::MaxSegmentSize)?;
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&TcpOption) -> bool,
+    {
+        let start = self.start_offset();
+        let mut end = self.end_offset();
+        let mut offset = start;
+
+        while offset < end {
+            let (kind, length) = read_kind_and_length(self.mbuf, offset, end)?;
+
+            match kind {
+                TcpOptionKinds::EndOfOptionList => break,
+                TcpOptionKinds::NoOperation => offset += length,
+                _ => {
+                    let option = read_option(self.mbuf, offset, kind, length)?;
+                    if f(&option) {
+                        offset += length;
+                    } else {
+                        self.mbuf.shrink(offset, length)?;
+                        end -= length;
+                    }
+                }
+            }
+        }
+
+        self.pad(end - start, offset - start)
+    }
+}
+
+impl fmt::Debug for TcpOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TcpOptions")
+            .field("offset", &self.start_offset())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::{Ethernet, Packet, Tcp4};
+    use crate::testils::byte_arrays::IPV4_TCP_PACKET;
+
+    #[test]
+    fn tcp_option_kind_to_string() {
+        assert_eq!(
+            "Maximum Segment Size",
+            TcpOptionKinds::MaxSegmentSize.to_string()
+        );
+        assert_eq!("Timestamps", TcpOptionKinds::Timestamps.to_string());
+        assert_eq!("30", TcpOptionKind(30).to_string());
+    }
+
+    #[test]
+    fn encode_and_decode_tcp_options() {
+        let options = [
+            TcpOption::Mss(1460),
+            TcpOption::WindowScale(7),
+            TcpOption::SackPermitted,
+            TcpOption::Sack(vec![(1, 2), (3, 4)]),
+            TcpOption::Timestamps(0xdead_beef, 42),
+            TcpOption::Unknown(TcpOptionKind(30), vec![1, 2, 3]),
+        ];
+
+        for option in options.iter() {
+            let bytes = option.encode();
+            assert_eq!(option.length(), bytes.len());
+            assert_eq!(option.kind().0, bytes[0]);
+            assert_eq!(option.length() as u8, bytes[1]);
+
+            let decoded = TcpOption::decode(option.kind(), &bytes[2..]).unwrap();
+            assert_eq!(*option, decoded);
+        }
+
+        assert!(TcpOption::decode(TcpOptionKinds::MaxSegmentSize, &[1]).is_err());
+        assert!(TcpOption::decode(TcpOptionKinds::Sack, &[0; 12]).is_err());
+    }
+
+    #[capsule::test]
+    fn iterate_tcp_options() {
+        let packet = Mbuf::from_bytes(&SYN_WITH_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let tcp = ipv4.parse::<Tcp4>().unwrap();
+
+        let mut iter = tcp.options_iter();
+        assert_eq!(Some(TcpOption::Mss(1460)), iter.next().unwrap());
+        assert_eq!(Some(TcpOption::SackPermitted), iter.next().unwrap());
+        assert_eq!(
+            Some(TcpOption::Timestamps(0x0102_0304, 0)),
+            iter.next().unwrap()
+        );
+        assert_eq!(Some(TcpOption::WindowScale(7)), iter.next().unwrap());
+        assert_eq!(None, iter.next().unwrap());
+    }
+
+    #[capsule::test]
+    fn invalid_tcp_option_length() {
+        let packet = Mbuf::from_bytes(&INVALID_OPTION_LENGTH).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let tcp = ipv4.parse::<Tcp4>().unwrap();
+
+        assert!(tcp.options_iter().next().is_err());
+    }
+
+    #[capsule::test]
+    fn append_tcp_option() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut tcp = ipv4.parse::<Tcp4>().unwrap();
+        let len = tcp.len();
+
+        let mut options = tcp.options_mut();
+        assert!(options.append(&TcpOption::WindowScale(2)).is_ok());
+
+        // 4 bytes MSS + 3 bytes window scale, padded to 8.
+        assert_eq!(7, tcp.data_offset());
+        assert_eq!(len + 4, tcp.len());
+
+        let mut iter = tcp.options_iter();
+        assert_eq!(Some(TcpOption::Mss(1460)), iter.next().unwrap());
+        assert_eq!(Some(TcpOption::WindowScale(2)), iter.next().unwrap());
+        assert_eq!(None, iter.next().unwrap());
+    }
+
+    #[capsule::test]
+    fn append_tcp_option_exceeds_max_len() {
+        let packet = Mbuf::from_bytes(&SYN_WITH_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut tcp = ipv4.parse::<Tcp4>().unwrap();
+
+        let mut options = tcp.options_mut();
+        let sack = TcpOption::Sack(vec![(1, 2), (3, 4), (5, 6)]);
+        assert!(options.append(&sack).is_err());
+
+        // options are left untouched
+        assert_eq!(10, tcp.data_offset());
+    }
+
+    #[capsule::test]
+    fn set_tcp_option_in_place() {
+        let packet = Mbuf::from_bytes(&SYN_WITH_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut tcp = ipv4.parse::<Tcp4>().unwrap();
+
+        let mut options = tcp.options_mut();
+        assert!(options.set(&TcpOption::Mss(1360)).is_ok());
+        assert!(options.set(&TcpOption::Timestamps(1, 2)).is_ok());
+
+        assert_eq!(10, tcp.data_offset());
+
+        let mut iter = tcp.options_iter();
+        assert_eq!(Some(TcpOption::Mss(1360)), iter.next().unwrap());
+        assert_eq!(Some(TcpOption::SackPermitted), iter.next().unwrap());
+        assert_eq!(Some(TcpOption::Timestamps(1, 2)), iter.next().unwrap());
+    }
+
+    #[capsule::test]
+    fn remove_tcp_option() {
+        let packet = Mbuf::from_bytes(&SYN_WITH_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut tcp = ipv4.parse::<Tcp4>().unwrap();
+        let len = tcp.len();
+
+        let mut options = tcp.options_mut();
+        assert!(options.remove(TcpOptionKinds::Timestamps).is_ok());
+
+        // 20 bytes of options less 10 bytes of timestamps, padded to 12.
+        assert_eq!(8, tcp.data_offset());
+        assert_eq!(len - 8, tcp.len());
+
+        let mut iter = tcp.options_iter();
+        assert_eq!(Some(TcpOption::Mss(1460)), iter.next().unwrap());
+        assert_eq!(Some(TcpOption::SackPermitted), iter.next().unwrap());
+        assert_eq!(Some(TcpOption::WindowScale(7)), iter.next().unwrap());
+        assert_eq!(None, iter.next().unwrap());
+    }
+
+    #[capsule::test]
+    fn retain_tcp_options() {
+        let packet = Mbuf::from_bytes(&SYN_WITH_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut tcp = ipv4.parse::<Tcp4>().unwrap();
+
+        let mut options = tcp.options_mut();
+        options
+            .retain(|option| option.kind() == TcpOptionKinds::MaxSegmentSize)
+            .unwrap();
+
+        // the no-operation padding is preserved.
+        assert_eq!(7, tcp.data_offset());
+
+        let mut iter = tcp.options_iter();
+        assert_eq!(Some(TcpOption::Mss(1460)), iter.next().unwrap());
+        assert_eq!(None, iter.next().unwrap());
+    }
+
+    /// TCP SYN packet with MSS, SACK permitted, timestamps and window
+    /// scale options.
+    #[rustfmt::skip]
+    const SYN_WITH_OPTIONS_PACKET: [u8; 74] = [
+        // ** ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x08, 0x00,
+        // ** IPv4 header
+        0x45, 0x00,
+        // total length
+        0x00, 0x3c,
+        0x08, 0xb8, 0x40, 0x00,
+        0x40, 0x06, 0x00, 0x00,
+        0x0a, 0x00, 0x00, 0x01,
+        0x0a, 0x00, 0x00, 0x02,
+        // ** TCP header
+        0x90, 0x05, 0x00, 0x50,
+        0x72, 0x14, 0xf1, 0x14,
+        0x00, 0x00, 0x00, 0x00,
+        // data offset = 10, flags = SYN
+        0xa0, 0x02,
+        0xfa, 0xf0, 0x00, 0x00, 0x00, 0x00,
+        // MSS = 1460
+        0x02, 0x04, 0x05, 0xb4,
+        // SACK permitted
+        0x04, 0x02,
+        // timestamps
+        0x08, 0x0a, 0x01, 0x02, 0x03, 0x04, 0x00, 0x00, 0x00, 0x00,
+        // no-operation
+        0x01,
+        // window scale = 7
+        0x03, 0x03, 0x07,
+    ];
+
+    /// TCP packet with an MSS option that runs past the header.
+    #[rustfmt::skip]
+    const INVALID_OPTION_LENGTH: [u8; 58] = [
+        // ** ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x08, 0x00,
+        // ** IPv4 header
+        0x45, 0x00,
+        0x00, 0x2c,
+        0x08, 0xb8, 0x40, 0x00,
+        0x40, 0x06, 0x00, 0x00,
+        0x0a, 0x00, 0x00, 0x01,
+        0x0a, 0x00, 0x00, 0x02,
+        // ** TCP header
+        0x90, 0x05, 0x00, 0x50,
+        0x72, 0x14, 0xf1, 0x14,
+        0x00, 0x00, 0x00, 0x00,
+        0x60, 0x02,
+        0xfa, 0xf0, 0x00, 0x00, 0x00, 0x00,
+        // MSS option with invalid length
+        0x02, 0x08, 0x05, 0xb4,
+    ];
+}
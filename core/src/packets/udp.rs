@@ -204,18 +204,13 @@ impl<E: IpPacket> Udp<E> {
     fn compute_checksum(&mut self) {
         self.no_checksum();
 
-        if let Ok(data) = self.mbuf().read_data_slice(self.offset, self.len()) {
-            let data = unsafe { data.as_ref() };
-            let pseudo_header_sum = self
-                .envelope()
-                .pseudo_header(data.len() as u16, ProtocolNumbers::Udp)
-                .sum();
-            let checksum = checksum::compute(pseudo_header_sum, data);
-            self.set_checksum(checksum);
-        } else {
-            // we are reading till the end of buffer, should never run out
-            unreachable!()
-        }
+        let pseudo_header_sum = self
+            .envelope()
+            .pseudo_header(self.len() as u16, ProtocolNumbers::Udp)
+            .sum();
+        let data = self.mbuf().data_segments(self.offset, self.len());
+        let checksum = checksum::compute_segments(pseudo_header_sum, data);
+        self.set_checksum(checksum);
     }
 }
 
@@ -456,6 +451,33 @@ mod tests {
         assert_eq!(expected, udp.checksum());
     }
 
+    #[capsule::test]
+    fn compute_checksum_over_chained_segments() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut udp = ipv4.push::<Udp4>().unwrap();
+
+        // a jumbo payload that doesn't fit in one segment
+        let payload = (0..8000).map(|i| i as u8).collect::<Vec<_>>();
+        let offset = udp.payload_offset();
+        udp.mbuf_mut().extend(offset, payload.len()).unwrap();
+        udp.mbuf_mut().copy_from_slice(offset, &payload).unwrap();
+        assert!(udp.mbuf().num_segments() > 1);
+
+        udp.reconcile_all();
+        assert_eq!(8008, udp.length());
+
+        // the checksum over the packet including the checksum field is 0
+        let mut data = vec![0; udp.len()];
+        udp.mbuf().copy_to_slice(udp.offset(), &mut data).unwrap();
+        let pseudo_header_sum = udp
+            .envelope()
+            .pseudo_header(data.len() as u16, ProtocolNumbers::Udp)
+            .sum();
+        assert_eq!(0, checksum::compute(pseudo_header_sum, &data));
+    }
+
     #[capsule::test]
     fn push_udp_packet() {
         let packet = Mbuf::new().unwrap();
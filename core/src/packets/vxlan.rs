@@ -0,0 +1,519 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::{Flow, IpPacket, ProtocolNumbers};
+use crate::packets::types::u32be;
+use crate::packets::{EtherTypes, Ethernet, EthernetEnvelope, Internal, Packet, Tcp, Udp};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ptr::NonNull;
+
+/// The IANA assigned UDP destination port for VXLAN.
+pub const VXLAN_PORT: u16 = 4789;
+
+/// The largest VXLAN network identifier, which is 24 bits.
+pub const VXLAN_MAX_VNI: u32 = 0x00ff_ffff;
+
+// Flags.
+const FLAG_VNI: u8 = 0x08;
+
+/// Virtual eXtensible Local Area Network packet based on [IETF RFC 7348].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |R|R|R|R|I|R|R|R|            Reserved                           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                VXLAN Network Identifier (VNI) |   Reserved    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Flags*:      8 bits. The I flag must be set to 1 for a valid VXLAN
+///                 Network ID (VNI). The other 7 bits are reserved.
+///
+/// - *VNI*:        24 bits. Used to designate the individual VXLAN overlay
+///                 network on which the communicating VMs are situated.
+///
+/// - *Reserved*:   Reserved fields must be set to zero on transmission and
+///                 ignored on receipt.
+///
+/// The payload of a VXLAN packet is an inner `Ethernet` frame.
+///
+/// ```
+/// let udp = ipv4.parse::<Udp<Ipv4>>()?;
+/// if udp.dst_port() == VXLAN_PORT {
+///     let vxlan = udp.parse::<Vxlan<Ipv4>>()?;
+///     let inner = vxlan.parse::<Ethernet<Vxlan<Ipv4>>>()?;
+/// }
+/// ```
+///
+/// [IETF RFC 7348]: https://tools.ietf.org/html/rfc7348#section-5
+pub struct Vxlan<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<VxlanHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Vxlan<E> {
+    #[inline]
+    fn header(&self) -> &VxlanHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut VxlanHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the flags.
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        self.header().flags
+    }
+
+    /// Sets the flags.
+    #[inline]
+    pub fn set_flags(&mut self, flags: u8) {
+        self.header_mut().flags = flags;
+    }
+
+    /// Returns a flag indicating whether the VNI is valid.
+    #[inline]
+    pub fn vni_valid(&self) -> bool {
+        self.flags() & FLAG_VNI != 0
+    }
+
+    /// Returns the VXLAN network identifier.
+    #[inline]
+    pub fn vni(&self) -> u32 {
+        let vni: u32 = self.header().vni_reserved.into();
+        vni >> 8
+    }
+
+    /// Sets the VXLAN network identifier.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the identifier is larger than [`VXLAN_MAX_VNI`].
+    ///
+    /// [`VXLAN_MAX_VNI`]: VXLAN_MAX_VNI
+    #[inline]
+    pub fn set_vni(&mut self, vni: u32) -> Result<()> {
+        ensure!(
+            vni <= VXLAN_MAX_VNI,
+            anyhow!("VNI {} is larger than 24 bits.", vni)
+        );
+
+        self.header_mut().vni_reserved = (vni << 8).into();
+        Ok(())
+    }
+
+    /// Encapsulates the envelope's payload, an Ethernet frame, in a VXLAN
+    /// packet.
+    ///
+    /// A UDP packet and a VXLAN packet are pushed in front of the frame.
+    /// The UDP destination port is set to [`VXLAN_PORT`], and the source
+    /// port is derived from the hash of the inner flow to provide entropy
+    /// for load balancing. The packet is reconciled afterwards, so the
+    /// outer UDP and IP lengths are filled in.
+    ///
+    /// ```
+    /// let ethernet = mbuf.push::<Ethernet>()?;
+    /// let mut ipv4 = ethernet.push::<Ipv4>()?;
+    /// ipv4.set_src(local_vtep);
+    /// ipv4.set_dst(remote_vtep);
+    /// let vxlan = Vxlan::encap(ipv4, 100)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the VNI is invalid, or if the buffer does not
+    /// have enough free space.
+    ///
+    /// [`VXLAN_PORT`]: VXLAN_PORT
+    pub fn encap(envelope: E, vni: u32) -> Result<Self> {
+        let udp = envelope.push::<Udp<E>>()?;
+        let mut vxlan = udp.push::<Vxlan<E>>()?;
+        vxlan.set_vni(vni)?;
+
+        let src_port = vxlan.entropy_port();
+        vxlan.envelope_mut().set_src_port(src_port);
+        vxlan.reconcile_all();
+
+        Ok(vxlan)
+    }
+
+    /// Decapsulates the inner Ethernet frame by removing all the headers
+    /// in front of it.
+    ///
+    /// The returned buffer can be parsed as an Ethernet frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have sufficient data to
+    /// remove.
+    pub fn decap(self) -> Result<Mbuf> {
+        let len = self.payload_offset();
+        let mut mbuf = self.reset();
+        mbuf.shrink(0, len)?;
+        Ok(mbuf)
+    }
+
+    /// Returns a UDP source port derived from the inner flow.
+    ///
+    /// If the inner frame is not an IP packet, the port is derived from the
+    /// inner Ethernet addresses instead.
+    fn entropy_port(&self) -> u16 {
+        let hash = match self.peek::<Ethernet<Vxlan<E>>>() {
+            Ok(ethernet) => match inner_flow(&ethernet) {
+                Some(flow) => hash_of(&flow),
+                None => hash_of(&(ethernet.src().octets(), ethernet.dst().octets())),
+            },
+            Err(_) => 0,
+        };
+
+        // uses the dynamic port range 49152-65535 recommended by RFC 7348.
+        49152 + (hash % 16384) as u16
+    }
+}
+
+/// Returns the flow of the IP packet inside the Ethernet frame.
+fn inner_flow<E: EthernetEnvelope>(ethernet: &Ethernet<E>) -> Option<Flow> {
+    match ethernet.ether_type() {
+        EtherTypes::Ipv4 => ip_flow(&*ethernet.peek::<Ipv4<Ethernet<E>>>().ok()?),
+        EtherTypes::Ipv6 => ip_flow(&*ethernet.peek::<Ipv6<Ethernet<E>>>().ok()?),
+        _ => None,
+    }
+}
+
+/// Returns the flow of the IP packet. If the payload is neither TCP nor
+/// UDP, the ports are 0.
+fn ip_flow<E: IpPacket>(ip: &E) -> Option<Flow> {
+    match ip.next_protocol() {
+        ProtocolNumbers::Tcp => Some(ip.peek::<Tcp<E>>().ok()?.flow()),
+        ProtocolNumbers::Udp => Some(ip.peek::<Udp<E>>().ok()?.flow()),
+        protocol => Some(Flow::new(ip.src(), ip.dst(), 0, 0, protocol)),
+    }
+}
+
+fn hash_of<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<E: IpPacket> fmt::Debug for Vxlan<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("vxlan")
+            .field("flags", &format!("0x{:02x}", self.flags()))
+            .field("vni", &self.vni())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Vxlan<E> {
+    /// The preceding type for a VXLAN packet must be UDP.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        VxlanHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Vxlan::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the UDP payload as a VXLAN packet.
+    ///
+    /// The UDP destination port is not checked because deployments may use
+    /// a port other than [`VXLAN_PORT`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the I flag is not set. Returns an error if the
+    /// payload does not have sufficient data for the VXLAN header.
+    ///
+    /// [`VXLAN_PORT`]: VXLAN_PORT
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Vxlan {
+            envelope,
+            header,
+            offset,
+        };
+
+        ensure!(packet.vni_valid(), anyhow!("not a VXLAN packet."));
+
+        Ok(packet)
+    }
+
+    /// Prepends a VXLAN packet to the beginning of the UDP's payload.
+    ///
+    /// The I flag is set, and the UDP destination port is set to
+    /// [`VXLAN_PORT`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`VXLAN_PORT`]: VXLAN_PORT
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, VxlanHeader::size_of())?;
+        let header = mbuf.write_data(offset, &VxlanHeader::default())?;
+
+        envelope.set_dst_port(VXLAN_PORT);
+
+        Ok(Vxlan {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+impl<E: IpPacket> EthernetEnvelope for Vxlan<E> {
+    /// The VXLAN payload is always an Ethernet frame.
+    #[inline]
+    fn is_ethernet_payload(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn set_ethernet_payload(&mut self) {}
+}
+
+/// VXLAN header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct VxlanHeader {
+    flags: u8,
+    reserved: [u8; 3],
+    vni_reserved: u32be,
+}
+
+impl Default for VxlanHeader {
+    fn default() -> Self {
+        VxlanHeader {
+            flags: FLAG_VNI,
+            reserved: [0; 3],
+            vni_reserved: u32be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testils::byte_arrays::IPV4_UDP_PACKET;
+
+    #[test]
+    fn size_of_vxlan_header() {
+        assert_eq!(8, VxlanHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_vxlan_packet() {
+        let packet = Mbuf::from_bytes(&VXLAN_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        assert_eq!(VXLAN_PORT, udp.dst_port());
+
+        let vxlan = udp.parse::<Vxlan<Ipv4>>().unwrap();
+        assert!(vxlan.vni_valid());
+        assert_eq!(100, vxlan.vni());
+
+        let inner = vxlan.parse::<Ethernet<Vxlan<Ipv4>>>().unwrap();
+        assert_eq!("00:00:00:00:00:01", inner.dst().to_string());
+        assert_eq!(EtherTypes::Ipv4, inner.ether_type());
+
+        let ipv4 = inner.parse::<Ipv4<Ethernet<Vxlan<Ipv4>>>>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4<Ethernet<Vxlan<Ipv4>>>>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+        assert_eq!(1087, udp.dst_port());
+    }
+
+    #[capsule::test]
+    fn parse_vxlan_without_vni_flag() {
+        // clears the I flag.
+        let mut bytes = VXLAN_PACKET;
+        bytes[42] = 0x00;
+
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Vxlan<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_and_set_vxlan_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let udp = ipv6.push::<Udp<Ipv6>>().unwrap();
+        let mut vxlan = udp.push::<Vxlan<Ipv6>>().unwrap();
+
+        assert_eq!(VxlanHeader::size_of(), vxlan.len());
+        assert_eq!(VXLAN_PORT, vxlan.envelope().dst_port());
+        assert!(vxlan.vni_valid());
+
+        vxlan.set_vni(VXLAN_MAX_VNI).unwrap();
+        assert_eq!(VXLAN_MAX_VNI, vxlan.vni());
+        assert!(vxlan.set_vni(VXLAN_MAX_VNI + 1).is_err());
+        assert_eq!(VXLAN_MAX_VNI, vxlan.vni());
+
+        let inner = vxlan.push::<Ethernet<Vxlan<Ipv6>>>().unwrap();
+        assert_eq!(14, inner.len());
+    }
+
+    #[capsule::test]
+    fn encap_ethernet_frame() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let expected_port = {
+            let ethernet = packet.peek::<Ethernet>().unwrap();
+            let flow = inner_flow(&ethernet).unwrap();
+            49152 + (hash_of(&flow) % 16384) as u16
+        };
+
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let vxlan = Vxlan::encap(ipv4, 100).unwrap();
+
+        assert_eq!(100, vxlan.vni());
+        assert_eq!(VXLAN_PORT, vxlan.envelope().dst_port());
+        assert_eq!(expected_port, vxlan.envelope().src_port());
+        assert_eq!(
+            (8 + 8 + IPV4_UDP_PACKET.len()) as u16,
+            vxlan.envelope().length()
+        );
+        assert_eq!(
+            (20 + 8 + 8 + IPV4_UDP_PACKET.len()) as u16,
+            vxlan.envelope().envelope().total_length()
+        );
+
+        let inner = vxlan.parse::<Ethernet<Vxlan<Ipv4>>>().unwrap();
+        assert_eq!(EtherTypes::Ipv4, inner.ether_type());
+    }
+
+    #[capsule::test]
+    fn decap_ethernet_frame() {
+        let packet = Mbuf::from_bytes(&VXLAN_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let vxlan = udp.parse::<Vxlan<Ipv4>>().unwrap();
+
+        let mbuf = vxlan.decap().unwrap();
+        assert_eq!(IPV4_UDP_PACKET.len(), mbuf.data_len());
+
+        let ethernet = mbuf.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+    }
+
+    /// IPv4 UDP packet encapsulated in VXLAN with VNI 100.
+    #[rustfmt::skip]
+    const VXLAN_PACKET: [u8; 102] = [
+    // Ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x0a,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x0b,
+        0x08, 0x00,
+    // IPv4 header
+        0x45, 0x00,
+        // total length = 88
+        0x00, 0x58,
+        0x00, 0x00, 0x40, 0x00,
+        // ttl = 64, protocol = UDP
+        0x40, 0x11, 0x00, 0x00,
+        // src = 10.0.0.1
+        0x0a, 0x00, 0x00, 0x01,
+        // dst = 10.0.0.2
+        0x0a, 0x00, 0x00, 0x02,
+    // UDP header
+        // src_port = 50000, dst_port = 4789
+        0xc3, 0x50, 0x12, 0xb5,
+        // length = 68, checksum = 0
+        0x00, 0x44, 0x00, 0x00,
+    // VXLAN header
+        // I flag
+        0x08, 0x00, 0x00, 0x00,
+        // VNI = 100
+        0x00, 0x00, 0x64, 0x00,
+    // inner Ethernet header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x08, 0x00,
+    // inner IPv4 header
+        0x45, 0x00, 0x00, 0x26,
+        0xab, 0x49, 0x40, 0x00,
+        0xff, 0x11, 0xf7, 0x00,
+        0x8b, 0x85, 0xd9, 0x6e,
+        0x8b, 0x85, 0xe9, 0x02,
+    // inner UDP header
+        0x99, 0xd0, 0x04, 0x3f,
+        0x00, 0x12, 0x72, 0x28,
+    // inner UDP payload
+        0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+    ];
+}
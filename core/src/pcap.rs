@@ -23,11 +23,13 @@ use anyhow::Result;
 use std::fmt;
 use std::os::raw;
 use std::ptr::NonNull;
+use std::slice;
 use thiserror::Error;
 
 // DLT_EN10MB; LINKTYPE_ETHERNET=1; 10MB is historical
 const DLT_EN10MB: raw::c_int = 1;
-const PCAP_SNAPSHOT_LEN: raw::c_int = ffi::RTE_MBUF_DEFAULT_BUF_SIZE as raw::c_int;
+// large enough for packets stored in chained segments
+const PCAP_SNAPSHOT_LEN: raw::c_int = u16::MAX as raw::c_int;
 
 /// An error generated in `libpcap`.
 ///
@@ -113,10 +115,12 @@ impl Pcap {
         self.flush()
     }
 
+    /// A multi-segment packet is copied into a temporary buffer first, so
+    /// all the segments are captured.
     unsafe fn dump_packet(&self, ptr: *mut ffi::rte_mbuf) {
         let mut pcap_hdr = ffi::pcap_pkthdr::default();
-        pcap_hdr.len = (*ptr).data_len as u32;
-        pcap_hdr.caplen = pcap_hdr.len;
+        pcap_hdr.len = (*ptr).pkt_len;
+        pcap_hdr.caplen = (*ptr).pkt_len;
 
         // If this errors, we'll still want to write packet(s) to the pcap,
         let _ = libc::gettimeofday(
@@ -124,11 +128,26 @@ impl Pcap {
             std::ptr::null_mut(),
         );
 
-        ffi::pcap_dump(
-            self.dumper.as_ptr() as *mut raw::c_uchar,
-            &pcap_hdr,
-            ((*ptr).buf_addr as *mut u8).offset((*ptr).data_off as isize),
-        );
+        let seg_data =
+            |seg: *mut ffi::rte_mbuf| ((*seg).buf_addr as *mut u8).offset((*seg).data_off as isize);
+
+        let mut buffer = Vec::new();
+        let data = if (*ptr).next.is_null() {
+            seg_data(ptr)
+        } else {
+            buffer.reserve((*ptr).pkt_len as usize);
+            let mut seg = ptr;
+            while !seg.is_null() {
+                buffer.extend_from_slice(slice::from_raw_parts(
+                    seg_data(seg),
+                    (*seg).data_len as usize,
+                ));
+                seg = (*seg).next;
+            }
+            buffer.as_mut_ptr()
+        };
+
+        ffi::pcap_dump(self.dumper.as_ptr() as *mut raw::c_uchar, &pcap_hdr, data);
     }
 
     fn flush(&self) -> Result<()> {
@@ -309,6 +328,20 @@ mod tests {
         cleanup("foo1.pcap");
     }
 
+    #[capsule::test]
+    fn create_pcap_and_write_chained_packet() {
+        let writer = Pcap::create("foo5.pcap").unwrap();
+        let jumbo = Mbuf::from_bytes(&[0; 9000]).unwrap();
+        assert!(jumbo.num_segments() > 1);
+
+        let res = unsafe { writer.write(&[jumbo.into_ptr()]) };
+
+        assert!(res.is_ok());
+        let len = read_pcap_plen("foo5.pcap");
+        assert_eq!(9000, len);
+        cleanup("foo5.pcap");
+    }
+
     #[capsule::test]
     fn append_to_pcap_and_write_packet() {
         let open = Pcap::create("foo2.pcap");
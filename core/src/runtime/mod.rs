@@ -120,6 +120,7 @@ impl Runtime {
                 .cores(&conf.cores)?
                 .mempools(&mut mempools)
                 .rx_tx_queue_capacity(conf.rxd, conf.txd)?
+                .mtu(conf.mtu)?
                 .finish(conf.promiscuous, conf.multicast, conf.kni)?;
 
             debug!(?port);
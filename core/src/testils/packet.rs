@@ -17,8 +17,10 @@
 */
 
 use crate::packets::ip::v4::Ipv4;
-use crate::packets::ip::v6::{Ipv6, SegmentRouting};
+use crate::packets::ip::v6::{Fragment, Ipv6, Ipv6Packet, SegmentRouting};
+use crate::packets::ip::ProtocolNumbers;
 use crate::packets::{Ethernet, Packet, Tcp, Tcp4, Tcp6, Udp4, Udp6};
+use crate::Mbuf;
 
 /// [`Packet`] extension trait.
 ///
@@ -72,6 +74,64 @@ pub trait PacketExt: Packet + Sized {
     fn into_sr_tcp(self) -> Tcp<SegmentRouting<Ipv6>> {
         self.into_sr().parse::<Tcp<SegmentRouting<Ipv6>>>().unwrap()
     }
+
+    /// Returns a copy of the packet's payload, which may span more than
+    /// one segment.
+    fn payload_to_vec(&self) -> Vec<u8> {
+        let mut payload = vec![0; self.payload_len()];
+        self.mbuf()
+            .copy_to_slice(self.payload_offset(), &mut payload)
+            .unwrap();
+        payload
+    }
 }
 
 impl<T> PacketExt for T where T: Packet + Sized {}
+
+/// Returns an IPv4 packet carrying `payload` as UDP with the
+/// identification set to `id`.
+pub fn ipv4_udp_packet(id: u16, payload: &[u8]) -> Ipv4 {
+    let packet = Mbuf::from_bytes(payload).unwrap();
+    let ethernet = packet.push::<Ethernet>().unwrap();
+    let mut ipv4 = ethernet.push::<Ipv4>().unwrap();
+    ipv4.set_identification(id);
+    ipv4.set_protocol(ProtocolNumbers::Udp);
+    ipv4.reconcile();
+    ipv4
+}
+
+/// Returns an IPv4 fragment carrying `payload` at `offset`, in 8-octet
+/// units, of the UDP datagram identified by `id`.
+pub fn ipv4_udp_fragment(id: u16, offset: u16, payload: &[u8], more: bool) -> Ipv4 {
+    let mut ipv4 = ipv4_udp_packet(id, payload);
+    ipv4.set_fragment_offset(offset);
+    if more {
+        ipv4.set_more_fragments();
+    }
+    ipv4.reconcile();
+    ipv4
+}
+
+/// Returns an IPv6 packet carrying `payload` as UDP.
+pub fn ipv6_udp_packet(payload: &[u8]) -> Ipv6 {
+    let packet = Mbuf::from_bytes(payload).unwrap();
+    let ethernet = packet.push::<Ethernet>().unwrap();
+    let mut ipv6 = ethernet.push::<Ipv6>().unwrap();
+    ipv6.set_next_header(ProtocolNumbers::Udp);
+    ipv6.reconcile();
+    ipv6
+}
+
+/// Returns an IPv6 fragment carrying `payload` at `offset`, in 8-octet
+/// units, of the UDP datagram identified by `id`.
+pub fn ipv6_udp_fragment(id: u32, offset: u16, payload: &[u8], more: bool) -> Fragment<Ipv6> {
+    let ipv6 = ipv6_udp_packet(payload);
+    let mut frag = ipv6.push::<Fragment<Ipv6>>().unwrap();
+    frag.set_identification(id);
+    frag.set_fragment_offset(offset);
+    if more {
+        frag.set_more_fragments();
+    }
+    frag.reconcile_all();
+    frag
+}
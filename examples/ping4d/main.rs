@@ -44,7 +44,7 @@ fn reply_echo(packet: &Mbuf) -> Result<EchoReply> {
     let mut reply = reply.push::<EchoReply>()?;
     reply.set_identifier(request.identifier());
     reply.set_seq_no(request.seq_no());
-    reply.set_data(request.data())?;
+    reply.set_data(&request.data())?;
     reply.reconcile_all();
 
     debug!(?request);